@@ -80,7 +80,7 @@ export const DeclarationModal: React.FC<DeclarationModalProps> = ({ isOpen, onCl
                       {operation.label && <span className="text-gray-600 dark:text-gray-400">{operation.label}</span>}
                     </div>
                     <div className="flex items-center gap-2 text-gray-500">
-                      {operation.receipt_url && <span>📎</span>}
+                      {operation.receipt_key && <span>📎</span>}
                       {operation.vat_on_payments && <span title="TVA sur encaissement">⏰</span>}
                     </div>
                   </div>
@@ -23,11 +23,24 @@ interface Operation {
   amount_ttc_cents: number
   vat_on_payments: boolean
   label: string | null
-  receipt_url: string | null
+  receipt_key: string | null
   created_at: string
   updated_at: string
 }
 
+interface SupplierInvoiceImport {
+  receipt: { key: string; sha256: string; duplicate_of: string[]; already_stored: boolean }
+  operation: {
+    invoice_date: string
+    operation_type: 'purchase'
+    amount_ht_cents: number
+    vat_amount_cents: number | null
+    vat_on_payments: boolean
+    label: string | null
+  } | null
+  extraction_error: string | null
+}
+
 export const CompactOperationForm: React.FC<CompactOperationFormProps> = ({ 
   onOperationAdded 
 }) => {
@@ -174,7 +187,7 @@ export const CompactOperationForm: React.FC<CompactOperationFormProps> = ({
                         extension === 'png' ? 'image/png' : 'application/octet-stream'
         
         // Appeler la commande Tauri pour lire et uploader le fichier directement
-        const url = await invoke<string>('cmd_upload_file_from_path', {
+        const result = await invoke<SupplierInvoiceImport>('cmd_upload_file_from_path', {
           filePath: filePath,
           originalFilename: fileName,
           contentType: mimeType
@@ -182,10 +195,23 @@ export const CompactOperationForm: React.FC<CompactOperationFormProps> = ({
         
         setUploadedFile({
           name: fileName,
-          url: url
+          url: result.receipt.key
         })
         
-        console.log('✅ Fichier uploadé vers MinIO:', url)
+        // Facture Factur-X : pré-remplir l'achat à partir du XML embarqué
+        if (result.operation) {
+          const suggested = result.operation
+          setOperationType('purchase')
+          setIsPrestation(suggested.vat_on_payments)
+          setDateFacture(suggested.invoice_date)
+          setMontantHt((suggested.amount_ht_cents / 100).toFixed(2))
+          setMontantTva(((suggested.vat_amount_cents ?? 0) / 100).toFixed(2))
+          setLabel(suggested.label ?? '')
+        } else if (result.extraction_error) {
+          console.warn('⚠️ Factur-X non lu:', result.extraction_error)
+        }
+        
+        console.log('✅ Fichier uploadé:', result.receipt.key)
       } catch (uploadError) {
         setUploadError('Erreur lors de l\'upload vers MinIO')
         console.error('❌ Erreur upload fichier Tauri:', uploadError)
@@ -298,7 +324,7 @@ export const CompactOperationForm: React.FC<CompactOperationFormProps> = ({
         vat_rate: operationType === 'purchase' && montantHt ? (parseFloat(montantTva) / parseFloat(montantHt) * 100) : 20, // Défaut 20% si pas de HT
         vat_on_payments: operationType === 'sale' ? true : (operationType === 'purchase' ? isPrestation : false),
         label: label || `${operationType === 'sale' ? 'Vente' : 'Achat'} ${dateFacture}`,
-        receipt_url: uploadedFile?.url || undefined
+        receipt_key: uploadedFile?.url || undefined
       }
       
       console.log('📤 Envoi à Tauri:', operationDto)
@@ -103,7 +103,7 @@ export const OperationQuickForm: React.FC<OperationQuickFormProps> = ({ onOperat
         vat_on_payments: formData.vat_on_payments,
         invoice_date: formData.invoice_date,
         payment_date: formData.payment_date || undefined,
-        receipt_url: undefined // TODO: Gérer l'upload de fichier
+        receipt_key: undefined // TODO: Gérer l'upload de fichier
       }
       
       await addOperation(operationDto)
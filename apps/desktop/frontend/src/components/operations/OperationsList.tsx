@@ -6,7 +6,7 @@ import type { Operation } from '../../types'
 import { useCurrentPeriod } from '../../stores/useAppStore'
 import { cn } from '../../lib/utils'
 import { TauriClient } from '../../lib/tauriClient'
-import { openMinioJustificatif } from '../../lib/fileOpener'
+import { openReceipt } from '../../lib/fileOpener'
 
 interface OperationsListProps {
   showFilters?: boolean
@@ -173,10 +173,10 @@ export const OperationsList: React.FC<OperationsListProps> = ({ showFilters = tr
     }
   }
   
-  const handleOpenFile = async (fileUrl: string) => {
+  const handleOpenFile = async (receiptKey: string) => {
     try {
-      console.log('🔗 Ouverture du justificatif:', fileUrl)
-      await openMinioJustificatif(fileUrl)
+      console.log('🔗 Ouverture du justificatif:', receiptKey)
+      await openReceipt(receiptKey)
       console.log('✅ Justificatif ouvert avec succès')
     } catch (error) {
       console.error('❌ Erreur ouverture justificatif:', error)
@@ -357,11 +357,11 @@ export const OperationsList: React.FC<OperationsListProps> = ({ showFilters = tr
                     </td>
                     <td className="py-3 px-2">
                       <div className="flex items-center justify-center space-x-2">
-                        {operation.receipt_url && (
+                        {operation.receipt_key && (
                           <Button
                             variant="ghost"
                             size="sm"
-                            onClick={() => handleOpenFile(operation.receipt_url!)}
+                            onClick={() => handleOpenFile(operation.receipt_key!)}
                             className="p-1 text-blue-400 hover:text-blue-300"
                             title="Ouvrir le justificatif"
                           >
@@ -80,4 +80,12 @@ export async function openMinioJustificatif(url: string): Promise<void> {
   }
   
   return openFileUrlWithFallback(url)
-}
\ No newline at end of file
+}
+/**
+ * Ouvre un justificatif à partir de sa clé de stockage
+ * Le backend fournit une URL temporaire (signée pour MinIO)
+ */
+export async function openReceipt(key: string): Promise<void> {
+  const url = await invoke<string>('cmd_get_receipt_url', { key })
+  return openFileUrlWithFallback(url)
+}
@@ -82,7 +82,7 @@ export const deleteFromMinIO = async (url: string): Promise<void> => {
     
     // Appeler la commande Tauri pour suppression
     const { invoke } = await import('@tauri-apps/api/core')
-    await invoke('cmd_delete_justificatif', { fileUrl: url })
+    await invoke('cmd_delete_justificatif', { key: url })
     
     console.log('✅ Suppression MinIO réussie')
     
@@ -24,7 +24,7 @@ export interface Operation {
   amount_ttc_cents: number        // Montant TTC = HT + TVA
   vat_on_payments: boolean        // true = TVA à l'encaissement
   label?: string                  // Description optionnelle
-  receipt_url?: string            // URL MinIO pour justificatif
+  receipt_key?: string            // Clé du justificatif (YYYY-MM/fichier)
   created_at: string              // Date de création
   updated_at: string              // Date de modification
 
@@ -65,7 +65,7 @@ export interface CreateOperationDto {
   vat_on_payments: boolean
   invoice_date: string          // Date ISO
   payment_date?: string         // Date ISO (optionnel)
-  receipt_url?: string          // URL MinIO après upload (optionnel)
+  receipt_key?: string          // Clé renvoyée par l'upload (optionnel)
 }
 
 // =============================================================================
@@ -1,27 +1,72 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{path::PathBuf, sync::Arc};
+use std::{path::{Path, PathBuf}, sync::Arc};
 
-use app::{AppDeps, AppService, CreateInvoiceDto, CreateInvoiceSimpleDto, CreateWorkingDayDto, CreateSimulationDto, EnhancedDashboardData, CreateOperationDto, UpdateOperationDto, CreateYearlyPlanningDto, UpdateYearlyPlanningDto, UpdateMonthPlanningDto};
+use app::{AppDeps, AppService, CreateInvoiceDto, CreateInvoiceSimpleDto, CreateWorkingDayDto, CreateSimulationDto, EnhancedDashboardData, CreateOperationDto, UpdateOperationDto, CreateYearlyPlanningDto, UpdateYearlyPlanningDto, UpdateMonthPlanningDto, SupplierInvoiceImport};
 use bytes::Bytes;
 use chrono::NaiveDate;
 use domain::{
     DashboardSummary, Expense, MonthId, Settings, UrssafReport, VatReport, MonthRecap,
     // New imports for enhanced features
-    WorkingDay, WorkingDaysStats, TaxSchedule, Simulation, SimulationResults, MonthlyKPI,
+    WorkingDay, WorkingDaysStats, TaxSchedule, TaxPayment, TaxScheduleSyncPlan, Simulation, SimulationResults, MonthlyKPI,
     DailyRateCalculation, AnnualIncomeProjection, ProvisionOptimization, WorkingPatternAnalysis,
     // Operation model
-    Operation, OperationType,
+    Operation, OperationType, OperationQuery, OperationPage,
+    // Provisions and declarations
+    Provision, Declaration, DeclarationType,
     // Annual tax declaration
     AnnualTaxData,
     // Yearly Planning
-    YearlyPlanning
+    YearlyPlanning,
+    // Document storage
+    DocumentStore, DocumentMigrationReport, FileInfo, StorageStats, ReceiptUpload, ReceiptVerificationReport,
+    ReceiptAudit, MonthCloseChecklist,
+    // Invoice issuance
+    IssuerProfile, InvoiceDraft, IssuedInvoice, EInvoiceFormat, EInvoiceExport, SaleInvoiceDetails,
+    // Accountant export
+    ExportPeriod, ExportManifest, FecExport,
+    // Legal archive
+    ArchivedFiscalYear, ArchiveReport,
+    // Backups
+    BackupPolicy, BackupReason, BackupInfo, BackupRun, BackupVerification,
+    // Audit trail
+    AuditEntity, AuditEvent, AuditQuery, AuditChainCheck,
+    // Trash and undo
+    Trash, TrashPurge, UndoStep,
+    // Database doctor
+    DoctorReport,
+    // Workspaces
+    Workspace, WorkspaceList, BusinessRegime, ConsolidatedCashPlan, consolidate_cash_plans, DEFAULT_WORKSPACE_ID,
+    // Dataset export
+    DatasetImportMode, DatasetImportReport,
+    // Synchronisation
+    SyncSettings, SyncConflict, SyncReport
+};
+use infra::{
+    connect_and_migrate, LocalDocumentStore, MinioService, MinioConfig,
+    // Encrypted database
+    connect_encrypted_and_migrate, SqliteRepos, DatabaseKey, is_database_encrypted, check_database_key,
+    encrypt_database, decrypt_database, rotate_database_key, load_keyring_key, store_keyring_key, delete_keyring_key,
+    // Backups
+    apply_staged_restore,
+    // Workspaces
+    WorkspaceRegistry, PrefixedDocumentStore,
+    // Synchronisation
+    FolderChangeLog
 };
-use infra::{connect_and_migrate, MinioService, MinioConfig, FileInfo, StorageStats};
 use serde::{Deserialize, Serialize};
 use tauri::{Manager, State};
 
-struct AppState(Arc<AppService>);
+/// Service of the active workspace, replaced when another workspace is opened
+struct AppState(std::sync::RwLock<Arc<AppService>>);
+/// Installation folder: workspace registry, document backend and the default workspace
+struct DataDir(PathBuf);
+
+impl AppState {
+    fn service(&self) -> Arc<AppService> {
+        self.0.read().expect("service lock poisoned").clone()
+    }
+}
 
 #[tauri::command]
 async fn cmd_open_url(app: tauri::AppHandle, url: String) -> Result<(), String> {
@@ -67,7 +112,7 @@ async fn cmd_open_url(app: tauri::AppHandle, url: String) -> Result<(), String>
 
 #[tauri::command]
 async fn cmd_dashboard(state: State<'_, AppState>, month: i32, m: u8) -> Result<DashboardSummary, String> {
-    state.0.get_dashboard(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().get_dashboard(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -76,14 +121,14 @@ async fn cmd_list_invoices(state: State<'_, AppState>, month: Option<i32>, m: Op
         (Some(y), Some(m)) => Some(MonthId { year: y, month: m as u32 }),
         _ => None,
     };
-    let list = state.0.list_invoices(month).await.map_err(|e| e.to_string())?;
+    let list = state.service().list_invoices(month).await.map_err(|e| e.to_string())?;
     Ok(serde_json::to_value(list).unwrap())
 }
 
 #[tauri::command]
 async fn cmd_create_invoice(state: State<'_, AppState>, dto: CreateInvoiceDto) -> Result<(), String> {
     let inv = dto.into_entity().map_err(|e| e.to_string())?;
-    state.0.create_invoice(inv).await.map_err(|e| e.to_string())
+    state.service().create_invoice(inv).await.map_err(|e| e.to_string())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -112,7 +157,7 @@ async fn cmd_create_expense(state: State<'_, AppState>, dto: CreateExpenseDto) -
         paid_at,
         receipt_path: None,
     };
-    state.0.create_expense(exp).await.map_err(|e| e.to_string())
+    state.service().create_expense(exp).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -121,18 +166,233 @@ async fn cmd_list_expenses(state: State<'_, AppState>, month: Option<i32>, m: Op
         (Some(y), Some(m)) => Some(MonthId { year: y, month: m as u32 }),
         _ => None,
     };
-    let list = state.0.list_expenses(month).await.map_err(|e| e.to_string())?;
+    let list = state.service().list_expenses(month).await.map_err(|e| e.to_string())?;
     Ok(serde_json::to_value(list).unwrap())
 }
 
 #[tauri::command]
 async fn cmd_prepare_vat(state: State<'_, AppState>, y: i32, m: u8) -> Result<VatReport, String> {
-    state.0.prepare_vat(MonthId { year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().prepare_vat(MonthId { year: y, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_prepare_urssaf(state: State<'_, AppState>, y: i32, m: u8) -> Result<UrssafReport, String> {
-    state.0.prepare_urssaf(MonthId { year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().prepare_urssaf(MonthId { year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+}
+
+const DOCUMENT_BACKEND_FILE: &str = "document_backend.txt";
+
+/// DOCUMENT_STORE env var, else the backend chosen by the last migration, else local
+fn document_backend(base: &Path) -> String {
+    std::env::var("DOCUMENT_STORE").ok()
+        .or_else(|| std::fs::read_to_string(base.join(DOCUMENT_BACKEND_FILE)).ok())
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "local".to_string())
+}
+
+/// Documents of a workspace: its own folder in the local store, its prefix in the MinIO bucket
+fn open_document_store(backend: &str, base: &Path, workspace: &Workspace) -> Result<Arc<dyn DocumentStore>, String> {
+    let prefix = workspace.document_prefix.as_str();
+    match backend {
+        "local" => {
+            let root = std::env::var("DOCUMENTS_DIR").map(PathBuf::from).unwrap_or_else(|_| base.join("documents"));
+            let root = if prefix.is_empty() { root } else { root.join(prefix) };
+            Ok(Arc::new(LocalDocumentStore::new(root).map_err(|e| e.to_string())?))
+        }
+        "minio" => {
+            let store: Arc<dyn DocumentStore> = Arc::new(MinioService::new_lazy(MinioConfig::default()).map_err(|e| e.to_string())?);
+            Ok(if prefix.is_empty() { store } else { Arc::new(PrefixedDocumentStore::new(store, prefix)) })
+        }
+        other => Err(format!("Backend de stockage inconnu: '{}' (attendu: local ou minio)", other)),
+    }
+}
+
+const DB_ENCRYPTION_FILE: &str = "db_encryption.txt";
+const DB_ROTATE_KEY_FILE: &str = "db_rotate_key.pending";
+const KEYRING_ACCOUNT: &str = "database";
+
+/// Keyring account of a workspace's database key
+fn keyring_account(workspace: &Workspace) -> String {
+    if workspace.id == DEFAULT_WORKSPACE_ID {
+        KEYRING_ACCOUNT.to_string()
+    } else {
+        format!("{}.{}", KEYRING_ACCOUNT, workspace.id)
+    }
+}
+
+/// Key being installed: recorded before the migration so that an interruption never loses it
+fn keyring_next_account(account: &str) -> String {
+    format!("{}.next", account)
+}
+
+/// DB_ENCRYPTION env var (none, keyring or passphrase), else the mode chosen in the app, else none
+fn database_encryption(base: &Path) -> String {
+    std::env::var("DB_ENCRYPTION").ok()
+        .or_else(|| std::fs::read_to_string(base.join(DB_ENCRYPTION_FILE)).ok())
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "none".to_string())
+}
+
+/// Key opening the encrypted database: keyring (including an interrupted rotation), then passphrases
+async fn current_database_key(path: &Path, account: &str) -> Result<DatabaseKey, String> {
+    let mut candidates = Vec::new();
+    for account in [account.to_string(), keyring_next_account(account)] {
+        match load_keyring_key(&account) {
+            Ok(Some(key)) => candidates.push(key),
+            Ok(None) => {}
+            Err(e) => eprintln!("⚠️ {}", e),
+        }
+    }
+    for var in ["DB_PASSPHRASE", "DB_NEW_PASSPHRASE"] {
+        if let Ok(passphrase) = std::env::var(var) {
+            candidates.push(DatabaseKey::Passphrase(passphrase));
+        }
+    }
+    for key in candidates {
+        if check_database_key(path, Some(&key)).await {
+            return Ok(key);
+        }
+    }
+    Err("Base de données chiffrée: aucune clé valide (trousseau ou DB_PASSPHRASE)".to_string())
+}
+
+/// Key the database must be encrypted with once opened (None: in clear)
+fn target_database_key(mode: &str, current: Option<&DatabaseKey>, base: &Path, account: &str) -> Result<Option<DatabaseKey>, String> {
+    match mode {
+        "none" => Ok(None),
+        "passphrase" => std::env::var("DB_NEW_PASSPHRASE").or_else(|_| std::env::var("DB_PASSPHRASE"))
+            .map(|passphrase| Some(DatabaseKey::Passphrase(passphrase)))
+            .map_err(|_| "Chiffrement par phrase secrète: DB_PASSPHRASE non définie".to_string()),
+        "keyring" => match current {
+            Some(key @ DatabaseKey::Raw(_)) if !base.join(DB_ROTATE_KEY_FILE).exists() => Ok(Some(key.clone())),
+            _ => {
+                let key = DatabaseKey::generate().map_err(|e| e.to_string())?;
+                store_keyring_key(&keyring_next_account(account), &key).map_err(|e| e.to_string())?;
+                Ok(Some(key))
+            }
+        },
+        other => Err(format!("Mode de chiffrement inconnu: '{}' (attendu: none, keyring ou passphrase)", other)),
+    }
+}
+
+/// Open data.sqlite, first encrypting, decrypting or re-keying it in place to match the configured mode
+async fn open_database(base: &Path, account: &str) -> Result<SqliteRepos, String> {
+    let path = base.join("data.sqlite");
+    let conn_str = format!("sqlite:{}", path.display());
+    // The journal of the replaced database is checkpointed with its own key
+    let replaced_key = if is_database_encrypted(&path).map_err(|e| e.to_string())? {
+        Some(current_database_key(&path, account).await?)
+    } else {
+        None
+    };
+    if apply_staged_restore(&path, replaced_key.as_ref()).await.map_err(|e| format!("Restauration de la sauvegarde impossible: {}", e))? {
+        println!("♻️ Base de données restaurée depuis une sauvegarde");
+    }
+    let mode = database_encryption(base);
+    let current = if is_database_encrypted(&path).map_err(|e| e.to_string())? {
+        Some(current_database_key(&path, account).await?)
+    } else {
+        None
+    };
+    let target = target_database_key(&mode, current.as_ref(), base, account)?;
+
+    match (&current, &target) {
+        (None, Some(key)) if path.exists() => {
+            encrypt_database(&path, key).await.map_err(|e| format!("Chiffrement de la base impossible: {}", e))?;
+            println!("🔒 Base de données chiffrée");
+        }
+        (Some(key), None) => {
+            decrypt_database(&path, key).await.map_err(|e| format!("Déchiffrement de la base impossible: {}", e))?;
+            println!("🔓 Base de données déchiffrée");
+        }
+        (Some(old), Some(new)) if old != new => {
+            rotate_database_key(&path, old, new).await.map_err(|e| format!("Changement de clé impossible: {}", e))?;
+            println!("🔑 Clé de la base de données renouvelée");
+            if matches!(new, DatabaseKey::Passphrase(_)) {
+                println!("🔑 Pensez à remplacer DB_PASSPHRASE par la nouvelle phrase secrète");
+            }
+        }
+        _ => {}
+    }
+
+    // The database now opens with the target key: promote it and forget the previous one
+    match &target {
+        Some(key @ DatabaseKey::Raw(_)) => {
+            store_keyring_key(account, key).map_err(|e| e.to_string())?;
+            delete_keyring_key(&keyring_next_account(account)).ok();
+        }
+        _ if matches!(current, Some(DatabaseKey::Raw(_))) => {
+            delete_keyring_key(account).ok();
+            delete_keyring_key(&keyring_next_account(account)).ok();
+        }
+        _ => {}
+    }
+    std::fs::remove_file(base.join(DB_ROTATE_KEY_FILE)).ok();
+
+    let repos = match &target {
+        Some(key) => connect_encrypted_and_migrate(&conn_str, key).await,
+        None => connect_and_migrate(&conn_str).await,
+    };
+    repos.map_err(|e| e.to_string())
+}
+
+/// Open a workspace: its database and documents, with the service running on them
+async fn open_workspace(base: &Path, workspace: &Workspace) -> Result<Arc<AppService>, String> {
+    let dir = WorkspaceRegistry::new(base).dir(workspace);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Impossible de créer le dossier de l'espace: {}", e))?;
+    let repos = open_database(&dir, &keyring_account(workspace)).await?;
+
+    // Document storage never blocks startup: MinIO is only contacted on first use
+    let documents = open_document_store(&document_backend(base), base, workspace)?;
+
+    let deps = AppDeps {
+        provisions: Arc::new(repos.provisions()),
+        config: Arc::new(repos.config()),
+        months: Arc::new(repos.months()),
+        // New dependencies
+        operations: Arc::new(repos.operations()),
+        declarations: Arc::new(repos.declarations()),
+        working_days: Arc::new(repos.working_days()),
+        tax_schedules: Arc::new(repos.tax_schedules()),
+        simulations: Arc::new(repos.simulations()),
+        kpis: Arc::new(repos.kpis()),
+        yearly_planning: Arc::new(repos.yearly_planning()),
+        issued_invoices: Arc::new(repos.issued_invoices()),
+        archives: Arc::new(repos.archives()),
+        backups: Arc::new(repos.backups()),
+        audit: Arc::new(repos.audit()),
+        doctor: Arc::new(repos.doctor()),
+        sync: Arc::new(repos.sync()),
+        units_of_work: Arc::new(repos.units_of_work()),
+        // External services
+        documents,
+    };
+    Ok(Arc::new(AppService::new(deps)))
+}
+
+/// Housekeeping when a workspace becomes the active one
+async fn maintain_workspace(service: &AppService) {
+    match service.migrate_receipt_urls_to_keys().await {
+        Ok(0) => {}
+        Ok(n) => println!("🔑 {} justificatif(s) référencé(s) par clé au lieu d'URL", n),
+        Err(e) => eprintln!("⚠️ Migration des URL de justificatifs impossible: {}", e),
+    }
+    match service.purge_expired_trash().await {
+        Ok(purge) if purge.operations + purge.yearly_plannings > 0 => println!(
+            "🗑️ Corbeille: {} opération(s) et {} planning(s) supprimé(s) définitivement",
+            purge.operations, purge.yearly_plannings,
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("⚠️ Purge de la corbeille impossible: {}", e),
+    }
+}
+
+/// Folder of the active workspace: its database, backups and encryption settings
+fn active_workspace_dir(base: &Path) -> Result<PathBuf, String> {
+    let registry = WorkspaceRegistry::new(base);
+    registry.active().map(|workspace| registry.dir(&workspace)).map_err(|e| e.to_string())
 }
 
 fn data_dir<R: tauri::Runtime>(_app: &tauri::App<R>) -> PathBuf {
@@ -167,36 +427,27 @@ fn main() {
         .setup(|app: &mut tauri::App<tauri::Wry>| {
             let base = data_dir(app);
             std::fs::create_dir_all(&base).ok();
-            let db_path = base.join("data.sqlite");
             let app_handle = app.handle();
             tauri::async_runtime::block_on(async move {
-                let conn_str = format!("sqlite:{}", db_path.display());
-                let repos = connect_and_migrate(&conn_str).await.expect("db init");
-                
-                // Initialize MinIO service
-                let minio_config = MinioConfig::default();
-                let minio_service = MinioService::new(minio_config).await
-                    .expect("Failed to initialize MinIO service");
-                
-                let deps = AppDeps {
-                    invoices: Arc::new(repos.invoices()),
-                    expenses: Arc::new(repos.expenses()),
-                    provisions: Arc::new(repos.provisions()),
-                    config: Arc::new(repos.config()),
-                    months: Arc::new(repos.months()),
-                    // New dependencies
-                    operations: Arc::new(repos.operations()),
-                    declarations: Arc::new(repos.declarations()),
-                    working_days: Arc::new(repos.working_days()),
-                    tax_schedules: Arc::new(repos.tax_schedules()),
-                    simulations: Arc::new(repos.simulations()),
-                    kpis: Arc::new(repos.kpis()),
-                    yearly_planning: Arc::new(repos.yearly_planning()),
-                    // External services
-                    minio_service: Arc::new(minio_service),
-                };
-                let service = AppService::new(deps);
-                app_handle.manage(AppState(Arc::new(service)));
+                let workspace = WorkspaceRegistry::new(&base).active().expect("workspaces init");
+                let service = open_workspace(&base, &workspace).await.expect("db init");
+                println!("🏢 Espace de travail: {}", workspace.name);
+                maintain_workspace(&service).await;
+                app_handle.manage(AppState(std::sync::RwLock::new(service)));
+
+                // Daily snapshot of the active workspace, checked every hour while the app is open
+                let scheduled = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    loop {
+                        match scheduled.state::<AppState>().service().run_scheduled_backup().await {
+                            Ok(Some(run)) => println!("💾 Sauvegarde {} ({} ancienne(s) supprimée(s))", run.backup.name, run.pruned.len()),
+                            Ok(None) => {}
+                            Err(e) => eprintln!("⚠️ Sauvegarde automatique impossible: {}", e),
+                        }
+                        tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                    }
+                });
+                app_handle.manage(DataDir(base));
             });
             Ok(())
         })
@@ -213,6 +464,14 @@ fn main() {
             cmd_month_recap,
             cmd_close_month,
             cmd_month_status,
+            cmd_list_provisions,
+            cmd_mark_provision_paid,
+            cmd_refresh_provision_statuses,
+            cmd_list_declarations,
+            cmd_generate_declaration,
+            cmd_file_declaration,
+            cmd_record_declaration_payment,
+            cmd_refresh_declaration_statuses,
             cmd_get_settings,
             cmd_save_settings,
             cmd_forecast,
@@ -227,6 +486,8 @@ fn main() {
             cmd_list_tax_schedules,
             cmd_get_overdue_schedules,
             cmd_mark_tax_schedule_paid,
+            cmd_record_tax_payment,
+            cmd_list_tax_payments,
             cmd_create_simulation,
             cmd_update_simulation,
             cmd_list_simulations,
@@ -237,6 +498,7 @@ fn main() {
             cmd_calculate_optimal_daily_rate,
             cmd_project_annual_income,
             cmd_compute_tax_schedule,
+            cmd_sync_tax_schedules,
             cmd_optimize_provisions,
             cmd_analyze_working_patterns,
             // Operation commands
@@ -247,6 +509,7 @@ fn main() {
             cmd_list_operations,
             cmd_list_operations_by_type,
             cmd_list_operations_by_payment_month,
+            cmd_query_operations,
             // V2 business logic commands
             cmd_get_dashboard_v2,
             cmd_prepare_vat_v2,
@@ -256,8 +519,72 @@ fn main() {
             cmd_upload_justificatif,
             cmd_upload_file_from_path,
             cmd_delete_justificatif,
+            cmd_get_receipt_url,
             cmd_list_justificatifs_by_month,
             cmd_get_storage_stats,
+            cmd_get_document_backend,
+            cmd_upload_receipt,
+            cmd_verify_receipts,
+            cmd_audit_receipts,
+            cmd_attach_receipt,
+            cmd_purge_orphan_receipts,
+            cmd_relink_dangling_receipts,
+            cmd_month_close_checklist,
+            cmd_migrate_documents,
+            // Invoice issuance
+            cmd_get_issuer_profile,
+            cmd_save_issuer_profile,
+            cmd_issue_invoice,
+            cmd_get_issued_invoice,
+            cmd_list_issued_invoices,
+            cmd_export_e_invoice,
+            // Accountant export
+            cmd_export_accountant_pack,
+            cmd_export_fec,
+            // Legal archive
+            cmd_archive_fiscal_year,
+            cmd_get_archive_report,
+            // Encrypted database
+            cmd_get_database_encryption,
+            cmd_set_database_encryption,
+            cmd_rotate_database_key,
+            // Backups
+            cmd_get_backup_policy,
+            cmd_save_backup_policy,
+            cmd_create_backup,
+            cmd_list_backups,
+            cmd_verify_backup,
+            cmd_restore_backup,
+            // Database doctor
+            cmd_run_database_doctor,
+            // Audit trail
+            cmd_get_entity_history,
+            cmd_list_audit_events,
+            cmd_verify_audit_trail,
+            // Trash and undo
+            cmd_list_trash,
+            cmd_restore_operation,
+            cmd_restore_yearly_planning,
+            cmd_purge_operation,
+            cmd_purge_yearly_planning,
+            cmd_purge_expired_trash,
+            cmd_list_undo_steps,
+            cmd_undo,
+            // Workspaces
+            cmd_list_workspaces,
+            cmd_create_workspace,
+            cmd_update_workspace,
+            cmd_switch_workspace,
+            cmd_get_consolidated_cash_plan,
+            // Dataset export
+            cmd_export_dataset,
+            cmd_import_dataset,
+            // Synchronisation
+            cmd_get_sync_settings,
+            cmd_save_sync_settings,
+            cmd_sync_now,
+            cmd_list_sync_conflicts,
+            cmd_dismiss_sync_conflict,
             // Annual tax declaration
             cmd_get_annual_tax_data,
             // Yearly Planning
@@ -274,62 +601,119 @@ fn main() {
 
 #[tauri::command]
 async fn cmd_create_invoice_simple(state: State<'_, AppState>, dto: CreateInvoiceSimpleDto) -> Result<(), String> {
-    state.0.create_invoice_simple(dto).await.map_err(|e| e.to_string())
+    state.service().create_invoice_simple(dto).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_month_recap(state: State<'_, AppState>, y: i32, m: u8) -> Result<MonthRecap, String> {
-    state.0.month_recap(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().month_recap(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn cmd_close_month(state: State<'_, AppState>, y: i32, m: u8) -> Result<(), String> {
-    state.0.close_month(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+async fn cmd_close_month(state: State<'_, AppState>, y: i32, m: u8) -> Result<domain::MonthClosing, String> {
+    state.service().close_month(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_month_status(state: State<'_, AppState>, y: i32, m: u8) -> Result<domain::MonthStatus, String> {
-    state.0.get_month_status(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().get_month_status(MonthId{ year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_list_provisions(state: State<'_, AppState>, y: Option<i32>, m: Option<u8>) -> Result<Vec<Provision>, String> {
+    let month = match (y, m) {
+        (Some(y), Some(m)) => Some(MonthId { year: y, month: m as u32 }),
+        _ => None,
+    };
+    state.service().list_provisions(month).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_mark_provision_paid(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().mark_provision_paid(uuid).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_refresh_provision_statuses(state: State<'_, AppState>, as_of_date: String) -> Result<Vec<Provision>, String> {
+    let date = NaiveDate::parse_from_str(&as_of_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    state.service().refresh_provision_statuses(date).await.map_err(|e| e.to_string())
+}
+
+// Declaration Commands
+#[tauri::command]
+async fn cmd_list_declarations(state: State<'_, AppState>, year: Option<i32>) -> Result<Vec<Declaration>, String> {
+    state.service().list_declarations(year).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_generate_declaration(state: State<'_, AppState>, declaration_type: String, y: i32, m: u8) -> Result<Declaration, String> {
+    let declaration_type = match declaration_type.as_str() {
+        "vat" => DeclarationType::Vat,
+        "urssaf" => DeclarationType::Urssaf,
+        _ => return Err("Declaration type invalid: must be 'vat' or 'urssaf'".into()),
+    };
+    state.service().generate_declaration(declaration_type, MonthId { year: y, month: m as u32 }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_file_declaration(state: State<'_, AppState>, id: String, filing_date: String) -> Result<Declaration, String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let date = NaiveDate::parse_from_str(&filing_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    state.service().file_declaration(uuid, date).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_record_declaration_payment(state: State<'_, AppState>, id: String, payment_date: String) -> Result<Declaration, String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let date = NaiveDate::parse_from_str(&payment_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    state.service().record_declaration_payment(uuid, date).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_refresh_declaration_statuses(state: State<'_, AppState>, as_of_date: String) -> Result<Vec<Declaration>, String> {
+    let date = NaiveDate::parse_from_str(&as_of_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    state.service().refresh_declaration_statuses(date).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_forecast(state: State<'_, AppState>, y: i32, m: u8, horizon: u32) -> Result<domain::ForecastResult, String> {
-    state.0.forecast(MonthId{ year: y, month: m as u32 }, horizon).await.map_err(|e| e.to_string())
+    state.service().forecast(MonthId{ year: y, month: m as u32 }, horizon).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
-    state.0.get_settings().await.map_err(|e| e.to_string())
+    state.service().get_settings().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_save_settings(state: State<'_, AppState>, s: Settings) -> Result<(), String> {
-    state.0.save_settings(s).await.map_err(|e| e.to_string())
+    state.service().save_settings(s).await.map_err(|e| e.to_string())
 }
 
 // ============ New Tauri Commands ============
 
 #[tauri::command]
 async fn cmd_get_enhanced_dashboard(state: State<'_, AppState>, month: i32, m: u8) -> Result<EnhancedDashboardData, String> {
-    state.0.get_enhanced_dashboard(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().get_enhanced_dashboard(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 // Working Days Commands
 #[tauri::command]
 async fn cmd_create_working_day(state: State<'_, AppState>, dto: CreateWorkingDayDto) -> Result<(), String> {
     let working_day = dto.into_entity().map_err(|e| e.to_string())?;
-    state.0.create_working_day(working_day).await.map_err(|e| e.to_string())
+    state.service().create_working_day(working_day).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_update_working_day(state: State<'_, AppState>, working_day: WorkingDay) -> Result<(), String> {
-    state.0.update_working_day(working_day).await.map_err(|e| e.to_string())
+    state.service().update_working_day(working_day).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_delete_working_day(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
-    state.0.delete_working_day(uuid).await.map_err(|e| e.to_string())
+    state.service().delete_working_day(uuid).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -341,7 +725,7 @@ async fn cmd_list_working_days(
     let start = start_date.map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d")).transpose().map_err(|e| e.to_string())?;
     let end = end_date.map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d")).transpose().map_err(|e| e.to_string())?;
     
-    state.0.list_working_days(start, end).await.map_err(|e| e.to_string())
+    state.service().list_working_days(start, end).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -353,7 +737,7 @@ async fn cmd_get_working_days_stats(
     let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
     let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
     
-    state.0.get_working_days_stats(start, end).await.map_err(|e| e.to_string())
+    state.service().get_working_days_stats(start, end).await.map_err(|e| e.to_string())
 }
 
 // Tax Schedule Commands
@@ -366,20 +750,39 @@ async fn cmd_list_tax_schedules(
     let start = start_date.map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d")).transpose().map_err(|e| e.to_string())?;
     let end = end_date.map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d")).transpose().map_err(|e| e.to_string())?;
     
-    state.0.list_tax_schedules(start, end).await.map_err(|e| e.to_string())
+    state.service().list_tax_schedules(start, end).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_get_overdue_schedules(state: State<'_, AppState>, as_of_date: String) -> Result<Vec<TaxSchedule>, String> {
     let date = NaiveDate::parse_from_str(&as_of_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
-    state.0.get_overdue_schedules(date).await.map_err(|e| e.to_string())
+    state.service().get_overdue_schedules(date).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_mark_tax_schedule_paid(state: State<'_, AppState>, id: String, paid_date: String) -> Result<(), String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
     let date = NaiveDate::parse_from_str(&paid_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
-    state.0.mark_tax_schedule_as_paid(uuid, date).await.map_err(|e| e.to_string())
+    state.service().mark_tax_schedule_as_paid(uuid, date).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_record_tax_payment(
+    state: State<'_, AppState>,
+    id: String,
+    paid_date: String,
+    amount_cents: i64,
+    reference: Option<String>
+) -> Result<(), String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let date = NaiveDate::parse_from_str(&paid_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
+    state.service().record_tax_payment(uuid, date, amount_cents, reference).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_list_tax_payments(state: State<'_, AppState>, id: String) -> Result<Vec<TaxPayment>, String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().list_tax_payments(uuid).await.map_err(|e| e.to_string())
 }
 
 // Simulation Commands
@@ -387,43 +790,43 @@ async fn cmd_mark_tax_schedule_paid(state: State<'_, AppState>, id: String, paid
 async fn cmd_create_simulation(state: State<'_, AppState>, dto: CreateSimulationDto) -> Result<String, String> {
     let simulation = dto.into_entity().map_err(|e| e.to_string())?;
     let id = simulation.id.to_string();
-    state.0.create_simulation(simulation).await.map_err(|e| e.to_string())?;
+    state.service().create_simulation(simulation).await.map_err(|e| e.to_string())?;
     Ok(id)
 }
 
 #[tauri::command]
 async fn cmd_update_simulation(state: State<'_, AppState>, simulation: Simulation) -> Result<(), String> {
-    state.0.update_simulation(simulation).await.map_err(|e| e.to_string())
+    state.service().update_simulation(simulation).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_list_simulations(state: State<'_, AppState>) -> Result<Vec<Simulation>, String> {
-    state.0.list_simulations().await.map_err(|e| e.to_string())
+    state.service().list_simulations().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_run_simulation(state: State<'_, AppState>, id: String) -> Result<SimulationResults, String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
-    state.0.run_simulation(uuid).await.map_err(|e| e.to_string())
+    state.service().run_simulation(uuid).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_delete_simulation(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
-    state.0.delete_simulation(uuid).await.map_err(|e| e.to_string())
+    state.service().delete_simulation(uuid).await.map_err(|e| e.to_string())
 }
 
 // KPI Commands
 #[tauri::command]
 async fn cmd_get_monthly_kpi(state: State<'_, AppState>, year: i32, month: u8) -> Result<Option<MonthlyKPI>, String> {
     let month_id = MonthId { year, month: month as u32 };
-    state.0.get_monthly_kpi(&month_id).await.map_err(|e| e.to_string())
+    state.service().get_monthly_kpi(&month_id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn cmd_compute_monthly_kpis(state: State<'_, AppState>, year: i32, month: u8) -> Result<MonthlyKPI, String> {
     let month_id = MonthId { year, month: month as u32 };
-    state.0.compute_and_save_monthly_kpis(&month_id).await.map_err(|e| e.to_string())
+    state.service().compute_and_save_monthly_kpis(&month_id).await.map_err(|e| e.to_string())
 }
 
 // Business Logic Commands
@@ -434,7 +837,7 @@ async fn cmd_calculate_optimal_daily_rate(
     working_days_per_year: f64,
     annual_expenses_cents: i64
 ) -> Result<DailyRateCalculation, String> {
-    state.0.calculate_optimal_daily_rate(target_annual_income_cents, working_days_per_year, annual_expenses_cents)
+    state.service().calculate_optimal_daily_rate(target_annual_income_cents, working_days_per_year, annual_expenses_cents)
         .await.map_err(|e| e.to_string())
 }
 
@@ -445,7 +848,7 @@ async fn cmd_project_annual_income(
     working_months: u32,
     annual_expenses_cents: i64
 ) -> Result<AnnualIncomeProjection, String> {
-    state.0.project_annual_income(monthly_avg_revenue_cents, working_months, annual_expenses_cents)
+    state.service().project_annual_income(monthly_avg_revenue_cents, working_months, annual_expenses_cents)
         .await.map_err(|e| e.to_string())
 }
 
@@ -457,7 +860,18 @@ async fn cmd_compute_tax_schedule(
     horizon_months: u32
 ) -> Result<Vec<TaxSchedule>, String> {
     let month_id = MonthId { year: current_year, month: current_month as u32 };
-    state.0.compute_tax_schedule(&month_id, horizon_months).await.map_err(|e| e.to_string())
+    state.service().compute_tax_schedule(&month_id, horizon_months).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_sync_tax_schedules(
+    state: State<'_, AppState>,
+    start_year: i32,
+    start_month: u8,
+    horizon_months: u32
+) -> Result<TaxScheduleSyncPlan, String> {
+    let month_id = MonthId { year: start_year, month: start_month as u32 };
+    state.service().sync_tax_schedules(&month_id, horizon_months).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -466,7 +880,7 @@ async fn cmd_optimize_provisions(
     available_cash_cents: i64,
     optimization_horizon_days: u32
 ) -> Result<ProvisionOptimization, String> {
-    state.0.optimize_provisions(available_cash_cents, optimization_horizon_days).await.map_err(|e| e.to_string())
+    state.service().optimize_provisions(available_cash_cents, optimization_horizon_days).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -478,7 +892,7 @@ async fn cmd_analyze_working_patterns(
     let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
     let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d").map_err(|e| e.to_string())?;
     
-    state.0.analyze_working_patterns(start, end).await.map_err(|e| e.to_string())
+    state.service().analyze_working_patterns(start, end).await.map_err(|e| e.to_string())
 }
 
 // ============ Operation Commands ============
@@ -487,7 +901,7 @@ async fn cmd_analyze_working_patterns(
 #[tauri::command]
 async fn cmd_create_operation(state: State<'_, AppState>, dto: CreateOperationDto) -> Result<String, String> {
     let id = uuid::Uuid::new_v4().to_string();
-    state.0.create_operation_from_dto(dto).await.map_err(|e| e.to_string())?;
+    state.service().create_operation_from_dto(dto).await.map_err(|e| e.to_string())?;
     Ok(id)
 }
 
@@ -495,7 +909,7 @@ async fn cmd_create_operation(state: State<'_, AppState>, dto: CreateOperationDt
 #[tauri::command]
 async fn cmd_get_operation(state: State<'_, AppState>, id: String) -> Result<Operation, String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
-    state.0.get_operation(uuid).await.map_err(|e| e.to_string())
+    state.service().get_operation(uuid).await.map_err(|e| e.to_string())
 }
 
 /// Update an existing operation
@@ -503,18 +917,18 @@ async fn cmd_get_operation(state: State<'_, AppState>, id: String) -> Result<Ope
 async fn cmd_update_operation(state: State<'_, AppState>, dto: UpdateOperationDto) -> Result<(), String> {
     // First get the existing operation
     let existing_id = uuid::Uuid::parse_str(&dto.id).map_err(|e| e.to_string())?;
-    let existing = state.0.get_operation(existing_id).await.map_err(|e| e.to_string())?;
+    let existing = state.service().get_operation(existing_id).await.map_err(|e| e.to_string())?;
     
     // Convert DTO to entity using existing operation data
     let operation = dto.into_entity(existing).map_err(|e| e.to_string())?;
-    state.0.update_operation(operation).await.map_err(|e| e.to_string())
+    state.service().update_operation(operation).await.map_err(|e| e.to_string())
 }
 
-/// Delete an operation
+/// Move an operation to the trash
 #[tauri::command]
 async fn cmd_delete_operation(state: State<'_, AppState>, id: String) -> Result<(), String> {
     let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
-    state.0.delete_operation(uuid).await.map_err(|e| e.to_string())
+    state.service().delete_operation(uuid).await.map_err(|e| e.to_string())
 }
 
 /// List operations with optional month filter
@@ -528,7 +942,7 @@ async fn cmd_list_operations(
         (Some(y), Some(m)) => Some(MonthId { year: y, month: m as u32 }),
         _ => None,
     };
-    state.0.list_operations(month_filter).await.map_err(|e| e.to_string())
+    state.service().list_operations(month_filter).await.map_err(|e| e.to_string())
 }
 
 
@@ -551,7 +965,7 @@ async fn cmd_list_operations_by_type(
         _ => None,
     };
     
-    state.0.list_operations_by_type(op_type, month_filter).await.map_err(|e| e.to_string())
+    state.service().list_operations_by_type(op_type, month_filter).await.map_err(|e| e.to_string())
 }
 
 /// List operations by payment month for VAT calculation
@@ -562,7 +976,16 @@ async fn cmd_list_operations_by_payment_month(
     month: u8
 ) -> Result<Vec<Operation>, String> {
     let month_id = MonthId { year, month: month as u32 };
-    state.0.list_operations_by_payment_month(month_id).await.map_err(|e| e.to_string())
+    state.service().list_operations_by_payment_month(month_id).await.map_err(|e| e.to_string())
+}
+
+/// Filtered, sorted and paginated operations, searched by label and client
+#[tauri::command]
+async fn cmd_query_operations(
+    state: State<'_, AppState>,
+    query: OperationQuery
+) -> Result<OperationPage, String> {
+    state.service().query_operations(query).await.map_err(|e| e.to_string())
 }
 
 // ============ V2 Business Logic Commands (Operation-based) ============
@@ -570,30 +993,30 @@ async fn cmd_list_operations_by_payment_month(
 /// Get dashboard summary using the new Operation model
 #[tauri::command]
 async fn cmd_get_dashboard_v2(state: State<'_, AppState>, month: i32, m: u8) -> Result<DashboardSummary, String> {
-    state.0.get_dashboard_v2(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
+    state.service().get_dashboard_v2(MonthId { year: month, month: m as u32 }).await.map_err(|e| e.to_string())
 }
 
 /// Calculate VAT using the new Operation model (more accurate for "encaissements")
 #[tauri::command]
 async fn cmd_prepare_vat_v2(state: State<'_, AppState>, year: i32, month: u8) -> Result<VatReport, String> {
-    state.0.prepare_vat_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
+    state.service().prepare_vat_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
 }
 
 /// Calculate URSSAF using the new Operation model
 #[tauri::command]
 async fn cmd_prepare_urssaf_v2(state: State<'_, AppState>, year: i32, month: u8) -> Result<UrssafReport, String> {
-    state.0.prepare_urssaf_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
+    state.service().prepare_urssaf_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
 }
 
 /// Get month recap using the new Operation model
 #[tauri::command]
 async fn cmd_month_recap_v2(state: State<'_, AppState>, year: i32, month: u8) -> Result<MonthRecap, String> {
-    state.0.month_recap_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
+    state.service().month_recap_v2(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
 }
 
 // ============ File Upload Commands ============
 
-/// Upload justificatif file to MinIO
+/// Upload justificatif file to the document store
 #[tauri::command]
 async fn cmd_upload_justificatif(
     state: State<'_, AppState>, 
@@ -602,29 +1025,35 @@ async fn cmd_upload_justificatif(
     content_type: Option<String>
 ) -> Result<String, String> {
     let bytes = Bytes::from(file_content);
-    state.0.upload_justificatif(bytes, &original_filename, content_type).await.map_err(|e| e.to_string())
+    state.service().upload_justificatif(bytes, &original_filename, content_type).await.map_err(|e| e.to_string())
 }
 
-/// Upload file from path to MinIO (for drag & drop)
+/// Upload file from path to the document store (for drag & drop)
 #[tauri::command]
 async fn cmd_upload_file_from_path(
     state: State<'_, AppState>,
     file_path: String,
     original_filename: String,
     content_type: Option<String>
-) -> Result<String, String> {
+) -> Result<SupplierInvoiceImport, String> {
     // Read the file from the filesystem
     let file_content = std::fs::read(&file_path)
         .map_err(|e| format!("Erreur lecture fichier: {}", e))?;
     
-    let bytes = Bytes::from(file_content);
-    state.0.upload_justificatif(bytes, &original_filename, content_type).await.map_err(|e| e.to_string())
+    // Factur-X invoices come back with a prefilled purchase
+    state.service().import_supplier_invoice(file_content, &original_filename, content_type).await.map_err(|e| e.to_string())
 }
 
-/// Delete justificatif file from MinIO
+/// Delete justificatif file from the document store
 #[tauri::command] 
-async fn cmd_delete_justificatif(state: State<'_, AppState>, file_url: String) -> Result<(), String> {
-    state.0.delete_justificatif(&file_url).await.map_err(|e| e.to_string())
+async fn cmd_delete_justificatif(state: State<'_, AppState>, key: String) -> Result<(), String> {
+    state.service().delete_justificatif(&key).await.map_err(|e| e.to_string())
+}
+
+/// Short-lived URL to open a receipt (presigned when stored on MinIO)
+#[tauri::command]
+async fn cmd_get_receipt_url(state: State<'_, AppState>, key: String) -> Result<String, String> {
+    state.service().receipt_download_url(&key).await.map_err(|e| e.to_string())
 }
 
 /// List justificatifs for a specific month
@@ -634,19 +1063,431 @@ async fn cmd_list_justificatifs_by_month(
     year: i32, 
     month: u8
 ) -> Result<Vec<FileInfo>, String> {
-    state.0.list_justificatifs_by_month(year, month as u32).await.map_err(|e| e.to_string())
+    state.service().list_justificatifs_by_month(year, month as u32).await.map_err(|e| e.to_string())
 }
 
 /// Get storage statistics 
 #[tauri::command]
 async fn cmd_get_storage_stats(state: State<'_, AppState>) -> Result<StorageStats, String> {
-    state.0.get_storage_stats().await.map_err(|e| e.to_string())
+    state.service().get_storage_stats().await.map_err(|e| e.to_string())
+}
+
+/// Upload a receipt, reusing the stored object when the same content was already uploaded
+#[tauri::command]
+async fn cmd_upload_receipt(
+    state: State<'_, AppState>,
+    file_content: Vec<u8>,
+    original_filename: String,
+    content_type: Option<String>
+) -> Result<ReceiptUpload, String> {
+    state.service().upload_receipt(file_content, &original_filename, content_type).await.map_err(|e| e.to_string())
+}
+
+/// Re-hash all receipts and report missing or altered documents
+#[tauri::command]
+async fn cmd_verify_receipts(state: State<'_, AppState>) -> Result<ReceiptVerificationReport, String> {
+    state.service().verify_receipts().await.map_err(|e| e.to_string())
+}
+
+/// Receipt consistency report, for one month or the whole store
+#[tauri::command]
+async fn cmd_audit_receipts(state: State<'_, AppState>, year: Option<i32>, month: Option<u8>) -> Result<ReceiptAudit, String> {
+    let month = match (year, month) {
+        (Some(y), Some(m)) => Some(MonthId { year: y, month: m as u32 }),
+        _ => None,
+    };
+    state.service().audit_receipts(month).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_attach_receipt(state: State<'_, AppState>, operation_id: String, key: String) -> Result<(), String> {
+    let id = uuid::Uuid::parse_str(&operation_id).map_err(|e| format!("ID invalide: {}", e))?;
+    state.service().attach_receipt(id, &key).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_purge_orphan_receipts(state: State<'_, AppState>, keys: Vec<String>) -> Result<u32, String> {
+    state.service().purge_orphan_receipts(keys).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_relink_dangling_receipts(state: State<'_, AppState>) -> Result<u32, String> {
+    state.service().relink_dangling_receipts().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_month_close_checklist(state: State<'_, AppState>, year: i32, month: u8) -> Result<MonthCloseChecklist, String> {
+    state.service().month_close_checklist(MonthId { year, month: month as u32 }).await.map_err(|e| e.to_string())
+}
+
+/// Name of the active document backend
+#[tauri::command]
+async fn cmd_get_document_backend(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.service().document_backend().to_string())
+}
+
+/// Copy the documents of every workspace to another backend ("local" or "minio"), which becomes active on next start
+#[tauri::command]
+async fn cmd_migrate_documents(
+    state: State<'_, AppState>,
+    data_dir: State<'_, DataDir>,
+    target: String
+) -> Result<DocumentMigrationReport, String> {
+    let list = WorkspaceRegistry::new(&data_dir.0).load().map_err(|e| e.to_string())?;
+    let mut total: Option<DocumentMigrationReport> = None;
+    for workspace in list.workspaces {
+        let service = if workspace.id == list.active { state.service() } else { open_workspace(&data_dir.0, &workspace).await? };
+        let target_store = open_document_store(&target, &data_dir.0, &workspace)?;
+        let report = service.migrate_documents(target_store).await.map_err(|e| e.to_string())?;
+        total = Some(match total {
+            Some(total) => DocumentMigrationReport {
+                files_copied: total.files_copied + report.files_copied,
+                bytes_copied: total.bytes_copied + report.bytes_copied,
+                ..total
+            },
+            None => report,
+        });
+    }
+    std::fs::write(data_dir.0.join(DOCUMENT_BACKEND_FILE), &target)
+        .map_err(|e| format!("Erreur enregistrement du backend: {}", e))?;
+    total.ok_or_else(|| "Aucun espace de travail".to_string())
+}
+
+// ============ Invoice Issuance Commands ============
+
+#[tauri::command]
+async fn cmd_get_issuer_profile(state: State<'_, AppState>) -> Result<Option<IssuerProfile>, String> {
+    state.service().get_issuer_profile().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_save_issuer_profile(state: State<'_, AppState>, profile: IssuerProfile) -> Result<(), String> {
+    state.service().save_issuer_profile(profile).await.map_err(|e| e.to_string())
+}
+
+/// Number the invoice, store its PDF and record the sale operation
+#[tauri::command]
+async fn cmd_issue_invoice(state: State<'_, AppState>, draft: InvoiceDraft) -> Result<IssuedInvoice, String> {
+    state.service().issue_invoice(draft).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_get_issued_invoice(state: State<'_, AppState>, id: String) -> Result<IssuedInvoice, String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().get_issued_invoice(uuid).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_list_issued_invoices(state: State<'_, AppState>, year: Option<i32>) -> Result<Vec<IssuedInvoice>, String> {
+    state.service().list_issued_invoices(year).await.map_err(|e| e.to_string())
+}
+
+/// Factur-X PDF, CII or UBL XML of a sale; `sale` completes one not issued from the application
+#[tauri::command]
+async fn cmd_export_e_invoice(state: State<'_, AppState>, operation_id: String, format: EInvoiceFormat, sale: Option<SaleInvoiceDetails>) -> Result<EInvoiceExport, String> {
+    let uuid = uuid::Uuid::parse_str(&operation_id).map_err(|e| e.to_string())?;
+    state.service().export_e_invoice(uuid, format, sale).await.map_err(|e| e.to_string())
+}
+
+// ============ Accountant Export Commands ============
+
+/// Write the accountant ZIP (journal, reports, receipts, manifest) for a month, quarter or year
+#[tauri::command]
+async fn cmd_export_accountant_pack(state: State<'_, AppState>, period: ExportPeriod, target_path: String) -> Result<ExportManifest, String> {
+    state.service().export_accountant_pack(period, std::path::Path::new(&target_path)).await.map_err(|e| e.to_string())
+}
+
+/// Write the validated FEC of a fiscal year into the chosen folder (`SIRENFECAAAAMMJJ.txt`)
+#[tauri::command]
+async fn cmd_export_fec(state: State<'_, AppState>, year: i32, target_dir: String) -> Result<FecExport, String> {
+    state.service().export_fec(year, std::path::Path::new(&target_dir)).await.map_err(|e| e.to_string())
+}
+
+// ============ Legal Archive Commands ============
+
+/// Make an ended fiscal year read-only and retain its documents for 10 years
+#[tauri::command]
+async fn cmd_archive_fiscal_year(state: State<'_, AppState>, year: i32) -> Result<ArchivedFiscalYear, String> {
+    state.service().archive_fiscal_year(year).await.map_err(|e| e.to_string())
+}
+
+/// What is retained until when, with the integrity of each archived document
+#[tauri::command]
+async fn cmd_get_archive_report(state: State<'_, AppState>) -> Result<ArchiveReport, String> {
+    state.service().archive_report().await.map_err(|e| e.to_string())
+}
+
+// ============ Encrypted Database Commands ============
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DatabaseEncryptionStatus {
+    /// Mode applied at next startup: none, keyring or passphrase
+    mode: String,
+    encrypted: bool,
+    key_rotation_pending: bool,
+}
+
+#[tauri::command]
+async fn cmd_get_database_encryption(data_dir: State<'_, DataDir>) -> Result<DatabaseEncryptionStatus, String> {
+    let dir = active_workspace_dir(&data_dir.0)?;
+    Ok(DatabaseEncryptionStatus {
+        mode: database_encryption(&dir),
+        encrypted: is_database_encrypted(&dir.join("data.sqlite")).map_err(|e| e.to_string())?,
+        key_rotation_pending: dir.join(DB_ROTATE_KEY_FILE).exists(),
+    })
+}
+
+/// The active workspace's database is encrypted or decrypted in place when next opened (it cannot be swapped while open)
+#[tauri::command]
+async fn cmd_set_database_encryption(data_dir: State<'_, DataDir>, mode: String) -> Result<(), String> {
+    let mode = mode.trim().to_lowercase();
+    if !matches!(mode.as_str(), "none" | "keyring" | "passphrase") {
+        return Err(format!("Mode de chiffrement inconnu: '{}' (attendu: none, keyring ou passphrase)", mode));
+    }
+    if mode == "passphrase" && std::env::var("DB_PASSPHRASE").is_err() {
+        return Err("Définissez DB_PASSPHRASE avant de choisir le chiffrement par phrase secrète".to_string());
+    }
+    std::fs::write(active_workspace_dir(&data_dir.0)?.join(DB_ENCRYPTION_FILE), &mode)
+        .map_err(|e| format!("Erreur enregistrement du mode de chiffrement: {}", e))
+}
+
+/// Generate a new keyring key at next startup (passphrases rotate through DB_NEW_PASSPHRASE)
+#[tauri::command]
+async fn cmd_rotate_database_key(data_dir: State<'_, DataDir>) -> Result<(), String> {
+    let dir = active_workspace_dir(&data_dir.0)?;
+    if database_encryption(&dir) != "keyring" {
+        return Err("Renouvellement automatique réservé à la clé du trousseau: utilisez DB_NEW_PASSPHRASE".to_string());
+    }
+    std::fs::write(dir.join(DB_ROTATE_KEY_FILE), "")
+        .map_err(|e| format!("Erreur planification du changement de clé: {}", e))
+}
+
+// ============ Backup Commands ============
+
+#[tauri::command]
+async fn cmd_get_backup_policy(state: State<'_, AppState>) -> Result<BackupPolicy, String> {
+    state.service().get_backup_policy().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_save_backup_policy(state: State<'_, AppState>, policy: BackupPolicy) -> Result<(), String> {
+    state.service().save_backup_policy(policy).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_create_backup(state: State<'_, AppState>) -> Result<BackupRun, String> {
+    state.service().create_backup(BackupReason::Manual).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_list_backups(state: State<'_, AppState>) -> Result<Vec<BackupInfo>, String> {
+    state.service().list_backups().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_verify_backup(state: State<'_, AppState>, name: String) -> Result<BackupVerification, String> {
+    state.service().verify_backup(&name).await.map_err(|e| e.to_string())
+}
+
+/// The verified snapshot replaces the database at next startup
+#[tauri::command]
+async fn cmd_restore_backup(state: State<'_, AppState>, name: String) -> Result<BackupVerification, String> {
+    state.service().restore_backup(&name).await.map_err(|e| e.to_string())
+}
+
+// ============ Database Doctor Commands ============
+
+/// Without `repair`, only lists the malformed rows and the fixes the doctor would apply
+#[tauri::command]
+async fn cmd_run_database_doctor(state: State<'_, AppState>, repair: bool) -> Result<DoctorReport, String> {
+    state.service().run_database_doctor(repair).await.map_err(|e| e.to_string())
+}
+
+// ============ Audit Trail Commands ============
+
+/// Every recorded change of one entity (operation id, declaration id, "settings", year…), most recent first
+#[tauri::command]
+async fn cmd_get_entity_history(state: State<'_, AppState>, entity: AuditEntity, entity_id: String) -> Result<Vec<AuditEvent>, String> {
+    state.service().entity_history(entity, &entity_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_list_audit_events(state: State<'_, AppState>, query: AuditQuery) -> Result<Vec<AuditEvent>, String> {
+    state.service().list_audit_events(query).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_verify_audit_trail(state: State<'_, AppState>) -> Result<AuditChainCheck, String> {
+    state.service().verify_audit_trail().await.map_err(|e| e.to_string())
+}
+
+// ============ Trash and Undo Commands ============
+
+#[tauri::command]
+async fn cmd_list_trash(state: State<'_, AppState>) -> Result<Trash, String> {
+    state.service().list_trash().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_restore_operation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().restore_operation(uuid).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_restore_yearly_planning(state: State<'_, AppState>, year: i32) -> Result<(), String> {
+    state.service().restore_yearly_planning(year).await.map_err(|e| e.to_string())
+}
+
+/// Delete a trashed operation for good
+#[tauri::command]
+async fn cmd_purge_operation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().purge_operation(uuid).await.map_err(|e| e.to_string())
+}
+
+/// Delete a trashed yearly planning for good
+#[tauri::command]
+async fn cmd_purge_yearly_planning(state: State<'_, AppState>, year: i32) -> Result<(), String> {
+    state.service().purge_yearly_planning(year).await.map_err(|e| e.to_string())
+}
+
+/// Purge what has been in the trash for more than 30 days (also done at startup)
+#[tauri::command]
+async fn cmd_purge_expired_trash(state: State<'_, AppState>) -> Result<TrashPurge, String> {
+    state.service().purge_expired_trash().await.map_err(|e| e.to_string())
+}
+
+/// Actions that can be undone, most recent first
+#[tauri::command]
+async fn cmd_list_undo_steps(state: State<'_, AppState>) -> Result<Vec<UndoStep>, String> {
+    Ok(state.service().list_undo_steps())
+}
+
+/// Undo the most recent action, returning what was undone
+#[tauri::command]
+async fn cmd_undo(state: State<'_, AppState>) -> Result<UndoStep, String> {
+    state.service().undo_last().await.map_err(|e| e.to_string())
+}
+
+// ============ Workspace Commands ============
+
+#[tauri::command]
+async fn cmd_list_workspaces(data_dir: State<'_, DataDir>) -> Result<WorkspaceList, String> {
+    WorkspaceRegistry::new(&data_dir.0).load().map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CreateWorkspaceDto {
+    id: String,
+    name: String,
+    siren: Option<String>,
+    regime: BusinessRegime,
+}
+
+/// Register a company with its own empty database, settings and documents
+#[tauri::command]
+async fn cmd_create_workspace(data_dir: State<'_, DataDir>, dto: CreateWorkspaceDto) -> Result<Workspace, String> {
+    let workspace = Workspace::new(&dto.id, &dto.name, dto.siren, dto.regime, chrono::Utc::now().naive_utc())
+        .map_err(|e| e.to_string())?;
+    WorkspaceRegistry::new(&data_dir.0).create(workspace.clone()).map_err(|e| e.to_string())?;
+    Ok(workspace)
+}
+
+/// Rename a workspace or change its SIREN and regime
+#[tauri::command]
+async fn cmd_update_workspace(data_dir: State<'_, DataDir>, workspace: Workspace) -> Result<Workspace, String> {
+    WorkspaceRegistry::new(&data_dir.0).update_identity(workspace).map_err(|e| e.to_string())
+}
+
+/// Open another workspace: every following command runs on its database and documents
+#[tauri::command]
+async fn cmd_switch_workspace(state: State<'_, AppState>, data_dir: State<'_, DataDir>, id: String) -> Result<Workspace, String> {
+    let registry = WorkspaceRegistry::new(&data_dir.0);
+    let workspace = registry.get(&id).map_err(|e| e.to_string())?;
+    let service = open_workspace(&data_dir.0, &workspace).await?;
+    registry.set_active(&workspace.id).map_err(|e| e.to_string())?;
+    maintain_workspace(&service).await;
+    *state.0.write().map_err(|e| e.to_string())? = service;
+    println!("🏢 Espace de travail: {}", workspace.name);
+    Ok(workspace)
+}
+
+/// Forecast of every workspace and their sum; inactive workspaces are opened just for the computation
+#[tauri::command]
+async fn cmd_get_consolidated_cash_plan(
+    state: State<'_, AppState>,
+    data_dir: State<'_, DataDir>,
+    y: i32,
+    m: u8,
+    horizon: u32
+) -> Result<ConsolidatedCashPlan, String> {
+    let start = MonthId { year: y, month: m as u32 };
+    let list = WorkspaceRegistry::new(&data_dir.0).load().map_err(|e| e.to_string())?;
+    let mut plans = Vec::new();
+    for workspace in list.workspaces {
+        let service = if workspace.id == list.active { state.service() } else { open_workspace(&data_dir.0, &workspace).await? };
+        plans.push(service.workspace_cash_plan(workspace, start.clone(), horizon).await.map_err(|e| e.to_string())?);
+    }
+    Ok(consolidate_cash_plans(&start, plans))
+}
+
+// ============ Dataset Export Commands ============
+
+/// Write the whole dataset of the active workspace as versioned JSON
+#[tauri::command]
+async fn cmd_export_dataset(state: State<'_, AppState>, target_path: String) -> Result<(), String> {
+    let dump = state.service().export_dataset().await.map_err(|e| e.to_string())?;
+    let content = serde_json::to_vec_pretty(&dump).map_err(|e| e.to_string())?;
+    std::fs::write(&target_path, content).map_err(|e| format!("Erreur écriture de l'export: {}", e))
+}
+
+/// Load an export into the active workspace, after a backup; every record is checked before anything is written
+#[tauri::command]
+async fn cmd_import_dataset(state: State<'_, AppState>, source_path: String, mode: DatasetImportMode) -> Result<DatasetImportReport, String> {
+    let content = std::fs::read(&source_path).map_err(|e| format!("Erreur lecture de l'export: {}", e))?;
+    state.service().import_dataset(&content, mode).await.map_err(|e| e.to_string())
+}
+
+// ============ Synchronisation Commands ============
+
+#[tauri::command]
+async fn cmd_get_sync_settings(state: State<'_, AppState>) -> Result<Option<SyncSettings>, String> {
+    state.service().get_sync_settings().await.map_err(|e| e.to_string())
+}
+
+/// Name this installation and choose the folder shared with the others (Syncthing, NAS)
+#[tauri::command]
+async fn cmd_save_sync_settings(state: State<'_, AppState>, settings: SyncSettings) -> Result<(), String> {
+    state.service().save_sync_settings(settings).await.map_err(|e| e.to_string())
+}
+
+/// Publish the local changes to the shared folder and replay those of the other installations
+#[tauri::command]
+async fn cmd_sync_now(state: State<'_, AppState>) -> Result<SyncReport, String> {
+    let service = state.service();
+    let settings = service.get_sync_settings().await.map_err(|e| e.to_string())?
+        .ok_or("Synchronisation non configurée")?;
+    let log = FolderChangeLog::new(&settings.shared_dir).map_err(|e| e.to_string())?;
+    service.sync_now(&log).await.map_err(|e| e.to_string())
+}
+
+/// Values overwritten or kept during synchronisation, for review
+#[tauri::command]
+async fn cmd_list_sync_conflicts(state: State<'_, AppState>) -> Result<Vec<SyncConflict>, String> {
+    state.service().list_sync_conflicts().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cmd_dismiss_sync_conflict(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = uuid::Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    state.service().dismiss_sync_conflict(uuid).await.map_err(|e| e.to_string())
 }
 
 /// Get annual tax declaration data for a given year
 #[tauri::command]
 async fn cmd_get_annual_tax_data(state: State<'_, AppState>, year: i32) -> Result<AnnualTaxData, String> {
-    state.0.get_annual_tax_data(year).await.map_err(|e| e.to_string())
+    state.service().get_annual_tax_data(year).await.map_err(|e| e.to_string())
 }
 
 // ============ Yearly Planning Commands ============
@@ -654,31 +1495,31 @@ async fn cmd_get_annual_tax_data(state: State<'_, AppState>, year: i32) -> Resul
 /// Create a yearly planning
 #[tauri::command]
 async fn cmd_create_yearly_planning(state: State<'_, AppState>, dto: CreateYearlyPlanningDto) -> Result<(), String> {
-    state.0.create_yearly_planning(dto).await.map_err(|e| e.to_string())
+    state.service().create_yearly_planning(dto).await.map_err(|e| e.to_string())
 }
 
 /// Update a yearly planning
 #[tauri::command]
 async fn cmd_update_yearly_planning(state: State<'_, AppState>, dto: UpdateYearlyPlanningDto) -> Result<(), String> {
-    state.0.update_yearly_planning(dto).await.map_err(|e| e.to_string())
+    state.service().update_yearly_planning(dto).await.map_err(|e| e.to_string())
 }
 
 /// Get yearly planning for a specific year
 #[tauri::command]
 async fn cmd_get_yearly_planning(state: State<'_, AppState>, year: i32) -> Result<Option<YearlyPlanning>, String> {
-    state.0.get_yearly_planning(year).await.map_err(|e| e.to_string())
+    state.service().get_yearly_planning(year).await.map_err(|e| e.to_string())
 }
 
-/// Delete yearly planning for a specific year
+/// Move the yearly planning of a specific year to the trash
 #[tauri::command]
 async fn cmd_delete_yearly_planning(state: State<'_, AppState>, year: i32) -> Result<(), String> {
-    state.0.delete_yearly_planning(year).await.map_err(|e| e.to_string())
+    state.service().delete_yearly_planning(year).await.map_err(|e| e.to_string())
 }
 
 /// List all yearly plannings
 #[tauri::command]
 async fn cmd_list_yearly_plannings(state: State<'_, AppState>) -> Result<Vec<YearlyPlanning>, String> {
-    state.0.list_yearly_plannings().await.map_err(|e| e.to_string())
+    state.service().list_yearly_plannings().await.map_err(|e| e.to_string())
 }
 
 /// Update a specific month planning
@@ -689,5 +1530,5 @@ async fn cmd_update_month_planning(
     month: u32, 
     dto: UpdateMonthPlanningDto
 ) -> Result<(), String> {
-    state.0.update_month_planning(year, month, dto).await.map_err(|e| e.to_string())
+    state.service().update_month_planning(year, month, dto).await.map_err(|e| e.to_string())
 }
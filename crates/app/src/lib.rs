@@ -1,13 +1,17 @@
 use domain::*;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use infra::MinioService;
-use chrono::Datelike;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use chrono::{Datelike, Timelike};
+
+tokio::task_local! {
+    // Changes recorded by the undoable use case being run, see `AppService::undoable`
+    static UNDO_CHANGES: RefCell<Vec<UndoChange>>;
+}
 
 #[derive(Clone)]
 pub struct AppDeps {
-    pub invoices: Arc<dyn InvoiceRepo>,
-    pub expenses: Arc<dyn ExpenseRepo>,
     pub provisions: Arc<dyn ProvisionRepo>,
     pub config: Arc<dyn ConfigRepo>,
     pub months: Arc<dyn MonthRepo>,
@@ -19,31 +23,47 @@ pub struct AppDeps {
     pub simulations: Arc<dyn SimulationRepo>,
     pub kpis: Arc<dyn KPIRepo>,
     pub yearly_planning: Arc<dyn YearlyPlanningRepo>,
+    pub issued_invoices: Arc<dyn IssuedInvoiceRepo>,
+    pub archives: Arc<dyn ArchiveRepo>,
+    pub backups: Arc<dyn BackupRepo>,
+    pub audit: Arc<dyn AuditRepo>,
+    pub doctor: Arc<dyn DoctorRepo>,
+    pub sync: Arc<dyn SyncRepo>,
+    pub units_of_work: Arc<dyn UnitOfWorkFactory>,
     // External services
-    pub minio_service: Arc<MinioService>,
+    pub documents: Arc<dyn DocumentStore>,
 }
 
 
 #[derive(Clone)]
 pub struct AppService {
     deps: AppDeps,
+    undo: Arc<Mutex<VecDeque<UndoStep>>>, // Most recent last
+    in_transaction: bool, // Transactional repositories come from a unit of work, see `AppService::transaction`
 }
 
 impl AppService {
-    pub fn new(deps: AppDeps) -> Self { Self { deps } }
+    pub fn new(deps: AppDeps) -> Self { Self { deps, undo: Arc::default(), in_transaction: false } }
+
+    // Legacy invoice/expense API, backed by operations since migration 0007
 
+    /// Dashboard as the legacy API computed it: every operation read as a legacy invoice or expense,
+    /// so VAT is due on payment whatever the operation says. `get_dashboard_v2` follows each
+    /// operation's VAT regime and differs for VAT on debits
     pub async fn get_dashboard(&self, month: MonthId) -> DomainResult<DashboardSummary> {
-        let (invoices, expenses, provisions, settings) = tokio::try_join!(
-            self.deps.invoices.list_invoices(None),
-            self.deps.expenses.list_expenses(None),
+        let (operations, provisions, settings) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
             self.deps.provisions.list_provisions(None),
             self.deps.config.load_settings(),
-        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        )?;
+        let invoices: Vec<Invoice> = operations.iter().filter(|op| op.operation_type == OperationType::Sale).map(invoice_from_operation).collect();
+        let expenses: Vec<Expense> = operations.iter().filter(|op| op.operation_type == OperationType::Purchase).map(expense_from_operation).collect();
         Ok(compute_dashboard(&month, &invoices, &expenses, &provisions, &settings))
     }
 
     pub async fn list_invoices(&self, month: Option<MonthId>) -> DomainResult<Vec<Invoice>> {
-        self.deps.invoices.list_invoices(month).await
+        let sales = self.deps.operations.list_operations_by_type(OperationType::Sale, None).await?;
+        Ok(sales.iter().filter(|op| paid_in(op, month.as_ref())).map(invoice_from_operation).collect())
     }
 
     pub async fn create_invoice(&self, mut inv: Invoice) -> DomainResult<()> {
@@ -52,11 +72,13 @@ impl AppService {
             inv.amount_tva = ((inv.amount_ht as i128) * (inv.vat_rate_ppm as i128) / 1_000_000i128) as i64;
         }
         if inv.amount_ttc == 0 { inv.amount_ttc = inv.amount_ht + inv.amount_tva; }
-        self.deps.invoices.create_invoice(inv).await
+        let now = chrono::Utc::now().naive_utc();
+        self.undoable("create_invoice", self.insert_operation("create_invoice", operation_from_invoice(&inv, now))).await
     }
 
     pub async fn list_expenses(&self, month: Option<MonthId>) -> DomainResult<Vec<Expense>> {
-        self.deps.expenses.list_expenses(month).await
+        let purchases = self.deps.operations.list_operations_by_type(OperationType::Purchase, None).await?;
+        Ok(purchases.iter().filter(|op| paid_in(op, month.as_ref())).map(expense_from_operation).collect())
     }
 
     pub async fn create_expense(&self, mut exp: Expense) -> DomainResult<()> {
@@ -64,21 +86,16 @@ impl AppService {
             exp.amount_tva = ((exp.amount_ht as i128) * (exp.vat_rate_ppm as i128) / 1_000_000i128) as i64;
         }
         if exp.amount_ttc == 0 { exp.amount_ttc = exp.amount_ht + exp.amount_tva; }
-        self.deps.expenses.create_expense(exp).await
+        let now = chrono::Utc::now().naive_utc();
+        self.undoable("create_expense", self.insert_operation("create_expense", operation_from_expense(&exp, now))).await
     }
 
     pub async fn prepare_vat(&self, month: MonthId) -> DomainResult<VatReport> {
-        let (invoices, expenses) = tokio::try_join!(
-            self.deps.invoices.list_invoices(Some(month.clone())),
-            self.deps.expenses.list_expenses(Some(month.clone())),
-        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
-        Ok(compute_vat_for_month(&month, &invoices, &expenses))
+        self.prepare_vat_v2(month).await
     }
 
     pub async fn prepare_urssaf(&self, month: MonthId) -> DomainResult<UrssafReport> {
-        let settings = self.deps.config.load_settings().await?;
-        let invoices = self.deps.invoices.list_invoices(Some(month.clone())).await?;
-        Ok(compute_urssaf_for_month(&month, &invoices, settings.urssaf_rate_ppm))
+        self.prepare_urssaf_v2(month).await
     }
 
     pub async fn get_settings(&self) -> DomainResult<Settings> {
@@ -86,16 +103,15 @@ impl AppService {
     }
 
     pub async fn save_settings(&self, s: Settings) -> DomainResult<()> {
-        self.deps.config.save_settings(s).await
+        self.undoable("save_settings", async {
+            let before = self.deps.config.load_settings().await?;
+            self.deps.config.save_settings(s.clone()).await?;
+            self.record_audit("save_settings", AuditEntity::Settings, "settings", Some(&before), Some(&s)).await
+        }).await
     }
 
     pub async fn month_recap(&self, month: MonthId) -> DomainResult<MonthRecap> {
-        let settings = self.deps.config.load_settings().await?;
-        let (invoices, expenses) = tokio::try_join!(
-            self.deps.invoices.list_invoices(Some(month.clone())),
-            self.deps.expenses.list_expenses(Some(month.clone())),
-        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
-        Ok(compute_month_recap(&month, &invoices, &expenses, &settings))
+        self.month_recap_v2(month).await
     }
 
     pub async fn forecast(&self, start: MonthId, horizon: u32) -> DomainResult<ForecastResult> {
@@ -107,15 +123,222 @@ impl AppService {
         self.deps.months.get_status(&month).await
     }
 
-    pub async fn close_month(&self, month: MonthId) -> DomainResult<()> {
-        let now = chrono::Utc::now().naive_utc();
-        self.deps.months.close_month(&month, now).await
+    /// Close a month and set aside its VAT and URSSAF provisions
+    /// The provisions and the closing are committed together, then the database is backed up;
+    /// a failed backup is reported, the month staying closed
+    pub async fn close_month(&self, month: MonthId) -> DomainResult<MonthClosing> {
+        let closed = month.clone();
+        self.transaction(|app| async move {
+            let (operations, existing, settings) = tokio::try_join!(
+                app.deps.operations.list_operations(None),
+                app.deps.provisions.list_provisions(Some(month.clone())),
+                app.deps.config.load_settings(),
+            ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+            let vat = compute_vat_for_month_v2(&month, &operations);
+            let urssaf = compute_urssaf_for_month_v2(&month, &operations, settings.urssaf_rate_ppm);
+            let today = chrono::Local::now().naive_local().date();
+            for provision in generate_provisions_for_month(&month, &vat, &urssaf, &settings, &existing, today) {
+                let before = existing.iter().find(|p| p.id == provision.id);
+                app.store_provision("close_month", provision.clone(), before).await?;
+            }
+
+            let now = chrono::Utc::now().naive_utc();
+            app.deps.months.close_month(&month, now).await
+        }).await?;
+        let (backup, backup_error) = match self.create_backup(BackupReason::MonthClose).await {
+            Ok(run) => (Some(run), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Ok(MonthClosing { month: closed, backup, backup_error })
+    }
+
+    /// Review a month before closing it: tax amounts and receipt consistency
+    pub async fn month_close_checklist(&self, month: MonthId) -> DomainResult<MonthCloseChecklist> {
+        let (operations, trashed, files, status, settings) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.operations.list_trashed_operations(),
+            self.deps.documents.list_all(),
+            self.deps.months.get_status(&month),
+            self.deps.config.load_settings(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+        let receipts = audit_receipts(Some(&month), &operations, &trashed, &files);
+        Ok(MonthCloseChecklist {
+            vat: compute_vat_for_month_v2(&month, &operations),
+            urssaf: compute_urssaf_for_month_v2(&month, &operations, settings.urssaf_rate_ppm),
+            already_closed: status.closed_at.is_some(),
+            ready: receipts.is_ready_for_close(),
+            receipts,
+            month,
+        })
+    }
+
+    // ============ Provision Use Cases ============
+
+    pub async fn list_provisions(&self, month: Option<MonthId>) -> DomainResult<Vec<Provision>> {
+        self.deps.provisions.list_provisions(month).await
+    }
+
+    /// Mark a provision as paid once the corresponding VAT/URSSAF payment is recorded
+    pub async fn mark_provision_paid(&self, id: uuid::Uuid) -> DomainResult<()> {
+        let before = self.deps.provisions.list_provisions(None).await?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or(DomainError::NotFound)?;
+        let mut provision = before.clone();
+        provision.status = ProvisionStatus::Paid;
+        provision.updated_at = chrono::Utc::now().naive_utc();
+        self.undoable("mark_provision_paid", self.store_provision("mark_provision_paid", provision, Some(&before))).await
+    }
+
+    /// Flag unpaid provisions past their due date as overdue and persist the changes
+    pub async fn refresh_provision_statuses(&self, today: chrono::NaiveDate) -> DomainResult<Vec<Provision>> {
+        let mut provisions = self.deps.provisions.list_provisions(None).await?;
+        for provision in provisions.iter_mut() {
+            let status = provision_status_as_of(provision, today);
+            if status != provision.status {
+                let before = provision.clone();
+                provision.status = status;
+                provision.updated_at = chrono::Utc::now().naive_utc();
+                self.store_provision("refresh_provision_statuses", provision.clone(), Some(&before)).await?;
+            }
+        }
+        Ok(provisions)
+    }
+
+    // ============ Declaration Use Cases ============
+
+    pub async fn list_declarations(&self, year: Option<i32>) -> DomainResult<Vec<Declaration>> {
+        self.deps.declarations.list_declarations(year).await
+    }
+
+    pub async fn get_declaration(&self, id: uuid::Uuid) -> DomainResult<Declaration> {
+        self.deps.declarations.get_declaration(id).await
+    }
+
+    /// Generate (or refresh) the declaration of a period from the computed reports
+    /// Once filed, the declared amount is frozen and any difference is kept in `recomputed_amount_cents`
+    pub async fn generate_declaration(&self, declaration_type: DeclarationType, month: MonthId) -> DomainResult<Declaration> {
+        self.undoable("generate_declaration", async move {
+            let (operations, settings, existing) = tokio::try_join!(
+                self.deps.operations.list_operations(None),
+                self.deps.config.load_settings(),
+                self.deps.declarations.get_declaration_by_period(declaration_type.clone(), month.year, month.month),
+            ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+            let vat = compute_vat_for_month_v2(&month, &operations);
+            let urssaf = compute_urssaf_for_month_v2(&month, &operations, settings.urssaf_rate_ppm);
+            let (amount_cents, due_date) = declaration_amount_and_due_date(&declaration_type, &month, &vat, &urssaf, &settings);
+            let today = chrono::Local::now().naive_local().date();
+            let now = chrono::Utc::now().naive_utc();
+
+            let declaration = match existing {
+                Some(mut declaration) => {
+                    if declaration.filing_date.is_none() && declaration.status != DeclarationStatus::Paid {
+                        declaration.amount_due_cents = amount_cents;
+                        declaration.due_date = due_date;
+                        declaration.recomputed_amount_cents = None;
+                    } else {
+                        declaration.recomputed_amount_cents = (amount_cents != declaration.amount_due_cents).then_some(amount_cents);
+                    }
+                    declaration.status = declaration_status_as_of(&declaration, today);
+                    declaration.updated_at = now;
+                    self.store_declaration("generate_declaration", declaration.clone()).await?;
+                    declaration
+                }
+                None => {
+                    let mut declaration = Declaration {
+                        id: uuid::Uuid::new_v4(),
+                        declaration_type,
+                        period_year: month.year,
+                        period_month: month.month,
+                        amount_due_cents: amount_cents,
+                        due_date,
+                        filing_date: None,
+                        payment_date: None,
+                        status: DeclarationStatus::Pending,
+                        recomputed_amount_cents: None,
+                        created_at: now,
+                        updated_at: now,
+                    };
+                    declaration.status = declaration_status_as_of(&declaration, today);
+                    self.deps.declarations.create_declaration(declaration.clone()).await?;
+                    self.record_audit("generate_declaration", AuditEntity::Declaration, declaration.id, None, Some(&declaration)).await?;
+                    declaration
+                }
+            };
+            Ok(declaration)
+        }).await
+    }
+
+    /// Record that a declaration was filed: its amount is frozen from now on
+    pub async fn file_declaration(&self, id: uuid::Uuid, filing_date: chrono::NaiveDate) -> DomainResult<Declaration> {
+        self.undoable("file_declaration", async move {
+            let mut declaration = self.deps.declarations.get_declaration(id).await?;
+            if declaration.status == DeclarationStatus::Paid {
+                return Err(DomainError::Validation("Déclaration déjà payée".into()));
+            }
+            declaration.filing_date = Some(filing_date);
+            declaration.status = DeclarationStatus::Filed;
+            declaration.status = declaration_status_as_of(&declaration, chrono::Local::now().naive_local().date());
+            declaration.updated_at = chrono::Utc::now().naive_utc();
+            self.store_declaration("file_declaration", declaration.clone()).await?;
+            Ok(declaration)
+        }).await
+    }
+
+    /// Record the payment of a declaration and settle the matching provision
+    pub async fn record_declaration_payment(&self, id: uuid::Uuid, payment_date: chrono::NaiveDate) -> DomainResult<Declaration> {
+        self.undoable("record_declaration_payment", self.transaction(|app| async move {
+            let mut declaration = app.deps.declarations.get_declaration(id).await?;
+            if declaration.filing_date.is_none() {
+                return Err(DomainError::Validation("La déclaration doit être déposée avant d'être payée".into()));
+            }
+            declaration.payment_date = Some(payment_date);
+            declaration.status = DeclarationStatus::Paid;
+            declaration.updated_at = chrono::Utc::now().naive_utc();
+            app.store_declaration("record_declaration_payment", declaration.clone()).await?;
+
+            let provision_type = match declaration.declaration_type {
+                DeclarationType::Vat => ProvisionType::Vat,
+                DeclarationType::Urssaf => ProvisionType::Urssaf,
+            };
+            let period = MonthId::new(declaration.period_year, declaration.period_month);
+            let provision = app.deps.provisions.list_provisions(Some(period)).await?
+                .into_iter()
+                .find(|p| p.provision_type == provision_type);
+            if let Some(provision) = provision {
+                app.mark_provision_paid(provision.id).await?;
+            }
+            Ok(declaration)
+        })).await
+    }
+
+    /// Flag unpaid declarations past their due date as overdue and persist the changes
+    pub async fn refresh_declaration_statuses(&self, today: chrono::NaiveDate) -> DomainResult<Vec<Declaration>> {
+        self.transaction(|app| async move {
+            let mut declarations = app.deps.declarations.list_declarations(None).await?;
+            for declaration in declarations.iter_mut() {
+                let status = declaration_status_as_of(declaration, today);
+                if status != declaration.status {
+                    declaration.status = status;
+                    declaration.updated_at = chrono::Utc::now().naive_utc();
+                    app.store_declaration("refresh_declaration_statuses", declaration.clone()).await?;
+                }
+            }
+            Ok(declarations)
+        }).await
     }
 
     // ============ Operation Use Cases ============
 
-    pub async fn create_operation(&self, operation: Operation) -> DomainResult<()> {
-        self.deps.operations.create_operation(operation).await
+    pub async fn create_operation(&self, mut operation: Operation) -> DomainResult<()> {
+        self.undoable("create_operation", async move {
+            self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+            fill_receipt_sha256(&mut operation);
+            self.insert_operation("create_operation", operation).await
+        }).await
     }
     
     pub async fn create_operation_from_dto(&self, dto: CreateOperationDto) -> DomainResult<()> {
@@ -130,19 +353,36 @@ impl AppService {
     }
 
     pub async fn update_operation(&self, mut operation: Operation) -> DomainResult<()> {
-        // Auto-calculate TTC from HT + TVA
-        if operation.amount_ttc_cents == 0 {
-            operation.amount_ttc_cents = operation.amount_ht_cents + operation.vat_amount_cents;
-        }
-        
-        // Set updated_at
-        operation.updated_at = chrono::Utc::now().naive_utc();
-        
-        self.deps.operations.update_operation(operation).await
+        self.undoable("update_operation", async move {
+            // Auto-calculate TTC from HT + TVA
+            if operation.amount_ttc_cents == 0 {
+                operation.amount_ttc_cents = operation.amount_ht_cents + operation.vat_amount_cents;
+            }
+
+            // Set updated_at
+            operation.updated_at = chrono::Utc::now().naive_utc();
+            fill_receipt_sha256(&mut operation);
+
+            // Neither changed in, moved out of nor moved into an archived year
+            let before = self.deps.operations.get_operation(operation.id).await?;
+            if !records_payment_only(&before, &operation) {
+                self.ensure_fiscal_year_open(before.invoice_date.year()).await?;
+                self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+                // The sale of an issued invoice follows the invoice: only its payment can be recorded
+                if self.invoiced_operation_ids().await?.contains(&operation.id) {
+                    return Err(DomainError::Validation(
+                        "Opération liée à une facture émise: seul son encaissement peut être saisi, établir un avoir pour la corriger".into()
+                    ));
+                }
+            }
+
+            self.store_operation("update_operation", operation).await
+        }).await
     }
 
+    /// Move an operation to the trash, from where it can be restored until purged
     pub async fn delete_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
-        self.deps.operations.delete_operation(id).await
+        self.undoable("delete_operation", self.trash_operation("delete_operation", id)).await
     }
 
     pub async fn list_operations(&self, month: Option<MonthId>) -> DomainResult<Vec<Operation>> {
@@ -158,12 +398,19 @@ impl AppService {
         self.deps.operations.list_operations_by_payment_month(month).await
     }
 
+    /// Filtered, sorted page of the operations; pass `next_cursor` back for the following page
+    pub async fn query_operations(&self, query: OperationQuery) -> DomainResult<OperationPage> {
+        query.validate()?;
+        self.deps.operations.query_operations(&query).await
+    }
+
     // ============ Operation-based Business Logic ============
 
+    /// Read-only: provision statuses are refreshed by `refresh_provision_statuses`
     pub async fn get_dashboard_v2(&self, month: MonthId) -> DomainResult<DashboardSummary> {
         let (operations, provisions, settings) = tokio::try_join!(
             self.deps.operations.list_operations(None),
-            self.deps.provisions.list_provisions(None),
+            self.deps.provisions.list_provisions(Some(month.clone())),
             self.deps.config.load_settings(),
         ).map_err(|e| DomainError::Repo(format!("{e}")))?;
         Ok(compute_dashboard_v2(&month, &operations, &provisions, &settings))
@@ -301,6 +548,81 @@ impl AppService {
     }
 }
 
+/// Validity of the URLs handed out to view receipts
+const RECEIPT_URL_TTL_SECS: u32 = 15 * 60;
+
+fn sha256_hex(content: &[u8]) -> String {
+    use sha2::Digest;
+    hex::encode(sha2::Sha256::digest(content))
+}
+
+/// Keep the recorded receipt hash consistent with the key (content-addressed keys embed it)
+fn fill_receipt_sha256(operation: &mut Operation) {
+    match operation.receipt_key.as_deref() {
+        None => operation.receipt_sha256 = None,
+        Some(key) => {
+            if let Some(sha256) = sha256_from_receipt_key(key) {
+                operation.receipt_sha256 = Some(sha256);
+            }
+        }
+    }
+}
+
+/// Legacy listings filter on the payment month
+fn paid_in(op: &Operation, month: Option<&MonthId>) -> bool {
+    match month {
+        Some(m) => op.payment_date.map(|d| d.year() == m.year && d.month() == m.month).unwrap_or(false),
+        None => true,
+    }
+}
+
+/// Entity state recorded in an undo step
+fn undo_state<T: serde::de::DeserializeOwned>(state: &Option<serde_json::Value>) -> DomainResult<Option<T>> {
+    state.clone().map(serde_json::from_value).transpose().map_err(|e| DomainError::Repo(e.to_string()))
+}
+
+/// Whether an entity is still in the state an undo step left it in
+/// Record times are left out: they are stored to the second, and reverting the later changes
+/// of a step rewrites the modification times
+fn undo_state_matches(current: &Option<serde_json::Value>, expected: &Option<serde_json::Value>) -> bool {
+    fn without_times(value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(fields) => fields.iter()
+                .filter(|(name, _)| !matches!(name.as_str(), "created_at" | "updated_at"))
+                .map(|(name, field)| (name.clone(), without_times(field)))
+                .collect(),
+            serde_json::Value::Array(items) => items.iter().map(without_times).collect(),
+            other => other.clone(),
+        }
+    }
+    current.as_ref().map(without_times) == expected.as_ref().map(without_times)
+}
+
+/// Audit origin of the changes made by a dataset import
+const IMPORT_ORIGIN: &str = "import_dataset";
+
+/// Record as exchanged in the change logs
+fn sync_state<T: Serialize>(record: Option<T>) -> DomainResult<Option<serde_json::Value>> {
+    record.map(serde_json::to_value).transpose().map_err(|e| DomainError::Repo(e.to_string()))
+}
+
+fn sync_record<T: serde::de::DeserializeOwned>(state: serde_json::Value) -> DomainResult<T> {
+    serde_json::from_value(state).map_err(|e| DomainError::Validation(format!("État synchronisé illisible: {}", e)))
+}
+
+/// A missing record is not an error for synchronisation
+fn sync_found<T>(result: DomainResult<T>) -> DomainResult<Option<T>> {
+    match result {
+        Err(DomainError::NotFound) => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+/// Whether an imported record matches the stored one, compared as serialized
+fn same_state<T: Serialize>(current: &T, imported: &T) -> bool {
+    serde_json::to_value(current).ok() == serde_json::to_value(imported).ok()
+}
+
 // DTOs for Tauri commands
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInvoiceDto {
@@ -392,7 +714,7 @@ impl AppService {
     pub async fn create_invoice_simple(&self, dto: CreateInvoiceSimpleDto) -> DomainResult<()> {
         let settings = self.deps.config.load_settings().await?;
         let inv = dto.into_entity(settings.default_vat_rate_ppm).map_err(|e| DomainError::Validation(e))?;
-        self.deps.invoices.create_invoice(inv).await
+        self.create_invoice(inv).await
     }
     
     // ============ New Service Methods ============
@@ -447,6 +769,37 @@ impl AppService {
         self.deps.tax_schedules.mark_as_paid(id, paid_date).await
     }
 
+    /// Record a full or partial payment of a tax schedule
+    pub async fn record_tax_payment(
+        &self,
+        id: uuid::Uuid,
+        paid_date: chrono::NaiveDate,
+        amount_cents: i64,
+        reference: Option<String>,
+    ) -> DomainResult<()> {
+        if amount_cents <= 0 {
+            return Err(DomainError::Validation("Le montant payé doit être positif".into()));
+        }
+        let schedule = self.deps.tax_schedules.get_tax_schedule(id).await?;
+        if amount_cents > schedule.outstanding_cents() {
+            return Err(DomainError::Validation(format!(
+                "Le montant payé dépasse le reste dû ({} €)", schedule.outstanding_cents() as f64 / 100.0
+            )));
+        }
+        self.deps.tax_schedules.record_payment(TaxPayment {
+            id: uuid::Uuid::new_v4(),
+            tax_schedule_id: id,
+            paid_date,
+            amount_cents,
+            reference,
+            created_at: chrono::Utc::now().naive_utc(),
+        }).await
+    }
+
+    pub async fn list_tax_payments(&self, id: uuid::Uuid) -> DomainResult<Vec<TaxPayment>> {
+        self.deps.tax_schedules.list_payments(id).await
+    }
+
     // Simulation Management
     pub async fn create_simulation(&self, simulation: Simulation) -> DomainResult<()> {
         self.deps.simulations.create_simulation(simulation).await
@@ -528,31 +881,47 @@ impl AppService {
         current_month: &MonthId,
         horizon_months: u32,
     ) -> DomainResult<Vec<TaxSchedule>> {
-        let settings = self.deps.config.load_settings().await?;
-        
-        // Generate VAT and URSSAF reports for the horizon
-        let mut vat_reports = Vec::new();
-        let mut urssaf_reports = Vec::new();
-        
-        for i in 0..horizon_months {
-            let mut month = current_month.clone();
-            month.month += i;
-            if month.month > 12 {
-                month.year += ((month.month - 1) / 12) as i32;
-                month.month = ((month.month - 1) % 12) + 1;
-            }
-            
-            let invoices = self.deps.invoices.list_invoices(Some(month.clone())).await?;
-            let expenses = self.deps.expenses.list_expenses(Some(month.clone())).await?;
-            
-            let vat = compute_vat_for_month(&month, &invoices, &expenses);
-            let urssaf = compute_urssaf_for_month(&month, &invoices, settings.urssaf_rate_ppm);
-            
-            vat_reports.push(vat);
-            urssaf_reports.push(urssaf);
+        let (operations, settings) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.config.load_settings(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        Ok(expected_tax_schedule(current_month, horizon_months, &operations, &settings))
+    }
+
+    /// Persist the expected VAT/URSSAF schedule computed from operations
+    /// Idempotent: entries are matched by (tax type, period), paid entries are kept as-is and
+    /// stale unpaid entries of the window are removed
+    pub async fn sync_tax_schedules(&self, start_month: &MonthId, horizon_months: u32) -> DomainResult<TaxScheduleSyncPlan> {
+        let (operations, settings) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.config.load_settings(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+        let expected = expected_tax_schedule(start_month, horizon_months, &operations, &settings);
+
+        // First month after the window
+        let month = (0..horizon_months).fold(start_month.clone(), |m, _| m.next());
+        let window_start = chrono::NaiveDate::from_ymd_opt(start_month.year, start_month.month, 1)
+            .ok_or_else(|| DomainError::Validation("Mois invalide".into()))?;
+        let window_end = chrono::NaiveDate::from_ymd_opt(month.year, month.month, 1)
+            .ok_or_else(|| DomainError::Validation("Mois invalide".into()))?;
+        let existing: Vec<_> = self.deps.tax_schedules.list_tax_schedules(None, None).await?
+            .into_iter()
+            .filter(|s| matches!(s.tax_type, TaxType::Vat | TaxType::Urssaf))
+            .filter(|s| s.period_start >= window_start && s.period_start < window_end)
+            .collect();
+
+        let plan = plan_tax_schedule_sync(&expected, &existing);
+        for schedule in &plan.to_create {
+            self.deps.tax_schedules.create_tax_schedule(schedule.clone()).await?;
         }
-        
-        Ok(compute_tax_schedule(current_month, horizon_months, &vat_reports, &urssaf_reports, &settings))
+        for schedule in &plan.to_update {
+            self.deps.tax_schedules.update_tax_schedule(schedule.clone()).await?;
+        }
+        for id in &plan.to_delete {
+            self.deps.tax_schedules.delete_tax_schedule(*id).await?;
+        }
+        Ok(plan)
     }
 
     /// Optimize provisions based on cash flow
@@ -588,13 +957,12 @@ impl AppService {
     /// Compute and save monthly KPIs
     pub async fn compute_and_save_monthly_kpis(&self, month: &MonthId) -> DomainResult<MonthlyKPI> {
         let settings = self.deps.config.load_settings().await?;
-        let (invoices, expenses, working_days) = tokio::try_join!(
-            self.deps.invoices.list_invoices(None),
-            self.deps.expenses.list_expenses(None),
+        let (operations, working_days) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
             self.deps.working_days.get_working_days_for_month(month),
         ).map_err(|e| DomainError::Repo(format!("{e}")))?;
 
-        let kpi = compute_monthly_kpis(month, &invoices, &expenses, &working_days, &settings);
+        let kpi = compute_monthly_kpis_v2(month, &operations, &working_days, &settings);
         self.deps.kpis.save_monthly_kpi(kpi.clone()).await?;
         Ok(kpi)
     }
@@ -689,133 +1057,1924 @@ impl AppService {
     // ============ File Upload Use Cases ============
 
     pub async fn upload_justificatif(&self, file_content: bytes::Bytes, original_filename: &str, content_type: Option<String>) -> DomainResult<String> {
-        self.deps.minio_service.upload_file(file_content, original_filename, content_type).await
+        Ok(self.upload_receipt(file_content.to_vec(), original_filename, content_type).await?.key)
+    }
+
+    /// Store a receipt under its content hash
+    /// Content already stored is not stored again, whatever its month folder or whether an operation
+    /// links it: the existing key is returned
+    pub async fn upload_receipt(&self, content: Vec<u8>, original_filename: &str, _content_type: Option<String>) -> DomainResult<ReceiptUpload> {
+        let sha256 = sha256_hex(&content);
+        let existing = self.deps.operations.list_operations_by_receipt_sha256(&sha256).await?;
+        let duplicate_of: Vec<uuid::Uuid> = existing.iter().map(|op| op.id).collect();
+        if let Some(key) = existing.iter().find_map(|op| op.receipt_key.clone()) {
+            return Ok(ReceiptUpload { key, sha256, duplicate_of, already_stored: true });
+        }
+        let stored = self.deps.documents.list_all().await?.into_iter()
+            .find(|f| sha256_from_receipt_key(&f.key).as_deref() == Some(sha256.as_str()));
+        if let Some(file) = stored {
+            return Ok(ReceiptUpload { key: file.key, sha256, duplicate_of, already_stored: true });
+        }
+
+        let today = chrono::Local::now().naive_local().date();
+        let key = receipt_key(&sha256, original_filename, today);
+        self.deps.documents.put(&key, content).await?;
+        Ok(ReceiptUpload { key, sha256, duplicate_of, already_stored: false })
+    }
+
+    /// Store a supplier invoice and, when it is a Factur-X PDF, propose the matching purchase
+    /// A malformed PDF or XML does not prevent the upload: the error is reported next to the receipt
+    pub async fn import_supplier_invoice(&self, content: Vec<u8>, original_filename: &str, content_type: Option<String>) -> DomainResult<SupplierInvoiceImport> {
+        let (invoice, extraction_error) = match infra::read_facturx_invoice(&content) {
+            Ok(invoice) => (invoice, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let receipt = self.upload_receipt(content, original_filename, content_type).await?;
+        let operation = invoice.as_ref().map(|invoice| CreateOperationDto::from_supplier_invoice(invoice, &receipt));
+        Ok(SupplierInvoiceImport { receipt, invoice, operation, extraction_error })
     }
 
-    pub async fn delete_justificatif(&self, file_url: &str) -> DomainResult<()> {
-        self.deps.minio_service.delete_file(file_url).await
+    /// Purchases without receipt, stored files attached to nothing and keys pointing to missing objects
+    pub async fn audit_receipts(&self, month: Option<MonthId>) -> DomainResult<ReceiptAudit> {
+        let (operations, trashed, files) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.operations.list_trashed_operations(),
+            self.deps.documents.list_all(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        Ok(audit_receipts(month.as_ref(), &operations, &trashed, &files))
+    }
+
+    /// Attach a stored document to an operation (also used to re-link a dangling receipt by hand)
+    pub async fn attach_receipt(&self, operation_id: uuid::Uuid, key: &str) -> DomainResult<()> {
+        self.undoable("attach_receipt", async move {
+            let content = match self.deps.documents.open(key).await {
+                Err(DomainError::NotFound) => return Err(DomainError::Validation(format!("Justificatif introuvable: {}", key))),
+                other => other?,
+            };
+            let mut operation = self.deps.operations.get_operation(operation_id).await?;
+            operation.receipt_key = Some(key.to_string());
+            operation.receipt_sha256 = Some(sha256_hex(&content));
+            operation.updated_at = chrono::Utc::now().naive_utc();
+            self.store_operation("attach_receipt", operation).await
+        }).await
+    }
+
+    /// Delete the given stored documents, skipping any that is attached to an operation (even trashed) or under legal retention
+    pub async fn purge_orphan_receipts(&self, keys: Vec<String>) -> DomainResult<u32> {
+        let audit = self.audit_receipts(None).await?;
+        let mut purged = 0;
+        for key in keys {
+            if audit.orphan_receipts.iter().any(|f| f.key == key) && self.ensure_document_deletable(&key).await.is_ok() {
+                self.deps.documents.delete(&key).await?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
     }
 
-    pub async fn list_justificatifs_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<infra::FileInfo>> {
-        self.deps.minio_service.list_files_by_month(year, month).await
+    /// Point dangling receipts to a stored document with the same content hash, when there is one
+    pub async fn relink_dangling_receipts(&self) -> DomainResult<u32> {
+        let (operations, files) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.documents.list_all(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        let audit = audit_receipts(None, &operations, &[], &files);
+
+        let mut relinked = 0;
+        for dangling in audit.dangling_receipts {
+            let Some(mut operation) = operations.iter().find(|op| op.id == dangling.operation_id).cloned() else { continue };
+            let Some(sha256) = operation.receipt_sha256.clone().or_else(|| sha256_from_receipt_key(&dangling.receipt_key)) else { continue };
+            let Some(file) = files.iter().find(|f| sha256_from_receipt_key(&f.key).as_deref() == Some(sha256.as_str())) else { continue };
+            operation.receipt_key = Some(file.key.clone());
+            operation.receipt_sha256 = Some(sha256);
+            operation.updated_at = chrono::Utc::now().naive_utc();
+            self.store_operation("relink_dangling_receipts", operation).await?;
+            relinked += 1;
+        }
+        Ok(relinked)
     }
 
-    pub async fn get_storage_stats(&self) -> DomainResult<infra::StorageStats> {
-        self.deps.minio_service.get_storage_stats().await
+    /// Short-lived URL to view a receipt (presigned on S3 backends)
+    pub async fn receipt_download_url(&self, key: &str) -> DomainResult<String> {
+        self.deps.documents.download_url(key, RECEIPT_URL_TTL_SECS).await
     }
-}
 
-// ============ New DTOs ============
+    /// Replace receipt URLs stored before documents were referenced by key
+    /// Returns the number of operations updated; values that are not recognised are left untouched
+    pub async fn migrate_receipt_urls_to_keys(&self) -> DomainResult<u32> {
+        let mut migrated = 0;
+        for mut operation in self.deps.operations.list_operations(None).await? {
+            let Some(stored) = operation.receipt_key.as_deref() else { continue };
+            if !stored.contains("://") {
+                continue;
+            }
+            let Some(key) = document_key_from_url(stored) else { continue };
+            operation.receipt_key = Some(key);
+            fill_receipt_sha256(&mut operation);
+            operation.updated_at = chrono::Utc::now().naive_utc();
+            self.store_operation("migrate_receipt_urls_to_keys", operation).await?;
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct EnhancedDashboardData {
-    pub basic_summary: DashboardSummary,
-    pub monthly_kpi: Option<MonthlyKPI>,
-    pub working_pattern: Option<WorkingPatternAnalysis>,
-    pub working_days_count: f64,
-}
+    /// Re-hash every receipt linked to an operation and report missing, altered or unhashed
+    /// receipts, then the stored documents no operation refers to
+    pub async fn verify_receipts(&self) -> DomainResult<ReceiptVerificationReport> {
+        let (operations, trashed, files) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.operations.list_trashed_operations(),
+            self.deps.documents.list_all(),
+        )?;
+        let mut report = ReceiptVerificationReport::default();
+        let referenced: HashSet<String> = operations.iter().chain(trashed.iter().map(|t| &t.operation))
+            .filter_map(|o| o.receipt_key.clone())
+            .collect();
+        report.unreferenced = files.into_iter()
+            .filter(|f| !f.key.starts_with(BACKUP_KEY_PREFIX) && !f.key.starts_with(WORKSPACE_KEY_PREFIX))
+            .filter(|f| !referenced.contains(&f.key))
+            .collect();
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateWorkingDayDto {
-    pub date: String, // ISO date
-    pub hours_worked: f64,
-    pub billable_hours: f64,
-    pub hourly_rate_cents: i64,
-    pub description: Option<String>,
-}
+        for mut operation in operations {
+            let Some(key) = operation.receipt_key.clone() else { continue };
+            report.checked += 1;
+
+            let content = match self.deps.documents.open(&key).await {
+                Ok(content) => content,
+                Err(DomainError::NotFound) => {
+                    report.issues.push(ReceiptIssue {
+                        operation_id: operation.id,
+                        receipt_key: key,
+                        kind: ReceiptIssueKind::Missing,
+                        expected_sha256: operation.receipt_sha256.clone(),
+                        actual_sha256: None,
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let actual = sha256_hex(&content);
+            match operation.receipt_sha256.clone() {
+                Some(expected) if expected != actual => report.issues.push(ReceiptIssue {
+                    operation_id: operation.id,
+                    receipt_key: key,
+                    kind: ReceiptIssueKind::Mismatch,
+                    expected_sha256: Some(expected),
+                    actual_sha256: Some(actual),
+                }),
+                Some(_) => report.valid += 1,
+                None => {
+                    report.issues.push(ReceiptIssue {
+                        operation_id: operation.id,
+                        receipt_key: key,
+                        kind: ReceiptIssueKind::Unhashed,
+                        expected_sha256: None,
+                        actual_sha256: Some(actual.clone()),
+                    });
+                    operation.receipt_sha256 = Some(actual);
+                    operation.updated_at = chrono::Utc::now().naive_utc();
+                    self.store_operation("verify_receipts", operation).await?;
+                }
+            }
+        }
+        Ok(report)
+    }
 
-impl CreateWorkingDayDto {
-    pub fn into_entity(self) -> Result<WorkingDay, String> {
-        let date = chrono::NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
-            .map_err(|e| e.to_string())?;
-        
-        let now = chrono::Utc::now().naive_utc();
-        
-        Ok(WorkingDay {
-            id: uuid::Uuid::new_v4(),
-            date,
-            hours_worked: self.hours_worked,
-            billable_hours: self.billable_hours,
-            hourly_rate_cents: self.hourly_rate_cents,
-            description: self.description,
-            created_at: now,
-            updated_at: now,
-        })
+    pub async fn delete_justificatif(&self, key: &str) -> DomainResult<()> {
+        self.ensure_document_deletable(key).await?;
+        self.deps.documents.delete(key).await
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateSimulationDto {
-    pub name: String,
-    pub scenario_type: String, // "DailyRateOptimization", "AnnualIncomeProjection", etc.
-    pub parameters: SimulationParameters,
-}
+    pub async fn list_justificatifs_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>> {
+        self.deps.documents.list_by_month(year, month).await
+    }
 
-impl CreateSimulationDto {
-    pub fn into_entity(self) -> Result<Simulation, String> {
-        let scenario_type = match self.scenario_type.as_str() {
-            "DailyRateOptimization" => SimulationScenario::DailyRateOptimization,
-            "AnnualIncomeProjection" => SimulationScenario::AnnualIncomeProjection,
-            "TaxOptimization" => SimulationScenario::TaxOptimization,
-            "WorkingDaysImpact" => SimulationScenario::WorkingDaysImpact,
-            _ => return Err("Type de scénario invalide".into()),
-        };
-        
-        let now = chrono::Utc::now().naive_utc();
-        
-        Ok(Simulation {
-            id: uuid::Uuid::new_v4(),
-            name: self.name,
-            scenario_type,
-            parameters: self.parameters,
-            results: None,
-            created_at: now,
-            updated_at: now,
-        })
+    pub async fn get_storage_stats(&self) -> DomainResult<StorageStats> {
+        self.deps.documents.stats().await
     }
-}
 
-// ============ Operation DTOs ============
+    pub async fn open_justificatif(&self, key: &str) -> DomainResult<Vec<u8>> {
+        self.deps.documents.open(key).await
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CreateOperationDto {
-    pub invoice_date: String,               // "YYYY-MM-DD" Invoice date
-    pub payment_date: Option<String>,       // "YYYY-MM-DD" Unified payment/encaissement date
-    pub operation_type: String,             // "sale" or "purchase"
-    pub amount_ht_cents: i64,               // HT amount in cents
-    pub vat_amount_cents: Option<i64>,      // VAT amount direct (calculated if not provided)
-    pub vat_on_payments: bool,              // true by default
-    pub label: Option<String>,              // Description
-    pub receipt_url: Option<String>,        // MinIO receipt URL
-}
+    pub fn document_backend(&self) -> &'static str {
+        self.deps.documents.backend()
+    }
 
-impl CreateOperationDto {
-    pub fn into_entity(self, default_vat_rate_ppm: i32) -> Result<Operation, String> {
-        let invoice_date = chrono::NaiveDate::parse_from_str(&self.invoice_date, "%Y-%m-%d")
-            .map_err(|e| format!("Invoice date invalid: {}", e))?;
-        
-        let operation_type = match self.operation_type.as_str() {
-            "purchase" => OperationType::Purchase,
-            "sale" => OperationType::Sale,
-            _ => return Err("Operation type invalid: must be 'sale' or 'purchase'".into()),
-        };
+    /// Copy every document of the active backend into `target`, keeping the same keys
+    /// Source files are left in place; the target becomes active on the next start
+    pub async fn migrate_documents(&self, target: Arc<dyn DocumentStore>) -> DomainResult<DocumentMigrationReport> {
+        let source = &self.deps.documents;
+        if source.backend() == target.backend() {
+            return Err(DomainError::Validation(format!("Les documents sont déjà stockés sur '{}'", target.backend())));
+        }
 
-        let payment_date = if let Some(date_str) = self.payment_date {
-            Some(chrono::NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
-                .map_err(|e| format!("Payment date invalid: {}", e))?)
-        } else {
-            None
+        let mut report = DocumentMigrationReport {
+            from_backend: source.backend().to_string(),
+            to_backend: target.backend().to_string(),
+            ..Default::default()
         };
+        for file in source.list_all().await? {
+            let content = source.open(&file.key).await?;
+            report.bytes_copied += content.len() as u64;
+            target.put(&file.key, content).await?;
+            report.files_copied += 1;
+        }
+        Ok(report)
+    }
 
-        // Automatic VAT calculation if not provided
-        let vat_amount_cents = if let Some(vat) = self.vat_amount_cents {
-            vat
-        } else {
-            // Use default rate
-            ((self.amount_ht_cents as i128) * (default_vat_rate_ppm as i128) / 1_000_000i128) as i64
-        };
+    // ============ Invoice Issuance Use Cases ============
 
-        let amount_ttc_cents = self.amount_ht_cents + vat_amount_cents;
+    pub async fn get_issuer_profile(&self) -> DomainResult<Option<IssuerProfile>> {
+        self.deps.issued_invoices.load_issuer_profile().await
+    }
+
+    pub async fn save_issuer_profile(&self, profile: IssuerProfile) -> DomainResult<()> {
+        profile.validate()?;
+        self.deps.issued_invoices.save_issuer_profile(profile).await
+    }
+
+    /// Number the invoice, render its PDF into the document store and record the sale
+    /// Invoice, sequence and operation are written in one transaction; the PDF is removed if it fails
+    pub async fn issue_invoice(&self, draft: InvoiceDraft) -> DomainResult<IssuedInvoice> {
+        let issuer = self.deps.issued_invoices.load_issuer_profile().await?
+            .ok_or_else(|| DomainError::Validation("Renseignez le profil émetteur (SIRET, TVA, pénalités) avant d'émettre une facture".into()))?;
+
+        // Numbers must follow issue dates within the year
+        let year = draft.issue_date.year();
+        if let Some(last) = self.deps.issued_invoices.list_issued_invoices(Some(year)).await?.last() {
+            if last.issue_date > draft.issue_date {
+                return Err(DomainError::Validation(format!(
+                    "La facture {} est datée du {}: une nouvelle facture ne peut pas être antérieure",
+                    last.number, last.issue_date.format("%d/%m/%Y"),
+                )));
+            }
+        }
+
+        let sequence = self.deps.issued_invoices.next_invoice_sequence(year).await?;
+        let now = chrono::Utc::now().naive_utc();
+        let mut invoice = build_issued_invoice(issuer, draft, sequence, now)?;
+
+        let pdf = infra::render_facturx_invoice(&invoice, now)?;
+        let receipt = self.upload_receipt(pdf, &format!("{}.pdf", invoice.number), Some("application/pdf".into())).await?;
+        invoice.pdf_key = receipt.key.clone();
+        let operation = invoice.to_operation(&receipt.sha256, now);
+
+        if let Err(e) = self.deps.issued_invoices.issue_invoice(invoice.clone(), operation.clone()).await {
+            if !receipt.already_stored {
+                let _ = self.deps.documents.delete(&receipt.key).await;
+            }
+            return Err(e);
+        }
+        self.record_audit("issue_invoice", AuditEntity::Operation, operation.id, None, Some(&operation)).await?;
+        Ok(invoice)
+    }
+
+    pub async fn get_issued_invoice(&self, id: uuid::Uuid) -> DomainResult<IssuedInvoice> {
+        self.deps.issued_invoices.get_issued_invoice(id).await
+    }
+
+    pub async fn list_issued_invoices(&self, year: Option<i32>) -> DomainResult<Vec<IssuedInvoice>> {
+        self.deps.issued_invoices.list_issued_invoices(year).await
+    }
+
+    /// Structured export of a sale
+    /// A sale issued from the application is exported as issued: Factur-X reuses the stored PDF, attaching
+    /// the CII XML to invoices issued before it was generated. Any other sale needs its invoice number and
+    /// client details; its Factur-X PDF is rendered from them
+    pub async fn export_e_invoice(&self, operation_id: uuid::Uuid, format: EInvoiceFormat, sale: Option<SaleInvoiceDetails>) -> DomainResult<EInvoiceExport> {
+        let (invoice, issued) = match self.deps.issued_invoices.find_issued_invoice_by_operation(operation_id).await? {
+            Some(invoice) => (invoice, true),
+            None => {
+                let details = sale.ok_or_else(|| DomainError::Validation(
+                    "Vente non facturée depuis l'application: indiquez le numéro de la facture et les coordonnées du client".into()
+                ))?;
+                let issuer = self.deps.issued_invoices.load_issuer_profile().await?
+                    .ok_or_else(|| DomainError::Validation("Renseignez le profil émetteur (SIRET, TVA, pénalités) avant d'exporter une facture".into()))?;
+                let operation = self.deps.operations.get_operation(operation_id).await?;
+                (invoice_from_sale(&operation, issuer, details, chrono::Utc::now().naive_utc())?, false)
+            }
+        };
+
+        Ok(match format {
+            EInvoiceFormat::FacturX => {
+                let content = if issued {
+                    let stored = self.deps.documents.open(&invoice.pdf_key).await?;
+                    if infra::extract_facturx_xml(&stored)?.is_some() {
+                        stored
+                    } else {
+                        infra::embed_facturx_xml(&stored, infra::invoice_to_cii_xml(&invoice).as_bytes(), infra::FACTURX_CONFORMANCE_EN16931, invoice.created_at)?
+                    }
+                } else {
+                    infra::render_facturx_invoice(&invoice, invoice.created_at)?
+                };
+                EInvoiceExport { filename: format!("{}.pdf", invoice.number), content_type: "application/pdf".into(), content }
+            }
+            EInvoiceFormat::Cii => EInvoiceExport {
+                filename: format!("{}_cii.xml", invoice.number),
+                content_type: "application/xml".into(),
+                content: infra::invoice_to_cii_xml(&invoice).into_bytes(),
+            },
+            EInvoiceFormat::Ubl => EInvoiceExport {
+                filename: format!("{}_ubl.xml", invoice.number),
+                content_type: "application/xml".into(),
+                content: infra::invoice_to_ubl_xml(&invoice).into_bytes(),
+            },
+        })
+    }
+
+    // ============ Accountant Export Use Cases ============
+
+    /// ZIP for the accountant: operations journal, VAT/URSSAF reports, month recaps, receipts and a hashed manifest
+    /// A receipt missing from the store is listed in the manifest instead of failing the export
+    pub async fn export_accountant_pack(&self, period: ExportPeriod, target: &std::path::Path) -> DomainResult<ExportManifest> {
+        period.validate()?;
+        let (operations, settings) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.config.load_settings(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+        let months = period.months();
+        let vat: Vec<VatReport> = months.iter().map(|m| compute_vat_for_month_v2(m, &operations)).collect();
+        let urssaf: Vec<UrssafReport> = months.iter().map(|m| compute_urssaf_for_month_v2(m, &operations, settings.urssaf_rate_ppm)).collect();
+        let recaps: Vec<MonthRecap> = months.iter().map(|m| compute_month_recap_v2(m, &operations, &settings)).collect();
+        let selected = operations_in_period(&period, &operations);
+        let receipt_names = receipt_export_names(&selected);
+
+        let mut files = vec![
+            ("journal_operations.csv".to_string(), operations_journal_csv(&selected, &receipt_names).into_bytes()),
+            ("tva.csv".to_string(), vat_reports_csv(&vat).into_bytes()),
+            ("urssaf.csv".to_string(), urssaf_reports_csv(&urssaf).into_bytes()),
+            ("recapitulatif_mensuel.csv".to_string(), month_recaps_csv(&recaps).into_bytes()),
+        ];
+        let mut missing_receipts = Vec::new();
+        for (operation_id, name) in &receipt_names {
+            let Some(key) = selected.iter().find(|op| op.id == *operation_id).and_then(|op| op.receipt_key.clone()) else { continue };
+            match self.deps.documents.open(&key).await {
+                Ok(content) => files.push((format!("justificatifs/{}", name), content)),
+                Err(DomainError::NotFound) => missing_receipts.push(DanglingReceipt { operation_id: *operation_id, receipt_key: key }),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let manifest = ExportManifest {
+            period,
+            label: period.label(),
+            generated_at: chrono::Local::now().naive_local(),
+            operations_count: selected.len() as u32,
+            receipts_count: (receipt_names.len() - missing_receipts.len()) as u32,
+            files: files.iter()
+                .map(|(path, content)| ExportManifestEntry { path: path.clone(), size_bytes: content.len() as u64, sha256: sha256_hex(content) })
+                .collect(),
+            missing_receipts,
+        };
+        let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| DomainError::Repo(e.to_string()))?;
+        files.push(("manifest.json".to_string(), manifest_json));
+
+        infra::write_zip(target, &files)?;
+        Ok(manifest)
+    }
+
+    // ============ Legal Archive Use Cases ============
+
+    /// Archive an ended fiscal year: its operations become read-only and its documents are retained 10 years
+    /// Every document must be present and intact; on S3 with Object Lock, they are also locked until the retention date
+    pub async fn archive_fiscal_year(&self, year: i32) -> DomainResult<ArchivedFiscalYear> {
+        let now = chrono::Local::now().naive_local();
+        if year >= now.year() {
+            return Err(DomainError::Validation(format!("L'exercice {} n'est pas terminé", year)));
+        }
+        let (archived, operations, invoices) = tokio::try_join!(
+            self.deps.archives.list_archived_years(),
+            self.deps.operations.list_operations(None),
+            self.deps.issued_invoices.list_issued_invoices(Some(year)),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        if archived.iter().any(|a| a.year == year) {
+            return Err(DomainError::Validation(format!("L'exercice {} est déjà archivé", year)));
+        }
+
+        let operations: Vec<Operation> = operations.into_iter().filter(|op| op.invoice_date.year() == year).collect();
+        let mut keys: Vec<(String, Option<String>)> = operations.iter()
+            .filter_map(|op| op.receipt_key.clone().map(|key| (key, op.receipt_sha256.clone())))
+            .chain(invoices.iter().map(|inv| (inv.pdf_key.clone(), None)))
+            .collect();
+        keys.sort();
+        keys.dedup_by(|a, b| a.0 == b.0);
+
+        let retain_until = retention_until(year);
+        let mut documents = Vec::new();
+        let mut problems = Vec::new();
+        for (key, recorded) in keys {
+            match self.deps.documents.open(&key).await {
+                Ok(content) => {
+                    let sha256 = sha256_hex(&content);
+                    if recorded.is_some_and(|recorded| recorded != sha256) {
+                        problems.push(format!("{} (contenu modifié)", key));
+                    } else {
+                        documents.push(ArchivedDocument { key, fiscal_year: year, sha256, retain_until, object_locked: false });
+                    }
+                }
+                Err(DomainError::NotFound) => problems.push(format!("{} (introuvable)", key)),
+                Err(e) => return Err(e),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(DomainError::Validation(format!("Justificatifs à corriger avant l'archivage: {}", problems.join(", "))));
+        }
+
+        // Hashes are recorded with the archive, in the same transaction: the operations are read-only afterwards
+        let archive = ArchivedFiscalYear { year, archived_at: now, retain_until, operations_count: operations.len() as u32 };
+        self.transaction(|app| {
+            let (archive, documents) = (archive.clone(), documents.clone());
+            async move {
+                for mut operation in operations.into_iter().filter(|op| op.receipt_key.is_some() && op.receipt_sha256.is_none()) {
+                    operation.receipt_sha256 = documents.iter()
+                        .find(|d| Some(&d.key) == operation.receipt_key.as_ref())
+                        .map(|d| d.sha256.clone());
+                    operation.updated_at = chrono::Utc::now().naive_utc();
+                    app.store_operation("archive_fiscal_year", operation).await?;
+                }
+                app.deps.archives.archive_fiscal_year(archive, documents).await
+            }
+        }).await?;
+
+        // Object Lock cannot be lifted: only documents of a recorded archive are locked
+        let mut unlocked = Vec::new();
+        for document in &documents {
+            match self.deps.documents.set_retention(&document.key, retain_until).await {
+                Ok(true) => self.deps.archives.mark_object_locked(&document.key).await?,
+                Ok(false) => {}
+                Err(e) => unlocked.push(format!("{} ({})", document.key, e)),
+            }
+        }
+        if !unlocked.is_empty() {
+            return Err(DomainError::Repo(format!(
+                "Exercice {} archivé, mais verrouillage impossible pour: {}", year, unlocked.join(", ")
+            )));
+        }
+        Ok(archive)
+    }
+
+    /// Archived fiscal years and documents with their retention date, each document re-hashed
+    pub async fn archive_report(&self) -> DomainResult<ArchiveReport> {
+        let (fiscal_years, documents) = tokio::try_join!(
+            self.deps.archives.list_archived_years(),
+            self.deps.archives.list_archived_documents(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+        let mut checks = Vec::with_capacity(documents.len());
+        for document in documents {
+            let state = match self.deps.documents.open(&document.key).await {
+                Ok(content) if sha256_hex(&content) == document.sha256 => ArchivedDocumentState::Intact,
+                Ok(_) => ArchivedDocumentState::Mismatch,
+                Err(DomainError::NotFound) => ArchivedDocumentState::Missing,
+                Err(e) => return Err(e),
+            };
+            checks.push(ArchivedDocumentCheck { document, state });
+        }
+        Ok(ArchiveReport { checked_at: chrono::Local::now().naive_local(), fiscal_years, documents: checks })
+    }
+
+    async fn ensure_fiscal_year_open(&self, year: i32) -> DomainResult<()> {
+        if self.deps.archives.list_archived_years().await?.iter().any(|a| a.year == year) {
+            return Err(DomainError::Validation(format!("Exercice {} archivé: opérations en lecture seule", year)));
+        }
+        Ok(())
+    }
+
+    async fn ensure_document_deletable(&self, key: &str) -> DomainResult<()> {
+        let today = chrono::Local::now().date_naive();
+        match self.deps.archives.get_archived_document(key).await? {
+            Some(document) if document.is_retained(today) => Err(DomainError::Validation(format!(
+                "Document archivé, conservation obligatoire jusqu'au {}", document.retain_until.format("%d/%m/%Y")
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // ============ FEC Export Use Cases ============
+
+    /// FEC of a calendar fiscal year, written to `target_dir` under its regulatory name
+    /// The generated file is validated first and not written when a check fails
+    pub async fn export_fec(&self, year: i32, target_dir: &std::path::Path) -> DomainResult<FecExport> {
+        let issuer = self.deps.issued_invoices.load_issuer_profile().await?
+            .ok_or_else(|| DomainError::Validation("Renseignez le profil émetteur (SIRET) pour nommer le FEC".into()))?;
+        let siren: String = issuer.siret.chars().filter(|c| c.is_ascii_digit()).take(9).collect();
+        if siren.len() != 9 {
+            return Err(DomainError::Validation(format!("SIRET invalide dans le profil émetteur: {}", issuer.siret)));
+        }
+        let closing_date = chrono::NaiveDate::from_ymd_opt(year, 12, 31)
+            .ok_or_else(|| DomainError::Validation(format!("Exercice invalide: {}", year)))?;
+
+        let (operations, declarations, invoices) = tokio::try_join!(
+            self.deps.operations.list_operations(None),
+            self.deps.declarations.list_declarations(None),
+            self.deps.issued_invoices.list_issued_invoices(None),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+
+        let lines = fec_lines(year, &operations, &declarations, &invoices);
+        let content = fec_file_content(&lines, '|');
+        let validation = validate_fec_file(&content, year);
+        if !validation.is_valid() {
+            let errors: Vec<&str> = validation.errors.iter().take(5).map(String::as_str).collect();
+            return Err(DomainError::Validation(format!("FEC non conforme: {}", errors.join("; "))));
+        }
+
+        let filename = fec_filename(&siren, closing_date);
+        std::fs::write(target_dir.join(&filename), content)
+            .map_err(|e| DomainError::Repo(format!("Erreur écriture FEC: {}", e)))?;
+        Ok(FecExport { filename, validation, trial_balance: fec_trial_balance(&lines) })
+    }
+
+    // ============ Audit Trail Use Cases ============
+
+    /// Changes of one entity, most recent first
+    pub async fn entity_history(&self, entity: AuditEntity, entity_id: &str) -> DomainResult<Vec<AuditEvent>> {
+        self.deps.audit.list_audit_events(AuditQuery {
+            entity: Some(entity),
+            entity_id: Some(entity_id.to_string()),
+            ..AuditQuery::default()
+        }).await
+    }
+
+    pub async fn list_audit_events(&self, query: AuditQuery) -> DomainResult<Vec<AuditEvent>> {
+        self.deps.audit.list_audit_events(query).await
+    }
+
+    pub async fn verify_audit_trail(&self) -> DomainResult<AuditChainCheck> {
+        self.deps.audit.verify_audit_chain().await
+    }
+
+    /// Append a create (no `before`), update or delete (no `after`) event to the audit trail
+    async fn record_audit<T: Serialize>(
+        &self,
+        origin: &str,
+        entity: AuditEntity,
+        entity_id: impl ToString,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> DomainResult<()> {
+        let to_json = |state: Option<&T>| state.map(serde_json::to_value).transpose().map_err(|e| DomainError::Repo(e.to_string()));
+        let action = match (before, after) {
+            (None, _) => AuditAction::Create,
+            (Some(_), Some(_)) => AuditAction::Update,
+            (Some(_), None) => AuditAction::Delete,
+        };
+        let (entity_id, before, after) = (entity_id.to_string(), to_json(before)?, to_json(after)?);
+        self.deps.audit.append_audit_event(NewAuditEvent {
+            entity,
+            entity_id: entity_id.clone(),
+            action,
+            before: before.clone(),
+            after: after.clone(),
+            origin: origin.to_string(),
+        }).await?;
+        // Inside an undoable use case the change also joins its undo step
+        let _ = UNDO_CHANGES.try_with(|changes| changes.borrow_mut().push(UndoChange { entity, entity_id, before, after }));
+        Ok(())
+    }
+
+    async fn insert_operation(&self, origin: &str, operation: Operation) -> DomainResult<()> {
+        self.deps.operations.create_operation(operation.clone()).await?;
+        self.record_audit(origin, AuditEntity::Operation, operation.id, None, Some(&operation)).await
+    }
+
+    async fn store_operation(&self, origin: &str, operation: Operation) -> DomainResult<()> {
+        let before = self.deps.operations.get_operation(operation.id).await?;
+        self.deps.operations.update_operation(operation.clone()).await?;
+        self.record_audit(origin, AuditEntity::Operation, operation.id, Some(&before), Some(&operation)).await
+    }
+
+    async fn store_declaration(&self, origin: &str, declaration: Declaration) -> DomainResult<()> {
+        let before = self.deps.declarations.get_declaration(declaration.id).await?;
+        self.deps.declarations.update_declaration(declaration.clone()).await?;
+        self.record_audit(origin, AuditEntity::Declaration, declaration.id, Some(&before), Some(&declaration)).await
+    }
+
+    async fn store_provision(&self, origin: &str, provision: Provision, before: Option<&Provision>) -> DomainResult<()> {
+        self.deps.provisions.upsert_provision(provision.clone()).await?;
+        self.record_audit(origin, AuditEntity::Provision, provision.id, before, Some(&provision)).await
+    }
+
+    // ============ Trash and Undo Use Cases ============
+
+    /// Trashed operations and plannings, most recently trashed first
+    pub async fn list_trash(&self) -> DomainResult<Trash> {
+        let (operations, yearly_plannings) = tokio::try_join!(
+            self.deps.operations.list_trashed_operations(),
+            self.deps.yearly_planning.list_trashed_yearly_plannings(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        Ok(Trash { operations, yearly_plannings })
+    }
+
+    pub async fn restore_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.undoable("restore_operation", self.restore_trashed_operation("restore_operation", id)).await
+    }
+
+    pub async fn restore_yearly_planning(&self, year: i32) -> DomainResult<()> {
+        self.undoable("restore_yearly_planning", self.restore_trashed_yearly_planning("restore_yearly_planning", year)).await
+    }
+
+    /// Delete a trashed operation for good
+    pub async fn purge_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        let trashed = self.find_trashed_operation(id).await?;
+        self.ensure_fiscal_year_open(trashed.operation.invoice_date.year()).await?;
+        if self.invoiced_operation_ids().await?.contains(&id) {
+            return Err(DomainError::Validation(
+                "Opération liée à une facture émise: elle ne peut pas être supprimée définitivement, établir un avoir".into()
+            ));
+        }
+        self.transaction(|app| async move {
+            app.deps.operations.delete_operation(id).await?;
+            app.record_audit("purge_operation", AuditEntity::Operation, id, Some(&trashed.operation), None).await
+        }).await
+    }
+
+    /// Delete a trashed planning and its months for good
+    pub async fn purge_yearly_planning(&self, year: i32) -> DomainResult<()> {
+        let trashed = self.find_trashed_yearly_planning(year).await?;
+        self.transaction(|app| async move {
+            app.deps.yearly_planning.delete_yearly_planning(year).await?;
+            app.record_audit("purge_yearly_planning", AuditEntity::YearlyPlanning, year, Some(&trashed.planning), None).await
+        }).await
+    }
+
+    /// Purge what has been in the trash for `TRASH_RETENTION_DAYS`
+    /// Operations of an archived fiscal year stay there, the archive forbids deleting them,
+    /// and so do operations of an issued invoice, which only a credit note can cancel
+    pub async fn purge_expired_trash(&self) -> DomainResult<TrashPurge> {
+        let (trash, archived, invoiced) = tokio::try_join!(
+            self.list_trash(),
+            self.deps.archives.list_archived_years(),
+            self.invoiced_operation_ids(),
+        ).map_err(|e| DomainError::Repo(format!("{e}")))?;
+        let now = chrono::Utc::now().naive_utc();
+
+        let mut purge = TrashPurge::default();
+        for trashed in trash.operations.into_iter().filter(|t| is_trash_expired(t.deleted_at, now)) {
+            if archived.iter().any(|a| a.year == trashed.operation.invoice_date.year()) {
+                purge.skipped += 1;
+                continue;
+            }
+            if invoiced.contains(&trashed.operation.id) {
+                purge.invoiced += 1;
+                continue;
+            }
+            self.transaction(|app| async move {
+                app.deps.operations.delete_operation(trashed.operation.id).await?;
+                app.record_audit("purge_expired_trash", AuditEntity::Operation, trashed.operation.id, Some(&trashed.operation), None).await
+            }).await?;
+            purge.operations += 1;
+        }
+        for trashed in trash.yearly_plannings.into_iter().filter(|t| is_trash_expired(t.deleted_at, now)) {
+            self.transaction(|app| async move {
+                app.deps.yearly_planning.delete_yearly_planning(trashed.planning.year).await?;
+                app.record_audit("purge_expired_trash", AuditEntity::YearlyPlanning, trashed.planning.year, Some(&trashed.planning), None).await
+            }).await?;
+            purge.yearly_plannings += 1;
+        }
+        Ok(purge)
+    }
+
+    /// Undo steps available, most recent first
+    pub fn list_undo_steps(&self) -> Vec<UndoStep> {
+        self.undo_stack().iter().rev().cloned().collect()
+    }
+
+    /// Revert the changes of the most recent undo step, last change first
+    /// Reverts are audited with the origin `undo:<use case>` and cannot themselves be undone
+    pub async fn undo_last(&self) -> DomainResult<UndoStep> {
+        let step = self.undo_stack().pop_back()
+            .ok_or_else(|| DomainError::Validation("Aucune action à annuler".into()))?;
+        let origin = format!("undo:{}", step.origin);
+        let changes = step.changes.clone();
+        let reverted = self.transaction(|app| async move {
+            for change in changes.iter().rev() {
+                app.revert_change(&origin, change).await?;
+            }
+            Ok(())
+        }).await;
+        if let Err(e) = reverted {
+            // Nothing was reverted: the step stays on the stack
+            self.undo_stack().push_back(step);
+            return Err(e);
+        }
+        Ok(step)
+    }
+
+    /// Run a use case, collecting the changes it records into one undo step
+    /// A use case called by another one joins the step of the outermost
+    async fn undoable<T>(&self, origin: &str, action: impl std::future::Future<Output = DomainResult<T>>) -> DomainResult<T> {
+        if UNDO_CHANGES.try_with(|_| ()).is_ok() {
+            return action.await;
+        }
+        let (result, changes) = UNDO_CHANGES.scope(RefCell::new(Vec::new()), async {
+            let result = action.await;
+            (result, UNDO_CHANGES.with(|changes| changes.take()))
+        }).await;
+
+        // Even a failed use case keeps the changes it made before failing, unless its transaction rolled them back
+        if !changes.is_empty() {
+            let mut stack = self.undo_stack();
+            stack.push_back(UndoStep { origin: origin.to_string(), performed_at: chrono::Utc::now().naive_utc(), changes });
+            while stack.len() > UNDO_STACK_DEPTH {
+                stack.pop_front();
+            }
+        }
+        result
+    }
+
+    /// Run a use case as one unit of work: its provision, month, operation, declaration, planning,
+    /// settings, working day, simulation, KPI, audit and synchronisation writes are committed
+    /// together, or all rolled back if it fails
+    /// A transaction started within another one joins it
+    async fn transaction<T, F, Fut>(&self, action: F) -> DomainResult<T>
+    where
+        F: FnOnce(AppService) -> Fut,
+        Fut: std::future::Future<Output = DomainResult<T>>,
+    {
+        if self.in_transaction {
+            return action(self.clone()).await;
+        }
+        let unit = self.deps.units_of_work.begin().await?;
+        let deps = AppDeps {
+            provisions: unit.provisions(),
+            config: unit.config(),
+            months: unit.months(),
+            operations: unit.operations(),
+            declarations: unit.declarations(),
+            yearly_planning: unit.yearly_planning(),
+            audit: unit.audit(),
+            working_days: unit.working_days(),
+            simulations: unit.simulations(),
+            kpis: unit.kpis(),
+            sync: unit.sync(),
+            archives: unit.archives(),
+            ..self.deps.clone()
+        };
+        let scoped = AppService { deps, undo: self.undo.clone(), in_transaction: true };
+
+        let recorded = UNDO_CHANGES.try_with(|changes| changes.borrow().len()).ok();
+        let result = match action(scoped).await {
+            Ok(value) => unit.commit().await.map(|_| value),
+            Err(e) => {
+                // Dropping the unit rolls it back anyway: the use case error is the one to report
+                let _ = unit.rollback().await;
+                Err(e)
+            }
+        };
+        if let (Err(_), Some(recorded)) = (&result, recorded) {
+            UNDO_CHANGES.with(|changes| changes.borrow_mut().truncate(recorded));
+        }
+        result
+    }
+
+    fn undo_stack(&self) -> std::sync::MutexGuard<'_, VecDeque<UndoStep>> {
+        self.undo.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Current state of the entity of an undo change, serialized as in the change; None if absent or trashed
+    async fn undo_current_state(&self, change: &UndoChange) -> DomainResult<Option<serde_json::Value>> {
+        let invalid = || DomainError::Repo(format!("Étape d'annulation invalide: {} {}", change.entity.as_str(), change.entity_id));
+        match change.entity {
+            AuditEntity::Operation => {
+                let id = change.entity_id.parse().map_err(|_| invalid())?;
+                sync_state(sync_found(self.deps.operations.get_operation(id).await)?)
+            }
+            AuditEntity::YearlyPlanning => {
+                let year = change.entity_id.parse().map_err(|_| invalid())?;
+                sync_state(self.deps.yearly_planning.get_yearly_planning(year).await?)
+            }
+            AuditEntity::MonthPlanning => {
+                let month = undo_state::<MonthPlanning>(&change.before)?
+                    .or(undo_state::<MonthPlanning>(&change.after)?)
+                    .ok_or_else(invalid)?;
+                sync_state(self.deps.yearly_planning.get_month_planning(month.year, month.month).await?)
+            }
+            AuditEntity::Declaration => {
+                let id = change.entity_id.parse().map_err(|_| invalid())?;
+                sync_state(sync_found(self.deps.declarations.get_declaration(id).await)?)
+            }
+            AuditEntity::Provision => {
+                let id: uuid::Uuid = change.entity_id.parse().map_err(|_| invalid())?;
+                sync_state(self.deps.provisions.list_provisions(None).await?.into_iter().find(|p| p.id == id))
+            }
+            AuditEntity::Settings => sync_state(Some(self.deps.config.load_settings().await?)),
+        }
+    }
+
+    /// Put an entity back in its state before the change, provided nothing changed it since
+    async fn revert_change(&self, origin: &str, change: &UndoChange) -> DomainResult<()> {
+        if !undo_state_matches(&self.undo_current_state(change).await?, &change.after) {
+            return Err(DomainError::Validation(format!(
+                "Annulation impossible: {} {} a été modifié depuis", change.entity.as_str(), change.entity_id
+            )));
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let invalid = || DomainError::Repo(format!("Étape d'annulation invalide: {} {}", change.entity.as_str(), change.entity_id));
+        match change.entity {
+            AuditEntity::Operation => {
+                let id = change.entity_id.parse().map_err(|_| invalid())?;
+                match (undo_state::<Operation>(&change.before)?, &change.after) {
+                    (None, _) => self.trash_operation(origin, id).await,
+                    (Some(mut before), Some(_)) => {
+                        before.updated_at = now;
+                        self.store_operation(origin, before).await
+                    }
+                    (Some(_), None) => self.restore_trashed_operation(origin, id).await,
+                }
+            }
+            AuditEntity::YearlyPlanning => {
+                let year = change.entity_id.parse().map_err(|_| invalid())?;
+                match (undo_state::<YearlyPlanning>(&change.before)?, &change.after) {
+                    (None, _) => self.trash_yearly_planning(origin, year).await,
+                    (Some(mut before), Some(_)) => {
+                        let current = self.deps.yearly_planning.get_yearly_planning(year).await?.ok_or(DomainError::NotFound)?;
+                        before.updated_at = now;
+                        self.deps.yearly_planning.update_yearly_planning(before.clone()).await?;
+                        self.record_audit(origin, AuditEntity::YearlyPlanning, year, Some(&current), Some(&before)).await
+                    }
+                    (Some(_), None) => self.restore_trashed_yearly_planning(origin, year).await,
+                }
+            }
+            AuditEntity::MonthPlanning => {
+                let mut before = undo_state::<MonthPlanning>(&change.before)?.ok_or_else(invalid)?;
+                let current = self.deps.yearly_planning.get_month_planning(before.year, before.month).await?.ok_or(DomainError::NotFound)?;
+                before.updated_at = now;
+                self.deps.yearly_planning.update_month_planning(before.clone()).await?;
+                self.record_audit(origin, AuditEntity::MonthPlanning, &change.entity_id, Some(&current), Some(&before)).await
+            }
+            AuditEntity::Declaration => {
+                let id = change.entity_id.parse().map_err(|_| invalid())?;
+                match undo_state::<Declaration>(&change.before)? {
+                    None => {
+                        let current = self.deps.declarations.get_declaration(id).await?;
+                        self.deps.declarations.delete_declaration(id).await?;
+                        self.record_audit(origin, AuditEntity::Declaration, id, Some(&current), None).await
+                    }
+                    Some(mut before) => {
+                        before.updated_at = now;
+                        self.store_declaration(origin, before).await
+                    }
+                }
+            }
+            AuditEntity::Provision => {
+                let id: uuid::Uuid = change.entity_id.parse().map_err(|_| invalid())?;
+                let current = self.deps.provisions.list_provisions(None).await?
+                    .into_iter()
+                    .find(|p| p.id == id)
+                    .ok_or(DomainError::NotFound)?;
+                match undo_state::<Provision>(&change.before)? {
+                    None => {
+                        self.deps.provisions.delete_provision(id).await?;
+                        self.record_audit(origin, AuditEntity::Provision, id, Some(&current), None).await
+                    }
+                    Some(mut before) => {
+                        before.updated_at = now;
+                        self.store_provision(origin, before, Some(&current)).await
+                    }
+                }
+            }
+            AuditEntity::Settings => {
+                let before = undo_state::<Settings>(&change.before)?.ok_or_else(invalid)?;
+                let current = self.deps.config.load_settings().await?;
+                self.deps.config.save_settings(before.clone()).await?;
+                self.record_audit(origin, AuditEntity::Settings, "settings", Some(&current), Some(&before)).await
+            }
+        }
+    }
+
+    async fn find_trashed_operation(&self, id: uuid::Uuid) -> DomainResult<TrashedOperation> {
+        self.deps.operations.list_trashed_operations().await?
+            .into_iter()
+            .find(|t| t.operation.id == id)
+            .ok_or(DomainError::NotFound)
+    }
+
+    async fn find_trashed_yearly_planning(&self, year: i32) -> DomainResult<TrashedYearlyPlanning> {
+        self.deps.yearly_planning.list_trashed_yearly_plannings().await?
+            .into_iter()
+            .find(|t| t.planning.year == year)
+            .ok_or(DomainError::NotFound)
+    }
+
+    /// Operations created by issuing an invoice: only a credit note can cancel them
+    async fn invoiced_operation_ids(&self) -> DomainResult<HashSet<uuid::Uuid>> {
+        Ok(self.deps.issued_invoices.list_issued_invoices(None).await?
+            .into_iter().map(|i| i.operation_id).collect())
+    }
+
+    async fn trash_operation(&self, origin: &str, id: uuid::Uuid) -> DomainResult<()> {
+        let operation = self.deps.operations.get_operation(id).await?;
+        self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+        if self.invoiced_operation_ids().await?.contains(&id) {
+            return Err(DomainError::Validation(
+                "Opération liée à une facture émise: établir un avoir plutôt que de la supprimer".into()
+            ));
+        }
+        self.deps.operations.trash_operation(id, chrono::Utc::now().naive_utc()).await?;
+        self.record_audit(origin, AuditEntity::Operation, id, Some(&operation), None).await
+    }
+
+    async fn restore_trashed_operation(&self, origin: &str, id: uuid::Uuid) -> DomainResult<()> {
+        let trashed = self.find_trashed_operation(id).await?;
+        self.ensure_fiscal_year_open(trashed.operation.invoice_date.year()).await?;
+        self.deps.operations.restore_operation(id).await?;
+        self.record_audit(origin, AuditEntity::Operation, id, None, Some(&trashed.operation)).await
+    }
+
+    async fn trash_yearly_planning(&self, origin: &str, year: i32) -> DomainResult<()> {
+        let planning = self.deps.yearly_planning.get_yearly_planning(year).await?.ok_or(DomainError::NotFound)?;
+        self.deps.yearly_planning.trash_yearly_planning(year, chrono::Utc::now().naive_utc()).await?;
+        self.record_audit(origin, AuditEntity::YearlyPlanning, year, Some(&planning), None).await
+    }
+
+    async fn restore_trashed_yearly_planning(&self, origin: &str, year: i32) -> DomainResult<()> {
+        let trashed = self.find_trashed_yearly_planning(year).await?;
+        self.deps.yearly_planning.restore_yearly_planning(year).await?;
+        self.record_audit(origin, AuditEntity::YearlyPlanning, year, None, Some(&trashed.planning)).await
+    }
+
+    // ============ Backup Use Cases ============
+
+    pub async fn get_backup_policy(&self) -> DomainResult<BackupPolicy> {
+        self.deps.backups.load_backup_policy().await
+    }
+
+    pub async fn save_backup_policy(&self, policy: BackupPolicy) -> DomainResult<()> {
+        self.deps.backups.save_backup_policy(policy).await
+    }
+
+    /// Snapshot the database, push it off-site if enabled, then apply the retention policy
+    pub async fn create_backup(&self, reason: BackupReason) -> DomainResult<BackupRun> {
+        let policy = self.deps.backups.load_backup_policy().await?;
+        let backup = self.deps.backups.create_backup(reason).await?;
+
+        let offsite_key = if policy.offsite {
+            let key = format!("{}{}", BACKUP_KEY_PREFIX, backup.name);
+            let content = self.deps.backups.read_backup(&backup.name).await?;
+            self.deps.documents.put(&key, content).await?;
+            Some(key)
+        } else {
+            None
+        };
+
+        let pruned = backups_to_prune(&self.deps.backups.list_backups().await?, &policy);
+        for name in &pruned {
+            self.deps.backups.delete_backup(name).await?;
+            if policy.offsite {
+                match self.deps.documents.delete(&format!("{}{}", BACKUP_KEY_PREFIX, name)).await {
+                    Ok(()) | Err(DomainError::NotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(BackupRun { backup, offsite_key, pruned })
+    }
+
+    /// Daily snapshot, taken on the first call of the day
+    pub async fn run_scheduled_backup(&self) -> DomainResult<Option<BackupRun>> {
+        let today = chrono::Local::now().date_naive();
+        let backups = self.deps.backups.list_backups().await?;
+        if backups.iter().any(|b| b.reason == BackupReason::Scheduled && b.created_at.date() == today) {
+            return Ok(None);
+        }
+        self.create_backup(BackupReason::Scheduled).await.map(Some)
+    }
+
+    pub async fn list_backups(&self) -> DomainResult<Vec<BackupInfo>> {
+        self.deps.backups.list_backups().await
+    }
+
+    pub async fn verify_backup(&self, name: &str) -> DomainResult<BackupVerification> {
+        self.deps.backups.verify_backup(name).await
+    }
+
+    /// Integrity-check a snapshot and schedule it to replace the database at next startup
+    pub async fn restore_backup(&self, name: &str) -> DomainResult<BackupVerification> {
+        let verification = self.deps.backups.verify_backup(name).await?;
+        if !verification.ok {
+            return Err(DomainError::Validation(format!("Sauvegarde {} inutilisable: {}", name, verification.message)));
+        }
+        self.deps.backups.stage_restore(name).await?;
+        Ok(verification)
+    }
+
+    // ============ Database Doctor Use Cases ============
+
+    /// Scan every table for values the application cannot read; with `repair`, snapshot the
+    /// database then apply the proposed fixes. The report lists what is left afterwards
+    pub async fn run_database_doctor(&self, repair: bool) -> DomainResult<DoctorReport> {
+        let scan = self.deps.doctor.scan_database().await?;
+        let mut report = DoctorReport {
+            scanned_at: chrono::Local::now().naive_local(),
+            rows_scanned: scan.rows_scanned,
+            issues: scan.issues,
+            repairs: Vec::new(),
+            backup: None,
+        };
+        let fixable: Vec<DoctorIssue> = report.issues.iter().filter(|i| i.fix.is_some()).cloned().collect();
+        if !repair || fixable.is_empty() {
+            return Ok(report);
+        }
+
+        report.backup = Some(self.create_backup(BackupReason::PreRepair).await?.backup.name);
+        for issue in fixable {
+            let error = match self.deps.doctor.apply_fix(&issue).await {
+                Ok(()) => {
+                    self.record_repair(&issue).await?;
+                    None
+                }
+                Err(e) => Some(e.to_string()),
+            };
+            report.repairs.push(DoctorRepair { issue, error });
+        }
+
+        let rescan = self.deps.doctor.scan_database().await?;
+        report.rows_scanned = rescan.rows_scanned;
+        report.issues = rescan.issues;
+        Ok(report)
+    }
+
+    /// Repairs of audited tables join the audit trail, with the repaired column as state
+    async fn record_repair(&self, issue: &DoctorIssue) -> DomainResult<()> {
+        let entity = match issue.table.as_str() {
+            "operations" => AuditEntity::Operation,
+            "declarations" => AuditEntity::Declaration,
+            "provisions" => AuditEntity::Provision,
+            "yearly_planning" => AuditEntity::YearlyPlanning,
+            "month_planning" => AuditEntity::MonthPlanning,
+            _ => return Ok(()),
+        };
+        let column = issue.column.clone().unwrap_or_else(|| "row".to_string());
+        let before = serde_json::json!({ column.clone(): issue.value });
+        let after = match &issue.fix {
+            Some(DoctorFix::SetValue { value }) => Some(serde_json::json!({ column: value })),
+            _ => None,
+        };
+        self.record_audit("database_doctor", entity, &issue.row_id, Some(&before), after.as_ref()).await
+    }
+
+    // ============ Workspace Use Cases ============
+
+    /// Cash plan of the workspace this service runs on, to be consolidated with the others
+    pub async fn workspace_cash_plan(&self, workspace: Workspace, start: MonthId, horizon: u32) -> DomainResult<WorkspaceCashPlan> {
+        let (forecast, provisions) = tokio::try_join!(
+            self.forecast(start, horizon),
+            self.deps.provisions.list_provisions(None),
+        )?;
+        let pending_provisions_cents = provisions.iter()
+            .filter(|p| p.status != ProvisionStatus::Paid)
+            .map(|p| p.amount_cents)
+            .sum();
+        Ok(WorkspaceCashPlan { workspace, pending_provisions_cents, forecast })
+    }
+
+    // ============ Dataset Export Use Cases ============
+
+    /// Every record of the workspace in the portable dump format, see `DatasetDump`
+    pub async fn export_dataset(&self) -> DomainResult<DatasetDump> {
+        let (settings, operations, declarations, provisions, closed_months, yearly_plannings, simulations, monthly_kpis, working_days) = tokio::try_join!(
+            self.deps.config.load_settings(),
+            self.deps.operations.list_operations(None),
+            self.deps.declarations.list_declarations(None),
+            self.deps.provisions.list_provisions(None),
+            self.deps.months.list_closed_months(),
+            self.deps.yearly_planning.list_yearly_plannings(),
+            self.deps.simulations.list_simulations(),
+            self.list_all_monthly_kpis(),
+            self.deps.working_days.list_working_days(None, None),
+        )?;
+        Ok(DatasetDump {
+            format: DATASET_FORMAT.to_string(),
+            version: DATASET_VERSION,
+            exported_at: chrono::Utc::now().naive_utc(),
+            settings: Some(settings),
+            operations,
+            declarations,
+            provisions,
+            closed_months,
+            yearly_plannings,
+            simulations,
+            monthly_kpis,
+            working_days,
+        })
+    }
+
+    /// Load a dump into the workspace in one transaction, after a snapshot of the database
+    /// Operations, simulations and working days are matched on their identifier; declarations and
+    /// provisions on their period, plannings on their year and KPIs on their month, so that dumps
+    /// of two installations merge. Financial changes join the audit trail; the import itself
+    /// cannot be undone, the snapshot is there for that
+    /// A replace never reopens a closed month nor deletes the operation of an issued invoice
+    pub async fn import_dataset(&self, content: &[u8], mode: DatasetImportMode) -> DomainResult<DatasetImportReport> {
+        let (dump, source_version) = DatasetDump::parse(content)?;
+        dump.validate()?;
+        let backup = self.create_backup(BackupReason::PreImport).await?.backup.name;
+        let replace = mode == DatasetImportMode::Replace;
+        let counts = self.transaction(|app| async move {
+            Ok(vec![
+                app.import_settings(dump.settings).await?,
+                app.import_operations(dump.operations, replace).await?,
+                app.import_declarations(dump.declarations, replace).await?,
+                app.import_provisions(dump.provisions, replace).await?,
+                app.import_closed_months(dump.closed_months, replace).await?,
+                app.import_yearly_plannings(dump.yearly_plannings, replace).await?,
+                app.import_simulations(dump.simulations, replace).await?,
+                app.import_monthly_kpis(dump.monthly_kpis, replace).await?,
+                app.import_working_days(dump.working_days, replace).await?,
+            ])
+        }).await?;
+        Ok(DatasetImportReport { mode, source_version, backup, counts })
+    }
+
+    async fn list_all_monthly_kpis(&self) -> DomainResult<Vec<MonthlyKPI>> {
+        self.deps.kpis.list_monthly_kpis(&MonthId::new(1, 1), &MonthId::new(9999, 12)).await
+    }
+
+    async fn import_settings(&self, settings: Option<Settings>) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("settings");
+        let Some(settings) = settings else { return Ok(count) };
+        let before = self.deps.config.load_settings().await?;
+        if same_state(&before, &settings) {
+            count.unchanged += 1;
+        } else {
+            self.deps.config.save_settings(settings.clone()).await?;
+            self.record_audit(IMPORT_ORIGIN, AuditEntity::Settings, "settings", Some(&before), Some(&settings)).await?;
+            count.updated += 1;
+        }
+        Ok(count)
+    }
+
+    async fn import_operations(&self, operations: Vec<Operation>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("operations");
+        let mut current: HashMap<uuid::Uuid, Operation> = self.deps.operations.list_operations(None).await?
+            .into_iter().map(|o| (o.id, o)).collect();
+        let mut trashed: HashMap<uuid::Uuid, Operation> = self.deps.operations.list_trashed_operations().await?
+            .into_iter().map(|t| (t.operation.id, t.operation)).collect();
+        if replace {
+            let imported: HashSet<uuid::Uuid> = operations.iter().map(|o| o.id).collect();
+            let invoiced = self.invoiced_operation_ids().await?;
+            for operation in current.values().chain(trashed.values()).filter(|o| !imported.contains(&o.id)) {
+                if invoiced.contains(&operation.id) {
+                    return Err(DomainError::Validation(format!(
+                        "Opération {} d'une facture émise absente de l'export: les factures émises n'en font pas partie", operation.id
+                    )));
+                }
+                self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+                self.deps.operations.delete_operation(operation.id).await?;
+                self.record_audit(IMPORT_ORIGIN, AuditEntity::Operation, operation.id, Some(operation), None).await?;
+                count.deleted += 1;
+            }
+        }
+        for operation in operations {
+            let restored = trashed.remove(&operation.id);
+            if let Some(before) = &restored {
+                self.restore_trashed_operation(IMPORT_ORIGIN, before.id).await?;
+                current.insert(before.id, before.clone());
+            }
+            match current.get(&operation.id) {
+                Some(before) if same_state(before, &operation) => {
+                    if restored.is_some() { count.updated += 1 } else { count.unchanged += 1 }
+                }
+                Some(before) => {
+                    self.ensure_fiscal_year_open(before.invoice_date.year()).await?;
+                    self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+                    self.store_operation(IMPORT_ORIGIN, operation).await?;
+                    count.updated += 1;
+                }
+                None => {
+                    self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+                    self.insert_operation(IMPORT_ORIGIN, operation).await?;
+                    count.created += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_declarations(&self, declarations: Vec<Declaration>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("declarations");
+        let key = |d: &Declaration| (format!("{:?}", d.declaration_type), d.period_year, d.period_month);
+        let current: HashMap<_, Declaration> = self.deps.declarations.list_declarations(None).await?
+            .into_iter().map(|d| (key(&d), d)).collect();
+        if replace {
+            let imported: HashSet<_> = declarations.iter().map(key).collect();
+            for declaration in current.values().filter(|d| !imported.contains(&key(d))) {
+                self.deps.declarations.delete_declaration(declaration.id).await?;
+                self.record_audit(IMPORT_ORIGIN, AuditEntity::Declaration, declaration.id, Some(declaration), None).await?;
+                count.deleted += 1;
+            }
+        }
+        for declaration in declarations {
+            match current.get(&key(&declaration)) {
+                Some(before) => {
+                    let declaration = Declaration { id: before.id, ..declaration };
+                    if same_state(before, &declaration) {
+                        count.unchanged += 1;
+                    } else {
+                        self.store_declaration(IMPORT_ORIGIN, declaration).await?;
+                        count.updated += 1;
+                    }
+                }
+                None => {
+                    self.deps.declarations.create_declaration(declaration.clone()).await?;
+                    self.record_audit(IMPORT_ORIGIN, AuditEntity::Declaration, declaration.id, None, Some(&declaration)).await?;
+                    count.created += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_provisions(&self, provisions: Vec<Provision>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("provisions");
+        let key = |p: &Provision| (format!("{:?}", p.provision_type), p.period_year, p.period_month);
+        let current: HashMap<_, Provision> = self.deps.provisions.list_provisions(None).await?
+            .into_iter().map(|p| (key(&p), p)).collect();
+        if replace {
+            let imported: HashSet<_> = provisions.iter().map(key).collect();
+            for provision in current.values().filter(|p| !imported.contains(&key(p))) {
+                self.deps.provisions.delete_provision(provision.id).await?;
+                self.record_audit(IMPORT_ORIGIN, AuditEntity::Provision, provision.id, Some(provision), None).await?;
+                count.deleted += 1;
+            }
+        }
+        for provision in provisions {
+            let before = current.get(&key(&provision));
+            let provision = Provision { id: before.map_or(provision.id, |b| b.id), ..provision };
+            match before {
+                Some(before) if same_state(before, &provision) => count.unchanged += 1,
+                _ => {
+                    self.store_provision(IMPORT_ORIGIN, provision, before).await?;
+                    if before.is_some() { count.updated += 1 } else { count.created += 1 }
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_closed_months(&self, closed_months: Vec<MonthStatus>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("closed_months");
+        let current: HashSet<(i32, u32)> = self.deps.months.list_closed_months().await?
+            .into_iter().map(|m| (m.month.year, m.month.month)).collect();
+        if replace {
+            let imported: HashSet<(i32, u32)> = closed_months.iter().map(|m| (m.month.year, m.month.month)).collect();
+            let mut reopened: Vec<String> = current.difference(&imported).map(|(y, m)| format!("{:04}-{:02}", y, m)).collect();
+            if !reopened.is_empty() {
+                reopened.sort();
+                return Err(DomainError::Validation(format!(
+                    "Mois clôturés absents de l'export, une clôture ne s'annule pas: {}", reopened.join(", ")
+                )));
+            }
+        }
+        for status in closed_months {
+            match status.closed_at {
+                Some(closed_at) if !current.contains(&(status.month.year, status.month.month)) => {
+                    self.deps.months.close_month(&status.month, closed_at).await?;
+                    count.created += 1;
+                }
+                _ => count.unchanged += 1,
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_yearly_plannings(&self, plannings: Vec<YearlyPlanning>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("yearly_plannings");
+        let mut current: HashMap<i32, YearlyPlanning> = self.deps.yearly_planning.list_yearly_plannings().await?
+            .into_iter().map(|p| (p.year, p)).collect();
+        let mut trashed: HashMap<i32, YearlyPlanning> = self.deps.yearly_planning.list_trashed_yearly_plannings().await?
+            .into_iter().map(|t| (t.planning.year, t.planning)).collect();
+        if replace {
+            let imported: HashSet<i32> = plannings.iter().map(|p| p.year).collect();
+            for planning in current.values().chain(trashed.values()).filter(|p| !imported.contains(&p.year)) {
+                self.deps.yearly_planning.delete_yearly_planning(planning.year).await?;
+                self.record_audit(IMPORT_ORIGIN, AuditEntity::YearlyPlanning, planning.year, Some(planning), None).await?;
+                count.deleted += 1;
+            }
+        }
+        for planning in plannings {
+            let restored = trashed.remove(&planning.year);
+            if let Some(before) = &restored {
+                self.restore_trashed_yearly_planning(IMPORT_ORIGIN, before.year).await?;
+                current.insert(before.year, before.clone());
+            }
+            match current.get(&planning.year) {
+                Some(before) => {
+                    let planning = YearlyPlanning { id: before.id, ..planning };
+                    if same_state(before, &planning) {
+                        if restored.is_some() { count.updated += 1 } else { count.unchanged += 1 }
+                    } else {
+                        self.deps.yearly_planning.update_yearly_planning(planning.clone()).await?;
+                        self.record_audit(IMPORT_ORIGIN, AuditEntity::YearlyPlanning, planning.year, Some(before), Some(&planning)).await?;
+                        count.updated += 1;
+                    }
+                }
+                None => {
+                    self.deps.yearly_planning.create_yearly_planning(planning.clone()).await?;
+                    self.record_audit(IMPORT_ORIGIN, AuditEntity::YearlyPlanning, planning.year, None, Some(&planning)).await?;
+                    count.created += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_simulations(&self, simulations: Vec<Simulation>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("simulations");
+        let current: HashMap<uuid::Uuid, Simulation> = self.deps.simulations.list_simulations().await?
+            .into_iter().map(|s| (s.id, s)).collect();
+        if replace {
+            let imported: HashSet<uuid::Uuid> = simulations.iter().map(|s| s.id).collect();
+            for id in current.keys().filter(|id| !imported.contains(id)) {
+                self.deps.simulations.delete_simulation(*id).await?;
+                count.deleted += 1;
+            }
+        }
+        for simulation in simulations {
+            match current.get(&simulation.id) {
+                Some(before) if same_state(before, &simulation) => count.unchanged += 1,
+                Some(_) => {
+                    self.deps.simulations.update_simulation(simulation).await?;
+                    count.updated += 1;
+                }
+                None => {
+                    self.deps.simulations.create_simulation(simulation).await?;
+                    count.created += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_monthly_kpis(&self, kpis: Vec<MonthlyKPI>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("monthly_kpis");
+        let current: HashMap<(i32, u32), MonthlyKPI> = self.list_all_monthly_kpis().await?
+            .into_iter().map(|k| ((k.month.year, k.month.month), k)).collect();
+        if replace {
+            let imported: HashSet<(i32, u32)> = kpis.iter().map(|k| (k.month.year, k.month.month)).collect();
+            for kpi in current.values().filter(|k| !imported.contains(&(k.month.year, k.month.month))) {
+                self.deps.kpis.delete_monthly_kpi(&kpi.month).await?;
+                count.deleted += 1;
+            }
+        }
+        for kpi in kpis {
+            let before = current.get(&(kpi.month.year, kpi.month.month));
+            let kpi = MonthlyKPI { id: before.map_or(kpi.id, |b| b.id), ..kpi };
+            match before {
+                Some(before) if same_state(before, &kpi) => count.unchanged += 1,
+                _ => {
+                    self.deps.kpis.save_monthly_kpi(kpi).await?;
+                    if before.is_some() { count.updated += 1 } else { count.created += 1 }
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn import_working_days(&self, working_days: Vec<WorkingDay>, replace: bool) -> DomainResult<DatasetImportCount> {
+        let mut count = DatasetImportCount::new("working_days");
+        let current: HashMap<uuid::Uuid, WorkingDay> = self.deps.working_days.list_working_days(None, None).await?
+            .into_iter().map(|w| (w.id, w)).collect();
+        if replace {
+            let imported: HashSet<uuid::Uuid> = working_days.iter().map(|w| w.id).collect();
+            for id in current.keys().filter(|id| !imported.contains(id)) {
+                self.deps.working_days.delete_working_day(*id).await?;
+                count.deleted += 1;
+            }
+        }
+        for working_day in working_days {
+            match current.get(&working_day.id) {
+                Some(before) if same_state(before, &working_day) => count.unchanged += 1,
+                Some(_) => {
+                    self.deps.working_days.update_working_day(working_day).await?;
+                    count.updated += 1;
+                }
+                None => {
+                    self.deps.working_days.create_working_day(working_day).await?;
+                    count.created += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    // ============ Synchronisation Use Cases ============
+
+    pub async fn get_sync_settings(&self) -> DomainResult<Option<SyncSettings>> {
+        self.deps.sync.load_sync_settings().await
+    }
+
+    pub async fn save_sync_settings(&self, settings: SyncSettings) -> DomainResult<()> {
+        settings.validate()?;
+        self.deps.sync.save_sync_settings(settings).await
+    }
+
+    /// Most recent first
+    pub async fn list_sync_conflicts(&self) -> DomainResult<Vec<SyncConflict>> {
+        self.deps.sync.list_sync_conflicts().await
+    }
+
+    /// The user has reviewed the conflict
+    pub async fn dismiss_sync_conflict(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.deps.sync.dismiss_sync_conflict(id).await
+    }
+
+    /// Publish the changes made here to the own log, then replay those of the other installations
+    /// Each change is replayed in its own transaction; one that cannot be applied (closed fiscal
+    /// year, period already declared…) is skipped and reported so it does not hold back the others
+    pub async fn sync_now(&self, log: &dyn ChangeLog) -> DomainResult<SyncReport> {
+        let settings = self.deps.sync.load_sync_settings().await?
+            .ok_or_else(|| DomainError::Validation("Synchronisation non configurée".into()))?;
+        let published = self.publish_sync_changes(log, &settings.peer_id).await?;
+
+        let SyncLogs { changes, unreadable } = log.read_changes(&settings.peer_id).await?;
+        let mut positions = self.deps.sync.applied_positions().await?;
+        for (peer, position) in positions.iter_mut() {
+            // A log shorter than what was applied from it started over, in another shared folder
+            // (an unreadable one has no changes here, and keeps its position)
+            let length = changes.iter().filter(|c| &c.peer == peer).count() as i64;
+            if length > 0 && length < *position {
+                *position = 0;
+            }
+        }
+
+        let pending = changes.into_iter()
+            .filter(|c| c.seq > positions.get(&c.peer).copied().unwrap_or(0))
+            .collect();
+        let mut report = SyncReport { peer_id: settings.peer_id, published, applied: 0, conflicts: Vec::new(), unreadable_logs: unreadable };
+        for change in interleave_sync_changes(pending) {
+            let conflicts = match self.apply_sync_change(change.clone()).await {
+                Ok(conflicts) => conflicts,
+                Err(DomainError::Repo(e)) => return Err(DomainError::Repo(e)),
+                Err(e) => self.skip_sync_change(change, e).await?,
+            };
+            report.applied += 1;
+            report.conflicts.extend(conflicts);
+        }
+        Ok(report)
+    }
+
+    /// Append the changes made here since the last publication to the own log
+    /// A new log starts with the current state instead, which also covers what predates the audit trail
+    async fn publish_sync_changes(&self, log: &dyn ChangeLog, peer: &str) -> DomainResult<u32> {
+        let mut position = log.log_length(peer).await?;
+        let (mut changes, published) = if position == 0 {
+            let latest = self.deps.audit.list_audit_events(AuditQuery { limit: Some(1), ..AuditQuery::default() }).await?;
+            (self.sync_snapshot(peer).await?, latest.first().map_or(0, |e| e.seq))
+        } else {
+            let published = self.deps.sync.published_audit_seq().await?;
+            let mut events = self.deps.audit.list_audit_events(AuditQuery { after_seq: Some(published), ..AuditQuery::default() }).await?;
+            events.reverse();
+            let published = events.last().map_or(published, |e| e.seq);
+            let changes = events.into_iter()
+                .filter(|e| !e.origin.starts_with(SYNC_ORIGIN_PREFIX))
+                .map(|e| SyncChange {
+                    peer: peer.to_string(),
+                    seq: 0,
+                    entity: e.entity,
+                    entity_id: e.entity_id,
+                    action: e.action,
+                    before: e.before,
+                    after: e.after,
+                    changed_at: e.occurred_at,
+                })
+                .collect::<Vec<_>>();
+            (changes, published)
+        };
+        for change in changes.iter_mut() {
+            position += 1;
+            change.seq = position;
+        }
+
+        // Written before the positions are saved: a failure in between publishes the changes twice, which replays as nothing
+        log.append_changes(peer, &changes).await?;
+        let count = changes.len() as u32;
+        self.transaction(|app| async move {
+            for change in &changes {
+                app.stamp_sync_clocks(change).await?;
+            }
+            app.deps.sync.set_published_audit_seq(published).await
+        }).await?;
+        Ok(count)
+    }
+
+    /// Current records as creations, stamped with their last modification to the second, like
+    /// the audit events published after them
+    /// Settings never changed here carry nothing to share and are left out
+    async fn sync_snapshot(&self, peer: &str) -> DomainResult<Vec<SyncChange>> {
+        let dump = self.export_dataset().await?;
+        let created = |entity: AuditEntity, entity_id: String, state: Option<serde_json::Value>, changed_at: chrono::NaiveDateTime| SyncChange {
+            peer: peer.to_string(),
+            seq: 0,
+            entity,
+            entity_id,
+            action: AuditAction::Create,
+            before: None,
+            after: state,
+            changed_at: changed_at.with_nanosecond(0).unwrap_or(changed_at),
+        };
+        let mut changes = Vec::new();
+        let settings_changed = self.entity_history(AuditEntity::Settings, "settings").await?.first().map(|e| e.occurred_at);
+        if let (Some(settings), Some(changed_at)) = (dump.settings, settings_changed) {
+            changes.push(created(AuditEntity::Settings, "settings".to_string(), sync_state(Some(settings))?, changed_at));
+        }
+        for operation in dump.operations {
+            changes.push(created(AuditEntity::Operation, operation.id.to_string(), sync_state(Some(&operation))?, operation.updated_at));
+        }
+        for declaration in dump.declarations {
+            changes.push(created(AuditEntity::Declaration, declaration.id.to_string(), sync_state(Some(&declaration))?, declaration.updated_at));
+        }
+        for provision in dump.provisions {
+            changes.push(created(AuditEntity::Provision, provision.id.to_string(), sync_state(Some(&provision))?, provision.updated_at));
+        }
+        for planning in dump.yearly_plannings {
+            changes.push(created(AuditEntity::YearlyPlanning, planning.year.to_string(), sync_state(Some(&planning))?, planning.updated_at));
+        }
+        Ok(changes)
+    }
+
+    /// Clocks of the fields a change wrote, and of its record when created or deleted
+    async fn stamp_sync_clocks(&self, change: &SyncChange) -> DomainResult<()> {
+        let stamp = change.stamp();
+        for field in change.changed_fields() {
+            self.deps.sync.advance_field_clock(change.entity, &change.entity_id, &field, &stamp).await?;
+        }
+        if change.action != AuditAction::Update {
+            self.deps.sync.advance_field_clock(change.entity, &change.entity_id, SYNC_RECORD_CLOCK, &stamp).await?;
+        }
+        Ok(())
+    }
+
+    async fn apply_sync_change(&self, change: SyncChange) -> DomainResult<Vec<SyncConflict>> {
+        self.transaction(|app| async move {
+            let conflicts = app.replay_sync_change(&change).await?;
+            app.stamp_sync_clocks(&change).await?;
+            for conflict in &conflicts {
+                app.deps.sync.add_sync_conflict(conflict.clone()).await?;
+            }
+            app.deps.sync.set_applied_position(&change.peer, change.seq).await?;
+            Ok(conflicts)
+        }).await
+    }
+
+    async fn skip_sync_change(&self, change: SyncChange, error: DomainError) -> DomainResult<Vec<SyncConflict>> {
+        let conflict = SyncConflict {
+            id: uuid::Uuid::new_v4(),
+            peer: change.peer.clone(),
+            entity: change.entity,
+            entity_id: change.entity_id.clone(),
+            field: None,
+            local_value: self.sync_record_state(change.entity, &change.entity_id).await.ok().flatten(),
+            remote_value: change.after.clone(),
+            kept: SyncSide::Local,
+            message: format!("Modification de {} non appliquée: {}", change.peer, error),
+            detected_at: chrono::Utc::now().naive_utc(),
+        };
+        self.transaction(|app| async move {
+            app.deps.sync.add_sync_conflict(conflict.clone()).await?;
+            app.deps.sync.set_applied_position(&change.peer, change.seq).await?;
+            Ok(vec![conflict])
+        }).await
+    }
+
+    /// Replay a change of another installation on the record it targets, see `merge_sync_change`
+    /// A deletion is only replayed when nothing was changed here since; a change to a record deleted
+    /// here is only replayed, restoring the record, when it is later than the deletion
+    async fn replay_sync_change(&self, change: &SyncChange) -> DomainResult<Vec<SyncConflict>> {
+        let origin = format!("{}{}", SYNC_ORIGIN_PREFIX, change.peer);
+        let stamp = change.stamp();
+        let conflict = |field: Option<String>, local_value, remote_value, kept, message: String| SyncConflict {
+            id: uuid::Uuid::new_v4(),
+            peer: change.peer.clone(),
+            entity: change.entity,
+            entity_id: change.entity_id.clone(),
+            field,
+            local_value,
+            remote_value,
+            kept,
+            message,
+            detected_at: chrono::Utc::now().naive_utc(),
+        };
+        let changed_fields = change.changed_fields();
+        if change.action == AuditAction::Update && changed_fields.is_empty() {
+            return Ok(Vec::new());
+        }
+        let clocks = self.deps.sync.field_clocks(change.entity, &change.entity_id).await?;
+        let mut local = self.sync_record_state(change.entity, &change.entity_id).await?;
+
+        if change.action == AuditAction::Delete {
+            let Some(current) = local else { return Ok(Vec::new()) };
+            if clocks.values().any(|clock| *clock > stamp) {
+                let message = format!("Supprimé sur {}, modifié ici depuis: conservé", change.peer);
+                return Ok(vec![conflict(None, Some(current), None, SyncSide::Local, message)]);
+            }
+            self.remove_synced_record(&origin, change.entity, &change.entity_id).await?;
+            return Ok(Vec::new());
+        }
+
+        if local.is_none() {
+            if clocks.get(SYNC_RECORD_CLOCK).is_some_and(|deleted| *deleted > stamp) {
+                let message = format!("Modifié sur {}, supprimé ici depuis: non restauré", change.peer);
+                return Ok(vec![conflict(None, None, change.after.clone(), SyncSide::Local, message)]);
+            }
+            if !self.restore_synced_record(&origin, change.entity, &change.entity_id).await? {
+                let state = change.after.clone().ok_or_else(|| DomainError::Validation("Modification sans état".into()))?;
+                self.write_synced_record(&origin, change.entity, &change.entity_id, None, state).await?;
+                return Ok(Vec::new());
+            }
+            local = self.sync_record_state(change.entity, &change.entity_id).await?;
+        }
+
+        let current = local.ok_or(DomainError::NotFound)?;
+        let merge = merge_sync_change(&current, &clocks, change);
+        if !merge.taken.is_empty() {
+            self.write_synced_record(&origin, change.entity, &change.entity_id, Some(current), merge.state).await?;
+        }
+        Ok(merge.conflicts.into_iter().map(|c| {
+            let message = match c.kept {
+                SyncSide::Local => format!("Modifié ici et sur {}: valeur d'ici conservée", change.peer),
+                SyncSide::Remote => format!("Modifié ici et sur {}: valeur de {} conservée", change.peer, change.peer),
+            };
+            conflict(Some(c.field), Some(c.local), Some(c.remote), c.kept, message)
+        }).collect())
+    }
+
+    /// Active record as exchanged in the change logs; trashed records count as absent
+    async fn sync_record_state(&self, entity: AuditEntity, entity_id: &str) -> DomainResult<Option<serde_json::Value>> {
+        let invalid = || DomainError::Validation(format!("Identifiant synchronisé invalide: {} {}", entity.as_str(), entity_id));
+        match entity {
+            AuditEntity::Operation => {
+                let id = entity_id.parse().map_err(|_| invalid())?;
+                sync_state(sync_found(self.deps.operations.get_operation(id).await)?)
+            }
+            AuditEntity::Declaration => {
+                let id = entity_id.parse().map_err(|_| invalid())?;
+                sync_state(sync_found(self.deps.declarations.get_declaration(id).await)?)
+            }
+            AuditEntity::Provision => {
+                let id: uuid::Uuid = entity_id.parse().map_err(|_| invalid())?;
+                sync_state(self.deps.provisions.list_provisions(None).await?.into_iter().find(|p| p.id == id))
+            }
+            AuditEntity::Settings => sync_state(Some(self.deps.config.load_settings().await?)),
+            AuditEntity::YearlyPlanning => {
+                let year = entity_id.parse().map_err(|_| invalid())?;
+                sync_state(self.deps.yearly_planning.get_yearly_planning(year).await?)
+            }
+            AuditEntity::MonthPlanning => {
+                let (year, month) = entity_id.split_once('-')
+                    .and_then(|(y, m)| Some((y.parse().ok()?, m.parse().ok()?)))
+                    .ok_or_else(invalid)?;
+                sync_state(self.deps.yearly_planning.get_month_planning(year, month).await?)
+            }
+        }
+    }
+
+    /// Write the merged state of a record, audited with the origin `sync:<peer>`
+    async fn write_synced_record(
+        &self,
+        origin: &str,
+        entity: AuditEntity,
+        entity_id: &str,
+        before: Option<serde_json::Value>,
+        state: serde_json::Value,
+    ) -> DomainResult<()> {
+        match entity {
+            AuditEntity::Operation => {
+                let operation: Operation = sync_record(state)?;
+                self.ensure_fiscal_year_open(operation.invoice_date.year()).await?;
+                match before.map(sync_record::<Operation>).transpose()? {
+                    Some(before) => {
+                        self.ensure_fiscal_year_open(before.invoice_date.year()).await?;
+                        self.store_operation(origin, operation).await
+                    }
+                    None => self.insert_operation(origin, operation).await,
+                }
+            }
+            AuditEntity::Declaration => {
+                let declaration: Declaration = sync_record(state)?;
+                if before.is_some() {
+                    return self.store_declaration(origin, declaration).await;
+                }
+                self.deps.declarations.create_declaration(declaration.clone()).await?;
+                self.record_audit(origin, AuditEntity::Declaration, declaration.id, None, Some(&declaration)).await
+            }
+            AuditEntity::Provision => {
+                let before: Option<Provision> = before.map(sync_record).transpose()?;
+                self.store_provision(origin, sync_record(state)?, before.as_ref()).await
+            }
+            AuditEntity::Settings => {
+                let settings: Settings = sync_record(state)?;
+                let before = self.deps.config.load_settings().await?;
+                self.deps.config.save_settings(settings.clone()).await?;
+                self.record_audit(origin, AuditEntity::Settings, "settings", Some(&before), Some(&settings)).await
+            }
+            AuditEntity::YearlyPlanning => {
+                let planning: YearlyPlanning = sync_record(state)?;
+                let before: Option<YearlyPlanning> = before.map(sync_record).transpose()?;
+                match &before {
+                    Some(_) => self.deps.yearly_planning.update_yearly_planning(planning.clone()).await?,
+                    None => self.deps.yearly_planning.create_yearly_planning(planning.clone()).await?,
+                }
+                self.record_audit(origin, AuditEntity::YearlyPlanning, planning.year, before.as_ref(), Some(&planning)).await
+            }
+            AuditEntity::MonthPlanning => {
+                // Months are created with their yearly planning
+                let before: MonthPlanning = before.map(sync_record).transpose()?.ok_or(DomainError::NotFound)?;
+                let month: MonthPlanning = sync_record(state)?;
+                self.deps.yearly_planning.update_month_planning(month.clone()).await?;
+                self.record_audit(origin, AuditEntity::MonthPlanning, entity_id, Some(&before), Some(&month)).await
+            }
+        }
+    }
+
+    /// Operations and plannings go to the trash, like a deletion made here
+    async fn remove_synced_record(&self, origin: &str, entity: AuditEntity, entity_id: &str) -> DomainResult<()> {
+        let invalid = || DomainError::Validation(format!("Identifiant synchronisé invalide: {} {}", entity.as_str(), entity_id));
+        match entity {
+            AuditEntity::Operation => self.trash_operation(origin, entity_id.parse().map_err(|_| invalid())?).await,
+            AuditEntity::YearlyPlanning => self.trash_yearly_planning(origin, entity_id.parse().map_err(|_| invalid())?).await,
+            AuditEntity::Declaration => {
+                let declaration = self.deps.declarations.get_declaration(entity_id.parse().map_err(|_| invalid())?).await?;
+                self.deps.declarations.delete_declaration(declaration.id).await?;
+                self.record_audit(origin, AuditEntity::Declaration, declaration.id, Some(&declaration), None).await
+            }
+            AuditEntity::Provision => {
+                let id: uuid::Uuid = entity_id.parse().map_err(|_| invalid())?;
+                let provision = self.deps.provisions.list_provisions(None).await?
+                    .into_iter()
+                    .find(|p| p.id == id)
+                    .ok_or(DomainError::NotFound)?;
+                self.deps.provisions.delete_provision(id).await?;
+                self.record_audit(origin, AuditEntity::Provision, id, Some(&provision), None).await
+            }
+            AuditEntity::Settings | AuditEntity::MonthPlanning => Err(invalid()),
+        }
+    }
+
+    /// Whether the record was in the trash and has been restored
+    async fn restore_synced_record(&self, origin: &str, entity: AuditEntity, entity_id: &str) -> DomainResult<bool> {
+        let restored = match entity {
+            AuditEntity::Operation => match entity_id.parse() {
+                Ok(id) => sync_found(self.restore_trashed_operation(origin, id).await)?,
+                Err(_) => None,
+            },
+            AuditEntity::YearlyPlanning => match entity_id.parse() {
+                Ok(year) => sync_found(self.restore_trashed_yearly_planning(origin, year).await)?,
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        Ok(restored.is_some())
+    }
+}
+
+// ============ New DTOs ============
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnhancedDashboardData {
+    pub basic_summary: DashboardSummary,
+    pub monthly_kpi: Option<MonthlyKPI>,
+    pub working_pattern: Option<WorkingPatternAnalysis>,
+    pub working_days_count: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWorkingDayDto {
+    pub date: String, // ISO date
+    pub hours_worked: f64,
+    pub billable_hours: f64,
+    pub hourly_rate_cents: i64,
+    pub description: Option<String>,
+}
+
+impl CreateWorkingDayDto {
+    pub fn into_entity(self) -> Result<WorkingDay, String> {
+        let date = chrono::NaiveDate::parse_from_str(&self.date, "%Y-%m-%d")
+            .map_err(|e| e.to_string())?;
+        
+        let now = chrono::Utc::now().naive_utc();
+        
+        Ok(WorkingDay {
+            id: uuid::Uuid::new_v4(),
+            date,
+            hours_worked: self.hours_worked,
+            billable_hours: self.billable_hours,
+            hourly_rate_cents: self.hourly_rate_cents,
+            description: self.description,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSimulationDto {
+    pub name: String,
+    pub scenario_type: String, // "DailyRateOptimization", "AnnualIncomeProjection", etc.
+    pub parameters: SimulationParameters,
+}
+
+impl CreateSimulationDto {
+    pub fn into_entity(self) -> Result<Simulation, String> {
+        let scenario_type = match self.scenario_type.as_str() {
+            "DailyRateOptimization" => SimulationScenario::DailyRateOptimization,
+            "AnnualIncomeProjection" => SimulationScenario::AnnualIncomeProjection,
+            "TaxOptimization" => SimulationScenario::TaxOptimization,
+            "WorkingDaysImpact" => SimulationScenario::WorkingDaysImpact,
+            _ => return Err("Type de scénario invalide".into()),
+        };
+        
+        let now = chrono::Utc::now().naive_utc();
+        
+        Ok(Simulation {
+            id: uuid::Uuid::new_v4(),
+            name: self.name,
+            scenario_type,
+            parameters: self.parameters,
+            results: None,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+}
+
+// ============ Operation DTOs ============
+
+/// Receipt stored from a supplier file, with what could be read from its Factur-X data
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierInvoiceImport {
+    pub receipt: ReceiptUpload,
+    pub invoice: Option<ExtractedInvoice>,
+    pub operation: Option<CreateOperationDto>, // Prefilled purchase, to be reviewed before creation
+    pub extraction_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOperationDto {
+    pub invoice_date: String,               // "YYYY-MM-DD" Invoice date
+    pub payment_date: Option<String>,       // "YYYY-MM-DD" Unified payment/encaissement date
+    pub operation_type: String,             // "sale" or "purchase"
+    pub amount_ht_cents: i64,               // HT amount in cents
+    pub vat_amount_cents: Option<i64>,      // VAT amount direct (calculated if not provided)
+    pub vat_on_payments: bool,              // true by default
+    pub label: Option<String>,              // Description
+    #[serde(default)]
+    pub client: Option<String>,             // Client of a sale, supplier of a purchase
+    #[serde(default)]
+    pub category: Option<String>,           // Category id of the frontend catalogue
+    #[serde(alias = "receipt_url")]
+    pub receipt_key: Option<String>,        // Document store key returned by upload_receipt
+    pub receipt_sha256: Option<String>,     // Returned by upload_receipt
+}
+
+impl CreateOperationDto {
+    /// Purchase prefilled from a Factur-X invoice; credit notes give negative amounts
+    pub fn from_supplier_invoice(invoice: &ExtractedInvoice, receipt: &ReceiptUpload) -> Self {
+        let sign = if invoice.is_credit_note() { -1 } else { 1 };
+        Self {
+            invoice_date: invoice.issue_date.format("%Y-%m-%d").to_string(),
+            payment_date: None,
+            operation_type: "purchase".to_string(),
+            amount_ht_cents: sign * invoice.total_ht_cents,
+            vat_amount_cents: Some(sign * invoice.total_vat_cents),
+            vat_on_payments: invoice.vat_on_payments(),
+            label: Some(format!("{} - {}", invoice.seller_name, invoice.invoice_number)),
+            client: Some(invoice.seller_name.clone()),
+            category: None,
+            receipt_key: Some(receipt.key.clone()),
+            receipt_sha256: Some(receipt.sha256.clone()),
+        }
+    }
+
+    pub fn into_entity(self, default_vat_rate_ppm: i32) -> Result<Operation, String> {
+        let invoice_date = chrono::NaiveDate::parse_from_str(&self.invoice_date, "%Y-%m-%d")
+            .map_err(|e| format!("Invoice date invalid: {}", e))?;
+        
+        let operation_type = match self.operation_type.as_str() {
+            "purchase" => OperationType::Purchase,
+            "sale" => OperationType::Sale,
+            _ => return Err("Operation type invalid: must be 'sale' or 'purchase'".into()),
+        };
+
+        let payment_date = if let Some(date_str) = self.payment_date {
+            Some(chrono::NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                .map_err(|e| format!("Payment date invalid: {}", e))?)
+        } else {
+            None
+        };
+
+        // Automatic VAT calculation if not provided
+        let vat_amount_cents = if let Some(vat) = self.vat_amount_cents {
+            vat
+        } else {
+            // Use default rate
+            ((self.amount_ht_cents as i128) * (default_vat_rate_ppm as i128) / 1_000_000i128) as i64
+        };
+
+        let amount_ttc_cents = self.amount_ht_cents + vat_amount_cents;
 
         let now = chrono::Utc::now().naive_utc();
 
@@ -829,7 +2988,10 @@ impl CreateOperationDto {
             amount_ttc_cents,
             vat_on_payments: self.vat_on_payments,
             label: self.label,
-            receipt_url: self.receipt_url,
+            client: self.client.filter(|c| !c.trim().is_empty()),
+            category: self.category.filter(|c| !c.trim().is_empty()),
+            receipt_key: self.receipt_key,
+            receipt_sha256: self.receipt_sha256,
             created_at: now,
             updated_at: now,
         })
@@ -846,7 +3008,13 @@ pub struct UpdateOperationDto {
     pub operation_type: String,
     pub vat_on_payments: bool,
     pub payment_date: Option<String>,
-    pub receipt_url: Option<String>,
+    #[serde(default)]
+    pub client: Option<String>, // Absent: unchanged; empty: cleared
+    #[serde(default)]
+    pub category: Option<String>, // Absent: unchanged; empty: cleared
+    #[serde(alias = "receipt_url")]
+    pub receipt_key: Option<String>,
+    pub receipt_sha256: Option<String>,
 }
 
 impl UpdateOperationDto {
@@ -882,7 +3050,16 @@ impl UpdateOperationDto {
             amount_ttc_cents,
             vat_on_payments: self.vat_on_payments,
             label: self.label,
-            receipt_url: self.receipt_url,
+            client: match self.client {
+                Some(client) => Some(client).filter(|c| !c.trim().is_empty()),
+                None => existing_operation.client,
+            },
+            category: match self.category {
+                Some(category) => Some(category).filter(|c| !c.trim().is_empty()),
+                None => existing_operation.category,
+            },
+            receipt_key: self.receipt_key,
+            receipt_sha256: self.receipt_sha256,
             created_at: existing_operation.created_at, // Preserve creation date
             updated_at: chrono::Utc::now().naive_utc(),
         })
@@ -998,25 +3175,43 @@ impl UpdateYearlyPlanningDto {
 impl AppService {
     // Yearly Planning Services
     pub async fn create_yearly_planning(&self, dto: CreateYearlyPlanningDto) -> DomainResult<()> {
-        let planning = dto.to_domain();
-        self.deps.yearly_planning.create_yearly_planning(planning).await
+        self.undoable("create_yearly_planning", self.transaction(|app| async move {
+            let planning = dto.to_domain();
+            let trashed = app.deps.yearly_planning.list_trashed_yearly_plannings().await?;
+            if trashed.iter().any(|t| t.planning.year == planning.year) {
+                return Err(DomainError::Validation(format!(
+                    "Le planning {} est dans la corbeille: restaurez-le ou supprimez-le définitivement", planning.year
+                )));
+            }
+            app.deps.yearly_planning.create_yearly_planning(planning.clone()).await?;
+            app.record_audit("create_yearly_planning", AuditEntity::YearlyPlanning, planning.year, None, Some(&planning)).await
+        })).await
     }
 
     pub async fn update_yearly_planning(&self, dto: UpdateYearlyPlanningDto) -> DomainResult<()> {
-        // Get existing planning to preserve creation dates and IDs
-        let existing_planning = self.deps.yearly_planning.get_yearly_planning(dto.year).await?
-            .ok_or_else(|| DomainError::NotFound)?;
-        
-        let updated_planning = dto.to_domain(&existing_planning);
-        self.deps.yearly_planning.update_yearly_planning(updated_planning).await
+        self.undoable("update_yearly_planning", self.transaction(|app| async move {
+            // Get existing planning to preserve creation dates and IDs
+            let existing_planning = app.deps.yearly_planning.get_yearly_planning(dto.year).await?
+                .ok_or_else(|| DomainError::NotFound)?;
+
+            let updated_planning = dto.to_domain(&existing_planning);
+            app.deps.yearly_planning.update_yearly_planning(updated_planning.clone()).await?;
+            app.record_audit("update_yearly_planning", AuditEntity::YearlyPlanning, updated_planning.year, Some(&existing_planning), Some(&updated_planning)).await
+        })).await
     }
 
     pub async fn get_yearly_planning(&self, year: i32) -> DomainResult<Option<YearlyPlanning>> {
         self.deps.yearly_planning.get_yearly_planning(year).await
     }
 
+    /// Move a planning and its months to the trash, from where it can be restored until purged
     pub async fn delete_yearly_planning(&self, year: i32) -> DomainResult<()> {
-        self.deps.yearly_planning.delete_yearly_planning(year).await
+        self.undoable("delete_yearly_planning", async move {
+            match self.deps.yearly_planning.get_yearly_planning(year).await? {
+                Some(_) => self.trash_yearly_planning("delete_yearly_planning", year).await,
+                None => Ok(()),
+            }
+        }).await
     }
 
     pub async fn list_yearly_plannings(&self) -> DomainResult<Vec<YearlyPlanning>> {
@@ -1024,22 +3219,701 @@ impl AppService {
     }
 
     pub async fn update_month_planning(&self, year: i32, month: u32, dto: UpdateMonthPlanningDto) -> DomainResult<()> {
-        let existing_month = self.deps.yearly_planning.get_month_planning(year, month).await?
-            .ok_or_else(|| DomainError::NotFound)?;
-        
-        let updated_month = MonthPlanning {
-            id: existing_month.id,
-            year,
-            month,
-            max_working_days: dto.max_working_days,
-            holidays_taken: dto.holidays_taken,
-            public_holidays: dto.public_holidays,
-            working_days: dto.working_days,
-            estimated_revenue_cents: dto.estimated_revenue_cents,
-            created_at: existing_month.created_at,
-            updated_at: chrono::Utc::now().naive_utc(),
+        self.undoable("update_month_planning", self.transaction(|app| async move {
+            let existing_month = app.deps.yearly_planning.get_month_planning(year, month).await?
+                .ok_or_else(|| DomainError::NotFound)?;
+
+            let updated_month = MonthPlanning {
+                id: existing_month.id,
+                year,
+                month,
+                max_working_days: dto.max_working_days,
+                holidays_taken: dto.holidays_taken,
+                public_holidays: dto.public_holidays,
+                working_days: dto.working_days,
+                estimated_revenue_cents: dto.estimated_revenue_cents,
+                created_at: existing_month.created_at,
+                updated_at: chrono::Utc::now().naive_utc(),
+            };
+
+            app.deps.yearly_planning.update_month_planning(updated_month.clone()).await?;
+            let entity_id = format!("{:04}-{:02}", year, month);
+            app.record_audit("update_month_planning", AuditEntity::MonthPlanning, entity_id, Some(&existing_month), Some(&updated_month)).await
+        })).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Service over a fresh database file in `dir`, backups and documents next to it
+    async fn service_in(dir: &std::path::Path) -> AppService {
+        std::fs::create_dir_all(dir).unwrap();
+        let repos = infra::connect_and_migrate(&format!("sqlite://{}", dir.join("cash.db").display())).await.unwrap();
+        let root = dir.join("documents");
+        AppService::new(AppDeps {
+            provisions: Arc::new(repos.provisions()),
+            config: Arc::new(repos.config()),
+            months: Arc::new(repos.months()),
+            operations: Arc::new(repos.operations()),
+            declarations: Arc::new(repos.declarations()),
+            working_days: Arc::new(repos.working_days()),
+            tax_schedules: Arc::new(repos.tax_schedules()),
+            simulations: Arc::new(repos.simulations()),
+            kpis: Arc::new(repos.kpis()),
+            yearly_planning: Arc::new(repos.yearly_planning()),
+            issued_invoices: Arc::new(repos.issued_invoices()),
+            archives: Arc::new(repos.archives()),
+            backups: Arc::new(repos.backups()),
+            audit: Arc::new(repos.audit()),
+            doctor: Arc::new(repos.doctor()),
+            sync: Arc::new(repos.sync()),
+            units_of_work: Arc::new(repos.units_of_work()),
+            documents: Arc::new(infra::LocalDocumentStore::new(root).unwrap()),
+        })
+    }
+
+    async fn service() -> AppService {
+        service_in(&scratch_dir()).await
+    }
+
+    fn scratch_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cash-planner-app-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn sale(invoice: &str, payment: Option<&str>, amount_ht_cents: i64) -> Operation {
+        let now = chrono::Utc::now().naive_utc();
+        Operation {
+            id: uuid::Uuid::new_v4(),
+            invoice_date: date(invoice),
+            payment_date: payment.map(date),
+            operation_type: OperationType::Sale,
+            amount_ht_cents,
+            vat_amount_cents: amount_ht_cents / 5,
+            amount_ttc_cents: amount_ht_cents + amount_ht_cents / 5,
+            vat_on_payments: true,
+            label: Some("Mission".to_string()),
+            client: None,
+            category: None,
+            receipt_key: None,
+            receipt_sha256: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archived_year_is_read_only() {
+        let app = service().await;
+        let last_year = chrono::Local::now().year() - 1;
+        let archived_day = format!("{}-06-15", last_year);
+
+        let receipt_key = app.deps.documents.upload(b"facture".to_vec(), "facture.pdf", None).await.unwrap();
+        let mut paid = sale(&archived_day, Some(&archived_day), 100000);
+        paid.receipt_key = Some(receipt_key);
+        let unpaid = sale(&archived_day, None, 50000);
+        let current = sale(&format!("{}-01-10", last_year + 1), None, 20000);
+        for operation in [&paid, &unpaid, &current] {
+            app.create_operation(operation.clone()).await.unwrap();
+        }
+
+        let archive = app.archive_fiscal_year(last_year).await.unwrap();
+        assert_eq!(archive.operations_count, 2);
+        // The receipt hash was recorded with the archive
+        let stored = app.get_operation(paid.id).await.unwrap();
+        assert!(stored.receipt_sha256.is_some());
+        assert_eq!(app.archive_report().await.unwrap().documents.len(), 1);
+        assert!(matches!(app.archive_fiscal_year(last_year).await, Err(DomainError::Validation(_))));
+
+        // No change, no move in or out, no addition; only the payment of an unpaid operation
+        let mut edited = stored.clone();
+        edited.amount_ht_cents += 100;
+        assert!(matches!(app.update_operation(edited).await, Err(DomainError::Validation(_))));
+        let mut moved_out = app.get_operation(unpaid.id).await.unwrap();
+        moved_out.invoice_date = current.invoice_date;
+        assert!(matches!(app.update_operation(moved_out).await, Err(DomainError::Validation(_))));
+        let mut moved_in = app.get_operation(current.id).await.unwrap();
+        moved_in.invoice_date = date(&archived_day);
+        assert!(matches!(app.update_operation(moved_in).await, Err(DomainError::Validation(_))));
+        assert!(app.create_operation(sale(&archived_day, None, 1000)).await.is_err());
+        assert!(app.delete_operation(paid.id).await.is_err());
+
+        let mut settled = app.get_operation(unpaid.id).await.unwrap();
+        settled.payment_date = Some(date(&format!("{}-02-01", last_year + 1)));
+        app.update_operation(settled).await.unwrap();
+        assert_eq!(app.get_operation(unpaid.id).await.unwrap().payment_date, Some(date(&format!("{}-02-01", last_year + 1))));
+    }
+
+    #[tokio::test]
+    async fn test_close_month_reports_a_failed_backup() {
+        let dir = scratch_dir();
+        let app = service_in(&dir).await;
+        app.create_operation(sale("2025-03-03", Some("2025-03-10"), 100000)).await.unwrap();
+        let march = MonthId::new(2025, 3);
+
+        // A file in the way of the backup folder: the month is closed all the same
+        std::fs::write(dir.join("backups"), b"").unwrap();
+        let closing = app.close_month(march.clone()).await.unwrap();
+        assert!(closing.backup.is_none());
+        assert!(closing.backup_error.is_some());
+        assert!(app.get_month_status(march.clone()).await.unwrap().closed_at.is_some());
+        assert_eq!(app.list_provisions(Some(march)).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_provision_and_declaration_lifecycle() {
+        let app = service().await;
+        app.create_operation(sale("2025-03-03", Some("2025-03-10"), 100000)).await.unwrap();
+        let march = MonthId::new(2025, 3);
+        app.close_month(march.clone()).await.unwrap();
+        let provisions = app.list_provisions(Some(march.clone())).await.unwrap();
+        let provision = |provision_type: ProvisionType| provisions.iter().find(|p| p.provision_type == provision_type).unwrap().clone();
+        assert_eq!((provision(ProvisionType::Vat).amount_cents, provision(ProvisionType::Urssaf).amount_cents), (20000, 22000));
+
+        // URSSAF is due on the 5th, VAT on the 20th
+        let refreshed = app.refresh_provision_statuses(date("2025-04-10")).await.unwrap();
+        let status = |provision_type: ProvisionType| refreshed.iter().find(|p| p.provision_type == provision_type).unwrap().status.clone();
+        assert_eq!((status(ProvisionType::Urssaf), status(ProvisionType::Vat)), (ProvisionStatus::Overdue, ProvisionStatus::Pending));
+
+        // Payment before filing is refused; once filed, the amount is frozen and drift is kept apart
+        let declaration = app.generate_declaration(DeclarationType::Vat, march.clone()).await.unwrap();
+        assert_eq!((declaration.amount_due_cents, declaration.due_date), (20000, date("2025-04-20")));
+        assert!(matches!(app.record_declaration_payment(declaration.id, date("2025-04-15")).await, Err(DomainError::Validation(_))));
+        app.file_declaration(declaration.id, date("2025-04-12")).await.unwrap();
+        app.create_operation(sale("2025-03-20", Some("2025-03-25"), 10000)).await.unwrap();
+        let drifted = app.generate_declaration(DeclarationType::Vat, march.clone()).await.unwrap();
+        assert_eq!(drifted.id, declaration.id);
+        assert_eq!((drifted.amount_due_cents, drifted.recomputed_amount_cents, drifted.amount_diff_cents()), (20000, Some(22000), 2000));
+
+        // Paying the declaration settles the provision of the period
+        let paid = app.record_declaration_payment(declaration.id, date("2025-04-15")).await.unwrap();
+        assert_eq!(paid.status, DeclarationStatus::Paid);
+        let provisions = app.list_provisions(Some(march.clone())).await.unwrap();
+        let settled = provisions.iter().find(|p| p.provision_type == ProvisionType::Vat).unwrap();
+        assert_eq!(settled.status, ProvisionStatus::Paid);
+        assert!(matches!(app.file_declaration(declaration.id, date("2025-04-16")).await, Err(DomainError::Validation(_))));
+
+        // Closing again leaves the paid provision as it was
+        app.close_month(march.clone()).await.unwrap();
+        let vat = app.list_provisions(Some(march)).await.unwrap().into_iter().find(|p| p.provision_type == ProvisionType::Vat).unwrap();
+        assert_eq!((vat.amount_cents, vat.status), (20000, ProvisionStatus::Paid));
+    }
+
+    #[tokio::test]
+    async fn test_tax_schedule_partial_payments() {
+        let app = service().await;
+        let march = MonthId::new(2025, 3);
+        app.create_operation(sale("2025-03-03", Some("2025-03-10"), 100000)).await.unwrap();
+        app.sync_tax_schedules(&march, 1).await.unwrap();
+        let stored = app.deps.tax_schedules.list_tax_schedules(None, None).await.unwrap();
+        let urssaf = stored.iter().find(|s| s.tax_type == TaxType::Urssaf).unwrap().clone();
+        assert_eq!(urssaf.amount_cents, 22000);
+
+        // Partial payments, never beyond what is left
+        app.record_tax_payment(urssaf.id, date("2025-04-05"), 10000, Some("ACOMPTE".to_string())).await.unwrap();
+        assert!(matches!(app.record_tax_payment(urssaf.id, date("2025-04-06"), 12001, None).await, Err(DomainError::Validation(_))));
+        assert!(matches!(app.record_tax_payment(urssaf.id, date("2025-04-06"), 0, None).await, Err(DomainError::Validation(_))));
+        let partly_paid = app.deps.tax_schedules.get_tax_schedule(urssaf.id).await.unwrap();
+        assert_eq!((partly_paid.paid_amount_cents, partly_paid.outstanding_cents()), (10000, 12000));
+        assert_eq!((partly_paid.paid_date, partly_paid.payment_reference.as_deref()), (Some(date("2025-04-05")), Some("ACOMPTE")));
+
+        app.record_tax_payment(urssaf.id, date("2025-04-20"), 12000, None).await.unwrap();
+        let paid = app.deps.tax_schedules.get_tax_schedule(urssaf.id).await.unwrap();
+        assert_eq!((paid.outstanding_cents(), paid.status), (0, TaxScheduleStatus::Paid));
+        assert_eq!(app.list_tax_payments(urssaf.id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tax_schedules_sync() {
+        let app = service().await;
+        let march = MonthId::new(2025, 3);
+        app.create_operation(sale("2025-03-03", Some("2025-03-10"), 100000)).await.unwrap();
+        let plan = app.sync_tax_schedules(&march, 2).await.unwrap();
+        assert_eq!(plan.to_create.len(), 2);
+        let again = app.sync_tax_schedules(&march, 2).await.unwrap();
+        assert!(again.to_create.is_empty() && again.to_update.is_empty() && again.to_delete.is_empty());
+        let tax_schedules = app.deps.tax_schedules.clone();
+        let schedule = |tax_type: TaxType| {
+            let tax_schedules = tax_schedules.clone();
+            async move { tax_schedules.list_tax_schedules(None, None).await.unwrap().into_iter().find(|s| s.tax_type == tax_type) }
         };
-        
-        self.deps.yearly_planning.update_month_planning(updated_month).await
+        let urssaf = schedule(TaxType::Urssaf).await.unwrap();
+        app.record_tax_payment(urssaf.id, date("2025-04-05"), 10000, None).await.unwrap();
+
+        // New figures refresh the unpaid entry in place and keep the one with a payment
+        let vat = schedule(TaxType::Vat).await.unwrap();
+        let extra = sale("2025-03-20", Some("2025-03-25"), 50000);
+        app.create_operation(extra.clone()).await.unwrap();
+        let plan = app.sync_tax_schedules(&march, 2).await.unwrap();
+        assert_eq!((plan.to_update.len(), plan.kept_paid.len()), (1, 1));
+        let refreshed = schedule(TaxType::Vat).await.unwrap();
+        assert_eq!((refreshed.id, refreshed.amount_cents), (vat.id, 30000));
+        assert_eq!(schedule(TaxType::Urssaf).await.unwrap().amount_cents, 22000);
+
+        // Stale unpaid entries go when the operations behind them do
+        app.delete_operation(extra.id).await.unwrap();
+        let mut all = app.list_operations(None).await.unwrap();
+        app.delete_operation(all.remove(0).id).await.unwrap();
+        let plan = app.sync_tax_schedules(&march, 2).await.unwrap();
+        assert_eq!(plan.to_delete, vec![vat.id]);
+        assert!(schedule(TaxType::Vat).await.is_none());
+        assert_eq!(schedule(TaxType::Urssaf).await.unwrap().id, urssaf.id);
+    }
+
+    #[tokio::test]
+    async fn test_upload_receipt_dedups_on_the_hash_alone() {
+        let app = service().await;
+        // Stored in an earlier month and linked to nothing
+        let sha256 = sha256_hex(b"ticket");
+        let earlier = receipt_key(&sha256, "ticket.pdf", date("2024-11-20"));
+        app.deps.documents.put(&earlier, b"ticket".to_vec()).await.unwrap();
+
+        let again = app.upload_receipt(b"ticket".to_vec(), "copie.PDF", None).await.unwrap();
+        assert_eq!((again.key.as_str(), again.already_stored), (earlier.as_str(), true));
+        assert!(again.duplicate_of.is_empty());
+        let fresh = app.upload_receipt(b"facture".to_vec(), "facture.pdf", None).await.unwrap();
+        assert!(!fresh.already_stored && fresh.key != earlier);
+        assert_eq!(app.deps.documents.list_all().await.unwrap().len(), 2);
+
+        // Once linked, the operations are reported as well
+        let linked = Operation { receipt_key: Some(earlier.clone()), receipt_sha256: Some(sha256), ..sale("2025-03-03", None, 1000) };
+        app.create_operation(linked.clone()).await.unwrap();
+        let third = app.upload_receipt(b"ticket".to_vec(), "ticket.pdf", None).await.unwrap();
+        assert_eq!((third.key, third.duplicate_of), (earlier, vec![linked.id]));
+    }
+
+    #[tokio::test]
+    async fn test_verify_receipts_covers_the_whole_store() {
+        let app = service().await;
+        let with_receipt = |key: &str| Operation { receipt_key: Some(key.to_string()), ..sale("2025-03-03", None, 1000) };
+        let intact = app.upload_receipt(b"ticket".to_vec(), "ticket.pdf", None).await.unwrap().key;
+        let altered = app.upload_receipt(b"facture".to_vec(), "facture.pdf", None).await.unwrap().key;
+        let missing = app.upload_receipt(b"note".to_vec(), "note.pdf", None).await.unwrap().key;
+        let unhashed = "2025-03/scan.pdf".to_string();
+        let trashed = "2025-03/avoir.pdf".to_string();
+        app.deps.documents.put(&unhashed, b"scan".to_vec()).await.unwrap();
+        app.deps.documents.put(&trashed, b"avoir".to_vec()).await.unwrap();
+        app.deps.documents.put("2025-03/oublie.pdf", b"oublie".to_vec()).await.unwrap();
+
+        let unhashed_operation = with_receipt(&unhashed);
+        let trashed_operation = with_receipt(&trashed);
+        for operation in [with_receipt(&intact), with_receipt(&altered), with_receipt(&missing), unhashed_operation.clone(), trashed_operation.clone()] {
+            app.create_operation(operation).await.unwrap();
+        }
+        app.delete_operation(trashed_operation.id).await.unwrap();
+        app.deps.documents.put(&altered, b"facture modifiee".to_vec()).await.unwrap();
+        app.deps.documents.delete(&missing).await.unwrap();
+
+        let report = app.verify_receipts().await.unwrap();
+        assert_eq!((report.checked, report.valid), (4, 1));
+        let issue = |key: &str| report.issues.iter().find(|i| i.receipt_key == key).map(|i| format!("{:?}", i.kind));
+        assert_eq!(issue(&altered).as_deref(), Some("Mismatch"));
+        assert_eq!(issue(&missing).as_deref(), Some("Missing"));
+        assert_eq!(issue(&unhashed).as_deref(), Some("Unhashed"));
+        assert_eq!(issue(&intact), None);
+        // The receipt of a trashed operation is still referenced
+        let unreferenced: Vec<&str> = report.unreferenced.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(unreferenced, ["2025-03/oublie.pdf"]);
+        let audit = app.audit_receipts(None).await.unwrap();
+        assert_eq!(audit.orphan_receipts.iter().map(|f| f.key.as_str()).collect::<Vec<_>>(), ["2025-03/oublie.pdf"]);
+
+        // Hashed by the first run, checked by the next
+        assert!(app.get_operation(unhashed_operation.id).await.unwrap().receipt_sha256.is_some());
+        let again = app.verify_receipts().await.unwrap();
+        assert_eq!((again.checked, again.valid, again.issues.len()), (4, 2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_dashboard_counts_vat_on_payment() {
+        let app = service().await;
+        let on_debits = Operation { vat_on_payments: false, ..sale("2025-03-03", Some("2025-04-10"), 100000) };
+        app.create_operation(on_debits).await.unwrap();
+        let legacy = Expense {
+            id: uuid::Uuid::new_v4(),
+            label: String::new(),
+            category: "fournitures".to_string(),
+            booking_date: date("2025-03-05"),
+            amount_ht: 10000,
+            vat_rate_ppm: 200_000,
+            amount_tva: 0,
+            amount_ttc: 0,
+            paid_at: Some(date("2025-03-05")),
+            receipt_path: None,
+        };
+        app.create_expense(legacy.clone()).await.unwrap();
+        // Labelled as migration 0007 labels legacy rows
+        assert_eq!(app.get_operation(legacy.id).await.unwrap().label.as_deref(), Some("fournitures"));
+
+        let march = MonthId::new(2025, 3);
+        let before_payment = app.get_dashboard(march.clone()).await.unwrap();
+        assert_eq!((before_payment.revenue_ht_cents, before_payment.vat_due_cents), (0, -2000));
+        assert_eq!(before_payment.expenses_ttc_cents, 12000);
+        // The operations dashboard charges the VAT on debits to the invoice month
+        let v2 = app.get_dashboard_v2(march).await.unwrap();
+        assert_eq!(v2.vat_due_cents, 20000 - 2000);
+
+        let april = app.get_dashboard(MonthId::new(2025, 4)).await.unwrap();
+        assert_eq!((april.revenue_ht_cents, april.vat_due_cents), (100000, 20000));
+    }
+
+    /// Installation `peer` over in-memory repositories, synchronised through `shared`
+    async fn peer(peer: &str, shared: &std::path::Path) -> AppService {
+        let memory = infra::InMemoryRepos::new();
+        let repos = infra::connect_and_migrate("sqlite::memory:").await.unwrap();
+        let app = AppService::new(AppDeps {
+            provisions: Arc::new(memory.clone()),
+            config: Arc::new(memory.clone()),
+            months: Arc::new(memory.clone()),
+            operations: Arc::new(memory.clone()),
+            declarations: Arc::new(memory.clone()),
+            working_days: Arc::new(memory.clone()),
+            tax_schedules: Arc::new(repos.tax_schedules()),
+            simulations: Arc::new(memory.clone()),
+            kpis: Arc::new(memory.clone()),
+            yearly_planning: Arc::new(memory.clone()),
+            issued_invoices: Arc::new(repos.issued_invoices()),
+            archives: Arc::new(memory.clone()),
+            backups: Arc::new(repos.backups()),
+            audit: Arc::new(memory.clone()),
+            doctor: Arc::new(repos.doctor()),
+            sync: Arc::new(memory.clone()),
+            units_of_work: Arc::new(memory),
+            documents: Arc::new(infra::LocalDocumentStore::new(scratch_dir()).unwrap()),
+        });
+        let settings = SyncSettings { peer_id: peer.to_string(), shared_dir: shared.display().to_string() };
+        app.save_sync_settings(settings).await.unwrap();
+        app
+    }
+
+    #[tokio::test]
+    async fn test_sync_between_two_installations() {
+        let shared = scratch_dir();
+        let log = infra::FolderChangeLog::new(&shared).unwrap();
+        let (desktop, laptop) = (peer("bureau", &shared).await, peer("portable", &shared).await);
+        let this_year = chrono::Local::now().year();
+        let mission = sale(&format!("{}-01-10", this_year), None, 100000);
+        let training = sale(&format!("{}-01-20", this_year), None, 50000);
+
+        // A new log starts with the current state
+        desktop.create_operation(mission.clone()).await.unwrap();
+        assert_eq!(desktop.sync_now(&log).await.unwrap().published, 1);
+        assert_eq!(laptop.sync_now(&log).await.unwrap().applied, 1);
+        assert_eq!(laptop.get_operation(mission.id).await.unwrap().amount_ht_cents, 100000);
+
+        // Deleted there, edited here since: the edit wins on both sides
+        desktop.delete_operation(mission.id).await.unwrap();
+        desktop.sync_now(&log).await.unwrap();
+        let mut edited = laptop.get_operation(mission.id).await.unwrap();
+        edited.amount_ht_cents = 120000;
+        laptop.update_operation(edited).await.unwrap();
+        let report = laptop.sync_now(&log).await.unwrap();
+        assert_eq!((report.published, report.applied), (1, 1));
+        assert!(report.conflicts[0].message.contains("modifié ici depuis"));
+        desktop.sync_now(&log).await.unwrap();
+        assert_eq!(desktop.get_operation(mission.id).await.unwrap().amount_ht_cents, 120000);
+
+        // Deletions and restorations from the trash travel
+        desktop.create_operation(training.clone()).await.unwrap();
+        desktop.delete_operation(training.id).await.unwrap();
+        desktop.sync_now(&log).await.unwrap();
+        laptop.sync_now(&log).await.unwrap();
+        assert!(laptop.get_operation(training.id).await.is_err());
+        desktop.restore_operation(training.id).await.unwrap();
+        desktop.sync_now(&log).await.unwrap();
+        laptop.sync_now(&log).await.unwrap();
+        assert_eq!(laptop.get_operation(training.id).await.unwrap().amount_ht_cents, 50000);
+
+        // A log started over is replayed from its start, as nothing new
+        std::fs::remove_file(shared.join("bureau.jsonl")).unwrap();
+        assert_eq!(desktop.sync_now(&log).await.unwrap().published, 2);
+        let report = laptop.sync_now(&log).await.unwrap();
+        assert_eq!(report.applied, 2);
+        assert!(report.conflicts.is_empty());
+
+        // A change that cannot be applied here is skipped and reported, once
+        laptop.archive_fiscal_year(this_year - 1).await.unwrap();
+        desktop.create_operation(sale(&format!("{}-06-15", this_year - 1), None, 30000)).await.unwrap();
+        desktop.sync_now(&log).await.unwrap();
+        let report = laptop.sync_now(&log).await.unwrap();
+        assert_eq!(report.applied, 1);
+        assert!(report.conflicts[0].message.contains("non appliquée"));
+        assert_eq!(laptop.sync_now(&log).await.unwrap().applied, 0);
+
+        // An unreadable log is left out, the others still sync
+        std::fs::write(shared.join("tiers.jsonl"), "{pas du json\n").unwrap();
+        desktop.create_operation(sale(&format!("{}-02-03", this_year), None, 10000)).await.unwrap();
+        desktop.sync_now(&log).await.unwrap();
+        let report = laptop.sync_now(&log).await.unwrap();
+        assert_eq!(report.applied, 1);
+        assert_eq!(report.unreadable_logs.iter().map(|u| u.peer.as_str()).collect::<Vec<_>>(), ["tiers"]);
+        let count = |app: AppService| async move { app.list_operations(None).await.unwrap().len() };
+        assert_eq!(count(laptop.clone()).await, 3);
+        assert_eq!(count(desktop.clone()).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_undo_reverts_create_update_delete() {
+        let app = service().await;
+        let operation = sale("2025-03-03", None, 100000);
+        app.create_operation(operation.clone()).await.unwrap();
+        let mut edited = app.get_operation(operation.id).await.unwrap();
+        edited.amount_ht_cents = 150000;
+        app.update_operation(edited).await.unwrap();
+        app.delete_operation(operation.id).await.unwrap();
+        let origins: Vec<String> = app.list_undo_steps().into_iter().map(|s| s.origin).collect();
+        assert_eq!(origins, ["delete_operation", "update_operation", "create_operation"]);
+
+        assert_eq!(app.undo_last().await.unwrap().origin, "delete_operation");
+        assert_eq!(app.get_operation(operation.id).await.unwrap().amount_ht_cents, 150000);
+        assert_eq!(app.undo_last().await.unwrap().origin, "update_operation");
+        assert_eq!(app.get_operation(operation.id).await.unwrap().amount_ht_cents, 100000);
+        assert_eq!(app.undo_last().await.unwrap().origin, "create_operation");
+        assert!(matches!(app.get_operation(operation.id).await, Err(DomainError::NotFound)));
+
+        // Reverts are audited but cannot be undone in turn
+        assert!(matches!(app.undo_last().await, Err(DomainError::Validation(_))));
+        let audit = app.list_audit_events(AuditQuery::default()).await.unwrap();
+        assert_eq!(audit.iter().filter(|e| e.origin.starts_with("undo:")).count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_undo_refuses_an_entity_changed_since() {
+        let app = service().await;
+        let operation = sale("2025-03-03", None, 100000);
+        app.create_operation(operation.clone()).await.unwrap();
+        let mut edited = app.get_operation(operation.id).await.unwrap();
+        edited.amount_ht_cents = 150000;
+        app.update_operation(edited.clone()).await.unwrap();
+
+        // Changed outside of any use case, as a replayed change would be
+        edited.label = Some("Mission modifiée".to_string());
+        app.deps.operations.update_operation(edited).await.unwrap();
+
+        match app.undo_last().await {
+            Err(DomainError::Validation(message)) => assert!(message.contains("modifié depuis")),
+            other => panic!("annulation acceptée: {:?}", other.map(|s| s.origin)),
+        }
+        let current = app.get_operation(operation.id).await.unwrap();
+        assert_eq!((current.amount_ht_cents, current.label.as_deref()), (150000, Some("Mission modifiée")));
+        assert_eq!(app.list_undo_steps().len(), 2);
+    }
+
+    #[test]
+    fn test_supplier_invoice_purchase_sign() {
+        let invoice = ExtractedInvoice {
+            profile: None,
+            type_code: "380".to_string(),
+            invoice_number: "FA-2024-0042".to_string(),
+            issue_date: date("2024-03-15"),
+            due_date: None,
+            seller_name: "Hébergeur SAS".to_string(),
+            seller_vat_id: None,
+            buyer_name: None,
+            currency: "EUR".to_string(),
+            vat_breakdown: Vec::new(),
+            total_ht_cents: 11_000,
+            total_vat_cents: 2_055,
+            total_ttc_cents: 13_055,
+        };
+        let receipt = ReceiptUpload { key: "2024-03/facture.pdf".to_string(), sha256: "ab".repeat(32), duplicate_of: Vec::new(), already_stored: false };
+
+        let purchase = CreateOperationDto::from_supplier_invoice(&invoice, &receipt);
+        assert_eq!((purchase.operation_type.as_str(), purchase.amount_ht_cents, purchase.vat_amount_cents), ("purchase", 11_000, Some(2_055)));
+        assert_eq!(purchase.receipt_key.as_deref(), Some("2024-03/facture.pdf"));
+        // A credit note reduces purchases
+        let credit_note = ExtractedInvoice { type_code: "381".to_string(), ..invoice };
+        let refund = CreateOperationDto::from_supplier_invoice(&credit_note, &receipt);
+        assert_eq!((refund.amount_ht_cents, refund.vat_amount_cents), (-11_000, Some(-2_055)));
+        assert_eq!(refund.into_entity(200_000).unwrap().amount_ttc_cents, -13_055);
+    }
+
+    /// Issue a one-line service invoice, the issuer profile saved first
+    async fn issue(app: &AppService, issue_date: &str) -> IssuedInvoice {
+        app.save_issuer_profile(IssuerProfile {
+            name: "Jean Dupont EI".into(),
+            address: "1 rue de la Paix\n75002 Paris".into(),
+            siret: "12345678900012".into(),
+            vat_number: Some("FR12123456789".into()),
+            vat_exempt: false,
+            late_penalty_rate_ppm: 100_000,
+            payment_terms_days: 30,
+            iban: None,
+            email: None,
+        }).await.unwrap();
+        app.issue_invoice(InvoiceDraft {
+            issue_date: date(issue_date),
+            is_service: true,
+            client: InvoiceClient { name: "Client SA".into(), address: "69002 Lyon".into(), siret: None, vat_number: None },
+            lines: vec![InvoiceLine { description: "Développement".into(), quantity_milli: 1_000, unit_price_ht_cents: 100000, vat_rate_ppm: 200_000 }],
+            notes: None,
+        }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issued_invoice_operations_stay_out_of_the_trash() {
+        let app = service().await;
+        let invoice = issue(&app, "2025-03-03").await;
+        match app.delete_operation(invoice.operation_id).await {
+            Err(DomainError::Validation(message)) => assert!(message.contains("avoir")),
+            other => panic!("suppression acceptée: {:?}", other),
+        }
+        assert!(app.get_operation(invoice.operation_id).await.is_ok());
+
+        // Trashed before the check existed: purges keep it and report it, the other operations go
+        let expired = chrono::Utc::now().naive_utc() - chrono::Duration::days(TRASH_RETENTION_DAYS + 1);
+        app.deps.operations.trash_operation(invoice.operation_id, expired).await.unwrap();
+        let other = sale("2025-03-05", None, 20000);
+        app.create_operation(other.clone()).await.unwrap();
+        app.deps.operations.trash_operation(other.id, expired).await.unwrap();
+        assert!(matches!(app.purge_operation(invoice.operation_id).await, Err(DomainError::Validation(_))));
+
+        let purge = app.purge_expired_trash().await.unwrap();
+        assert_eq!((purge.operations, purge.invoiced, purge.skipped), (1, 1, 0));
+        let trash = app.list_trash().await.unwrap();
+        assert_eq!(trash.operations.iter().map(|t| t.operation.id).collect::<Vec<_>>(), [invoice.operation_id]);
+        let audit = app.list_audit_events(AuditQuery::default()).await.unwrap();
+        assert_eq!(audit.iter().filter(|e| e.origin == "purge_expired_trash").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_issued_invoice_operation_only_takes_its_payment() {
+        let app = service().await;
+        let invoice = issue(&app, "2025-03-03").await;
+        let operation = app.get_operation(invoice.operation_id).await.unwrap();
+
+        let mut edited = operation.clone();
+        edited.amount_ht_cents += 100;
+        assert!(matches!(app.update_operation(edited).await, Err(DomainError::Validation(_))));
+        let mut relabelled = operation.clone();
+        relabelled.label = Some("Autre".to_string());
+        assert!(matches!(app.update_operation(relabelled).await, Err(DomainError::Validation(_))));
+
+        let mut paid = operation.clone();
+        paid.payment_date = Some(date("2025-04-02"));
+        app.update_operation(paid).await.unwrap();
+        let stored = app.get_operation(invoice.operation_id).await.unwrap();
+        assert_eq!((stored.payment_date, stored.amount_ht_cents), (Some(date("2025-04-02")), operation.amount_ht_cents));
+
+        // Once paid, the payment date is settled as well
+        let mut repaid = stored.clone();
+        repaid.payment_date = Some(date("2025-04-05"));
+        assert!(matches!(app.update_operation(repaid).await, Err(DomainError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_export_e_invoice_of_any_sale() {
+        let app = service().await;
+        let invoice = issue(&app, "2025-03-03").await;
+        let issued = app.export_e_invoice(invoice.operation_id, EInvoiceFormat::Cii, None).await.unwrap();
+        assert_eq!(issued.filename, format!("{}_cii.xml", invoice.number));
+
+        // Recorded by hand: the number and the client come with the export
+        let recorded = Operation { label: Some("Audit sécurité".to_string()), ..sale("2025-02-10", None, 120000) };
+        app.create_operation(recorded.clone()).await.unwrap();
+        assert!(matches!(app.export_e_invoice(recorded.id, EInvoiceFormat::Ubl, None).await, Err(DomainError::Validation(_))));
+        let details = SaleInvoiceDetails {
+            number: "2025-017".to_string(),
+            client: InvoiceClient { name: "Banque SA".into(), address: "75008 Paris".into(), siret: None, vat_number: Some("FR45987654321".into()) },
+        };
+        let ubl = app.export_e_invoice(recorded.id, EInvoiceFormat::Ubl, Some(details.clone())).await.unwrap();
+        let ubl = String::from_utf8(ubl.content).unwrap();
+        assert!(ubl.contains("<cbc:ID>2025-017</cbc:ID>") && ubl.contains("Banque SA") && ubl.contains("Audit sécurité"));
+
+        let facturx = app.export_e_invoice(recorded.id, EInvoiceFormat::FacturX, Some(details.clone())).await.unwrap();
+        assert_eq!(facturx.filename, "2025-017.pdf");
+        let extracted = infra::read_facturx_invoice(&facturx.content).unwrap().unwrap();
+        assert_eq!((extracted.invoice_number.as_str(), extracted.total_ht_cents, extracted.total_vat_cents), ("2025-017", 120000, 24000));
+
+        // VAT matching no rate, or a purchase, cannot be exported
+        let odd = Operation { vat_amount_cents: 12345, ..sale("2025-02-11", None, 100000) };
+        app.create_operation(odd.clone()).await.unwrap();
+        assert!(matches!(app.export_e_invoice(odd.id, EInvoiceFormat::Cii, Some(details.clone())).await, Err(DomainError::Validation(_))));
+        let purchase = Operation { operation_type: OperationType::Purchase, ..sale("2025-02-12", None, 10000) };
+        app.create_operation(purchase.clone()).await.unwrap();
+        assert!(matches!(app.export_e_invoice(purchase.id, EInvoiceFormat::Cii, Some(details)).await, Err(DomainError::Validation(_))));
+    }
+
+    /// Dump as JSON, without its export time
+    async fn dataset_json(app: &AppService) -> serde_json::Value {
+        let mut json = serde_json::to_value(app.export_dataset().await.unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("exported_at");
+        json
+    }
+
+    fn counts(report: &DatasetImportReport, entity: &str) -> (u32, u32, u32, u32) {
+        let count = report.counts.iter().find(|c| c.entity == entity).unwrap();
+        (count.created, count.updated, count.unchanged, count.deleted)
+    }
+
+    #[tokio::test]
+    async fn test_dataset_roundtrip() {
+        let source = service().await;
+        source.create_operation(sale("2025-03-03", Some("2025-03-10"), 100000)).await.unwrap();
+        source.create_operation(sale("2025-04-07", None, 40000)).await.unwrap();
+        source.close_month(MonthId::new(2025, 3)).await.unwrap();
+        let content = serde_json::to_vec(&source.export_dataset().await.unwrap()).unwrap();
+
+        let target = service().await;
+        let report = target.import_dataset(&content, DatasetImportMode::Replace).await.unwrap();
+        assert_eq!(report.source_version, DATASET_VERSION);
+        assert_eq!(counts(&report, "operations"), (2, 0, 0, 0));
+        assert_eq!(counts(&report, "closed_months"), (1, 0, 0, 0));
+        assert_eq!(dataset_json(&target).await, dataset_json(&source).await);
+        assert!(target.get_month_status(MonthId::new(2025, 3)).await.unwrap().closed_at.is_some());
+
+        // Importing the same dump again changes nothing
+        let again = target.import_dataset(&content, DatasetImportMode::Merge).await.unwrap();
+        assert!(again.counts.iter().all(|c| c.created == 0 && c.updated == 0 && c.deleted == 0));
+        assert_eq!(counts(&again, "operations"), (0, 0, 2, 0));
+        assert_eq!(dataset_json(&target).await, dataset_json(&source).await);
+    }
+
+    #[tokio::test]
+    async fn test_dataset_merge_keeps_and_replace_deletes() {
+        let source = service().await;
+        let shared = sale("2025-03-03", None, 100000);
+        source.create_operation(shared.clone()).await.unwrap();
+        let content = serde_json::to_vec(&source.export_dataset().await.unwrap()).unwrap();
+
+        let target = service().await;
+        let extra = sale("2025-05-12", None, 30000);
+        target.create_operation(extra.clone()).await.unwrap();
+
+        let merged = target.import_dataset(&content, DatasetImportMode::Merge).await.unwrap();
+        assert_eq!(counts(&merged, "operations"), (1, 0, 0, 0));
+        assert!(target.get_operation(extra.id).await.is_ok());
+        assert!(target.get_operation(shared.id).await.is_ok());
+
+        let replaced = target.import_dataset(&content, DatasetImportMode::Replace).await.unwrap();
+        assert_eq!(counts(&replaced, "operations"), (0, 0, 1, 1));
+        assert!(target.get_operation(extra.id).await.is_err());
+        assert!(target.get_operation(shared.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dataset_replace_keeps_closed_months() {
+        let source = service().await;
+        source.create_operation(sale("2025-03-03", None, 100000)).await.unwrap();
+        let content = serde_json::to_vec(&source.export_dataset().await.unwrap()).unwrap();
+
+        let target = service().await;
+        let extra = sale("2025-02-12", Some("2025-02-20"), 30000);
+        target.create_operation(extra.clone()).await.unwrap();
+        target.close_month(MonthId::new(2025, 2)).await.unwrap();
+
+        // February would reopen: nothing is imported
+        match target.import_dataset(&content, DatasetImportMode::Replace).await {
+            Err(DomainError::Validation(message)) => assert!(message.contains("2025-02")),
+            other => panic!("import accepté: {:?}", other.map(|r| r.counts.len())),
+        }
+        assert!(target.get_operation(extra.id).await.is_ok());
+        assert!(target.get_month_status(MonthId::new(2025, 2)).await.unwrap().closed_at.is_some());
+        target.import_dataset(&content, DatasetImportMode::Merge).await.unwrap();
     }
 }
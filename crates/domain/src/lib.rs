@@ -12,6 +12,10 @@ pub struct MonthId {
 
 impl MonthId {
     pub fn new(year: i32, month: u32) -> Self { Self { year, month } }
+
+    pub fn next(&self) -> Self {
+        if self.month >= 12 { Self { year: self.year + 1, month: 1 } } else { Self { year: self.year, month: self.month + 1 } }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,7 +54,7 @@ pub struct BankTx {
     pub label: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OperationType {
     #[serde(rename = "sale")]
     Sale,
@@ -81,7 +85,12 @@ pub struct Operation {
     pub amount_ttc_cents: i64,            // = HT + VAT
     pub vat_on_payments: bool,            // true by default
     pub label: Option<String>,            // Description
-    pub receipt_url: Option<String>,      // MinIO URL
+    #[serde(default)]
+    pub client: Option<String>,           // Client of a sale, supplier of a purchase
+    #[serde(default)]
+    pub category: Option<String>,         // Category id of the frontend catalogue
+    pub receipt_key: Option<String>,      // Document store key ("YYYY-MM/<file>")
+    pub receipt_sha256: Option<String>,   // Hex SHA-256 of the receipt content
     pub created_at: NaiveDateTime,        // Creation date
     pub updated_at: NaiveDateTime,        // Modification date
 }
@@ -94,10 +103,12 @@ pub enum DeclarationType {
     Urssaf,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DeclarationStatus {
     #[serde(rename = "pending")]
     Pending,
+    #[serde(rename = "filed")]
+    Filed,
     #[serde(rename = "paid")]
     Paid,
     #[serde(rename = "overdue")]
@@ -110,14 +121,23 @@ pub struct Declaration {
     pub declaration_type: DeclarationType,
     pub period_year: i32,
     pub period_month: u32, // 1..=12
-    pub amount_due_cents: i64,
+    pub amount_due_cents: i64,             // Frozen once the declaration is filed
     pub due_date: NaiveDate,
+    pub filing_date: Option<NaiveDate>,
     pub payment_date: Option<NaiveDate>,
     pub status: DeclarationStatus,
+    pub recomputed_amount_cents: Option<i64>, // Set when a later recomputation differs from the declared amount
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+impl Declaration {
+    /// Difference between the latest recomputed amount and the declared one (0 when in sync)
+    pub fn amount_diff_cents(&self) -> i64 {
+        self.recomputed_amount_cents.map(|r| r - self.amount_due_cents).unwrap_or(0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provision {
     pub id: Uuid,
@@ -131,7 +151,7 @@ pub struct Provision {
     pub updated_at: NaiveDateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ProvisionType {
     #[serde(rename = "vat")]
     Vat,
@@ -139,7 +159,7 @@ pub enum ProvisionType {
     Urssaf,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ProvisionStatus {
     #[serde(rename = "pending")]
     Pending,
@@ -199,10 +219,31 @@ pub struct TaxSchedule {
     pub period_start: NaiveDate,
     pub period_end: NaiveDate,
     pub status: TaxScheduleStatus,
+    pub paid_date: Option<NaiveDate>,         // Date of the last payment
+    pub paid_amount_cents: i64,               // Sum of all payments, may be partial
+    pub payment_reference: Option<String>,    // Reference of the last payment
     pub created_at: NaiveDateTime,
 }
 
+impl TaxSchedule {
+    /// Amount still to be paid
+    pub fn outstanding_cents(&self) -> i64 {
+        (self.amount_cents - self.paid_amount_cents).max(0)
+    }
+}
+
+/// A (possibly partial) payment of a tax schedule
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxPayment {
+    pub id: Uuid,
+    pub tax_schedule_id: Uuid,
+    pub paid_date: NaiveDate,
+    pub amount_cents: i64,
+    pub reference: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TaxType {
     Vat,
     Urssaf,
@@ -393,16 +434,35 @@ pub enum DomainError {
     #[error("Not found")] NotFound,
     #[error("Validation: {0}")] Validation(String),
     #[error("Repo error: {0}")] Repo(String),
+    #[error("Invalid data: {0}")] InvalidData(InvalidField),
+}
+
+/// A stored value that does not decode into its domain type (see the database doctor)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidField {
+    pub table: String,
+    pub row_id: String,
+    pub column: String,
+    pub value: String,
+    pub expected: String,
+}
+
+impl std::fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{} = '{}' (ligne {}): {} attendu", self.table, self.column, self.value, self.row_id, self.expected)
+    }
 }
 
 pub type DomainResult<T> = Result<T, DomainError>;
 
+/// Legacy invoices table, only kept to read databases predating migration 0007 (the app uses OperationRepo)
 #[async_trait::async_trait]
 pub trait InvoiceRepo: Send + Sync {
     async fn list_invoices(&self, month: Option<MonthId>) -> DomainResult<Vec<Invoice>>;
     async fn create_invoice(&self, inv: Invoice) -> DomainResult<()>;
 }
 
+/// Legacy expenses table, see InvoiceRepo
 #[async_trait::async_trait]
 pub trait ExpenseRepo: Send + Sync {
     async fn list_expenses(&self, month: Option<MonthId>) -> DomainResult<Vec<Expense>>;
@@ -418,6 +478,13 @@ pub trait OperationRepo: Send + Sync {
     async fn list_operations(&self, month: Option<MonthId>) -> DomainResult<Vec<Operation>>;
     async fn list_operations_by_type(&self, operation_type: OperationType, month: Option<MonthId>) -> DomainResult<Vec<Operation>>;
     async fn list_operations_by_payment_month(&self, month: MonthId) -> DomainResult<Vec<Operation>>;
+    async fn list_operations_by_receipt_sha256(&self, sha256: &str) -> DomainResult<Vec<Operation>>;
+    /// One page of the operations matching the query, in its order
+    async fn query_operations(&self, query: &OperationQuery) -> DomainResult<OperationPage>;
+    // Trash: trashed operations are left out of every read above; `delete_operation` purges for good
+    async fn trash_operation(&self, id: Uuid, deleted_at: NaiveDateTime) -> DomainResult<()>;
+    async fn restore_operation(&self, id: Uuid) -> DomainResult<()>;
+    async fn list_trashed_operations(&self) -> DomainResult<Vec<TrashedOperation>>;
 }
 
 #[async_trait::async_trait]
@@ -435,6 +502,7 @@ pub trait DeclarationRepo: Send + Sync {
 pub trait ProvisionRepo: Send + Sync {
     async fn upsert_provision(&self, p: Provision) -> DomainResult<()>;
     async fn list_provisions(&self, month: Option<MonthId>) -> DomainResult<Vec<Provision>>;
+    async fn delete_provision(&self, id: Uuid) -> DomainResult<()>;
 }
 
 #[async_trait::async_trait]
@@ -453,6 +521,8 @@ pub struct MonthStatus {
 pub trait MonthRepo: Send + Sync {
     async fn get_status(&self, month: &MonthId) -> DomainResult<MonthStatus>;
     async fn close_month(&self, month: &MonthId, closed_at: NaiveDateTime) -> DomainResult<()>;
+    /// Closed months, oldest first
+    async fn list_closed_months(&self) -> DomainResult<Vec<MonthStatus>>;
 }
 
 // ============ Yearly Planning Repository Trait ============
@@ -468,47 +538,2499 @@ pub trait YearlyPlanningRepo: Send + Sync {
     // Methods for individual month planning
     async fn update_month_planning(&self, month_planning: MonthPlanning) -> DomainResult<()>;
     async fn get_month_planning(&self, year: i32, month: u32) -> DomainResult<Option<MonthPlanning>>;
+
+    // Trash: a trashed planning and its months are left out of every read above
+    async fn trash_yearly_planning(&self, year: i32, deleted_at: NaiveDateTime) -> DomainResult<()>;
+    async fn restore_yearly_planning(&self, year: i32) -> DomainResult<()>;
+    async fn list_trashed_yearly_plannings(&self) -> DomainResult<Vec<TrashedYearlyPlanning>>;
+}
+
+// ============ New Repository Traits ============
+
+#[async_trait::async_trait]
+pub trait WorkingDayRepo: Send + Sync {
+    async fn create_working_day(&self, working_day: WorkingDay) -> DomainResult<()>;
+    async fn update_working_day(&self, working_day: WorkingDay) -> DomainResult<()>;
+    async fn delete_working_day(&self, id: Uuid) -> DomainResult<()>;
+    async fn get_working_day(&self, id: Uuid) -> DomainResult<WorkingDay>;
+    async fn list_working_days(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<WorkingDay>>;
+    async fn get_working_days_for_month(&self, month: &MonthId) -> DomainResult<Vec<WorkingDay>>;
+    async fn get_working_days_stats(&self, start_date: NaiveDate, end_date: NaiveDate) -> DomainResult<WorkingDaysStats>;
+}
+
+#[async_trait::async_trait]
+pub trait TaxScheduleRepo: Send + Sync {
+    async fn create_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()>;
+    async fn update_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()>;
+    async fn delete_tax_schedule(&self, id: Uuid) -> DomainResult<()>;
+    async fn get_tax_schedule(&self, id: Uuid) -> DomainResult<TaxSchedule>;
+    async fn list_tax_schedules(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<TaxSchedule>>;
+    async fn get_overdue_schedules(&self, as_of_date: NaiveDate) -> DomainResult<Vec<TaxSchedule>>;
+    /// Pay the whole outstanding amount of a schedule
+    async fn mark_as_paid(&self, id: Uuid, paid_date: NaiveDate) -> DomainResult<()>;
+    async fn record_payment(&self, payment: TaxPayment) -> DomainResult<()>;
+    async fn list_payments(&self, tax_schedule_id: Uuid) -> DomainResult<Vec<TaxPayment>>;
+}
+
+#[async_trait::async_trait]
+pub trait SimulationRepo: Send + Sync {
+    async fn create_simulation(&self, simulation: Simulation) -> DomainResult<()>;
+    async fn update_simulation(&self, simulation: Simulation) -> DomainResult<()>;
+    async fn delete_simulation(&self, id: Uuid) -> DomainResult<()>;
+    async fn get_simulation(&self, id: Uuid) -> DomainResult<Simulation>;
+    async fn list_simulations(&self) -> DomainResult<Vec<Simulation>>;
+}
+
+#[async_trait::async_trait]
+pub trait KPIRepo: Send + Sync {
+    async fn save_monthly_kpi(&self, kpi: MonthlyKPI) -> DomainResult<()>;
+    async fn get_monthly_kpi(&self, month: &MonthId) -> DomainResult<Option<MonthlyKPI>>;
+    async fn list_monthly_kpis(&self, start_month: &MonthId, end_month: &MonthId) -> DomainResult<Vec<MonthlyKPI>>;
+    async fn delete_monthly_kpi(&self, month: &MonthId) -> DomainResult<()>;
+}
+
+/// Issued sales invoices and their yearly numbering sequences
+#[async_trait::async_trait]
+pub trait IssuedInvoiceRepo: Send + Sync {
+    async fn load_issuer_profile(&self) -> DomainResult<Option<IssuerProfile>>;
+    async fn save_issuer_profile(&self, profile: IssuerProfile) -> DomainResult<()>;
+    /// Next number of the year's sequence, without reserving it
+    async fn next_invoice_sequence(&self, year: i32) -> DomainResult<u32>;
+    /// Consume the invoice's sequence number and save it with its sale operation in one transaction
+    /// Fails if the number is no longer the next one of the sequence
+    async fn issue_invoice(&self, invoice: IssuedInvoice, operation: Operation) -> DomainResult<()>;
+    async fn get_issued_invoice(&self, id: Uuid) -> DomainResult<IssuedInvoice>;
+    async fn find_issued_invoice_by_operation(&self, operation_id: Uuid) -> DomainResult<Option<IssuedInvoice>>;
+    async fn list_issued_invoices(&self, year: Option<i32>) -> DomainResult<Vec<IssuedInvoice>>;
+}
+
+#[async_trait::async_trait]
+pub trait ArchiveRepo: Send + Sync {
+    /// Make the fiscal year read-only and record its documents in one transaction
+    async fn archive_fiscal_year(&self, archive: ArchivedFiscalYear, documents: Vec<ArchivedDocument>) -> DomainResult<()>;
+    async fn list_archived_years(&self) -> DomainResult<Vec<ArchivedFiscalYear>>;
+    async fn list_archived_documents(&self) -> DomainResult<Vec<ArchivedDocument>>;
+    async fn get_archived_document(&self, key: &str) -> DomainResult<Option<ArchivedDocument>>;
+    /// Record that the storage now enforces the retention of an archived document
+    async fn mark_object_locked(&self, key: &str) -> DomainResult<()>;
+}
+
+/// Consistent snapshots of the database, kept next to it in a `backups/` folder
+#[async_trait::async_trait]
+pub trait BackupRepo: Send + Sync {
+    async fn load_backup_policy(&self) -> DomainResult<BackupPolicy>;
+    async fn save_backup_policy(&self, policy: BackupPolicy) -> DomainResult<()>;
+    /// Snapshot the live database (`VACUUM INTO`, encrypted with the database key if any)
+    async fn create_backup(&self, reason: BackupReason) -> DomainResult<BackupInfo>;
+    /// Most recent first
+    async fn list_backups(&self) -> DomainResult<Vec<BackupInfo>>;
+    async fn read_backup(&self, name: &str) -> DomainResult<Vec<u8>>;
+    async fn delete_backup(&self, name: &str) -> DomainResult<()>;
+    /// Open the snapshot and run an integrity check
+    async fn verify_backup(&self, name: &str) -> DomainResult<BackupVerification>;
+    /// Schedule a verified snapshot to replace the database at next startup
+    async fn stage_restore(&self, name: &str) -> DomainResult<()>;
+}
+
+/// Append-only journal of financial mutations; events can never be updated nor deleted
+#[async_trait::async_trait]
+pub trait AuditRepo: Send + Sync {
+    /// Number, timestamp and chain the event to the previous one
+    async fn append_audit_event(&self, event: NewAuditEvent) -> DomainResult<AuditEvent>;
+    /// Most recent first
+    async fn list_audit_events(&self, query: AuditQuery) -> DomainResult<Vec<AuditEvent>>;
+    /// Recompute every hash from the first event
+    async fn verify_audit_chain(&self) -> DomainResult<AuditChainCheck>;
+}
+
+/// Integrity scan of the stored rows, below the typed repositories
+#[async_trait::async_trait]
+pub trait DoctorRepo: Send + Sync {
+    async fn scan_database(&self) -> DomainResult<DoctorScan>;
+    /// Apply the proposed fix of an issue, provided the row still holds the scanned value
+    async fn apply_fix(&self, issue: &DoctorIssue) -> DomainResult<()>;
+}
+
+/// Synchronisation bookkeeping of an installation, see `SyncChange`
+#[async_trait::async_trait]
+pub trait SyncRepo: Send + Sync {
+    async fn load_sync_settings(&self) -> DomainResult<Option<SyncSettings>>;
+    async fn save_sync_settings(&self, settings: SyncSettings) -> DomainResult<()>;
+    /// Last audit event published to the own change log (0 before the first publication)
+    async fn published_audit_seq(&self) -> DomainResult<i64>;
+    async fn set_published_audit_seq(&self, seq: i64) -> DomainResult<()>;
+    /// Position of the last change applied from each other installation
+    async fn applied_positions(&self) -> DomainResult<std::collections::HashMap<String, i64>>;
+    async fn set_applied_position(&self, peer: &str, seq: i64) -> DomainResult<()>;
+    /// Clocks of a record's fields, and of the record itself under `SYNC_RECORD_CLOCK`
+    async fn field_clocks(&self, entity: AuditEntity, entity_id: &str) -> DomainResult<std::collections::HashMap<String, SyncStamp>>;
+    /// Keep the later of the stored and given stamps
+    async fn advance_field_clock(&self, entity: AuditEntity, entity_id: &str, field: &str, stamp: &SyncStamp) -> DomainResult<()>;
+    async fn add_sync_conflict(&self, conflict: SyncConflict) -> DomainResult<()>;
+    /// Most recent first
+    async fn list_sync_conflicts(&self) -> DomainResult<Vec<SyncConflict>>;
+    async fn dismiss_sync_conflict(&self, id: Uuid) -> DomainResult<()>;
+}
+
+/// Repositories sharing one transaction: their writes are committed together or not at all
+/// Dropping a unit of work without committing it rolls it back
+#[async_trait::async_trait]
+pub trait UnitOfWork: Send + Sync {
+    fn provisions(&self) -> std::sync::Arc<dyn ProvisionRepo>;
+    fn config(&self) -> std::sync::Arc<dyn ConfigRepo>;
+    fn months(&self) -> std::sync::Arc<dyn MonthRepo>;
+    fn operations(&self) -> std::sync::Arc<dyn OperationRepo>;
+    fn declarations(&self) -> std::sync::Arc<dyn DeclarationRepo>;
+    fn yearly_planning(&self) -> std::sync::Arc<dyn YearlyPlanningRepo>;
+    fn audit(&self) -> std::sync::Arc<dyn AuditRepo>;
+    fn working_days(&self) -> std::sync::Arc<dyn WorkingDayRepo>;
+    fn simulations(&self) -> std::sync::Arc<dyn SimulationRepo>;
+    fn kpis(&self) -> std::sync::Arc<dyn KPIRepo>;
+    fn sync(&self) -> std::sync::Arc<dyn SyncRepo>;
+    fn archives(&self) -> std::sync::Arc<dyn ArchiveRepo>;
+    /// Once committed or rolled back, the repositories of the unit refuse any further call
+    async fn commit(&self) -> DomainResult<()>;
+    async fn rollback(&self) -> DomainResult<()>;
+}
+
+#[async_trait::async_trait]
+pub trait UnitOfWorkFactory: Send + Sync {
+    async fn begin(&self) -> DomainResult<Box<dyn UnitOfWork>>;
+}
+
+// ============ Document Storage ============
+
+/// Information sur un fichier stocké
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub key: String,        // "YYYY-MM/<fichier>", identical across backends
+    pub size_bytes: u64,
+    pub last_modified: NaiveDateTime,
+}
+
+/// Statistiques de stockage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageStats {
+    pub total_files: u64,
+    pub total_size_bytes: u64,
+    pub files_by_type: std::collections::HashMap<String, u64>,
+}
+
+impl StorageStats {
+    pub fn from_files(files: &[FileInfo]) -> Self {
+        let mut stats = StorageStats::default();
+        for file in files {
+            stats.total_files += 1;
+            stats.total_size_bytes += file.size_bytes;
+            let extension = std::path::Path::new(&file.key)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_lowercase())
+                .unwrap_or_else(|| "unknown".to_string());
+            *stats.files_by_type.entry(extension).or_insert(0) += 1;
+        }
+        stats
+    }
+}
+
+/// Storage backend for receipts and other documents, organised in `YYYY-MM/` folders
+/// Documents are referenced by their key; URLs are only produced on demand and may expire
+#[async_trait::async_trait]
+pub trait DocumentStore: Send + Sync {
+    /// Short backend name ("local", "minio")
+    fn backend(&self) -> &'static str;
+    /// Store a new document under the current month and return its key
+    async fn upload(&self, content: Vec<u8>, original_filename: &str, content_type: Option<String>) -> DomainResult<String>;
+    /// Store a document under an explicit key
+    async fn put(&self, key: &str, content: Vec<u8>) -> DomainResult<()>;
+    async fn delete(&self, key: &str) -> DomainResult<()>;
+    async fn list_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>>;
+    async fn list_all(&self) -> DomainResult<Vec<FileInfo>>;
+    /// Read back the content of a document
+    async fn open(&self, key: &str) -> DomainResult<Vec<u8>>;
+    /// URL to view the document, valid for `expires_in_secs` when the backend supports expiry
+    async fn download_url(&self, key: &str, expires_in_secs: u32) -> DomainResult<String>;
+    /// Protect the document against deletion until `retain_until` (S3 Object Lock)
+    /// Returns false when the backend cannot enforce retention itself
+    async fn set_retention(&self, _key: &str, _retain_until: NaiveDate) -> DomainResult<bool> {
+        Ok(false)
+    }
+
+    async fn stats(&self) -> DomainResult<StorageStats> {
+        Ok(StorageStats::from_files(&self.list_all().await?))
+    }
+}
+
+/// Content-addressed key of a receipt: `YYYY-MM/<sha256>.<ext>`, month of the upload
+pub fn receipt_key(sha256: &str, original_filename: &str, uploaded_on: NaiveDate) -> String {
+    let extension = std::path::Path::new(original_filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_else(|| "bin".to_string());
+    format!("{:04}-{:02}/{}.{}", uploaded_on.year(), uploaded_on.month(), sha256, extension)
+}
+
+/// Hash embedded in a content-addressed receipt key, if any
+pub fn sha256_from_receipt_key(key: &str) -> Option<String> {
+    let file_name = key.rsplit('/').next()?;
+    let stem = file_name.split('.').next()?;
+    if stem.len() == 64 && stem.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(stem.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Document key found in a URL stored before keys were used
+/// (`https://host/bucket/2024-01/file.pdf`, `file:///data/documents/2024-01/file.pdf`...)
+pub fn document_key_from_url(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let mut segments = path.rsplit('/');
+    let file_name = segments.next().filter(|s| !s.is_empty())?;
+    let folder = segments.next()?;
+    let is_month_folder = folder.len() == 7
+        && folder.as_bytes()[4] == b'-'
+        && folder.chars().enumerate().all(|(i, c)| i == 4 || c.is_ascii_digit());
+    if is_month_folder { Some(format!("{}/{}", folder, file_name)) } else { None }
+}
+
+/// Outcome of a receipt upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptUpload {
+    pub key: String,
+    pub sha256: String,
+    pub duplicate_of: Vec<Uuid>, // Operations already linked to the same content
+    pub already_stored: bool,    // Same content found in the store, linked or not: nothing was written
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReceiptIssueKind {
+    #[serde(rename = "missing")]
+    Missing,   // The object is no longer in the document store
+    #[serde(rename = "mismatch")]
+    Mismatch,  // The stored content no longer matches the recorded hash
+    #[serde(rename = "unhashed")]
+    Unhashed,  // No hash was recorded, so a change cannot be told; hashed during this run
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptIssue {
+    pub operation_id: Uuid,
+    pub receipt_key: String,
+    pub kind: ReceiptIssueKind,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: Option<String>,
+}
+
+/// Result of re-hashing every receipt linked to an operation, over the whole document store
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReceiptVerificationReport {
+    pub checked: u32,
+    pub valid: u32, // Content matching its recorded hash
+    pub issues: Vec<ReceiptIssue>,
+    pub unreferenced: Vec<FileInfo>, // Stored but attached to no operation, not even a trashed one
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingReceipt {
+    pub operation_id: Uuid,
+    pub receipt_key: String,
+}
+
+/// Consistency between stored documents and the receipts referenced by operations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptAudit {
+    pub month: Option<MonthId>,
+    pub purchases_without_receipt: Vec<Operation>,
+    pub orphan_receipts: Vec<FileInfo>,          // Stored but attached to no operation
+    pub dangling_receipts: Vec<DanglingReceipt>, // Referenced but missing from the store
+}
+
+impl ReceiptAudit {
+    /// Nothing left to fix before closing the month (orphans are only informational)
+    pub fn is_ready_for_close(&self) -> bool {
+        self.purchases_without_receipt.is_empty() && self.dangling_receipts.is_empty()
+    }
+}
+
+/// Cross-check operations against stored documents
+/// `files` must list the whole store: a receipt uploaded in March may belong to a February operation
+/// With a month, operations are filtered on their invoice date and files on their `YYYY-MM/` folder
+/// Receipts of trashed operations are no orphans: restoring the operation brings them back
+pub fn audit_receipts(month: Option<&MonthId>, operations: &[Operation], trashed: &[TrashedOperation], files: &[FileInfo]) -> ReceiptAudit {
+    let stored: std::collections::HashSet<&str> = files.iter().map(|f| f.key.as_str()).collect();
+    let referenced: std::collections::HashSet<&str> = operations.iter()
+        .chain(trashed.iter().map(|t| &t.operation))
+        .filter_map(|op| op.receipt_key.as_deref())
+        .collect();
+    let in_month = |op: &&Operation| {
+        month.map(|m| op.invoice_date.year() == m.year && op.invoice_date.month() == m.month).unwrap_or(true)
+    };
+    let folder = month.map(|m| format!("{:04}-{:02}/", m.year, m.month));
+
+    ReceiptAudit {
+        month: month.cloned(),
+        purchases_without_receipt: operations.iter()
+            .filter(in_month)
+            .filter(|op| matches!(op.operation_type, OperationType::Purchase) && op.receipt_key.is_none())
+            .cloned()
+            .collect(),
+        orphan_receipts: files.iter()
+            .filter(|f| !f.key.starts_with(BACKUP_KEY_PREFIX) && !f.key.starts_with(WORKSPACE_KEY_PREFIX))
+            .filter(|f| folder.as_deref().map(|prefix| f.key.starts_with(prefix)).unwrap_or(true))
+            .filter(|f| !referenced.contains(f.key.as_str()))
+            .cloned()
+            .collect(),
+        dangling_receipts: operations.iter()
+            .filter(in_month)
+            .filter_map(|op| {
+                let key = op.receipt_key.as_deref()?;
+                (!stored.contains(key)).then(|| DanglingReceipt { operation_id: op.id, receipt_key: key.to_string() })
+            })
+            .collect(),
+    }
+}
+
+/// Everything to review before closing a month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthCloseChecklist {
+    pub month: MonthId,
+    pub already_closed: bool,
+    pub vat: VatReport,
+    pub urssaf: UrssafReport,
+    pub receipts: ReceiptAudit,
+    pub ready: bool,
+}
+
+/// A closed month and the backup taken right after; a failed backup leaves the month closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthClosing {
+    pub month: MonthId,
+    pub backup: Option<BackupRun>,
+    pub backup_error: Option<String>,
+}
+
+/// Result of copying every document from one backend to another
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentMigrationReport {
+    pub from_backend: String,
+    pub to_backend: String,
+    pub files_copied: u32,
+    pub bytes_copied: u64,
+}
+
+// ============ Electronic Invoicing ============
+
+/// One line of the VAT breakdown of an invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VatBreakdown {
+    pub rate_ppm: i32,
+    pub basis_ht_cents: i64,
+    pub vat_cents: i64,
+    pub category_code: String,  // "S" standard, "Z" zero rated, "E" exempt, "AE" reverse charge...
+    pub vat_on_payments: bool,  // DueDateTypeCode 72: VAT due when the invoice is paid
+}
+
+/// Data read from the structured part (CII XML) of a Factur-X invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedInvoice {
+    pub profile: Option<String>,     // Guideline URN (minimum, basic, en16931...)
+    pub type_code: String,           // 380 invoice, 381 credit note
+    pub invoice_number: String,
+    pub issue_date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    pub seller_name: String,
+    pub seller_vat_id: Option<String>,
+    pub buyer_name: Option<String>,
+    pub currency: String,
+    pub vat_breakdown: Vec<VatBreakdown>,
+    pub total_ht_cents: i64,
+    pub total_vat_cents: i64,
+    pub total_ttc_cents: i64,
+}
+
+impl ExtractedInvoice {
+    pub fn is_credit_note(&self) -> bool {
+        self.type_code == "381"
+    }
+
+    /// Services invoiced with VAT on payments, goods (or unspecified) on debits
+    pub fn vat_on_payments(&self) -> bool {
+        !self.vat_breakdown.is_empty() && self.vat_breakdown.iter().all(|line| line.vat_on_payments)
+    }
+}
+
+// ============ Invoice Issuance ============
+
+/// Late payment fixed recovery fee (art. D441-5 du code de commerce)
+pub const RECOVERY_FEE_CENTS: i64 = 40_00;
+
+/// Issuer identity and terms printed on every invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuerProfile {
+    pub name: String,
+    pub address: String,                 // Several lines separated by '\n'
+    pub siret: String,
+    pub vat_number: Option<String>,      // Required unless vat_exempt
+    pub vat_exempt: bool,                // Franchise en base de TVA
+    pub late_penalty_rate_ppm: i32,      // Annual rate, e.g. 10% = 100000 ppm
+    pub payment_terms_days: u32,
+    pub iban: Option<String>,
+    pub email: Option<String>,
+}
+
+impl IssuerProfile {
+    pub fn validate(&self) -> DomainResult<()> {
+        if self.name.trim().is_empty() || self.address.trim().is_empty() {
+            return Err(DomainError::Validation("Nom et adresse de l'émetteur obligatoires".into()));
+        }
+        if self.siret.len() != 14 || !self.siret.chars().all(|c| c.is_ascii_digit()) {
+            return Err(DomainError::Validation(format!("SIRET invalide: {}", self.siret)));
+        }
+        if !self.vat_exempt && self.vat_number.as_deref().map(str::trim).unwrap_or("").is_empty() {
+            return Err(DomainError::Validation("Numéro de TVA intracommunautaire obligatoire hors franchise en base".into()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceClient {
+    pub name: String,
+    pub address: String,
+    pub siret: Option<String>,
+    pub vat_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity_milli: i64,         // 1.5 days = 1500
+    pub unit_price_ht_cents: i64,
+    pub vat_rate_ppm: i32,
+}
+
+impl InvoiceLine {
+    pub fn total_ht_cents(&self) -> i64 {
+        round_div(self.quantity_milli as i128 * self.unit_price_ht_cents as i128, 1_000)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedInvoice {
+    pub id: Uuid,
+    pub number: String,              // "F2024-0001"
+    pub year: i32,
+    pub sequence: u32,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub is_service: bool,            // Services: VAT due on payment
+    pub issuer: IssuerProfile,       // Snapshot taken when issuing
+    pub client: InvoiceClient,
+    pub lines: Vec<InvoiceLine>,
+    pub vat_breakdown: Vec<VatBreakdown>,
+    pub total_ht_cents: i64,
+    pub total_vat_cents: i64,
+    pub total_ttc_cents: i64,
+    pub notes: Option<String>,
+    pub operation_id: Uuid,          // Sale operation created with the invoice
+    pub pdf_key: String,             // Document store key of the rendered PDF
+    pub created_at: NaiveDateTime,
+}
+
+impl IssuedInvoice {
+    /// Mentions required on a French B2B invoice, in display order
+    pub fn legal_mentions(&self) -> Vec<String> {
+        let mut mentions = Vec::new();
+        if self.issuer.vat_exempt {
+            mentions.push("TVA non applicable, art. 293 B du CGI".to_string());
+        }
+        mentions.push(format!(
+            "Paiement à {} jours, au plus tard le {}. Pas d'escompte pour paiement anticipé.",
+            self.issuer.payment_terms_days,
+            self.due_date.format("%d/%m/%Y"),
+        ));
+        mentions.push(format!(
+            "En cas de retard de paiement, pénalités au taux annuel de {} % et indemnité forfaitaire pour frais de recouvrement de {} € (art. L441-10 du code de commerce).",
+            format_ppm_percent(self.issuer.late_penalty_rate_ppm),
+            RECOVERY_FEE_CENTS / 100,
+        ));
+        mentions
+    }
+
+    /// Sale operation recorded in the books for this invoice
+    pub fn to_operation(&self, pdf_sha256: &str, now: NaiveDateTime) -> Operation {
+        Operation {
+            id: self.operation_id,
+            invoice_date: self.issue_date,
+            payment_date: None,
+            operation_type: OperationType::Sale,
+            amount_ht_cents: self.total_ht_cents,
+            vat_amount_cents: self.total_vat_cents,
+            amount_ttc_cents: self.total_ttc_cents,
+            vat_on_payments: self.is_service,
+            label: Some(format!("{} - {}", self.number, self.client.name)),
+            client: Some(self.client.name.clone()),
+            category: None,
+            receipt_key: Some(self.pdf_key.clone()),
+            receipt_sha256: Some(pdf_sha256.to_string()),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Gapless yearly numbering: F2024-0001, F2024-0002...
+pub fn invoice_number(year: i32, sequence: u32) -> String {
+    format!("F{}-{:04}", year, sequence)
+}
+
+/// VAT computed per rate on the sum of the lines (one rounding per rate)
+pub fn compute_invoice_vat(lines: &[InvoiceLine], vat_exempt: bool, is_service: bool) -> Vec<VatBreakdown> {
+    let mut by_rate: std::collections::BTreeMap<i32, i64> = std::collections::BTreeMap::new();
+    for line in lines {
+        let rate = if vat_exempt { 0 } else { line.vat_rate_ppm };
+        *by_rate.entry(rate).or_insert(0) += line.total_ht_cents();
+    }
+    by_rate.into_iter()
+        .rev()
+        .map(|(rate_ppm, basis_ht_cents)| VatBreakdown {
+            rate_ppm,
+            basis_ht_cents,
+            vat_cents: round_div(basis_ht_cents as i128 * rate_ppm as i128, 1_000_000),
+            category_code: if vat_exempt { "E".to_string() } else if rate_ppm == 0 { "Z".to_string() } else { "S".to_string() },
+            vat_on_payments: is_service && !vat_exempt,
+        })
+        .collect()
+}
+
+/// Invoice content entered by the user, before numbering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceDraft {
+    pub issue_date: NaiveDate,
+    pub is_service: bool,
+    pub client: InvoiceClient,
+    pub lines: Vec<InvoiceLine>,
+    pub notes: Option<String>,
+}
+
+/// Build an invoice ready to be issued, totals included
+/// `sequence` must come from IssuedInvoiceRepo::next_invoice_sequence
+pub fn build_issued_invoice(issuer: IssuerProfile, draft: InvoiceDraft, sequence: u32, now: NaiveDateTime) -> DomainResult<IssuedInvoice> {
+    issuer.validate()?;
+    let InvoiceDraft { issue_date, is_service, client, lines, notes } = draft;
+    if client.name.trim().is_empty() || client.address.trim().is_empty() {
+        return Err(DomainError::Validation("Nom et adresse du client obligatoires".into()));
+    }
+    if lines.is_empty() {
+        return Err(DomainError::Validation("Une facture doit comporter au moins une ligne".into()));
+    }
+    if let Some(line) = lines.iter().find(|l| l.description.trim().is_empty() || l.quantity_milli <= 0 || l.unit_price_ht_cents < 0) {
+        return Err(DomainError::Validation(format!("Ligne de facture invalide: '{}'", line.description)));
+    }
+
+    let vat_breakdown = compute_invoice_vat(&lines, issuer.vat_exempt, is_service);
+    let total_ht_cents = vat_breakdown.iter().map(|v| v.basis_ht_cents).sum();
+    let total_vat_cents = vat_breakdown.iter().map(|v| v.vat_cents).sum();
+    let year = issue_date.year();
+    Ok(IssuedInvoice {
+        id: Uuid::new_v4(),
+        number: invoice_number(year, sequence),
+        year,
+        sequence,
+        issue_date,
+        due_date: issue_date + chrono::Duration::days(issuer.payment_terms_days as i64),
+        is_service,
+        issuer,
+        client,
+        lines,
+        vat_breakdown,
+        total_ht_cents,
+        total_vat_cents,
+        total_ttc_cents: total_ht_cents + total_vat_cents,
+        notes,
+        operation_id: Uuid::new_v4(),
+        pdf_key: String::new(),
+        created_at: now,
+    })
+}
+
+/// Rates a sale recorded without invoice lines can be exported at (metropolitan France, then overseas)
+const SALE_VAT_RATES_PPM: [i32; 6] = [200_000, 100_000, 55_000, 21_000, 85_000, 0];
+
+/// What a sale recorded outside the application lacks to be exported as an invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaleInvoiceDetails {
+    pub number: String,              // Number of the invoice sent to the client
+    pub client: InvoiceClient,
+}
+
+/// Invoice of a sale recorded outside the application: one line for its amount,
+/// at the VAT rate that gives back its VAT amount
+pub fn invoice_from_sale(operation: &Operation, issuer: IssuerProfile, details: SaleInvoiceDetails, now: NaiveDateTime) -> DomainResult<IssuedInvoice> {
+    if !matches!(operation.operation_type, OperationType::Sale) {
+        return Err(DomainError::Validation("Seule une vente peut être exportée en facture électronique".into()));
+    }
+    if details.number.trim().is_empty() {
+        return Err(DomainError::Validation("Numéro de facture obligatoire".into()));
+    }
+    if operation.amount_ht_cents <= 0 {
+        return Err(DomainError::Validation("Montant HT négatif ou nul: un avoir ne peut pas être exporté ainsi".into()));
+    }
+    let rate = |rate_ppm: i32| round_div(operation.amount_ht_cents as i128 * rate_ppm as i128, 1_000_000) == operation.vat_amount_cents;
+    let vat_rate_ppm = match issuer.vat_exempt {
+        true if operation.vat_amount_cents == 0 => 0,
+        true => return Err(DomainError::Validation("Vente avec TVA alors que l'émetteur est en franchise en base".into())),
+        false => SALE_VAT_RATES_PPM.into_iter().find(|&r| rate(r)).ok_or_else(|| DomainError::Validation(format!(
+            "La TVA de la vente ({} €) ne correspond à aucun taux appliqué à {} € HT",
+            csv_amount(operation.vat_amount_cents), csv_amount(operation.amount_ht_cents),
+        )))?,
+    };
+
+    let draft = InvoiceDraft {
+        issue_date: operation.invoice_date,
+        is_service: operation.vat_on_payments,
+        client: details.client,
+        lines: vec![InvoiceLine {
+            description: operation.label.clone().filter(|l| !l.trim().is_empty()).unwrap_or_else(|| "Prestation".to_string()),
+            quantity_milli: 1_000,
+            unit_price_ht_cents: operation.amount_ht_cents,
+            vat_rate_ppm,
+        }],
+        notes: None,
+    };
+    let invoice = build_issued_invoice(issuer, draft, 0, now)?;
+    Ok(IssuedInvoice { number: details.number.trim().to_string(), operation_id: operation.id, ..invoice })
+}
+
+/// Structured formats an issued invoice can be exchanged in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EInvoiceFormat {
+    #[serde(rename = "facturx")]
+    FacturX,  // PDF/A-3B with the CII XML attached
+    #[serde(rename = "cii")]
+    Cii,      // UN/CEFACT Cross Industry Invoice D16B
+    #[serde(rename = "ubl")]
+    Ubl,      // OASIS UBL 2.1 Invoice
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EInvoiceExport {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// "10", "5,5", "2,1": French display of a ppm rate as a percentage
+pub fn format_ppm_percent(rate_ppm: i32) -> String {
+    let whole = rate_ppm / 10_000;
+    let decimals = format!("{:04}", (rate_ppm % 10_000).abs());
+    let decimals = decimals.trim_end_matches('0');
+    if decimals.is_empty() { whole.to_string() } else { format!("{},{}", whole, decimals) }
+}
+
+/// Integer division rounded half away from zero
+fn round_div(value: i128, divisor: i128) -> i64 {
+    let rounded = if value >= 0 { (value + divisor / 2) / divisor } else { (value - divisor / 2) / divisor };
+    rounded as i64
+}
+
+// ============ Accountant Export ============
+
+/// Period covered by an accountant export pack
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum ExportPeriod {
+    #[serde(rename = "month")]
+    Month { year: i32, month: u32 },
+    #[serde(rename = "quarter")]
+    Quarter { year: i32, quarter: u32 },
+    #[serde(rename = "year")]
+    Year { year: i32 },
+}
+
+impl ExportPeriod {
+    pub fn validate(&self) -> DomainResult<()> {
+        match *self {
+            ExportPeriod::Month { month, .. } if !(1..=12).contains(&month) => Err(DomainError::Validation(format!("Mois invalide: {}", month))),
+            ExportPeriod::Quarter { quarter, .. } if !(1..=4).contains(&quarter) => Err(DomainError::Validation(format!("Trimestre invalide: {}", quarter))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn months(&self) -> Vec<MonthId> {
+        match *self {
+            ExportPeriod::Month { year, month } => vec![MonthId::new(year, month)],
+            ExportPeriod::Quarter { year, quarter } => (1..=3).map(|m| MonthId::new(year, (quarter - 1) * 3 + m)).collect(),
+            ExportPeriod::Year { year } => (1..=12).map(|m| MonthId::new(year, m)).collect(),
+        }
+    }
+
+    /// "2024-03", "2024-T1", "2024"
+    pub fn label(&self) -> String {
+        match *self {
+            ExportPeriod::Month { year, month } => format!("{:04}-{:02}", year, month),
+            ExportPeriod::Quarter { year, quarter } => format!("{:04}-T{}", year, quarter),
+            ExportPeriod::Year { year } => format!("{:04}", year),
+        }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.months().iter().any(|m| m.year == date.year() && m.month == date.month())
+    }
+}
+
+/// One file of an export pack with its SHA-256
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifestEntry {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// Content of `manifest.json`, also returned to the caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub period: ExportPeriod,
+    pub label: String,
+    pub generated_at: NaiveDateTime,
+    pub operations_count: u32,
+    pub receipts_count: u32,
+    pub files: Vec<ExportManifestEntry>,
+    pub missing_receipts: Vec<DanglingReceipt>, // Referenced but unreadable from the store
+}
+
+/// Operations invoiced or paid during the period
+pub fn operations_in_period(period: &ExportPeriod, operations: &[Operation]) -> Vec<Operation> {
+    let mut selected: Vec<Operation> = operations.iter()
+        .filter(|op| period.contains(op.invoice_date) || op.payment_date.map(|d| period.contains(d)).unwrap_or(false))
+        .cloned()
+        .collect();
+    selected.sort_by(|a, b| a.invoice_date.cmp(&b.invoice_date).then(a.created_at.cmp(&b.created_at)));
+    selected
+}
+
+/// Operations journal, `;`-separated with French decimal commas (opens as-is in Excel)
+pub fn operations_journal_csv(operations: &[Operation], receipt_names: &[(Uuid, String)]) -> String {
+    let mut csv = String::from("Date facture;Date paiement;Type;Libellé;Montant HT;TVA;Montant TTC;TVA sur encaissements;Justificatif;SHA-256 justificatif\n");
+    for op in operations {
+        let receipt = receipt_names.iter().find(|(id, _)| *id == op.id).map(|(_, name)| name.as_str()).unwrap_or("");
+        let row = [
+            op.invoice_date.format("%Y-%m-%d").to_string(),
+            op.payment_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            match op.operation_type { OperationType::Sale => "Vente", OperationType::Purchase => "Achat" }.to_string(),
+            op.label.clone().unwrap_or_default(),
+            csv_amount(op.amount_ht_cents),
+            csv_amount(op.vat_amount_cents),
+            csv_amount(op.amount_ttc_cents),
+            if op.vat_on_payments { "Oui" } else { "Non" }.to_string(),
+            receipt.to_string(),
+            op.receipt_sha256.clone().unwrap_or_default(),
+        ];
+        csv_row(&mut csv, &row);
+    }
+    csv
+}
+
+/// VAT report of each month, with a total line when the period spans several months
+pub fn vat_reports_csv(reports: &[VatReport]) -> String {
+    let mut csv = String::from("Mois;TVA collectée;TVA déductible;TVA due\n");
+    let mut totals = [0i64; 3];
+    for report in reports {
+        csv_row(&mut csv, &[month_label(&report.month), csv_amount(report.collected_cents), csv_amount(report.deductible_cents), csv_amount(report.due_cents)]);
+        for (total, value) in totals.iter_mut().zip([report.collected_cents, report.deductible_cents, report.due_cents]) {
+            *total += value;
+        }
+    }
+    if reports.len() > 1 {
+        csv_row(&mut csv, &["Total".to_string(), csv_amount(totals[0]), csv_amount(totals[1]), csv_amount(totals[2])]);
+    }
+    csv
+}
+
+/// URSSAF report of each month, with a total line when the period spans several months
+pub fn urssaf_reports_csv(reports: &[UrssafReport]) -> String {
+    let mut csv = String::from("Mois;CA encaissé;Taux (%);Cotisations dues\n");
+    for report in reports {
+        csv_row(&mut csv, &[month_label(&report.month), csv_amount(report.ca_encaisse_cents), format_ppm_percent(report.rate_ppm), csv_amount(report.due_cents)]);
+    }
+    if reports.len() > 1 {
+        let revenue: i64 = reports.iter().map(|r| r.ca_encaisse_cents).sum();
+        let due: i64 = reports.iter().map(|r| r.due_cents).sum();
+        csv_row(&mut csv, &["Total".to_string(), csv_amount(revenue), String::new(), csv_amount(due)]);
+    }
+    csv
+}
+
+pub fn month_recaps_csv(recaps: &[MonthRecap]) -> String {
+    let mut csv = String::from("Mois;Encaissements HT;TVA encaissée;Encaissements TTC;Dépenses TTC;TVA due;URSSAF due;Net du mois;Après provisions\n");
+    for recap in recaps {
+        csv_row(&mut csv, &[
+            month_label(&recap.month),
+            csv_amount(recap.receipts_ht_cents),
+            csv_amount(recap.receipts_tva_cents),
+            csv_amount(recap.receipts_ttc_cents),
+            csv_amount(recap.expenses_ttc_cents),
+            csv_amount(recap.vat_due_cents),
+            csv_amount(recap.urssaf_due_cents),
+            csv_amount(recap.net_from_month_cents),
+            csv_amount(recap.after_provisions_cents),
+        ]);
+    }
+    csv
+}
+
+/// File name of each receipt in the pack: "2024-03-15_achat_Restaurant_Le_Zinc_45,90EUR.pdf"
+/// Operations sharing the same date, label and amount get their id appended
+pub fn receipt_export_names(operations: &[Operation]) -> Vec<(Uuid, String)> {
+    let mut used = std::collections::HashSet::new();
+    operations.iter()
+        .filter_map(|op| {
+            let key = op.receipt_key.as_deref()?;
+            let extension = key.rsplit('/').next()
+                .and_then(|name| name.rsplit_once('.'))
+                .map(|(_, ext)| format!(".{}", ext.to_ascii_lowercase()))
+                .unwrap_or_default();
+            let label: String = op.label.as_deref().unwrap_or("").chars()
+                .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+                .collect();
+            let label: String = label.split('_').filter(|word| !word.is_empty()).collect::<Vec<_>>().join("_").chars().take(50).collect();
+
+            let mut parts = vec![
+                op.invoice_date.format("%Y-%m-%d").to_string(),
+                match op.operation_type { OperationType::Sale => "vente", OperationType::Purchase => "achat" }.to_string(),
+            ];
+            if !label.is_empty() {
+                parts.push(label);
+            }
+            parts.push(format!("{}EUR", csv_amount(op.amount_ttc_cents)));
+            let stem = parts.join("_");
+
+            let mut name = format!("{}{}", stem, extension);
+            if !used.insert(name.clone()) {
+                name = format!("{}_{}{}", stem, &op.id.to_string()[..8], extension);
+                used.insert(name.clone());
+            }
+            Some((op.id, name))
+        })
+        .collect()
+}
+
+fn month_label(month: &MonthId) -> String {
+    format!("{:04}-{:02}", month.year, month.month)
+}
+
+/// -123456 -> "-1234,56"
+fn csv_amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{},{:02}", sign, cents.unsigned_abs() / 100, cents.unsigned_abs() % 100)
+}
+
+fn csv_row(csv: &mut String, fields: &[String]) {
+    let escaped: Vec<String> = fields.iter()
+        .map(|field| {
+            if field.contains([';', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    csv.push_str(&escaped.join(";"));
+    csv.push('\n');
+}
+
+// ============ FEC (Fichier des Écritures Comptables) ============
+
+/// Columns of the FEC, in the order set by article A47 A-1 of the LPF
+pub const FEC_COLUMNS: [&str; 18] = [
+    "JournalCode", "JournalLib", "EcritureNum", "EcritureDate", "CompteNum", "CompteLib",
+    "CompAuxNum", "CompAuxLib", "PieceRef", "PieceDate", "EcritureLib", "Debit", "Credit",
+    "EcritureLet", "DateLet", "ValidDate", "Montantdevise", "Idevise",
+];
+
+type FecAccount = (&'static str, &'static str);
+type FecJournal = (&'static str, &'static str);
+type FecLettering = (String, NaiveDate); // Code and date of the matching payment
+
+const ACCOUNT_SUPPLIERS: FecAccount = ("401", "Fournisseurs");
+const ACCOUNT_CLIENTS: FecAccount = ("411", "Clients");
+const ACCOUNT_VAT_DUE: FecAccount = ("44551", "TVA à décaisser");
+const ACCOUNT_VAT_DEDUCTIBLE: FecAccount = ("44566", "TVA déductible sur autres biens et services");
+const ACCOUNT_VAT_CREDIT: FecAccount = ("44567", "Crédit de TVA à reporter");
+const ACCOUNT_VAT_DEDUCTIBLE_PENDING: FecAccount = ("44568", "TVA déductible en attente de paiement");
+const ACCOUNT_VAT_COLLECTED: FecAccount = ("44571", "TVA collectée");
+const ACCOUNT_VAT_COLLECTED_PENDING: FecAccount = ("44574", "TVA collectée en attente d'encaissement");
+const ACCOUNT_BANK: FecAccount = ("512", "Banque");
+const ACCOUNT_PURCHASES: FecAccount = ("606", "Achats non stockés de matières et fournitures");
+const ACCOUNT_SOCIAL_CONTRIBUTIONS: FecAccount = ("646", "Cotisations sociales personnelles de l'exploitant");
+const ACCOUNT_MISC_CHARGES: FecAccount = ("658", "Charges diverses de gestion courante");
+const ACCOUNT_SALES: FecAccount = ("706", "Prestations de services");
+const ACCOUNT_MISC_INCOME: FecAccount = ("758", "Produits divers de gestion courante");
+
+/// Charge account of a purchase, from the fiscal code of its category in the frontend catalogue
+fn purchase_account(category: Option<&str>) -> FecAccount {
+    match category.unwrap_or_default() {
+        "materiel_info" => ("2183", "Matériel de bureau et matériel informatique"),
+        "logiciels" | "hebergement" | "domaines" => ("6061", "Fournitures non stockables"),
+        "bureau" | "livres" => ("6064", "Fournitures administratives"),
+        "sous_traitance" => ("611", "Sous-traitance générale"),
+        "assurance" => ("6161", "Primes d'assurance multirisques"),
+        "comptable" => ("6226", "Honoraires"),
+        "juridique" => ("6227", "Frais d'actes et de contentieux"),
+        "publicite" | "communication" => ("623", "Publicité, publications, relations publiques"),
+        "transport" => ("6251", "Voyages et déplacements"),
+        "repas" => ("6257", "Réceptions"),
+        "banque" => ("627", "Services bancaires et assimilés"),
+        "formation_achat" => ("6313", "Participation à la formation professionnelle continue"),
+        _ => ACCOUNT_PURCHASES,
+    }
+}
+
+const JOURNAL_PURCHASES: FecJournal = ("AC", "Achats");
+const JOURNAL_BANK: FecJournal = ("BQ", "Banque");
+const JOURNAL_MISC: FecJournal = ("OD", "Opérations diverses");
+const JOURNAL_SALES: FecJournal = ("VE", "Ventes");
+
+/// One line of the FEC (amounts in euros, no foreign currency)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FecLine {
+    pub journal_code: String,
+    pub journal_lib: String,
+    pub ecriture_num: String,
+    pub ecriture_date: NaiveDate,
+    pub compte_num: String,
+    pub compte_lib: String,
+    pub piece_ref: String,
+    pub piece_date: NaiveDate,
+    pub ecriture_lib: String,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+    pub ecriture_let: Option<String>, // Lettering code shared by an invoice and its payment
+    pub date_let: Option<NaiveDate>,
+    pub valid_date: NaiveDate,
+}
+
+/// Balance of one account over the exported lines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FecAccountBalance {
+    pub compte_num: String,
+    pub compte_lib: String,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+    pub balance_cents: i64, // debit - credit
+}
+
+/// Result of checking a FEC file against the format and double-entry rules
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FecValidation {
+    pub lines_count: u32,
+    pub entries_count: u32,
+    pub total_debit_cents: i64,
+    pub total_credit_cents: i64,
+    pub errors: Vec<String>,
+}
+
+impl FecValidation {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty() && self.total_debit_cents == self.total_credit_cents
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FecExport {
+    pub filename: String,
+    pub validation: FecValidation,
+    pub trial_balance: Vec<FecAccountBalance>,
+}
+
+/// Entry before numbering; amounts are signed (debit > 0, credit < 0)
+struct FecEntryDraft {
+    journal: FecJournal,
+    date: NaiveDate,
+    piece_ref: String,
+    piece_date: NaiveDate,
+    label: String,
+    lines: Vec<(FecAccount, i64, Option<FecLettering>)>,
+}
+
+/// "SIRENFECAAAAMMJJ.txt", dated at the closing of the fiscal year
+pub fn fec_filename(siren: &str, closing_date: NaiveDate) -> String {
+    format!("{}FEC{}.txt", siren, closing_date.format("%Y%m%d"))
+}
+
+/// Double-entry lines of a calendar fiscal year:
+/// sales (411/706/44571) and purchases (charge account of their category/44566/401) at their invoice date,
+/// their payments in the bank journal with lettering, and paid VAT and URSSAF declarations
+/// VAT on payments waits in 44574 (sales) or 44568 (purchases) until the payment, as the VAT settlement counts it then
+pub fn fec_lines(year: i32, operations: &[Operation], declarations: &[Declaration], invoices: &[IssuedInvoice]) -> Vec<FecLine> {
+    let closing_date = NaiveDate::from_ymd_opt(year, 12, 31).unwrap_or_default();
+    let mut sorted: Vec<&Operation> = operations.iter().collect();
+    sorted.sort_by(|a, b| a.invoice_date.cmp(&b.invoice_date).then(a.created_at.cmp(&b.created_at)));
+
+    let mut drafts = Vec::new();
+    let mut letterings = 0usize;
+    for op in sorted {
+        let piece_ref = invoices.iter()
+            .find(|inv| inv.operation_id == op.id)
+            .map(|inv| inv.number.clone())
+            .unwrap_or_else(|| format!("OP-{}", &op.id.to_string()[..8].to_uppercase()));
+        let label = op.label.clone().filter(|l| !l.trim().is_empty()).unwrap_or_else(|| match op.operation_type {
+            OperationType::Sale => "Vente".to_string(),
+            OperationType::Purchase => "Achat".to_string(),
+        });
+        // Lettering only once the payment is known at closing
+        let lettering = op.payment_date.filter(|d| *d <= closing_date).map(|d| {
+            letterings += 1;
+            (lettering_code(letterings), d)
+        });
+
+        let (vat_collected, vat_deductible) = if op.vat_on_payments {
+            (ACCOUNT_VAT_COLLECTED_PENDING, ACCOUNT_VAT_DEDUCTIBLE_PENDING)
+        } else {
+            (ACCOUNT_VAT_COLLECTED, ACCOUNT_VAT_DEDUCTIBLE)
+        };
+        match op.operation_type {
+            OperationType::Sale => drafts.push(FecEntryDraft {
+                journal: JOURNAL_SALES,
+                date: op.invoice_date,
+                piece_ref: piece_ref.clone(),
+                piece_date: op.invoice_date,
+                label: label.clone(),
+                lines: vec![
+                    (ACCOUNT_CLIENTS, op.amount_ttc_cents, lettering.clone()),
+                    (ACCOUNT_SALES, -op.amount_ht_cents, None),
+                    (vat_collected, -op.vat_amount_cents, None),
+                ],
+            }),
+            OperationType::Purchase => drafts.push(FecEntryDraft {
+                journal: JOURNAL_PURCHASES,
+                date: op.invoice_date,
+                piece_ref: piece_ref.clone(),
+                piece_date: op.invoice_date,
+                label: label.clone(),
+                lines: vec![
+                    (purchase_account(op.category.as_deref()), op.amount_ht_cents, None),
+                    (vat_deductible, op.vat_amount_cents, None),
+                    (ACCOUNT_SUPPLIERS, -op.amount_ttc_cents, lettering.clone()),
+                ],
+            }),
+        }
+
+        if let Some(payment_date) = op.payment_date {
+            let (label, mut lines) = match op.operation_type {
+                OperationType::Sale => (
+                    format!("Encaissement {}", label),
+                    vec![(ACCOUNT_BANK, op.amount_ttc_cents, None), (ACCOUNT_CLIENTS, -op.amount_ttc_cents, lettering)],
+                ),
+                OperationType::Purchase => (
+                    format!("Paiement {}", label),
+                    vec![(ACCOUNT_SUPPLIERS, op.amount_ttc_cents, lettering), (ACCOUNT_BANK, -op.amount_ttc_cents, None)],
+                ),
+            };
+            // The payment makes the waiting VAT collected or deductible
+            if op.vat_on_payments {
+                match op.operation_type {
+                    OperationType::Sale => lines.extend([
+                        (ACCOUNT_VAT_COLLECTED_PENDING, op.vat_amount_cents, None),
+                        (ACCOUNT_VAT_COLLECTED, -op.vat_amount_cents, None),
+                    ]),
+                    OperationType::Purchase => lines.extend([
+                        (ACCOUNT_VAT_DEDUCTIBLE, op.vat_amount_cents, None),
+                        (ACCOUNT_VAT_DEDUCTIBLE_PENDING, -op.vat_amount_cents, None),
+                    ]),
+                }
+            }
+            drafts.push(FecEntryDraft { journal: JOURNAL_BANK, date: payment_date, piece_ref, piece_date: op.invoice_date, label, lines });
+        }
+    }
+
+    for declaration in declarations {
+        let Some(payment_date) = declaration.payment_date else { continue };
+        let period = format!("{:04}-{:02}", declaration.period_year, declaration.period_month);
+        let due = declaration.amount_due_cents;
+        match declaration.declaration_type {
+            DeclarationType::Vat => {
+                // VAT settlement: collected and deductible VAT of the month move to the amount declared
+                let vat = compute_vat_for_month_v2(&MonthId::new(declaration.period_year, declaration.period_month), operations);
+                let settlement_date = declaration.filing_date.unwrap_or(payment_date);
+                let due_account = if due >= 0 { ACCOUNT_VAT_DUE } else { ACCOUNT_VAT_CREDIT };
+                let difference = due - (vat.collected_cents - vat.deductible_cents);
+                let mut lines = vec![
+                    (ACCOUNT_VAT_COLLECTED, vat.collected_cents, None),
+                    (ACCOUNT_VAT_DEDUCTIBLE, -vat.deductible_cents, None),
+                    (due_account, -due, None),
+                ];
+                if difference != 0 {
+                    lines.push((if difference > 0 { ACCOUNT_MISC_CHARGES } else { ACCOUNT_MISC_INCOME }, difference, None));
+                }
+                drafts.push(FecEntryDraft {
+                    journal: JOURNAL_MISC,
+                    date: settlement_date,
+                    piece_ref: format!("TVA-{}", period),
+                    piece_date: settlement_date,
+                    label: format!("Déclaration TVA {}", period),
+                    lines,
+                });
+                if due > 0 {
+                    drafts.push(FecEntryDraft {
+                        journal: JOURNAL_BANK,
+                        date: payment_date,
+                        piece_ref: format!("TVA-{}", period),
+                        piece_date: settlement_date,
+                        label: format!("Paiement TVA {}", period),
+                        lines: vec![(ACCOUNT_VAT_DUE, due, None), (ACCOUNT_BANK, -due, None)],
+                    });
+                }
+            }
+            DeclarationType::Urssaf => drafts.push(FecEntryDraft {
+                journal: JOURNAL_BANK,
+                date: payment_date,
+                piece_ref: format!("URSSAF-{}", period),
+                piece_date: declaration.filing_date.unwrap_or(payment_date),
+                label: format!("Cotisations URSSAF {}", period),
+                lines: vec![(ACCOUNT_SOCIAL_CONTRIBUTIONS, due, None), (ACCOUNT_BANK, -due, None)],
+            }),
+        }
+    }
+
+    drafts.retain(|d| d.date.year() == year && d.lines.iter().any(|(_, amount, _)| *amount != 0));
+    drafts.sort_by(|a, b| a.date.cmp(&b.date).then(a.journal.0.cmp(b.journal.0)).then(a.piece_ref.cmp(&b.piece_ref)));
+
+    let mut lines = Vec::new();
+    for (index, draft) in drafts.into_iter().enumerate() {
+        for ((compte_num, compte_lib), amount, lettering) in draft.lines.into_iter().filter(|(_, amount, _)| *amount != 0) {
+            lines.push(FecLine {
+                journal_code: draft.journal.0.to_string(),
+                journal_lib: draft.journal.1.to_string(),
+                ecriture_num: (index + 1).to_string(),
+                ecriture_date: draft.date,
+                compte_num: compte_num.to_string(),
+                compte_lib: compte_lib.to_string(),
+                piece_ref: draft.piece_ref.clone(),
+                piece_date: draft.piece_date,
+                ecriture_lib: draft.label.clone(),
+                debit_cents: amount.max(0),
+                credit_cents: (-amount).max(0),
+                ecriture_let: lettering.as_ref().map(|(code, _)| code.clone()),
+                date_let: lettering.map(|(_, date)| date),
+                valid_date: draft.date,
+            });
+        }
+    }
+    lines
+}
+
+/// FEC text: header then one record per line, `|` or tab separated, CRLF line endings
+pub fn fec_file_content(lines: &[FecLine], separator: char) -> String {
+    let date = |d: NaiveDate| d.format("%Y%m%d").to_string();
+    let mut content = FEC_COLUMNS.join(&separator.to_string());
+    content.push_str("\r\n");
+    for line in lines {
+        let fields = [
+            line.journal_code.clone(),
+            line.journal_lib.clone(),
+            line.ecriture_num.clone(),
+            date(line.ecriture_date),
+            line.compte_num.clone(),
+            line.compte_lib.clone(),
+            String::new(),
+            String::new(),
+            line.piece_ref.clone(),
+            date(line.piece_date),
+            line.ecriture_lib.clone(),
+            csv_amount(line.debit_cents),
+            csv_amount(line.credit_cents),
+            line.ecriture_let.clone().unwrap_or_default(),
+            line.date_let.map(date).unwrap_or_default(),
+            date(line.valid_date),
+            String::new(),
+            String::new(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| f.replace([separator, '\r', '\n'], " ")).collect();
+        content.push_str(&fields.join(&separator.to_string()));
+        content.push_str("\r\n");
+    }
+    content
+}
+
+/// Debit, credit and balance of each account
+pub fn fec_trial_balance(lines: &[FecLine]) -> Vec<FecAccountBalance> {
+    let mut balances: Vec<FecAccountBalance> = Vec::new();
+    for line in lines {
+        let index = match balances.iter().position(|b| b.compte_num == line.compte_num) {
+            Some(index) => index,
+            None => {
+                balances.push(FecAccountBalance { compte_num: line.compte_num.clone(), compte_lib: line.compte_lib.clone(), debit_cents: 0, credit_cents: 0, balance_cents: 0 });
+                balances.len() - 1
+            }
+        };
+        let balance = &mut balances[index];
+        balance.debit_cents += line.debit_cents;
+        balance.credit_cents += line.credit_cents;
+        balance.balance_cents = balance.debit_cents - balance.credit_cents;
+    }
+    balances.sort_by(|a, b| a.compte_num.cmp(&b.compte_num));
+    balances
+}
+
+/// Check a FEC file: header, 18 fields per record, dates and amounts format,
+/// dates within the fiscal year, continuous numbering in chronological order, and every entry balanced
+pub fn validate_fec_file(content: &str, year: i32) -> FecValidation {
+    let mut validation = FecValidation::default();
+    let mut records = content.lines().map(|l| l.trim_end_matches('\r'));
+    let Some(header) = records.next() else {
+        validation.errors.push("Fichier vide".to_string());
+        return validation;
+    };
+    let separator = if header.contains('|') { '|' } else { '\t' };
+    if header.split(separator).collect::<Vec<_>>() != FEC_COLUMNS {
+        validation.errors.push("En-tête: les 18 colonnes réglementaires sont attendues dans l'ordre".to_string());
+        return validation;
+    }
+
+    let parse_date = |value: &str| NaiveDate::parse_from_str(value, "%Y%m%d").ok().filter(|_| value.len() == 8);
+    let parse_amount = |value: &str| -> Option<i64> {
+        let (units, decimals) = value.split_once(',').unwrap_or((value, "00"));
+        if units.is_empty() || !units.chars().all(|c| c.is_ascii_digit()) || decimals.len() != 2 || !decimals.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(units.parse::<i64>().ok()? * 100 + decimals.parse::<i64>().ok()?)
+    };
+
+    // (num, journal, date, debit, credit) of the entry being read
+    let mut current: Option<(String, String, NaiveDate, i64, i64)> = None;
+    let mut last_num = 0u64;
+    let mut last_date: Option<NaiveDate> = None;
+    let close_entry = |entry: Option<(String, String, NaiveDate, i64, i64)>, errors: &mut Vec<String>| {
+        if let Some((num, _, _, debit, credit)) = entry.filter(|(_, _, _, debit, credit)| debit != credit) {
+            errors.push(format!("Écriture {}: déséquilibrée (débit {} / crédit {})", num, csv_amount(debit), csv_amount(credit)));
+        }
+    };
+
+    for (index, record) in records.enumerate().filter(|(_, r)| !r.is_empty()) {
+        let line_number = index + 2;
+        validation.lines_count += 1;
+        let fields: Vec<&str> = record.split(separator).collect();
+        if fields.len() != FEC_COLUMNS.len() {
+            validation.errors.push(format!("Ligne {}: {} champs au lieu de 18", line_number, fields.len()));
+            continue;
+        }
+        for (column, value) in FEC_COLUMNS.iter().zip(&fields) {
+            let required = matches!(*column, "JournalCode" | "JournalLib" | "EcritureNum" | "EcritureDate" | "CompteNum" | "CompteLib" | "PieceRef" | "PieceDate" | "EcritureLib" | "Debit" | "Credit" | "ValidDate");
+            if required && value.trim().is_empty() {
+                validation.errors.push(format!("Ligne {}: {} obligatoire", line_number, column));
+            }
+        }
+        let dates: Vec<Option<NaiveDate>> = [3, 9, 15].iter().map(|&i| parse_date(fields[i])).collect();
+        let (Some(ecriture_date), Some(_), Some(valid_date)) = (dates[0], dates[1], dates[2]) else {
+            validation.errors.push(format!("Ligne {}: date invalide (format AAAAMMJJ attendu)", line_number));
+            continue;
+        };
+        if !fields[14].is_empty() && parse_date(fields[14]).is_none() {
+            validation.errors.push(format!("Ligne {}: DateLet invalide", line_number));
+        }
+        if ecriture_date.year() != year {
+            validation.errors.push(format!("Ligne {}: écriture hors exercice {}", line_number, year));
+        }
+        if valid_date < ecriture_date {
+            validation.errors.push(format!("Ligne {}: ValidDate antérieure à EcritureDate", line_number));
+        }
+        if !fields[4].chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) || fields[4].len() < 3 {
+            validation.errors.push(format!("Ligne {}: CompteNum invalide", line_number));
+        }
+        let (Some(debit), Some(credit)) = (parse_amount(fields[11]), parse_amount(fields[12])) else {
+            validation.errors.push(format!("Ligne {}: montant invalide (format 1234,56 attendu)", line_number));
+            continue;
+        };
+        if debit != 0 && credit != 0 {
+            validation.errors.push(format!("Ligne {}: débit et crédit renseignés", line_number));
+        }
+        validation.total_debit_cents += debit;
+        validation.total_credit_cents += credit;
+
+        let num = fields[2].to_string();
+        match current.as_mut() {
+            Some((current_num, journal, date, total_debit, total_credit)) if *current_num == num => {
+                if journal != fields[0] || *date != ecriture_date {
+                    validation.errors.push(format!("Ligne {}: écriture {} sur plusieurs journaux ou dates", line_number, num));
+                }
+                *total_debit += debit;
+                *total_credit += credit;
+            }
+            _ => {
+                close_entry(current.take(), &mut validation.errors);
+                validation.entries_count += 1;
+                match num.parse::<u64>() {
+                    Ok(n) if n == last_num + 1 => last_num = n,
+                    _ => validation.errors.push(format!("Ligne {}: numérotation discontinue ({} après {})", line_number, num, last_num)),
+                }
+                if last_date.map(|d| ecriture_date < d).unwrap_or(false) {
+                    validation.errors.push(format!("Ligne {}: écriture {} hors ordre chronologique", line_number, num));
+                }
+                last_date = Some(ecriture_date);
+                current = Some((num, fields[0].to_string(), ecriture_date, debit, credit));
+            }
+        }
+    }
+    close_entry(current, &mut validation.errors);
+
+    if validation.total_debit_cents != validation.total_credit_cents {
+        validation.errors.push(format!(
+            "Total débit {} différent du total crédit {}",
+            csv_amount(validation.total_debit_cents),
+            csv_amount(validation.total_credit_cents)
+        ));
+    }
+    validation
+}
+
+/// "A".."Z", "AA".."ZZ", "AAA"...
+fn lettering_code(mut index: usize) -> String {
+    let mut code = Vec::new();
+    while index > 0 {
+        index -= 1;
+        code.push((b'A' + (index % 26) as u8) as char);
+        index /= 26;
+    }
+    code.iter().rev().collect()
+}
+
+// ============ Legal Archive ============
+
+/// Accounting documents are kept 10 years after the closing of their fiscal year (art. L123-22 du Code de commerce)
+pub const RETENTION_YEARS: i32 = 10;
+
+pub fn retention_until(fiscal_year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(fiscal_year + RETENTION_YEARS, 12, 31).unwrap_or(NaiveDate::MAX)
+}
+
+/// The only update an archived year still accepts, as its database trigger does: the payment of an unpaid operation
+pub fn records_payment_only(before: &Operation, after: &Operation) -> bool {
+    before.payment_date.is_none()
+        && after.id == before.id
+        && after.invoice_date == before.invoice_date
+        && after.operation_type == before.operation_type
+        && after.amount_ht_cents == before.amount_ht_cents
+        && after.vat_amount_cents == before.vat_amount_cents
+        && after.amount_ttc_cents == before.amount_ttc_cents
+        && after.vat_on_payments == before.vat_on_payments
+        && after.label == before.label
+        && after.client == before.client
+        && after.category == before.category
+        && after.receipt_key == before.receipt_key
+        && after.receipt_sha256 == before.receipt_sha256
+}
+
+/// Fiscal year whose operations and documents became read-only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFiscalYear {
+    pub year: i32,
+    pub archived_at: NaiveDateTime,
+    pub retain_until: NaiveDate,
+    pub operations_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedDocument {
+    pub key: String,
+    pub fiscal_year: i32,
+    pub sha256: String,
+    pub retain_until: NaiveDate,
+    pub object_locked: bool, // Retention also enforced by the storage backend
+}
+
+impl ArchivedDocument {
+    pub fn is_retained(&self, today: NaiveDate) -> bool {
+        today <= self.retain_until
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ArchivedDocumentState {
+    #[serde(rename = "intact")]
+    Intact,
+    #[serde(rename = "missing")]
+    Missing,
+    #[serde(rename = "mismatch")]
+    Mismatch, // The stored content no longer matches the archived hash
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedDocumentCheck {
+    pub document: ArchivedDocument,
+    pub state: ArchivedDocumentState,
+}
+
+/// What is retained until when, and whether every archived document is still intact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveReport {
+    pub checked_at: NaiveDateTime,
+    pub fiscal_years: Vec<ArchivedFiscalYear>,
+    pub documents: Vec<ArchivedDocumentCheck>,
+}
+
+impl ArchiveReport {
+    pub fn is_intact(&self) -> bool {
+        self.documents.iter().all(|check| check.state == ArchivedDocumentState::Intact)
+    }
+}
+
+// ============ Backups ============
+
+/// Document store folder receiving the off-site copies of the backups (never audited as receipts)
+pub const BACKUP_KEY_PREFIX: &str = "backups/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupReason {
+    #[serde(rename = "scheduled")]
+    Scheduled,
+    #[serde(rename = "pre_migration")]
+    PreMigration,
+    #[serde(rename = "month_close")]
+    MonthClose,
+    #[serde(rename = "pre_restore")]
+    PreRestore,
+    #[serde(rename = "pre_repair")]
+    PreRepair,
+    #[serde(rename = "pre_import")]
+    PreImport,
+    #[serde(rename = "manual")]
+    Manual,
+}
+
+impl BackupReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupReason::Scheduled => "scheduled",
+            BackupReason::PreMigration => "pre_migration",
+            BackupReason::MonthClose => "month_close",
+            BackupReason::PreRestore => "pre_restore",
+            BackupReason::PreRepair => "pre_repair",
+            BackupReason::PreImport => "pre_import",
+            BackupReason::Manual => "manual",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "scheduled" => Some(BackupReason::Scheduled),
+            "pre_migration" => Some(BackupReason::PreMigration),
+            "month_close" => Some(BackupReason::MonthClose),
+            "pre_restore" => Some(BackupReason::PreRestore),
+            "pre_repair" => Some(BackupReason::PreRepair),
+            "pre_import" => Some(BackupReason::PreImport),
+            "manual" => Some(BackupReason::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// How many snapshots to keep: the most recent one of each of the last N days, weeks and months
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPolicy {
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub keep_monthly: u32,
+    pub offsite: bool, // Also push each snapshot to the document store
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self { keep_daily: 7, keep_weekly: 4, keep_monthly: 12, offsite: false }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub name: String, // data-YYYYMMDD-HHMMSS-<reason>.sqlite
+    pub created_at: NaiveDateTime,
+    pub reason: BackupReason,
+    pub size_bytes: u64,
+}
+
+pub fn backup_name(created_at: NaiveDateTime, reason: BackupReason) -> String {
+    format!("data-{}-{}.sqlite", created_at.format("%Y%m%d-%H%M%S"), reason.as_str())
+}
+
+/// Date and reason of a snapshot from its file name; None for anything else
+pub fn parse_backup_name(name: &str) -> Option<(NaiveDateTime, BackupReason)> {
+    let stem = name.strip_prefix("data-")?.strip_suffix(".sqlite")?;
+    if stem.len() < 16 || !stem.is_char_boundary(15) {
+        return None;
+    }
+    let created_at = NaiveDateTime::parse_from_str(&stem[..15], "%Y%m%d-%H%M%S").ok()?;
+    let reason = BackupReason::parse(stem[15..].strip_prefix('-')?)?;
+    Some((created_at, reason))
+}
+
+/// Snapshots falling outside the retention policy; the most recent one is always kept
+pub fn backups_to_prune(backups: &[BackupInfo], policy: &BackupPolicy) -> Vec<String> {
+    let mut sorted: Vec<&BackupInfo> = backups.iter().collect();
+    sorted.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+
+    let mut keep: std::collections::HashSet<&str> = sorted.first().map(|b| b.name.as_str()).into_iter().collect();
+    keep_newest_per_period(&sorted, policy.keep_daily, |d| (d.year(), d.ordinal()), &mut keep);
+    keep_newest_per_period(&sorted, policy.keep_weekly, |d| (d.iso_week().year(), d.iso_week().week()), &mut keep);
+    keep_newest_per_period(&sorted, policy.keep_monthly, |d| (d.year(), d.month()), &mut keep);
+
+    sorted.iter().filter(|b| !keep.contains(b.name.as_str())).map(|b| b.name.clone()).collect()
+}
+
+fn keep_newest_per_period<'a, K: Eq + std::hash::Hash>(
+    sorted: &[&'a BackupInfo],
+    count: u32,
+    period: impl Fn(NaiveDate) -> K,
+    keep: &mut std::collections::HashSet<&'a str>,
+) {
+    let mut periods = std::collections::HashSet::new();
+    for backup in sorted {
+        let key = period(backup.created_at.date());
+        if periods.contains(&key) {
+            continue;
+        }
+        if periods.len() >= count as usize {
+            break;
+        }
+        periods.insert(key);
+        keep.insert(backup.name.as_str());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub backup: BackupInfo,
+    pub ok: bool,
+    pub message: String, // "ok" or what the integrity check reported
+}
+
+/// A snapshot taken, with what the retention policy removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRun {
+    pub backup: BackupInfo,
+    pub offsite_key: Option<String>,
+    pub pruned: Vec<String>,
+}
+
+// ============ Audit Trail ============
+
+/// `prev_hash` of the first event
+pub const AUDIT_GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditEntity {
+    #[serde(rename = "operation")]
+    Operation,
+    #[serde(rename = "declaration")]
+    Declaration,
+    #[serde(rename = "provision")]
+    Provision,
+    #[serde(rename = "settings")]
+    Settings,
+    #[serde(rename = "yearly_planning")]
+    YearlyPlanning,
+    #[serde(rename = "month_planning")]
+    MonthPlanning,
+}
+
+impl AuditEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEntity::Operation => "operation",
+            AuditEntity::Declaration => "declaration",
+            AuditEntity::Provision => "provision",
+            AuditEntity::Settings => "settings",
+            AuditEntity::YearlyPlanning => "yearly_planning",
+            AuditEntity::MonthPlanning => "month_planning",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "operation" => Some(AuditEntity::Operation),
+            "declaration" => Some(AuditEntity::Declaration),
+            "provision" => Some(AuditEntity::Provision),
+            "settings" => Some(AuditEntity::Settings),
+            "yearly_planning" => Some(AuditEntity::YearlyPlanning),
+            "month_planning" => Some(AuditEntity::MonthPlanning),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditAction {
+    #[serde(rename = "create")]
+    Create,
+    #[serde(rename = "update")]
+    Update,
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "create" => Some(AuditAction::Create),
+            "update" => Some(AuditAction::Update),
+            "delete" => Some(AuditAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// A mutation to record; the repository numbers, timestamps and chains it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAuditEvent {
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub origin: String, // Use case that made the change, e.g. "update_operation"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub seq: i64,
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub occurred_at: NaiveDateTime,
+    pub origin: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Text hashed (SHA-256) to chain an event; JSON states are hashed as stored
+#[allow(clippy::too_many_arguments)]
+pub fn audit_hash_input(
+    prev_hash: &str,
+    seq: i64,
+    entity: &str,
+    entity_id: &str,
+    action: &str,
+    before_json: Option<&str>,
+    after_json: Option<&str>,
+    occurred_at: &str,
+    origin: &str,
+) -> String {
+    [
+        prev_hash,
+        &seq.to_string(),
+        entity,
+        entity_id,
+        action,
+        before_json.unwrap_or("null"),
+        after_json.unwrap_or("null"),
+        occurred_at,
+        origin,
+    ]
+    .join("\n")
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub entity: Option<AuditEntity>,
+    pub entity_id: Option<String>,
+    pub before_seq: Option<i64>, // Paging: only events older than this one
+    pub after_seq: Option<i64>, // Only events newer than this one
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainCheck {
+    pub events_count: u64,
+    pub last_hash: Option<String>,
+    pub broken_at_seq: Option<i64>, // First event whose number, link or hash does not match
+    pub message: Option<String>,
+}
+
+impl AuditChainCheck {
+    pub fn is_valid(&self) -> bool {
+        self.broken_at_seq.is_none()
+    }
+}
+
+// ============ Database Doctor ============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoctorIssueKind {
+    #[serde(rename = "invalid_uuid")]
+    InvalidUuid,
+    #[serde(rename = "invalid_date")]
+    InvalidDate,
+    #[serde(rename = "invalid_datetime")]
+    InvalidDateTime,
+    #[serde(rename = "invalid_enum")]
+    InvalidEnum,
+    #[serde(rename = "missing_value")]
+    MissingValue,
+    #[serde(rename = "amount_mismatch")]
+    AmountMismatch, // HT + VAT != TTC
+    #[serde(rename = "orphan_row")]
+    OrphanRow,
+}
+
+/// Repair proposed for an issue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action")]
+pub enum DoctorFix {
+    #[serde(rename = "set_value")]
+    SetValue { value: String },
+    #[serde(rename = "delete_row")]
+    DeleteRow,
+}
+
+/// A row the application cannot read, or that breaks an invariant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorIssue {
+    pub table: String,
+    pub rowid: i64,             // SQLite rowid, locates the row to repair
+    pub row_id: String,         // Key shown to the user: id, year, key…
+    pub column: Option<String>, // None when the whole row is at fault
+    pub value: Option<String>,  // Value found, as text
+    pub kind: DoctorIssueKind,
+    pub message: String,
+    pub fix: Option<DoctorFix>, // None: to be corrected by hand
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorScan {
+    pub rows_scanned: u64,
+    pub issues: Vec<DoctorIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorRepair {
+    pub issue: DoctorIssue,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub scanned_at: NaiveDateTime,
+    pub rows_scanned: u64,
+    pub issues: Vec<DoctorIssue>,   // What remains after the repairs, if any
+    pub repairs: Vec<DoctorRepair>, // Empty unless fixes were applied
+    pub backup: Option<String>,     // Snapshot taken before repairing
+}
+
+impl DoctorReport {
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+// ============ Trash and Undo ============
+
+/// Days an item stays in the trash before being purged
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Undo steps kept in memory, oldest dropped first
+pub const UNDO_STACK_DEPTH: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedOperation {
+    pub operation: Operation,
+    pub deleted_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedYearlyPlanning {
+    pub planning: YearlyPlanning,
+    pub deleted_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Trash {
+    pub operations: Vec<TrashedOperation>,
+    pub yearly_plannings: Vec<TrashedYearlyPlanning>,
+}
+
+/// Whether an item trashed at `deleted_at` is due for purge
+pub fn is_trash_expired(deleted_at: NaiveDateTime, now: NaiveDateTime) -> bool {
+    now - deleted_at >= chrono::Duration::days(TRASH_RETENTION_DAYS)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashPurge {
+    pub operations: u32,
+    pub yearly_plannings: u32,
+    pub skipped: u32, // Operations of an archived fiscal year, kept until the end of their retention
+    pub invoiced: u32, // Operations of an issued invoice, kept since only a credit note cancels them
+}
+
+/// One entity change of an undo step, with the same states as its audit event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoChange {
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub before: Option<serde_json::Value>, // None: the entity was created (or restored from the trash)
+    pub after: Option<serde_json::Value>,  // None: the entity was trashed
+}
+
+/// The changes made by one use case, reverted together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoStep {
+    pub origin: String,
+    pub performed_at: NaiveDateTime,
+    pub changes: Vec<UndoChange>,
+}
+
+// ============ Workspaces ============
+
+/// Workspace of installations predating workspaces: its database and documents stay where they were
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+
+/// Document store folder holding the documents of the other workspaces (never audited as receipts)
+pub const WORKSPACE_KEY_PREFIX: &str = "workspaces/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BusinessRegime {
+    #[serde(rename = "micro_entreprise")]
+    MicroEntreprise,
+    #[serde(rename = "entreprise_individuelle")]
+    EntrepriseIndividuelle,
+    #[serde(rename = "eurl")]
+    Eurl,
+    #[serde(rename = "sasu")]
+    Sasu,
+    #[serde(rename = "other")]
+    Other,
+}
+
+/// One activity kept apart from the others: its own database, settings and documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,               // Lowercase letters, digits and '-'; names its folder
+    pub name: String,
+    pub siren: Option<String>,
+    pub regime: BusinessRegime,
+    pub document_prefix: String,  // Folder of its documents in the shared store ("" for the default workspace)
+    pub created_at: NaiveDateTime,
+}
+
+impl Workspace {
+    pub fn new(id: &str, name: &str, siren: Option<String>, regime: BusinessRegime, now: NaiveDateTime) -> DomainResult<Self> {
+        let workspace = Workspace {
+            id: id.trim().to_string(),
+            name: name.trim().to_string(),
+            siren: siren.map(|s| s.split_whitespace().collect::<String>()).filter(|s| !s.is_empty()),
+            regime,
+            document_prefix: if id.trim() == DEFAULT_WORKSPACE_ID { String::new() } else { format!("{}{}", WORKSPACE_KEY_PREFIX, id.trim()) },
+            created_at: now,
+        };
+        workspace.validate()?;
+        Ok(workspace)
+    }
+
+    pub fn validate(&self) -> DomainResult<()> {
+        if self.id.is_empty() || !self.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            return Err(DomainError::Validation(format!(
+                "Identifiant d'espace invalide: '{}' (lettres minuscules, chiffres et tirets)", self.id
+            )));
+        }
+        if self.name.is_empty() {
+            return Err(DomainError::Validation("Nom de l'espace obligatoire".into()));
+        }
+        if let Some(siren) = &self.siren {
+            if siren.len() != 9 || !siren.chars().all(|c| c.is_ascii_digit()) {
+                return Err(DomainError::Validation(format!("SIREN invalide: {}", siren)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Registered workspaces and the one the application has open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceList {
+    pub active: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+/// Cash outlook of one workspace, part of the consolidated view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceCashPlan {
+    pub workspace: Workspace,
+    pub pending_provisions_cents: i64, // VAT and URSSAF set aside but not paid yet
+    pub forecast: ForecastResult,
+}
+
+/// Personal cash planning across every workspace, month by month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsolidatedCashPlan {
+    pub start: MonthId,
+    pub workspaces: Vec<WorkspaceCashPlan>,
+    pub pending_provisions_cents: i64,
+    pub months: Vec<ForecastLine>, // Sum of the workspaces' forecast lines
+}
+
+pub fn consolidate_cash_plans(start: &MonthId, workspaces: Vec<WorkspaceCashPlan>) -> ConsolidatedCashPlan {
+    let mut months: Vec<ForecastLine> = Vec::new();
+    for line in workspaces.iter().flat_map(|w| &w.forecast.months) {
+        match months.iter_mut().find(|m| m.year == line.year && m.month == line.month) {
+            Some(total) => {
+                total.ht_cents += line.ht_cents;
+                total.vat_due_cents += line.vat_due_cents;
+                total.urssaf_due_cents += line.urssaf_due_cents;
+                total.expenses_ttc_cents += line.expenses_ttc_cents;
+                total.net_cents += line.net_cents;
+                total.after_provisions_cents += line.after_provisions_cents;
+            }
+            None => months.push(line.clone()),
+        }
+    }
+    months.sort_by_key(|m| (m.year, m.month));
+    ConsolidatedCashPlan {
+        start: start.clone(),
+        pending_provisions_cents: workspaces.iter().map(|w| w.pending_provisions_cents).sum(),
+        workspaces,
+        months,
+    }
+}
+
+// ============ Dataset Export ============
+
+/// Identifies a dataset dump among other JSON files
+pub const DATASET_FORMAT: &str = "cash-planner-dataset";
+
+/// Layout written by this build; older dumps are upgraded on import, newer ones refused
+pub const DATASET_VERSION: u32 = 1;
+
+/// Upgrade steps on the raw JSON of a dump: the step at index `i` turns version `i + 1` into `i + 2`
+/// A new collection or a field with a default needs no step, see `DatasetDump`
+const DATASET_UPGRADES: &[fn(&mut serde_json::Value) -> DomainResult<()>] = &[];
+
+const _: () = assert!(DATASET_UPGRADES.len() as u32 + 1 == DATASET_VERSION);
+
+/// Portable copy of a workspace's data, as one JSON document:
+///
+/// `{ "format": "cash-planner-dataset", "version": 1, "exported_at": "2025-01-31T18:00:00",
+///   "settings": {…}, "operations": […], "declarations": […], "provisions": […], "closed_months": […],
+///   "yearly_plannings": […], "simulations": […], "monthly_kpis": […], "working_days": […] }`
+///
+/// Records are serialized as in the application's commands: amounts in cents, rates in ppm,
+/// ISO 8601 dates. Documents are not part of the dump, operations only keep their receipt keys;
+/// trashed records are left out. A missing collection reads as empty and missing settings leave
+/// the current ones, so a seed file only lists what it needs
+///
+/// Issued invoices, tax schedules and archived fiscal years are not covered: numbering, PDFs and
+/// retention tie them to the installation that produced them. A dump carrying any other collection
+/// is refused rather than partly imported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetDump {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    #[serde(default)]
+    pub settings: Option<Settings>,
+    #[serde(default)]
+    pub operations: Vec<Operation>,
+    #[serde(default)]
+    pub declarations: Vec<Declaration>,
+    #[serde(default)]
+    pub provisions: Vec<Provision>,
+    #[serde(default)]
+    pub closed_months: Vec<MonthStatus>,
+    #[serde(default)]
+    pub yearly_plannings: Vec<YearlyPlanning>,
+    #[serde(default)]
+    pub simulations: Vec<Simulation>,
+    #[serde(default)]
+    pub monthly_kpis: Vec<MonthlyKPI>,
+    #[serde(default)]
+    pub working_days: Vec<WorkingDay>,
+}
+
+impl DatasetDump {
+    /// Read a dump of any version up to the current one, with the version it was written in
+    pub fn parse(content: &[u8]) -> DomainResult<(DatasetDump, u32)> {
+        let unreadable = |e: String| DomainError::Validation(format!("Export de données illisible: {}", e));
+        let mut value: serde_json::Value = serde_json::from_slice(content).map_err(|e| unreadable(e.to_string()))?;
+        if value.get("format").and_then(|f| f.as_str()) != Some(DATASET_FORMAT) {
+            return Err(unreadable(format!("format '{}' attendu", DATASET_FORMAT)));
+        }
+        let version = match value.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if v > DATASET_VERSION as u64 => return Err(DomainError::Validation(format!(
+                "Export en version {}: cette version de l'application lit jusqu'à la version {}", v, DATASET_VERSION
+            ))),
+            Some(v) if v >= 1 => v as u32,
+            _ => return Err(unreadable("version manquante ou invalide".into())),
+        };
+        for upgrade in &DATASET_UPGRADES[version as usize - 1..] {
+            upgrade(&mut value)?;
+        }
+        value["version"] = DATASET_VERSION.into();
+        let dump = serde_json::from_value(value).map_err(|e| unreadable(e.to_string()))?;
+        Ok((dump, version))
+    }
+
+    /// Consistency of the dump on its own: unique identifiers and periods, months within their
+    /// yearly planning. The error lists every problem found
+    pub fn validate(&self) -> DomainResult<()> {
+        fn duplicates<K: std::hash::Hash + Eq + Clone>(keys: impl IntoIterator<Item = K>) -> Vec<K> {
+            let mut seen = std::collections::HashSet::new();
+            keys.into_iter().filter(|k| !seen.insert(k.clone())).collect()
+        }
+        let mut problems = Vec::new();
+        let period = |year: i32, month: u32| format!("{:04}-{:02}", year, month);
+
+        problems.extend(duplicates(self.operations.iter().map(|o| o.id)).into_iter().map(|id| format!("opération {} en double", id)));
+        problems.extend(duplicates(self.declarations.iter().map(|d| d.id)).into_iter().map(|id| format!("déclaration {} en double", id)));
+        problems.extend(duplicates(self.declarations.iter().map(|d| (format!("{:?}", d.declaration_type), d.period_year, d.period_month)))
+            .into_iter().map(|(t, y, m)| format!("deux déclarations {} pour {}", t, period(y, m))));
+        problems.extend(duplicates(self.provisions.iter().map(|p| p.id)).into_iter().map(|id| format!("provision {} en double", id)));
+        problems.extend(duplicates(self.provisions.iter().map(|p| (format!("{:?}", p.provision_type), p.period_year, p.period_month)))
+            .into_iter().map(|(t, y, m)| format!("deux provisions {} pour {}", t, period(y, m))));
+        problems.extend(duplicates(self.closed_months.iter().map(|m| (m.month.year, m.month.month)))
+            .into_iter().map(|(y, m)| format!("mois {} clôturé deux fois", period(y, m))));
+        problems.extend(self.closed_months.iter().filter(|m| m.closed_at.is_none())
+            .map(|m| format!("mois {} sans date de clôture", period(m.month.year, m.month.month))));
+        problems.extend(duplicates(self.yearly_plannings.iter().map(|p| p.year)).into_iter().map(|y| format!("deux plannings pour {}", y)));
+        problems.extend(duplicates(self.yearly_plannings.iter().flat_map(|p| &p.months).map(|m| m.id))
+            .into_iter().map(|id| format!("mois de planning {} en double", id)));
+        problems.extend(duplicates(self.simulations.iter().map(|s| s.id)).into_iter().map(|id| format!("simulation {} en double", id)));
+        problems.extend(duplicates(self.monthly_kpis.iter().map(|k| k.id)).into_iter().map(|id| format!("indicateurs {} en double", id)));
+        problems.extend(duplicates(self.monthly_kpis.iter().map(|k| (k.month.year, k.month.month)))
+            .into_iter().map(|(y, m)| format!("deux indicateurs pour {}", period(y, m))));
+        problems.extend(duplicates(self.working_days.iter().map(|w| w.id)).into_iter().map(|id| format!("journée {} en double", id)));
+
+        let months = self.declarations.iter().map(|d| ("déclaration", d.period_year, d.period_month))
+            .chain(self.provisions.iter().map(|p| ("provision", p.period_year, p.period_month)))
+            .chain(self.monthly_kpis.iter().map(|k| ("indicateurs", k.month.year, k.month.month)))
+            .chain(self.closed_months.iter().map(|m| ("clôture", m.month.year, m.month.month)));
+        for (what, year, month) in months {
+            if !(1..=12).contains(&month) {
+                problems.push(format!("{} sur un mois invalide: {}", what, period(year, month)));
+            }
+        }
+        for planning in &self.yearly_plannings {
+            for month in &planning.months {
+                if month.year != planning.year || !(1..=12).contains(&month.month) {
+                    problems.push(format!("mois {} hors du planning {}", period(month.year, month.month), planning.year));
+                }
+            }
+            problems.extend(duplicates(planning.months.iter().map(|m| m.month))
+                .into_iter().map(|m| format!("mois {} en double dans le planning", period(planning.year, m))));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(DomainError::Validation(format!("Export incohérent: {}", problems.join("; "))))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DatasetImportMode {
+    /// Add the dump's records, overwriting those with the same identifier
+    #[serde(rename = "merge")]
+    Merge,
+    /// Also delete the records absent from the dump: the workspace ends up holding exactly the dump
+    #[serde(rename = "replace")]
+    Replace,
+}
+
+/// What an import did to one kind of record
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatasetImportCount {
+    pub entity: String, // Collection name in the dump
+    pub created: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    pub deleted: u32,
+}
+
+impl DatasetImportCount {
+    pub fn new(entity: &str) -> Self {
+        Self { entity: entity.to_string(), ..Self::default() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetImportReport {
+    pub mode: DatasetImportMode,
+    pub source_version: u32,
+    pub backup: String, // Snapshot taken before the import, to restore if needed
+    pub counts: Vec<DatasetImportCount>,
+}
+
+// ============ Synchronisation ============
+
+/// Audit origin of the changes replayed from another installation, followed by its name
+/// Each installation only publishes its own changes: replayed ones are not published again
+pub const SYNC_ORIGIN_PREFIX: &str = "sync:";
+
+/// Clock of a record's creation or deletion, kept next to the clocks of its fields
+pub const SYNC_RECORD_CLOCK: &str = "*";
+
+/// Identity and write timestamps take no part in the per-field resolution
+const SYNC_UNMERGED_FIELDS: &[&str] = &["id", "created_at", "updated_at"];
+
+/// Synchronisation of an installation with the others through a shared folder (Syncthing, NAS)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSettings {
+    pub peer_id: String, // Name of the installation among its peers, e.g. "bureau"; names its change log
+    pub shared_dir: String,
+}
+
+impl SyncSettings {
+    pub fn validate(&self) -> DomainResult<()> {
+        if self.peer_id.is_empty() || !self.peer_id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            return Err(DomainError::Validation(format!(
+                "Nom d'installation invalide: '{}' (lettres minuscules, chiffres et tirets)", self.peer_id
+            )));
+        }
+        if self.shared_dir.trim().is_empty() {
+            return Err(DomainError::Validation("Dossier partagé obligatoire".into()));
+        }
+        Ok(())
+    }
+}
+
+/// When and where a value was written: the later stamp wins, the installation name then the
+/// position in its log break ties (audit times are to the second), so every installation picks
+/// the same winner whatever the order it receives changes in
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SyncStamp {
+    pub changed_at: NaiveDateTime,
+    pub peer: String,
+    pub seq: i64,
+}
+
+/// One line of an installation's change log, taken from its audit trail
+///
+/// Every installation appends its own mutations to `<peer_id>.jsonl` in the shared folder and
+/// replays those of the others, field by field (last writer wins, see `merge_sync_change`).
+/// Settings, operations, declarations, provisions and plannings are synchronised; working days,
+/// simulations, KPIs and documents are not. A new log starts with the current state of the
+/// workspace, so a second installation can start from an empty workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncChange {
+    pub peer: String,
+    pub seq: i64, // Position in the peer's log, from 1
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub changed_at: NaiveDateTime,
+}
+
+impl SyncChange {
+    pub fn stamp(&self) -> SyncStamp {
+        SyncStamp { changed_at: self.changed_at, peer: self.peer.clone(), seq: self.seq }
+    }
+
+    /// Fields the change wrote: every field of a creation, the modified ones of an update
+    pub fn changed_fields(&self) -> Vec<String> {
+        let Some(after) = self.after.as_ref().and_then(|a| a.as_object()) else { return Vec::new() };
+        let before = self.before.as_ref().and_then(|b| b.as_object());
+        after.iter()
+            .filter(|(field, _)| !SYNC_UNMERGED_FIELDS.contains(&field.as_str()))
+            .filter(|(field, value)| before.is_none_or(|b| b.get(*field) != Some(*value)))
+            .map(|(field, _)| field.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncSide {
+    #[serde(rename = "local")]
+    Local,
+    #[serde(rename = "remote")]
+    Remote,
+}
+
+impl SyncSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncSide::Local => "local",
+            SyncSide::Remote => "remote",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "local" => Some(SyncSide::Local),
+            "remote" => Some(SyncSide::Remote),
+            _ => None,
+        }
+    }
+}
+
+/// Both installations changed the same value without seeing each other's change: the resolution
+/// kept one of them, the user reviews it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub id: Uuid,
+    pub peer: String, // Installation the change came from
+    pub entity: AuditEntity,
+    pub entity_id: String,
+    pub field: Option<String>, // None: the record as a whole (deletion, change that could not be applied)
+    pub local_value: Option<serde_json::Value>,
+    pub remote_value: Option<serde_json::Value>,
+    pub kept: SyncSide,
+    pub message: String,
+    pub detected_at: NaiveDateTime,
+}
+
+/// Field of a record both sides changed, see `merge_sync_change`
+#[derive(Debug, Clone)]
+pub struct SyncFieldConflict {
+    pub field: String,
+    pub local: serde_json::Value,
+    pub remote: serde_json::Value,
+    pub kept: SyncSide,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncMerge {
+    pub state: serde_json::Value,
+    pub taken: Vec<String>, // Fields taken from the change
+    pub conflicts: Vec<SyncFieldConflict>,
+}
+
+/// Apply a change to the local state of its record, field by field: a written field takes the
+/// change's value unless the local clock of that field is later. When the field was written here
+/// too and its local value is not the one the change started from, both sides wrote it
+/// concurrently: that is a conflict, whichever value is kept
+pub fn merge_sync_change(
+    local: &serde_json::Value,
+    clocks: &std::collections::HashMap<String, SyncStamp>,
+    change: &SyncChange,
+) -> SyncMerge {
+    let stamp = change.stamp();
+    let mut merge = SyncMerge { state: local.clone(), taken: Vec::new(), conflicts: Vec::new() };
+    let value = |state: Option<&serde_json::Value>, field: &str| state.and_then(|s| s.get(field)).cloned();
+    for field in change.changed_fields() {
+        let remote = value(change.after.as_ref(), &field).unwrap_or_default();
+        let current = value(Some(local), &field).unwrap_or_default();
+        if current == remote {
+            continue;
+        }
+        let clock = clocks.get(&field);
+        let wins = clock.is_none_or(|clock| stamp > *clock);
+        if clock.is_some() && value(change.before.as_ref(), &field).as_ref() != Some(&current) {
+            let kept = if wins { SyncSide::Remote } else { SyncSide::Local };
+            merge.conflicts.push(SyncFieldConflict { field: field.clone(), local: current, remote: remote.clone(), kept });
+        }
+        if wins {
+            if let Some(state) = merge.state.as_object_mut() {
+                state.insert(field.clone(), remote);
+            }
+            merge.taken.push(field);
+        }
+    }
+    // The record's timestamp is the one of the latest write it holds (ISO 8601 text sorts by date)
+    let (local_updated, remote_updated) = (value(Some(local), "updated_at"), value(change.after.as_ref(), "updated_at"));
+    if !merge.taken.is_empty() && remote_updated.as_ref().and_then(|v| v.as_str()) > local_updated.as_ref().and_then(|v| v.as_str()) {
+        if let (Some(state), Some(updated_at)) = (merge.state.as_object_mut(), remote_updated) {
+            state.insert("updated_at".to_string(), updated_at);
+        }
+    }
+    merge
+}
+
+/// Interleave the logs of several installations by stamp, each log keeping its own order, so an
+/// installation catching up replays the changes as they happened rather than one log after the other
+pub fn interleave_sync_changes(changes: Vec<SyncChange>) -> Vec<SyncChange> {
+    let mut logs: std::collections::BTreeMap<String, std::collections::VecDeque<SyncChange>> = Default::default();
+    for change in changes {
+        logs.entry(change.peer.clone()).or_default().push_back(change);
+    }
+    let mut ordered = Vec::new();
+    while let Some(log) = logs.values_mut().filter(|log| !log.is_empty()).min_by_key(|log| log[0].stamp()) {
+        ordered.extend(log.pop_front());
+    }
+    ordered
+}
+
+/// Change logs of the installations sharing a folder, one append-only log each
+#[async_trait::async_trait]
+pub trait ChangeLog: Send + Sync {
+    /// Number of changes in the installation's log
+    async fn log_length(&self, peer: &str) -> DomainResult<i64>;
+    async fn append_changes(&self, peer: &str, changes: &[SyncChange]) -> DomainResult<()>;
+    /// Changes of every other installation, each log in order
+    /// A log that cannot be read is left out whole and reported, so it does not hold back the others
+    async fn read_changes(&self, own_peer: &str) -> DomainResult<SyncLogs>;
+}
+
+/// What the change logs of the other installations hold
+#[derive(Debug, Clone, Default)]
+pub struct SyncLogs {
+    pub changes: Vec<SyncChange>,
+    pub unreadable: Vec<UnreadableSyncLog>,
+}
+
+/// Log of another installation skipped until it reads again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadableSyncLog {
+    pub peer: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub peer_id: String,
+    pub published: u32, // Local changes appended to the own log
+    pub applied: u32, // Changes of the other installations replayed here
+    pub conflicts: Vec<SyncConflict>, // Found during this run
+    pub unreadable_logs: Vec<UnreadableSyncLog>, // Nothing replayed from them during this run
+}
+
+// ============ Operation Query ============
+
+/// Page size of a query that sets none, and the largest one accepted
+pub const OPERATION_PAGE_SIZE: u32 = 50;
+pub const OPERATION_PAGE_MAX: u32 = 500;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationSortKey {
+    #[default]
+    #[serde(rename = "invoice_date")]
+    InvoiceDate,
+    #[serde(rename = "payment_date")]
+    PaymentDate, // Unpaid operations sort before every payment date
+    #[serde(rename = "amount_ht")]
+    AmountHt,
+    #[serde(rename = "label")]
+    Label, // Ignoring ASCII case
+    #[serde(rename = "created_at")]
+    CreatedAt,
 }
 
-// ============ New Repository Traits ============
+/// Value of an operation for a sort key, as the database compares it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OperationSortValue {
+    Cents(i64),
+    Text(String),
+}
 
-#[async_trait::async_trait]
-pub trait WorkingDayRepo: Send + Sync {
-    async fn create_working_day(&self, working_day: WorkingDay) -> DomainResult<()>;
-    async fn update_working_day(&self, working_day: WorkingDay) -> DomainResult<()>;
-    async fn delete_working_day(&self, id: Uuid) -> DomainResult<()>;
-    async fn get_working_day(&self, id: Uuid) -> DomainResult<WorkingDay>;
-    async fn list_working_days(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<WorkingDay>>;
-    async fn get_working_days_for_month(&self, month: &MonthId) -> DomainResult<Vec<WorkingDay>>;
-    async fn get_working_days_stats(&self, start_date: NaiveDate, end_date: NaiveDate) -> DomainResult<WorkingDaysStats>;
+impl OperationSortKey {
+    pub fn value_of(&self, operation: &Operation) -> OperationSortValue {
+        match self {
+            OperationSortKey::InvoiceDate => OperationSortValue::Text(operation.invoice_date.format("%Y-%m-%d").to_string()),
+            OperationSortKey::PaymentDate => OperationSortValue::Text(
+                operation.payment_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            ),
+            OperationSortKey::AmountHt => OperationSortValue::Cents(operation.amount_ht_cents),
+            OperationSortKey::Label => OperationSortValue::Text(operation.label.as_deref().unwrap_or_default().to_ascii_lowercase()),
+            OperationSortKey::CreatedAt => OperationSortValue::Text(operation.created_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+        }
+    }
 }
 
-#[async_trait::async_trait]
-pub trait TaxScheduleRepo: Send + Sync {
-    async fn create_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()>;
-    async fn update_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()>;
-    async fn delete_tax_schedule(&self, id: Uuid) -> DomainResult<()>;
-    async fn get_tax_schedule(&self, id: Uuid) -> DomainResult<TaxSchedule>;
-    async fn list_tax_schedules(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<TaxSchedule>>;
-    async fn get_overdue_schedules(&self, as_of_date: NaiveDate) -> DomainResult<Vec<TaxSchedule>>;
-    async fn mark_as_paid(&self, id: Uuid, paid_date: NaiveDate) -> DomainResult<()>;
+/// Last operation of a page: the next page starts right after it, so rows added or removed
+/// meanwhile shift nothing. Handed to the frontend as an opaque string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationCursor {
+    pub sort: OperationSortKey,
+    pub descending: bool,
+    pub value: OperationSortValue,
+    pub id: Uuid, // Ties on the sort value are ordered by id
 }
 
-#[async_trait::async_trait]
-pub trait SimulationRepo: Send + Sync {
-    async fn create_simulation(&self, simulation: Simulation) -> DomainResult<()>;
-    async fn update_simulation(&self, simulation: Simulation) -> DomainResult<()>;
-    async fn delete_simulation(&self, id: Uuid) -> DomainResult<()>;
-    async fn get_simulation(&self, id: Uuid) -> DomainResult<Simulation>;
-    async fn list_simulations(&self) -> DomainResult<Vec<Simulation>>;
+impl OperationCursor {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn decode(cursor: &str) -> DomainResult<Self> {
+        serde_json::from_str(cursor).map_err(|_| DomainError::Validation("Curseur de pagination invalide".into()))
+    }
 }
 
-#[async_trait::async_trait]
-pub trait KPIRepo: Send + Sync {
-    async fn save_monthly_kpi(&self, kpi: MonthlyKPI) -> DomainResult<()>;
-    async fn get_monthly_kpi(&self, month: &MonthId) -> DomainResult<Option<MonthlyKPI>>;
-    async fn list_monthly_kpis(&self, start_month: &MonthId, end_month: &MonthId) -> DomainResult<Vec<MonthlyKPI>>;
-    async fn delete_monthly_kpi(&self, month: &MonthId) -> DomainResult<()>;
+/// Filters of an operation query, all optional and combined; date bounds are inclusive
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationQuery {
+    pub invoice_from: Option<NaiveDate>,
+    pub invoice_to: Option<NaiveDate>,
+    pub payment_from: Option<NaiveDate>,
+    pub payment_to: Option<NaiveDate>,
+    pub operation_type: Option<OperationType>,
+    pub paid: Option<bool>,
+    pub min_amount_ht_cents: Option<i64>,
+    pub max_amount_ht_cents: Option<i64>,
+    pub client: Option<String>, // Exact name, ignoring ASCII case
+    pub categories: Vec<String>, // Any of them; empty: no filter
+    pub has_receipt: Option<bool>,
+    pub text: Option<String>, // Every word starts a word of the label or the client, accents ignored
+    pub sort: OperationSortKey,
+    pub descending: bool,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>, // `next_cursor` of the previous page
+}
+
+impl OperationQuery {
+    pub fn validate(&self) -> DomainResult<()> {
+        let reversed = |from: Option<NaiveDate>, to: Option<NaiveDate>| from.zip(to).is_some_and(|(from, to)| from > to);
+        if reversed(self.invoice_from, self.invoice_to) {
+            return Err(DomainError::Validation("Période de facturation invalide: début après la fin".into()));
+        }
+        if reversed(self.payment_from, self.payment_to) {
+            return Err(DomainError::Validation("Période de paiement invalide: début après la fin".into()));
+        }
+        if self.min_amount_ht_cents.zip(self.max_amount_ht_cents).is_some_and(|(min, max)| min > max) {
+            return Err(DomainError::Validation("Fourchette de montants invalide: minimum au-dessus du maximum".into()));
+        }
+        if self.limit.is_some_and(|limit| limit == 0 || limit > OPERATION_PAGE_MAX) {
+            return Err(DomainError::Validation(format!("Taille de page invalide: de 1 à {}", OPERATION_PAGE_MAX)));
+        }
+        self.after().map(|_| ())
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.limit.unwrap_or(OPERATION_PAGE_SIZE).clamp(1, OPERATION_PAGE_MAX)
+    }
+
+    /// Where this page starts; a cursor made for another order is refused
+    pub fn after(&self) -> DomainResult<Option<OperationCursor>> {
+        let Some(cursor) = self.cursor.as_deref() else { return Ok(None) };
+        let cursor = OperationCursor::decode(cursor)?;
+        if cursor.sort != self.sort || cursor.descending != self.descending {
+            return Err(DomainError::Validation("Curseur de pagination d'un autre tri: reprendre à la première page".into()));
+        }
+        Ok(Some(cursor))
+    }
+
+    /// Lowercased words of the text search
+    pub fn search_terms(&self) -> Vec<String> {
+        self.text.as_deref().unwrap_or_default()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+
+    pub fn cursor_of(&self, operation: &Operation) -> String {
+        OperationCursor { sort: self.sort, descending: self.descending, value: self.sort.value_of(operation), id: operation.id }.encode()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationPage {
+    pub operations: Vec<Operation>,
+    pub total: u64, // Operations matching the filters, over every page
+    pub next_cursor: Option<String>, // None on the last page
 }
 
 // ============ New Domain DTOs ============
@@ -580,8 +3102,9 @@ pub fn compute_dashboard(
         .filter(|i| i.paid_at.map(|d| d.year() == month.year && d.month() == month.month).unwrap_or(false))
         .map(|i| i.amount_ht)
         .sum();
-    let future_provisions: i64 = provisions.iter().map(|p| p.amount_cents).sum();
-    let available_cents = revenue_ht_cents - vat.due_cents - urssaf.due_cents - future_provisions - settings.buffer_cents;
+    let vat_to_set_aside = month_tax_to_set_aside(month, ProvisionType::Vat, vat.due_cents, provisions);
+    let urssaf_to_set_aside = month_tax_to_set_aside(month, ProvisionType::Urssaf, urssaf.due_cents, provisions);
+    let available_cents = revenue_ht_cents - vat_to_set_aside - urssaf_to_set_aside - settings.buffer_cents;
     
     // Calculate expenses for the month
     let expenses_ttc_cents: i64 = expenses
@@ -726,6 +3249,20 @@ pub fn compute_urssaf_for_month_v2(month: &MonthId, operations: &[Operation], ra
     }
 }
 
+/// What the month still has to set aside for one tax: once the month is closed, its provision
+/// (nothing when already paid); before, the due computed from its operations
+/// Provisions of other months are their own months' concern
+fn month_tax_to_set_aside(month: &MonthId, provision_type: ProvisionType, due_cents: i64, provisions: &[Provision]) -> i64 {
+    let provision = provisions.iter().find(|p| {
+        p.provision_type == provision_type && p.period_year == month.year && p.period_month == month.month
+    });
+    match provision {
+        Some(p) if p.status == ProvisionStatus::Paid => 0,
+        Some(p) => p.amount_cents,
+        None => due_cents,
+    }
+}
+
 /// Compute dashboard using unified Operation model
 pub fn compute_dashboard_v2(
     month: &MonthId,
@@ -780,8 +3317,9 @@ pub fn compute_dashboard_v2(
         })
         .count() as i64;
 
-    let future_provisions: i64 = provisions.iter().map(|p| p.amount_cents).sum();
-    let available_cents = revenue_ht_cents - vat.due_cents - urssaf.due_cents - future_provisions - settings.buffer_cents;
+    let vat_to_set_aside = month_tax_to_set_aside(month, ProvisionType::Vat, vat.due_cents, provisions);
+    let urssaf_to_set_aside = month_tax_to_set_aside(month, ProvisionType::Urssaf, urssaf.due_cents, provisions);
+    let available_cents = revenue_ht_cents - vat_to_set_aside - urssaf_to_set_aside - settings.buffer_cents;
 
     DashboardSummary {
         month: month.clone(),
@@ -879,6 +3417,107 @@ pub fn compute_month_recap_v2(month: &MonthId, operations: &[Operation], setting
     }
 }
 
+// ============ Provision Lifecycle ============
+
+/// Due date on `day` of the month following `month`, clamped to the last day of that month
+pub fn due_date_in_following_month(month: &MonthId, day: u8) -> NaiveDate {
+    let due_month = month.next();
+    let first_of_after = due_month.next();
+    let last_day = NaiveDate::from_ymd_opt(first_of_after.year, first_of_after.month, 1).unwrap().pred_opt().unwrap().day();
+    NaiveDate::from_ymd_opt(due_month.year, due_month.month, (day as u32).clamp(1, last_day)).unwrap()
+}
+
+/// Status a provision should have on `today`: paid provisions stay paid, unpaid ones become overdue after their due date
+pub fn provision_status_as_of(provision: &Provision, today: NaiveDate) -> ProvisionStatus {
+    match provision.status {
+        ProvisionStatus::Paid => ProvisionStatus::Paid,
+        _ if provision.due_date < today => ProvisionStatus::Overdue,
+        _ => ProvisionStatus::Pending,
+    }
+}
+
+/// Sum of provisions still to be paid (pending or overdue)
+pub fn outstanding_provisions_cents(provisions: &[Provision]) -> i64 {
+    provisions
+        .iter()
+        .filter(|p| p.status != ProvisionStatus::Paid)
+        .map(|p| p.amount_cents)
+        .sum()
+}
+
+/// Build the VAT and URSSAF provisions of a closed month
+/// Provisions already paid are left untouched, unpaid ones keep their id and are refreshed with the new amounts
+pub fn generate_provisions_for_month(
+    month: &MonthId,
+    vat: &VatReport,
+    urssaf: &UrssafReport,
+    settings: &Settings,
+    existing: &[Provision],
+    today: NaiveDate,
+) -> Vec<Provision> {
+    let now = chrono::Utc::now().naive_utc();
+    let candidates = [
+        (ProvisionType::Vat, vat.due_cents, settings.vat_pay_day),
+        (ProvisionType::Urssaf, urssaf.due_cents, settings.urssaf_pay_day),
+    ];
+
+    let mut provisions = Vec::new();
+    for (provision_type, amount_cents, pay_day) in candidates {
+        let previous = existing.iter().find(|p| {
+            p.provision_type == provision_type && p.period_year == month.year && p.period_month == month.month
+        });
+        if matches!(previous, Some(p) if p.status == ProvisionStatus::Paid) {
+            continue;
+        }
+        // Nothing to set aside (e.g. VAT credit) and no earlier provision to refresh
+        if amount_cents <= 0 && previous.is_none() {
+            continue;
+        }
+
+        let mut provision = Provision {
+            id: previous.map(|p| p.id).unwrap_or_else(Uuid::new_v4),
+            period_year: month.year,
+            period_month: month.month,
+            provision_type,
+            amount_cents: amount_cents.max(0),
+            due_date: due_date_in_following_month(month, pay_day),
+            status: ProvisionStatus::Pending,
+            created_at: previous.map(|p| p.created_at).unwrap_or(now),
+            updated_at: now,
+        };
+        provision.status = provision_status_as_of(&provision, today);
+        provisions.push(provision);
+    }
+    provisions
+}
+
+// ============ Declaration Lifecycle ============
+
+/// Amount and due date of a declaration computed from the month reports
+pub fn declaration_amount_and_due_date(
+    declaration_type: &DeclarationType,
+    month: &MonthId,
+    vat: &VatReport,
+    urssaf: &UrssafReport,
+    settings: &Settings,
+) -> (i64, NaiveDate) {
+    match declaration_type {
+        DeclarationType::Vat => (vat.due_cents.max(0), due_date_in_following_month(month, settings.vat_pay_day)),
+        DeclarationType::Urssaf => (urssaf.due_cents.max(0), due_date_in_following_month(month, settings.urssaf_pay_day)),
+    }
+}
+
+/// Status a declaration should have on `today`: unpaid declarations become overdue after their due date
+pub fn declaration_status_as_of(declaration: &Declaration, today: NaiveDate) -> DeclarationStatus {
+    match declaration.status {
+        DeclarationStatus::Paid => DeclarationStatus::Paid,
+        _ if declaration.due_date < today => DeclarationStatus::Overdue,
+        DeclarationStatus::Overdue if declaration.filing_date.is_some() => DeclarationStatus::Filed,
+        DeclarationStatus::Overdue => DeclarationStatus::Pending,
+        ref status => status.clone(),
+    }
+}
+
 // ============ New Use Cases ============
 
 /// Calculate the optimal daily rate to reach target annual income
@@ -1014,6 +3653,9 @@ pub fn compute_tax_schedule(
                     period_start,
                     period_end,
                     status: TaxScheduleStatus::Pending,
+                    paid_date: None,
+                    paid_amount_cents: 0,
+                    payment_reference: None,
                     created_at: chrono::Utc::now().naive_utc(),
                 });
             }
@@ -1058,6 +3700,9 @@ pub fn compute_tax_schedule(
                     period_start,
                     period_end,
                     status: TaxScheduleStatus::Pending,
+                    paid_date: None,
+                    paid_amount_cents: 0,
+                    payment_reference: None,
                     created_at: chrono::Utc::now().naive_utc(),
                 });
             }
@@ -1068,6 +3713,47 @@ pub fn compute_tax_schedule(
     schedules
 }
 
+/// Changes needed to bring stored tax schedules in line with the expected ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaxScheduleSyncPlan {
+    pub to_create: Vec<TaxSchedule>,
+    pub to_update: Vec<TaxSchedule>,
+    pub to_delete: Vec<Uuid>,
+    pub kept_paid: Vec<TaxSchedule>, // Entries with payments, never modified by a sync
+}
+
+/// Diff expected schedules against stored ones, keyed by (tax type, period start)
+/// `existing` must only contain the schedules of the synchronised window: unpaid entries
+/// without an expected counterpart are considered stale and deleted
+pub fn plan_tax_schedule_sync(expected: &[TaxSchedule], existing: &[TaxSchedule]) -> TaxScheduleSyncPlan {
+    let has_payments = |s: &TaxSchedule| s.status == TaxScheduleStatus::Paid || s.paid_amount_cents > 0;
+    let same_key = |a: &TaxSchedule, b: &TaxSchedule| a.tax_type == b.tax_type && a.period_start == b.period_start;
+
+    let mut plan = TaxScheduleSyncPlan::default();
+    for wanted in expected {
+        match existing.iter().find(|s| same_key(s, wanted)) {
+            Some(stored) if has_payments(stored) => plan.kept_paid.push(stored.clone()),
+            Some(stored) => {
+                if stored.amount_cents != wanted.amount_cents || stored.due_date != wanted.due_date || stored.period_end != wanted.period_end {
+                    plan.to_update.push(TaxSchedule {
+                        id: stored.id,
+                        created_at: stored.created_at,
+                        status: stored.status.clone(),
+                        ..wanted.clone()
+                    });
+                }
+            }
+            None => plan.to_create.push(wanted.clone()),
+        }
+    }
+    for stored in existing {
+        if !has_payments(stored) && !expected.iter().any(|wanted| same_key(stored, wanted)) {
+            plan.to_delete.push(stored.id);
+        }
+    }
+    plan
+}
+
 /// Optimize provisions based on cash flow and tax obligations
 pub fn optimize_provisions(
     available_cash_cents: i64,
@@ -1079,8 +3765,8 @@ pub fn optimize_provisions(
     
     let upcoming_obligations: i64 = upcoming_tax_schedules
         .iter()
-        .filter(|s| s.due_date <= cutoff_date && s.status == TaxScheduleStatus::Pending)
-        .map(|s| s.amount_cents)
+        .filter(|s| s.due_date <= cutoff_date && s.status != TaxScheduleStatus::Paid)
+        .map(|s| s.outstanding_cents())
         .sum();
     
     let required_provisions = upcoming_obligations + buffer_cents;
@@ -1259,6 +3945,211 @@ pub fn compute_monthly_kpis(
     }
 }
 
+/// Expected VAT/URSSAF schedule over the horizon, computed from operations
+pub fn expected_tax_schedule(
+    start_month: &MonthId,
+    horizon_months: u32,
+    operations: &[Operation],
+    settings: &Settings,
+) -> Vec<TaxSchedule> {
+    let mut vat_reports = Vec::new();
+    let mut urssaf_reports = Vec::new();
+    let mut month = start_month.clone();
+    for _ in 0..horizon_months {
+        vat_reports.push(compute_vat_for_month_v2(&month, operations));
+        urssaf_reports.push(compute_urssaf_for_month_v2(&month, operations, settings.urssaf_rate_ppm));
+        month = month.next();
+    }
+    compute_tax_schedule(start_month, horizon_months, &vat_reports, &urssaf_reports, settings)
+}
+
+/// Compute monthly KPIs from the unified Operation model
+/// Revenue and expenses are those paid during the month; VAT and URSSAF follow the v2 reports
+pub fn compute_monthly_kpis_v2(
+    month: &MonthId,
+    operations: &[Operation],
+    working_days: &[WorkingDay],
+    settings: &Settings,
+) -> MonthlyKPI {
+    let paid_in_month = |op: &&Operation| {
+        op.payment_date.map(|d| d.year() == month.year && d.month() == month.month).unwrap_or(false)
+    };
+    let month_working_days: Vec<_> = working_days.iter()
+        .filter(|d| d.date.year() == month.year && d.date.month() == month.month)
+        .collect();
+    let month_sales: Vec<_> = operations.iter()
+        .filter(|op| matches!(op.operation_type, OperationType::Sale))
+        .filter(paid_in_month)
+        .collect();
+    let month_purchases: Vec<_> = operations.iter()
+        .filter(|op| matches!(op.operation_type, OperationType::Purchase))
+        .filter(paid_in_month)
+        .collect();
+
+    let revenue_ht_cents: i64 = month_sales.iter().map(|op| op.amount_ht_cents).sum();
+    let revenue_ttc_cents: i64 = month_sales.iter().map(|op| op.amount_ttc_cents).sum();
+    let expenses_ttc_cents: i64 = month_purchases.iter().map(|op| op.amount_ttc_cents).sum();
+
+    let working_days_count = month_working_days.len() as f64;
+    let billable_hours: f64 = month_working_days.iter().map(|d| d.billable_hours).sum();
+    let total_worked_hours: f64 = month_working_days.iter().map(|d| d.hours_worked).sum();
+
+    let average_daily_rate_cents = if working_days_count > 0.0 {
+        (revenue_ht_cents as f64 / working_days_count) as i64
+    } else {
+        0
+    };
+
+    let average_hourly_rate_cents = if billable_hours > 0.0 {
+        (revenue_ht_cents as f64 / billable_hours) as i64
+    } else {
+        0
+    };
+
+    let vat = compute_vat_for_month_v2(month, operations);
+    let urssaf = compute_urssaf_for_month_v2(month, operations, settings.urssaf_rate_ppm);
+
+    let net_margin_cents = revenue_ttc_cents - expenses_ttc_cents - vat.due_cents - urssaf.due_cents;
+
+    let profitability_ratio = if revenue_ttc_cents > 0 {
+        net_margin_cents as f64 / revenue_ttc_cents as f64
+    } else {
+        0.0
+    };
+
+    let utilization_rate = if total_worked_hours > 0.0 {
+        billable_hours / total_worked_hours
+    } else {
+        0.0
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+
+    MonthlyKPI {
+        id: Uuid::new_v4(),
+        month: month.clone(),
+        revenue_ht_cents,
+        revenue_ttc_cents,
+        expenses_ttc_cents,
+        working_days: working_days_count,
+        billable_hours,
+        average_daily_rate_cents,
+        average_hourly_rate_cents,
+        vat_collected_cents: vat.collected_cents,
+        vat_due_cents: vat.due_cents,
+        urssaf_due_cents: urssaf.due_cents,
+        net_margin_cents,
+        profitability_ratio,
+        utilization_rate,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+// ============ Legacy Compatibility ============
+// Invoices and expenses are stored as operations since migration 0007; these map between both views
+
+/// Label of an operation made of two legacy fields, as migration 0007 builds it:
+/// both joined by " - ", or whichever one is set
+fn legacy_label(first: &str, second: &str) -> Option<String> {
+    match (first.is_empty(), second.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(first.to_string()),
+        (true, false) => Some(second.to_string()),
+        (false, false) => Some(format!("{} - {}", first, second)),
+    }
+}
+
+/// Convert a legacy invoice into a sale operation
+pub fn operation_from_invoice(inv: &Invoice, now: NaiveDateTime) -> Operation {
+    let label = legacy_label(&inv.number, &inv.client);
+    Operation {
+        id: inv.id,
+        invoice_date: inv.service_date,
+        payment_date: inv.paid_at,
+        operation_type: OperationType::Sale,
+        amount_ht_cents: inv.amount_ht,
+        vat_amount_cents: inv.amount_tva,
+        amount_ttc_cents: inv.amount_ttc,
+        vat_on_payments: true, // Legacy reports always used the payment date
+        label,
+        client: Some(inv.client.clone()).filter(|c| !c.is_empty()),
+        category: None,
+        receipt_key: inv.source.clone(),
+        receipt_sha256: inv.source.as_deref().and_then(sha256_from_receipt_key),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Convert a legacy expense into a purchase operation
+pub fn operation_from_expense(exp: &Expense, now: NaiveDateTime) -> Operation {
+    let label = legacy_label(&exp.label, &exp.category);
+    Operation {
+        id: exp.id,
+        invoice_date: exp.booking_date,
+        payment_date: exp.paid_at,
+        operation_type: OperationType::Purchase,
+        amount_ht_cents: exp.amount_ht,
+        vat_amount_cents: exp.amount_tva,
+        amount_ttc_cents: exp.amount_ttc,
+        vat_on_payments: true,
+        label,
+        client: None,
+        category: Some(exp.category.clone()).filter(|c| !c.is_empty()),
+        receipt_key: exp.receipt_path.clone(),
+        receipt_sha256: exp.receipt_path.as_deref().and_then(sha256_from_receipt_key),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn vat_rate_ppm_of(op: &Operation) -> i32 {
+    if op.amount_ht_cents == 0 { 0 } else { ((op.vat_amount_cents as i128) * 1_000_000 / (op.amount_ht_cents as i128)) as i32 }
+}
+
+/// Legacy view of a sale operation
+pub fn invoice_from_operation(op: &Operation) -> Invoice {
+    let label = op.label.clone().unwrap_or_default();
+    let (number, client) = match label.split_once(" - ") {
+        Some((number, client)) => (number.to_string(), client.to_string()),
+        None => (label, String::new()),
+    };
+    Invoice {
+        id: op.id,
+        number,
+        client,
+        service_date: op.invoice_date,
+        amount_ht: op.amount_ht_cents,
+        vat_rate_ppm: vat_rate_ppm_of(op),
+        amount_tva: op.vat_amount_cents,
+        amount_ttc: op.amount_ttc_cents,
+        paid_at: op.payment_date,
+        source: op.receipt_key.clone(),
+    }
+}
+
+/// Legacy view of a purchase operation
+pub fn expense_from_operation(op: &Operation) -> Expense {
+    let label = op.label.clone().unwrap_or_default();
+    let (label, category) = match label.split_once(" - ") {
+        Some((label, category)) => (label.to_string(), category.to_string()),
+        None => (label, String::new()),
+    };
+    Expense {
+        id: op.id,
+        label,
+        category,
+        booking_date: op.invoice_date,
+        amount_ht: op.amount_ht_cents,
+        vat_rate_ppm: vat_rate_ppm_of(op),
+        amount_tva: op.vat_amount_cents,
+        amount_ttc: op.amount_ttc_cents,
+        paid_at: op.payment_date,
+        receipt_path: op.receipt_key.clone(),
+    }
+}
+
 // ============ New Domain Result Types ============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1301,3 +4192,389 @@ pub struct WorkingPatternAnalysis {
     pub average_daily_rate_cents: i64,
     pub utilization_trends: Vec<(NaiveDate, f64)>, // (week_start, utilization_ratio)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn operation(operation_type: OperationType, invoice: &str, payment: Option<&str>, amount_ht_cents: i64) -> Operation {
+        let now = date(invoice).and_hms_opt(9, 0, 0).unwrap();
+        Operation {
+            id: Uuid::new_v4(),
+            invoice_date: date(invoice),
+            payment_date: payment.map(date),
+            operation_type,
+            amount_ht_cents,
+            vat_amount_cents: amount_ht_cents / 5,
+            amount_ttc_cents: amount_ht_cents + amount_ht_cents / 5,
+            vat_on_payments: true,
+            label: None,
+            client: None,
+            category: None,
+            receipt_key: None,
+            receipt_sha256: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_dashboard_sets_each_tax_aside_once() {
+        let march = MonthId::new(2025, 3);
+        let settings = Settings::default();
+        let operations = vec![operation(OperationType::Sale, "2025-03-03", Some("2025-03-10"), 100000)];
+        // Left over from February: not this month's to pay
+        let february = generate_provisions_for_month(
+            &MonthId::new(2025, 2),
+            &VatReport { month: MonthId::new(2025, 2), collected_cents: 5000, deductible_cents: 0, due_cents: 5000 },
+            &UrssafReport { month: MonthId::new(2025, 2), ca_encaisse_cents: 0, rate_ppm: settings.urssaf_rate_ppm, due_cents: 0 },
+            &settings,
+            &[],
+            date("2025-03-01"),
+        );
+
+        // Open month: VAT 20% and URSSAF 22% of the month's takings, then the buffer
+        let open = compute_dashboard_v2(&march, &operations, &february, &settings);
+        assert_eq!((open.vat_due_cents, open.urssaf_due_cents), (20000, 22000));
+        assert_eq!(open.available_cents, 100000 - 20000 - 22000 - settings.buffer_cents);
+
+        // Closed month: its provisions replace the dues instead of adding to them
+        let vat = compute_vat_for_month_v2(&march, &operations);
+        let urssaf = compute_urssaf_for_month_v2(&march, &operations, settings.urssaf_rate_ppm);
+        let mut provisions = generate_provisions_for_month(&march, &vat, &urssaf, &settings, &[], date("2025-03-31"));
+        provisions.extend(february);
+        let closed = compute_dashboard_v2(&march, &operations, &provisions, &settings);
+        assert_eq!(closed.available_cents, open.available_cents);
+
+        // A paid provision has nothing left to set aside
+        provisions.iter_mut().filter(|p| p.provision_type == ProvisionType::Vat).for_each(|p| p.status = ProvisionStatus::Paid);
+        let paid = compute_dashboard_v2(&march, &operations, &provisions, &settings);
+        assert_eq!(paid.available_cents, 100000 - 22000 - settings.buffer_cents);
+    }
+
+    fn declaration(declaration_type: DeclarationType, amount_due_cents: i64, filed: &str, paid: &str) -> Declaration {
+        let now = date(filed).and_hms_opt(9, 0, 0).unwrap();
+        Declaration {
+            id: Uuid::new_v4(),
+            declaration_type,
+            period_year: 2025,
+            period_month: 3,
+            amount_due_cents,
+            due_date: date(paid),
+            filing_date: Some(date(filed)),
+            payment_date: Some(date(paid)),
+            status: DeclarationStatus::Paid,
+            recomputed_amount_cents: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn fec_example() -> Vec<FecLine> {
+        let mut on_invoice = operation(OperationType::Sale, "2025-03-05", Some("2025-04-02"), 10000);
+        on_invoice.vat_on_payments = false;
+        let mut software = operation(OperationType::Purchase, "2025-03-04", Some("2025-03-20"), 20000);
+        software.category = Some("logiciels".to_string());
+        let mut supplies = operation(OperationType::Purchase, "2025-03-06", None, 5000);
+        supplies.vat_on_payments = false;
+        let operations = vec![
+            operation(OperationType::Sale, "2025-03-03", Some("2025-03-10"), 100000),
+            operation(OperationType::Sale, "2025-12-01", None, 50000), // Still awaited at closing
+            on_invoice,
+            software,
+            supplies,
+        ];
+        // March VAT: 20000 collected on payment + 2000 on invoice, 4000 + 1000 deductible
+        let declarations = vec![
+            declaration(DeclarationType::Vat, 17000, "2025-04-15", "2025-04-20"),
+            declaration(DeclarationType::Urssaf, 22000, "2025-04-01", "2025-04-05"),
+        ];
+        fec_lines(2025, &operations, &declarations, &[])
+    }
+
+    #[test]
+    fn test_fec_lines_balance_and_settle_vat() {
+        let lines = fec_example();
+        let mut entries: Vec<&str> = lines.iter().map(|l| l.ecriture_num.as_str()).collect();
+        entries.dedup();
+        for num in entries {
+            let entry: Vec<&FecLine> = lines.iter().filter(|l| l.ecriture_num == num).collect();
+            assert_eq!(entry.iter().map(|l| l.debit_cents).sum::<i64>(), entry.iter().map(|l| l.credit_cents).sum::<i64>());
+        }
+
+        let balance = |account: &str| fec_trial_balance(&lines).iter()
+            .find(|b| b.compte_num == account)
+            .map(|b| b.balance_cents)
+            .unwrap_or(0);
+        // Collected and deductible VAT are fully settled by the March declaration
+        assert_eq!(balance("44571"), 0);
+        assert_eq!(balance("44566"), 0);
+        assert_eq!(balance("44551"), 0);
+        // Only the VAT of the sale still awaited at closing is pending
+        assert_eq!(balance("44574"), -10000);
+        assert_eq!(balance("44568"), 0);
+        // Purchases go to the account of their category
+        assert_eq!(balance("6061"), 20000);
+        assert_eq!(balance("606"), 5000);
+        assert_eq!(balance("706"), -160000);
+        assert_eq!(balance("512"), 120000 + 12000 - 24000 - 17000 - 22000);
+    }
+
+    #[test]
+    fn test_fec_file_validation() {
+        let lines = fec_example();
+        let content = fec_file_content(&lines, '|');
+        let validation = validate_fec_file(&content, 2025);
+        assert!(validation.is_valid(), "{:?}", validation.errors);
+        assert_eq!(validation.lines_count as usize, lines.len());
+        assert_eq!(fec_file_content(&lines, '\t').lines().next().unwrap().split('\t').count(), 18);
+
+        let rejected = |content: &str| {
+            let validation = validate_fec_file(content, 2025);
+            assert!(!validation.is_valid());
+            validation.errors
+        };
+        rejected("");
+        rejected("JournalCode|JournalLib\r\n");
+        // Another fiscal year
+        assert!(rejected(&content.replace("2025", "2024")).iter().any(|e| e.contains("hors exercice")));
+
+        let mut unbalanced = lines.clone();
+        unbalanced[0].debit_cents += 1;
+        assert!(rejected(&fec_file_content(&unbalanced, '|')).iter().any(|e| e.contains("déséquilibrée")));
+        let mut renumbered = lines.clone();
+        let last = renumbered.len() - 1;
+        renumbered[last].ecriture_num = "99".to_string();
+        assert!(rejected(&fec_file_content(&renumbered, '|')).iter().any(|e| e.contains("numérotation discontinue")));
+        let bad_amount = content.replacen("|1200,00|", "|1200.00|", 1);
+        assert!(rejected(&bad_amount).iter().any(|e| e.contains("montant invalide")));
+        let missing_field = content.replacen("|Ventes|", "||", 1);
+        assert!(rejected(&missing_field).iter().any(|e| e.contains("JournalLib obligatoire")));
+    }
+
+    #[test]
+    fn test_due_dates_fall_in_the_following_month() {
+        assert_eq!(due_date_in_following_month(&MonthId::new(2025, 3), 20), date("2025-04-20"));
+        assert_eq!(due_date_in_following_month(&MonthId::new(2024, 12), 5), date("2025-01-05"));
+        // Clamped to the days the month has
+        assert_eq!(due_date_in_following_month(&MonthId::new(2025, 1), 31), date("2025-02-28"));
+        assert_eq!(due_date_in_following_month(&MonthId::new(2024, 1), 31), date("2024-02-29"));
+        assert_eq!(due_date_in_following_month(&MonthId::new(2025, 5), 0), date("2025-06-01"));
+    }
+
+    #[test]
+    fn test_provisions_of_a_closed_month() {
+        let march = MonthId::new(2025, 3);
+        let settings = Settings::default();
+        let operations = vec![
+            operation(OperationType::Sale, "2025-03-03", Some("2025-03-10"), 100000),
+            operation(OperationType::Purchase, "2025-03-04", Some("2025-03-04"), 10000),
+        ];
+        let vat = compute_vat_for_month_v2(&march, &operations);
+        let urssaf = compute_urssaf_for_month_v2(&march, &operations, settings.urssaf_rate_ppm);
+        assert_eq!((vat.collected_cents, vat.deductible_cents, vat.due_cents), (20000, 2000, 18000));
+        assert_eq!(urssaf.due_cents, 22000);
+
+        let provisions = generate_provisions_for_month(&march, &vat, &urssaf, &settings, &[], date("2025-04-01"));
+        let summary: Vec<_> = provisions.iter().map(|p| (p.provision_type.clone(), p.amount_cents, p.due_date, p.status.clone())).collect();
+        assert_eq!(summary, vec![
+            (ProvisionType::Vat, 18000, date("2025-04-20"), ProvisionStatus::Pending),
+            (ProvisionType::Urssaf, 22000, date("2025-04-05"), ProvisionStatus::Pending),
+        ]);
+        assert_eq!(outstanding_provisions_cents(&provisions), 40000);
+
+        // Unpaid provisions turn overdue after their due date, paid ones stay paid
+        let (mut vat_provision, urssaf_provision) = (provisions[0].clone(), provisions[1].clone());
+        assert_eq!(provision_status_as_of(&urssaf_provision, date("2025-04-05")), ProvisionStatus::Pending);
+        assert_eq!(provision_status_as_of(&urssaf_provision, date("2025-04-06")), ProvisionStatus::Overdue);
+        vat_provision.status = ProvisionStatus::Paid;
+        assert_eq!(provision_status_as_of(&vat_provision, date("2025-12-31")), ProvisionStatus::Paid);
+        assert_eq!(outstanding_provisions_cents(&[vat_provision.clone(), urssaf_provision.clone()]), 22000);
+
+        // Closing again leaves the paid provision alone and refreshes the other one in place
+        let more = UrssafReport { due_cents: 30000, ..urssaf.clone() };
+        let refreshed = generate_provisions_for_month(&march, &vat, &more, &settings, &[vat_provision, urssaf_provision.clone()], date("2025-04-10"));
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!((refreshed[0].id, refreshed[0].created_at), (urssaf_provision.id, urssaf_provision.created_at));
+        assert_eq!((refreshed[0].amount_cents, refreshed[0].status.clone()), (30000, ProvisionStatus::Overdue));
+
+        // A VAT credit sets nothing aside, and brings an earlier provision down to zero
+        let credit = VatReport { due_cents: -5000, ..vat };
+        let none = generate_provisions_for_month(&march, &credit, &urssaf, &settings, &[], date("2025-04-01"));
+        assert!(none.iter().all(|p| p.provision_type == ProvisionType::Urssaf));
+        let zeroed = generate_provisions_for_month(&march, &credit, &urssaf, &settings, &provisions, date("2025-04-01"));
+        assert_eq!((zeroed[0].id, zeroed[0].amount_cents), (provisions[0].id, 0));
+    }
+
+    #[test]
+    fn test_declaration_amounts_and_statuses() {
+        let march = MonthId::new(2025, 3);
+        let settings = Settings::default();
+        let vat = VatReport { month: march.clone(), collected_cents: 1000, deductible_cents: 3000, due_cents: -2000 };
+        let urssaf = UrssafReport { month: march.clone(), ca_encaisse_cents: 100000, rate_ppm: settings.urssaf_rate_ppm, due_cents: 22000 };
+        assert_eq!(declaration_amount_and_due_date(&DeclarationType::Vat, &march, &vat, &urssaf, &settings), (0, date("2025-04-20")));
+        assert_eq!(declaration_amount_and_due_date(&DeclarationType::Urssaf, &march, &vat, &urssaf, &settings), (22000, date("2025-04-05")));
+
+        let mut pending = declaration(DeclarationType::Urssaf, 22000, "2025-04-01", "2025-04-05");
+        (pending.filing_date, pending.payment_date, pending.status) = (None, None, DeclarationStatus::Pending);
+        assert_eq!(declaration_status_as_of(&pending, date("2025-04-05")), DeclarationStatus::Pending);
+        assert_eq!(declaration_status_as_of(&pending, date("2025-04-06")), DeclarationStatus::Overdue);
+        // Filed but unpaid is overdue all the same; back to filed when the due date moves
+        let filed = Declaration { filing_date: Some(date("2025-04-02")), status: DeclarationStatus::Filed, ..pending.clone() };
+        assert_eq!(declaration_status_as_of(&filed, date("2025-04-06")), DeclarationStatus::Overdue);
+        let postponed = Declaration { status: DeclarationStatus::Overdue, due_date: date("2025-04-30"), ..filed.clone() };
+        assert_eq!(declaration_status_as_of(&postponed, date("2025-04-06")), DeclarationStatus::Filed);
+        let reopened = Declaration { status: DeclarationStatus::Overdue, due_date: date("2025-04-30"), ..pending };
+        assert_eq!(declaration_status_as_of(&reopened, date("2025-04-06")), DeclarationStatus::Pending);
+        let paid = declaration(DeclarationType::Urssaf, 22000, "2025-04-02", "2025-04-05");
+        assert_eq!(declaration_status_as_of(&paid, date("2025-12-31")), DeclarationStatus::Paid);
+
+        assert_eq!(filed.amount_diff_cents(), 0);
+        assert_eq!(Declaration { recomputed_amount_cents: Some(25000), ..filed }.amount_diff_cents(), 3000);
+    }
+
+    #[test]
+    fn test_tax_schedule_sync_plan() {
+        let settings = Settings::default();
+        let operations = vec![
+            operation(OperationType::Sale, "2025-03-03", Some("2025-03-10"), 100000),
+            operation(OperationType::Sale, "2025-04-03", Some("2025-04-10"), 50000),
+        ];
+        let expected = expected_tax_schedule(&MonthId::new(2025, 3), 2, &operations, &settings);
+        let keys: Vec<_> = expected.iter().map(|s| (format!("{:?}", s.tax_type), s.period_start, s.amount_cents)).collect();
+        assert_eq!(keys.len(), 4);
+        assert!(keys.contains(&("Vat".to_string(), date("2025-03-01"), 20000)));
+        assert!(keys.contains(&("Urssaf".to_string(), date("2025-04-01"), 11000)));
+
+        // First sync creates everything, the next one finds nothing to do
+        let plan = plan_tax_schedule_sync(&expected, &[]);
+        assert_eq!((plan.to_create.len(), plan.to_update.len(), plan.to_delete.len()), (4, 0, 0));
+        let stored = plan.to_create;
+        let again = plan_tax_schedule_sync(&expected, &stored);
+        assert!(again.to_create.is_empty() && again.to_update.is_empty() && again.to_delete.is_empty());
+
+        // New amounts update unpaid entries in place but never those with payments
+        let vat_march = |s: &TaxSchedule| s.tax_type == TaxType::Vat && s.period_start == date("2025-03-01");
+        let mut stored = stored;
+        let partly_paid = stored.iter_mut().find(|s| s.tax_type == TaxType::Urssaf && s.period_start == date("2025-03-01")).unwrap();
+        partly_paid.paid_amount_cents = 10000;
+        assert_eq!(partly_paid.outstanding_cents(), 12000);
+        let changed: Vec<TaxSchedule> = expected.iter().map(|s| TaxSchedule { amount_cents: s.amount_cents + 100, ..s.clone() }).collect();
+        let plan = plan_tax_schedule_sync(&changed, &stored);
+        assert_eq!((plan.to_update.len(), plan.kept_paid.len()), (3, 1));
+        let updated = plan.to_update.iter().find(|s| vat_march(s)).unwrap();
+        let original = stored.iter().find(|s| vat_march(s)).unwrap();
+        assert_eq!((updated.id, updated.amount_cents), (original.id, 20100));
+
+        // Entries no longer expected go, unless they carry payments
+        let plan = plan_tax_schedule_sync(&[], &stored);
+        assert_eq!((plan.to_delete.len(), plan.kept_paid.len()), (3, 0));
+        assert!(!plan.to_delete.contains(&stored.iter().find(|s| s.paid_amount_cents > 0).unwrap().id));
+        let overpaid = TaxSchedule { paid_amount_cents: 50000, ..stored[0].clone() };
+        assert_eq!(overpaid.outstanding_cents(), 0);
+    }
+
+    #[test]
+    fn test_receipt_audit() {
+        let file = |key: &str| FileInfo { key: key.to_string(), size_bytes: 10, last_modified: date("2025-03-10").and_hms_opt(9, 0, 0).unwrap() };
+        let mut linked = operation(OperationType::Purchase, "2025-03-04", None, 10000);
+        linked.receipt_key = Some("2025-03/ticket.pdf".to_string());
+        let mut dangling = operation(OperationType::Purchase, "2025-03-05", None, 10000);
+        dangling.receipt_key = Some("2025-03/perdu.pdf".to_string());
+        let without = operation(OperationType::Purchase, "2025-03-06", None, 10000);
+        let sale = operation(OperationType::Sale, "2025-03-07", None, 10000);
+        let april = operation(OperationType::Purchase, "2025-04-01", None, 10000);
+        let operations = vec![linked, dangling.clone(), without.clone(), sale, april.clone()];
+        let mut deleted = operation(OperationType::Purchase, "2025-03-08", None, 10000);
+        deleted.receipt_key = Some("2025-03/corbeille.pdf".to_string());
+        let trashed = vec![TrashedOperation { operation: deleted, deleted_at: date("2025-03-20").and_hms_opt(9, 0, 0).unwrap() }];
+        let files = vec![
+            file("2025-03/ticket.pdf"),
+            file("2025-03/oublie.pdf"),
+            file("2025-03/corbeille.pdf"),
+            file("2025-04/autre.pdf"),
+            file(&format!("{}cash-2025-03-31.db", BACKUP_KEY_PREFIX)),
+        ];
+
+        let march = audit_receipts(Some(&MonthId::new(2025, 3)), &operations, &trashed, &files);
+        assert_eq!(march.purchases_without_receipt.iter().map(|o| o.id).collect::<Vec<_>>(), vec![without.id]);
+        assert_eq!(march.orphan_receipts.iter().map(|f| f.key.as_str()).collect::<Vec<_>>(), ["2025-03/oublie.pdf"]);
+        assert_eq!(march.dangling_receipts.iter().map(|d| d.operation_id).collect::<Vec<_>>(), vec![dangling.id]);
+        assert!(!march.is_ready_for_close());
+
+        let whole = audit_receipts(None, &operations, &trashed, &files);
+        assert_eq!(whole.purchases_without_receipt.len(), 2);
+        assert_eq!(whole.orphan_receipts.len(), 2);
+        // Without the trash, the receipt of the trashed operation would be an orphan
+        let untrashed = audit_receipts(None, &operations, &[], &files);
+        assert!(untrashed.orphan_receipts.iter().any(|f| f.key == "2025-03/corbeille.pdf"));
+        // Orphans alone do not hold back a close
+        let clean = audit_receipts(Some(&MonthId::new(2025, 3)), &operations[..1], &trashed, &files);
+        assert!(clean.is_ready_for_close() && !clean.orphan_receipts.is_empty());
+        assert!(whole.purchases_without_receipt.iter().any(|o| o.id == april.id));
+    }
+
+    #[test]
+    fn test_dataset_parse_versions() {
+        let parse = |json: serde_json::Value| DatasetDump::parse(json.to_string().as_bytes());
+        let (dump, version) = parse(serde_json::json!({
+            "format": DATASET_FORMAT, "version": 1, "exported_at": "2025-01-31T18:00:00",
+            "closed_months": [{ "month": { "year": 2025, "month": 1 }, "closed_at": "2025-02-01T09:00:00" }],
+        })).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(dump.version, DATASET_VERSION);
+        assert!(dump.settings.is_none() && dump.operations.is_empty());
+        assert_eq!(dump.closed_months.len(), 1);
+
+        let refused = |json: serde_json::Value| match parse(json) {
+            Err(DomainError::Validation(message)) => message,
+            other => panic!("export accepté: {:?}", other.map(|(_, v)| v)),
+        };
+        assert!(refused(serde_json::json!({ "format": "autre", "version": 1 })).contains("format"));
+        assert!(refused(serde_json::json!({ "format": DATASET_FORMAT, "exported_at": "2025-01-31T18:00:00" })).contains("version"));
+        assert!(refused(serde_json::json!({ "format": DATASET_FORMAT, "version": 0 })).contains("version"));
+        let newer = refused(serde_json::json!({ "format": DATASET_FORMAT, "version": DATASET_VERSION + 1 }));
+        assert!(newer.contains(&format!("jusqu'à la version {}", DATASET_VERSION)));
+        // Collections the dump does not cover are refused rather than dropped
+        assert!(refused(serde_json::json!({
+            "format": DATASET_FORMAT, "version": 1, "exported_at": "2025-01-31T18:00:00", "issued_invoices": [],
+        })).contains("issued_invoices"));
+    }
+
+    #[test]
+    fn test_dataset_validate_lists_every_problem() {
+        let exported_at = date("2025-01-31").and_hms_opt(18, 0, 0).unwrap();
+        let sale = operation(OperationType::Sale, "2025-01-10", None, 1000);
+        let mut dump = DatasetDump {
+            format: DATASET_FORMAT.to_string(),
+            version: DATASET_VERSION,
+            exported_at,
+            settings: None,
+            operations: vec![sale.clone()],
+            declarations: Vec::new(),
+            provisions: Vec::new(),
+            closed_months: vec![MonthStatus { month: MonthId::new(2025, 1), closed_at: Some(exported_at) }],
+            yearly_plannings: Vec::new(),
+            simulations: Vec::new(),
+            monthly_kpis: Vec::new(),
+            working_days: Vec::new(),
+        };
+        dump.validate().unwrap();
+
+        dump.operations.push(sale.clone());
+        dump.closed_months.push(MonthStatus { month: MonthId::new(2025, 1), closed_at: Some(exported_at) });
+        dump.closed_months.push(MonthStatus { month: MonthId { year: 2025, month: 13 }, closed_at: None });
+        let message = match dump.validate() {
+            Err(DomainError::Validation(message)) => message,
+            other => panic!("export accepté: {:?}", other),
+        };
+        assert!(message.contains(&format!("opération {} en double", sale.id)));
+        assert!(message.contains("mois 2025-01 clôturé deux fois"));
+        assert!(message.contains("mois 2025-13 sans date de clôture"));
+        assert!(message.contains("clôture sur un mois invalide: 2025-13"));
+    }
+}
@@ -0,0 +1,44 @@
+-- ============================================================================
+-- Migration: Declaration workflow (filing date, 'filed' status, amount drift)
+-- SQLite cannot alter a CHECK constraint, so the table is rebuilt
+-- ============================================================================
+
+CREATE TABLE declarations_new (
+    id TEXT PRIMARY KEY,
+    declaration_type TEXT NOT NULL CHECK (declaration_type IN ('vat', 'urssaf')),
+    period_year INTEGER NOT NULL,
+    period_month INTEGER NOT NULL CHECK (period_month >= 1 AND period_month <= 12),
+    amount_due_cents INTEGER NOT NULL,    -- Frozen once filed
+    due_date TEXT NOT NULL,
+    filing_date TEXT,
+    payment_date TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'filed', 'paid', 'overdue')),
+    recomputed_amount_cents INTEGER,      -- Latest recomputation when it differs from the declared amount
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Nothing prevented duplicates before uniq_declarations_period: one row is kept per type and period,
+-- the paid one first, then the most recently updated
+INSERT INTO declarations_new (
+    id, declaration_type, period_year, period_month, amount_due_cents,
+    due_date, payment_date, status, created_at, updated_at
+)
+SELECT id, declaration_type, period_year, period_month, amount_due_cents,
+       due_date, payment_date, status, created_at, updated_at
+FROM (
+    SELECT *, ROW_NUMBER() OVER (
+        PARTITION BY declaration_type, period_year, period_month
+        ORDER BY status = 'paid' DESC, payment_date IS NOT NULL DESC, updated_at DESC, id
+    ) AS period_rank
+    FROM declarations
+)
+WHERE period_rank = 1;
+
+DROP TABLE declarations;
+ALTER TABLE declarations_new RENAME TO declarations;
+
+CREATE INDEX IF NOT EXISTS idx_declarations_period ON declarations(period_year, period_month);
+CREATE INDEX IF NOT EXISTS idx_declarations_type_status ON declarations(declaration_type, status);
+CREATE INDEX IF NOT EXISTS idx_declarations_due_date ON declarations(due_date);
+CREATE UNIQUE INDEX IF NOT EXISTS uniq_declarations_period ON declarations(declaration_type, period_year, period_month);
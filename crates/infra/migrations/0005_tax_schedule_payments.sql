@@ -0,0 +1,30 @@
+-- ============================================================================
+-- Migration: Persistent tax schedules with (partial) payment history
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS tax_schedules (
+    id TEXT PRIMARY KEY,
+    tax_type TEXT NOT NULL,               -- Vat, Urssaf, IncomeTax or free label
+    due_date TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    period_start TEXT NOT NULL,
+    period_end TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'Pending' CHECK (status IN ('Pending', 'Paid', 'Overdue')),
+    paid_date TEXT,                       -- Date of the last payment
+    paid_amount_cents INTEGER NOT NULL DEFAULT 0,
+    payment_reference TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tax_schedule_payments (
+    id TEXT PRIMARY KEY,
+    tax_schedule_id TEXT NOT NULL,
+    paid_date TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL CHECK (amount_cents > 0),
+    reference TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (tax_schedule_id) REFERENCES tax_schedules(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_tax_schedules_due_date ON tax_schedules(due_date);
+CREATE INDEX IF NOT EXISTS idx_tax_schedule_payments_schedule ON tax_schedule_payments(tax_schedule_id);
@@ -0,0 +1,11 @@
+-- ============================================================================
+-- Migration: One tax schedule per (tax type, period) so that syncs are idempotent
+-- ============================================================================
+
+-- Keep the oldest entry if duplicates were created before the constraint
+DELETE FROM tax_schedules
+WHERE rowid NOT IN (
+    SELECT MIN(rowid) FROM tax_schedules GROUP BY tax_type, period_start
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS uniq_tax_schedules_period ON tax_schedules(tax_type, period_start);
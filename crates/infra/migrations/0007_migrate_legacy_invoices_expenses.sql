@@ -0,0 +1,62 @@
+-- ============================================================================
+-- Migration: Move legacy invoices and expenses into the unified operations table
+-- ============================================================================
+
+-- Older databases have these tables, newer ones never had them: create them empty so the copy below always runs
+CREATE TABLE IF NOT EXISTS invoices (
+    id TEXT PRIMARY KEY,
+    number TEXT NOT NULL DEFAULT '',
+    client TEXT NOT NULL DEFAULT '',
+    service_date TEXT NOT NULL,
+    amount_ht INTEGER NOT NULL,
+    vat_rate_ppm INTEGER NOT NULL,
+    amount_tva INTEGER NOT NULL,
+    amount_ttc INTEGER NOT NULL,
+    paid_at TEXT,
+    source TEXT
+);
+
+CREATE TABLE IF NOT EXISTS expenses (
+    id TEXT PRIMARY KEY,
+    label TEXT NOT NULL DEFAULT '',
+    category TEXT NOT NULL DEFAULT '',
+    booking_date TEXT NOT NULL,
+    amount_ht INTEGER NOT NULL,
+    vat_rate_ppm INTEGER NOT NULL,
+    amount_tva INTEGER NOT NULL,
+    amount_ttc INTEGER NOT NULL,
+    paid_at TEXT,
+    receipt_path TEXT
+);
+
+-- Ids are kept so that operations can be traced back to their legacy row
+-- Legacy reports always used the payment date, hence vat_on_payments = true
+INSERT OR IGNORE INTO operations (
+    id, invoice_date, payment_date, type, amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+    vat_on_payments, label, receipt_url, created_at, updated_at
+)
+SELECT
+    id, service_date, paid_at, 'sale', amount_ht, amount_tva, amount_ttc,
+    true,
+    CASE
+        WHEN number <> '' AND client <> '' THEN number || ' - ' || client
+        WHEN number <> '' THEN number
+        WHEN client <> '' THEN client
+    END,
+    source, datetime('now'), datetime('now')
+FROM invoices;
+
+INSERT OR IGNORE INTO operations (
+    id, invoice_date, payment_date, type, amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+    vat_on_payments, label, receipt_url, created_at, updated_at
+)
+SELECT
+    id, booking_date, paid_at, 'purchase', amount_ht, amount_tva, amount_ttc,
+    true,
+    CASE
+        WHEN label <> '' AND category <> '' THEN label || ' - ' || category
+        WHEN label <> '' THEN label
+        WHEN category <> '' THEN category
+    END,
+    receipt_path, datetime('now'), datetime('now')
+FROM expenses;
@@ -0,0 +1,7 @@
+-- ============================================================================
+-- Migration: Content hash of the receipt attached to an operation
+-- ============================================================================
+
+ALTER TABLE operations ADD COLUMN receipt_sha256 TEXT; -- Hex SHA-256 of the receipt content
+
+CREATE INDEX IF NOT EXISTS idx_operations_receipt_sha256 ON operations(receipt_sha256) WHERE receipt_sha256 IS NOT NULL;
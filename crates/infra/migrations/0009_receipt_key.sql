@@ -0,0 +1,6 @@
+-- ============================================================================
+-- Migration: Operations reference receipts by document key instead of public URL
+-- ============================================================================
+-- Existing URLs are converted to keys by the application at startup (migrate_receipt_urls_to_keys)
+
+ALTER TABLE operations RENAME COLUMN receipt_url TO receipt_key; -- "YYYY-MM/<file>"
@@ -0,0 +1,70 @@
+-- ============================================================================
+-- Migration: Sales invoice issuance (issuer profile, gapless yearly numbering)
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS issuer_profile (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    name TEXT NOT NULL,
+    address TEXT NOT NULL,
+    siret TEXT NOT NULL,
+    vat_number TEXT,
+    vat_exempt INTEGER NOT NULL DEFAULT 0,      -- Franchise en base (art. 293 B du CGI)
+    late_penalty_rate_ppm INTEGER NOT NULL,
+    payment_terms_days INTEGER NOT NULL DEFAULT 30,
+    iban TEXT,
+    email TEXT,
+    updated_at TEXT NOT NULL
+);
+
+-- Last number consumed for each year
+CREATE TABLE IF NOT EXISTS invoice_sequences (
+    year INTEGER PRIMARY KEY,
+    last_number INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS issued_invoices (
+    id TEXT PRIMARY KEY,
+    number TEXT NOT NULL UNIQUE,
+    year INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    issue_date TEXT NOT NULL,
+    due_date TEXT NOT NULL,
+    is_service INTEGER NOT NULL,
+    issuer_json TEXT NOT NULL,                  -- Issuer profile as it was when issuing
+    client_name TEXT NOT NULL,
+    client_address TEXT NOT NULL,
+    client_siret TEXT,
+    client_vat_number TEXT,
+    total_ht_cents INTEGER NOT NULL,
+    total_vat_cents INTEGER NOT NULL,
+    total_ttc_cents INTEGER NOT NULL,
+    notes TEXT,
+    operation_id TEXT NOT NULL REFERENCES operations(id),
+    pdf_key TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (year, sequence)
+);
+
+CREATE TABLE IF NOT EXISTS issued_invoice_lines (
+    invoice_id TEXT NOT NULL REFERENCES issued_invoices(id),
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity_milli INTEGER NOT NULL,
+    unit_price_ht_cents INTEGER NOT NULL,
+    vat_rate_ppm INTEGER NOT NULL,
+    PRIMARY KEY (invoice_id, position)
+);
+
+-- An issued invoice is never deleted: it is cancelled by a credit note
+CREATE TRIGGER IF NOT EXISTS issued_invoices_no_delete
+BEFORE DELETE ON issued_invoices
+BEGIN
+    SELECT RAISE(ABORT, 'Une facture émise ne peut pas être supprimée');
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_keep_issued_invoice
+BEFORE DELETE ON operations
+WHEN EXISTS (SELECT 1 FROM issued_invoices WHERE operation_id = OLD.id)
+BEGIN
+    SELECT RAISE(ABORT, 'Opération liée à une facture émise: établir un avoir');
+END;
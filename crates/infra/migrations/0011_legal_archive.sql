@@ -0,0 +1,66 @@
+-- ============================================================================
+-- Migration: Ten-year legal archive (read-only fiscal years, retained documents)
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS archived_fiscal_years (
+    year INTEGER PRIMARY KEY,
+    archived_at TEXT NOT NULL,
+    retain_until TEXT NOT NULL,                 -- 31/12 of the tenth following year
+    operations_count INTEGER NOT NULL
+);
+
+-- Documents of archived years (a receipt shared by several years keeps the latest date)
+CREATE TABLE IF NOT EXISTS archived_documents (
+    key TEXT PRIMARY KEY,
+    fiscal_year INTEGER NOT NULL REFERENCES archived_fiscal_years(year),
+    sha256 TEXT NOT NULL,
+    retain_until TEXT NOT NULL,
+    object_locked INTEGER NOT NULL DEFAULT 0    -- Retention also enforced by S3 Object Lock
+);
+
+CREATE TRIGGER IF NOT EXISTS archived_fiscal_years_no_delete
+BEFORE DELETE ON archived_fiscal_years
+BEGIN
+    SELECT RAISE(ABORT, 'Un exercice archivé ne peut pas être rouvert');
+END;
+
+CREATE TRIGGER IF NOT EXISTS archived_documents_keep_until_retention
+BEFORE DELETE ON archived_documents
+WHEN OLD.retain_until >= date('now')
+BEGIN
+    SELECT RAISE(ABORT, 'Document archivé: conservation légale en cours');
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_archived_no_insert
+BEFORE INSERT ON operations
+WHEN EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', NEW.invoice_date) AS INTEGER))
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: aucune opération ne peut y être ajoutée');
+END;
+
+-- Only the payment of an operation still unpaid at archiving can be recorded
+CREATE TRIGGER IF NOT EXISTS operations_archived_no_update
+BEFORE UPDATE ON operations
+WHEN EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', OLD.invoice_date) AS INTEGER))
+    AND NOT (
+        OLD.payment_date IS NULL
+        AND NEW.invoice_date IS OLD.invoice_date
+        AND NEW.type IS OLD.type
+        AND NEW.amount_ht_cents IS OLD.amount_ht_cents
+        AND NEW.vat_amount_cents IS OLD.vat_amount_cents
+        AND NEW.amount_ttc_cents IS OLD.amount_ttc_cents
+        AND NEW.vat_on_payments IS OLD.vat_on_payments
+        AND NEW.label IS OLD.label
+        AND NEW.receipt_key IS OLD.receipt_key
+        AND NEW.receipt_sha256 IS OLD.receipt_sha256
+    )
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: opération en lecture seule');
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_archived_no_delete
+BEFORE DELETE ON operations
+WHEN EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', OLD.invoice_date) AS INTEGER))
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: opération conservée 10 ans');
+END;
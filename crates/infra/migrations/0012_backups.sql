@@ -0,0 +1,11 @@
+-- ============================================================================
+-- Migration: Backup retention policy (snapshots live in the backups/ folder)
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS backup_policy (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    keep_daily INTEGER NOT NULL DEFAULT 7,
+    keep_weekly INTEGER NOT NULL DEFAULT 4,
+    keep_monthly INTEGER NOT NULL DEFAULT 12,
+    offsite INTEGER NOT NULL DEFAULT 0          -- Also push snapshots to the document store
+);
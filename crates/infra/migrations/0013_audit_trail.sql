@@ -0,0 +1,30 @@
+-- ============================================================================
+-- Migration: Append-only, hash-chained audit trail of financial mutations
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS audit_events (
+    seq INTEGER PRIMARY KEY,                    -- 1, 2, 3… without gaps
+    entity TEXT NOT NULL,                       -- operation, declaration, provision, settings, yearly_planning, month_planning
+    entity_id TEXT NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('create', 'update', 'delete')),
+    before_json TEXT,                           -- NULL on create
+    after_json TEXT,                            -- NULL on delete
+    occurred_at TEXT NOT NULL,
+    origin TEXT NOT NULL,                       -- Use case that made the change
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL                          -- SHA-256 of the event chained to prev_hash
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_events_entity ON audit_events(entity, entity_id);
+
+CREATE TRIGGER IF NOT EXISTS audit_events_no_update
+BEFORE UPDATE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'Journal d''audit en ajout seul: modification interdite');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_events_no_delete
+BEFORE DELETE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'Journal d''audit en ajout seul: suppression interdite');
+END;
@@ -0,0 +1,18 @@
+-- ============================================================================
+-- Migration: Soft delete (trash) for operations and yearly plannings
+-- ============================================================================
+
+-- Trashed rows are hidden from every read until restored or purged
+ALTER TABLE operations ADD COLUMN deleted_at TEXT;
+ALTER TABLE yearly_planning ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_operations_deleted_at ON operations(deleted_at);
+
+-- Archived operations can be neither trashed nor restored
+CREATE TRIGGER IF NOT EXISTS operations_archived_no_trash
+BEFORE UPDATE OF deleted_at ON operations
+WHEN EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', OLD.invoice_date) AS INTEGER))
+    AND NEW.deleted_at IS NOT OLD.deleted_at
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: opération en lecture seule');
+END;
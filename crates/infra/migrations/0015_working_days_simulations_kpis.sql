@@ -0,0 +1,50 @@
+-- ============================================================================
+-- Migration: Tables of the working days, simulations and monthly KPIs repositories
+-- ============================================================================
+
+-- Their repositories predate this migration: databases created since 0001 lacked them
+
+CREATE TABLE IF NOT EXISTS working_days (
+    id TEXT PRIMARY KEY,
+    date TEXT NOT NULL,                         -- YYYY-MM-DD
+    hours_worked REAL NOT NULL,
+    billable_hours REAL NOT NULL,
+    hourly_rate_cents INTEGER NOT NULL,
+    description TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_working_days_date ON working_days(date);
+
+CREATE TABLE IF NOT EXISTS simulations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    scenario_type TEXT NOT NULL,
+    parameters TEXT NOT NULL,                   -- JSON
+    results TEXT,                               -- JSON, NULL until run
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS monthly_kpis (
+    id TEXT PRIMARY KEY,
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    revenue_ht_cents INTEGER NOT NULL,
+    revenue_ttc_cents INTEGER NOT NULL,
+    expenses_ttc_cents INTEGER NOT NULL,
+    working_days REAL NOT NULL,
+    billable_hours REAL NOT NULL,
+    average_daily_rate_cents INTEGER NOT NULL,
+    average_hourly_rate_cents INTEGER NOT NULL,
+    vat_collected_cents INTEGER NOT NULL,
+    vat_due_cents INTEGER NOT NULL,
+    urssaf_due_cents INTEGER NOT NULL,
+    net_margin_cents INTEGER NOT NULL,
+    profitability_ratio REAL NOT NULL,
+    utilization_rate REAL NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE(year, month)
+);
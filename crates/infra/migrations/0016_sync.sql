@@ -0,0 +1,42 @@
+-- ============================================================================
+-- Migration: Synchronisation of installations through a shared folder of change logs
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS sync_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    peer_id TEXT NOT NULL,                      -- Name of this installation, and of its change log
+    shared_dir TEXT NOT NULL,
+    published_audit_seq INTEGER NOT NULL DEFAULT 0  -- Last audit event appended to the own log
+);
+
+-- Last change applied from each other installation's log
+CREATE TABLE IF NOT EXISTS sync_positions (
+    peer TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL
+);
+
+-- Stamp of the last write of each synchronised field; field '*' is the record's creation or deletion
+CREATE TABLE IF NOT EXISTS sync_field_clocks (
+    entity TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    peer TEXT NOT NULL,
+    seq INTEGER NOT NULL,                       -- Position of the change in the peer's log
+    PRIMARY KEY (entity, entity_id, field)
+);
+
+CREATE TABLE IF NOT EXISTS sync_conflicts (
+    id TEXT PRIMARY KEY,
+    peer TEXT NOT NULL,                         -- Installation the change came from
+    entity TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    field TEXT,                                 -- NULL: the record as a whole
+    local_json TEXT,
+    remote_json TEXT,
+    kept TEXT NOT NULL CHECK (kept IN ('local', 'remote')),
+    message TEXT NOT NULL,
+    detected_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_conflicts_detected_at ON sync_conflicts(detected_at);
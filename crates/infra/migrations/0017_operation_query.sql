@@ -0,0 +1,71 @@
+-- ============================================================================
+-- Migration: Operation client and category, indexes and full-text search of the operation query
+-- ============================================================================
+
+ALTER TABLE operations ADD COLUMN client TEXT;   -- Client of a sale, supplier of a purchase
+ALTER TABLE operations ADD COLUMN category TEXT; -- Category id of the frontend catalogue
+
+-- Issued invoices know their client; archived years are left as they were archived
+UPDATE operations
+SET client = (SELECT client_name FROM issued_invoices WHERE operation_id = operations.id)
+WHERE id IN (SELECT operation_id FROM issued_invoices)
+    AND CAST(strftime('%Y', invoice_date) AS INTEGER) NOT IN (SELECT year FROM archived_fiscal_years);
+
+CREATE INDEX IF NOT EXISTS idx_operations_invoice_date ON operations(invoice_date);
+CREATE INDEX IF NOT EXISTS idx_operations_payment_date ON operations(payment_date);
+CREATE INDEX IF NOT EXISTS idx_operations_amount_ht ON operations(amount_ht_cents);
+CREATE INDEX IF NOT EXISTS idx_operations_client ON operations(client COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS idx_operations_category ON operations(category);
+
+-- The new columns are read-only in archived years too
+DROP TRIGGER IF EXISTS operations_archived_no_update;
+CREATE TRIGGER operations_archived_no_update
+BEFORE UPDATE ON operations
+WHEN EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', OLD.invoice_date) AS INTEGER))
+    AND NOT (
+        OLD.payment_date IS NULL
+        AND NEW.invoice_date IS OLD.invoice_date
+        AND NEW.type IS OLD.type
+        AND NEW.amount_ht_cents IS OLD.amount_ht_cents
+        AND NEW.vat_amount_cents IS OLD.vat_amount_cents
+        AND NEW.amount_ttc_cents IS OLD.amount_ttc_cents
+        AND NEW.vat_on_payments IS OLD.vat_on_payments
+        AND NEW.label IS OLD.label
+        AND NEW.client IS OLD.client
+        AND NEW.category IS OLD.category
+        AND NEW.receipt_key IS OLD.receipt_key
+        AND NEW.receipt_sha256 IS OLD.receipt_sha256
+    )
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: opération en lecture seule');
+END;
+
+-- Label and client words, accents ignored. The index keeps its own copy keyed by operation id:
+-- the rowids of `operations` are not stable (VACUUM INTO renumbers them in backups)
+CREATE VIRTUAL TABLE IF NOT EXISTS operations_fts USING fts5(
+    id UNINDEXED,
+    label,
+    client,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO operations_fts (id, label, client) SELECT id, label, client FROM operations;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_insert
+AFTER INSERT ON operations
+BEGIN
+    INSERT INTO operations_fts (id, label, client) VALUES (NEW.id, NEW.label, NEW.client);
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_update
+AFTER UPDATE OF id, label, client ON operations
+BEGIN
+    DELETE FROM operations_fts WHERE id = OLD.id;
+    INSERT INTO operations_fts (id, label, client) VALUES (NEW.id, NEW.label, NEW.client);
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_delete
+AFTER DELETE ON operations
+BEGIN
+    DELETE FROM operations_fts WHERE id = OLD.id;
+END;
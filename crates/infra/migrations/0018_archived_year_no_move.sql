@@ -0,0 +1,28 @@
+-- ============================================================================
+-- Migration: Operations cannot be moved into an archived fiscal year either
+-- ============================================================================
+
+DROP TRIGGER IF EXISTS operations_archived_no_update;
+CREATE TRIGGER operations_archived_no_update
+BEFORE UPDATE ON operations
+WHEN (
+        EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', OLD.invoice_date) AS INTEGER))
+        OR EXISTS (SELECT 1 FROM archived_fiscal_years WHERE year = CAST(strftime('%Y', NEW.invoice_date) AS INTEGER))
+    )
+    AND NOT (
+        OLD.payment_date IS NULL
+        AND NEW.invoice_date IS OLD.invoice_date
+        AND NEW.type IS OLD.type
+        AND NEW.amount_ht_cents IS OLD.amount_ht_cents
+        AND NEW.vat_amount_cents IS OLD.vat_amount_cents
+        AND NEW.amount_ttc_cents IS OLD.amount_ttc_cents
+        AND NEW.vat_on_payments IS OLD.vat_on_payments
+        AND NEW.label IS OLD.label
+        AND NEW.client IS OLD.client
+        AND NEW.category IS OLD.category
+        AND NEW.receipt_key IS OLD.receipt_key
+        AND NEW.receipt_sha256 IS OLD.receipt_sha256
+    )
+BEGIN
+    SELECT RAISE(ABORT, 'Exercice archivé: opération en lecture seule');
+END;
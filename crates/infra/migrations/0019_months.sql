@@ -0,0 +1,10 @@
+-- ============================================================================
+-- Migration: Closed months, read and written by the month closing but never created
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS months (
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL CHECK (month BETWEEN 1 AND 12),
+    closed_at TEXT,                              -- NULL while the month is open
+    PRIMARY KEY (year, month)
+);
@@ -0,0 +1,49 @@
+-- ============================================================================
+-- Migration: Full-text index of operations keyed by integer rows
+-- ============================================================================
+
+-- Keyed by operation id, the index could only find a row to replace by scanning itself.
+-- Each operation now gets a document number of its own: an INTEGER PRIMARY KEY keeps its
+-- value through VACUUM INTO, unlike the rowids of `operations`
+DROP TRIGGER IF EXISTS operations_fts_insert;
+DROP TRIGGER IF EXISTS operations_fts_update;
+DROP TRIGGER IF EXISTS operations_fts_delete;
+DROP TABLE IF EXISTS operations_fts;
+
+CREATE TABLE IF NOT EXISTS operations_fts_keys (
+    docid INTEGER PRIMARY KEY,  -- Row of the operation in operations_fts
+    id TEXT NOT NULL UNIQUE     -- Operation id
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS operations_fts USING fts5(
+    label,
+    client,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO operations_fts_keys (id) SELECT id FROM operations;
+INSERT INTO operations_fts (rowid, label, client)
+SELECT k.docid, o.label, o.client FROM operations o JOIN operations_fts_keys k ON k.id = o.id;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_insert
+AFTER INSERT ON operations
+BEGIN
+    INSERT INTO operations_fts_keys (id) VALUES (NEW.id);
+    INSERT INTO operations_fts (rowid, label, client)
+    VALUES ((SELECT docid FROM operations_fts_keys WHERE id = NEW.id), NEW.label, NEW.client);
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_update
+AFTER UPDATE OF id, label, client ON operations
+BEGIN
+    UPDATE operations_fts_keys SET id = NEW.id WHERE id = OLD.id;
+    UPDATE operations_fts SET label = NEW.label, client = NEW.client
+    WHERE rowid = (SELECT docid FROM operations_fts_keys WHERE id = NEW.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS operations_fts_delete
+AFTER DELETE ON operations
+BEGIN
+    DELETE FROM operations_fts WHERE rowid = (SELECT docid FROM operations_fts_keys WHERE id = OLD.id);
+    DELETE FROM operations_fts_keys WHERE id = OLD.id;
+END;
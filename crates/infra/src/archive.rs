@@ -0,0 +1,65 @@
+use std::io::Write;
+use std::path::Path;
+
+use domain::{DomainError, DomainResult};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Écrit une archive ZIP à `path` (fichier temporaire puis renommage: pas d'archive tronquée)
+/// Les justificatifs déjà compressés (PDF, images) sont stockés tels quels
+pub fn write_zip(path: &Path, files: &[(String, Vec<u8>)]) -> DomainResult<()> {
+    let partial = path.with_extension("zip.part");
+    let result = write_zip_to(&partial, files).and_then(|_| {
+        std::fs::rename(&partial, path).map_err(|e| DomainError::Repo(format!("Erreur écriture archive: {}", e)))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn write_zip_to(path: &Path, files: &[(String, Vec<u8>)]) -> DomainResult<()> {
+    let zip_error = |e: zip::result::ZipError| DomainError::Repo(format!("Erreur écriture archive: {}", e));
+    let io_error = |e: std::io::Error| DomainError::Repo(format!("Erreur écriture archive: {}", e));
+
+    let file = std::fs::File::create(path).map_err(io_error)?;
+    let mut zip = ZipWriter::new(file);
+    for (name, content) in files {
+        let compression = if is_compressible(name) { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+        zip.start_file(name.as_str(), SimpleFileOptions::default().compression_method(compression)).map_err(zip_error)?;
+        zip.write_all(content).map_err(io_error)?;
+    }
+    zip.finish().map_err(zip_error)?.sync_all().map_err(io_error)
+}
+
+fn is_compressible(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    matches!(extension.as_deref(), Some("csv" | "json" | "txt" | "xml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_write_zip() {
+        let path = std::env::temp_dir().join(format!("cash-planner-export-{}.zip", uuid::Uuid::new_v4()));
+        let files = vec![
+            ("journal.csv".to_string(), "Date;Montant\n2024-03-15;12,50\n".as_bytes().to_vec()),
+            ("justificatifs/2024-03-15_achat_12,50EUR.pdf".to_string(), b"%PDF-1.7".to_vec()),
+        ];
+        write_zip(&path, &files).unwrap();
+        assert!(!path.with_extension("zip.part").exists());
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        for (name, content) in &files {
+            let mut entry = archive.by_name(name).unwrap();
+            let mut read = Vec::new();
+            entry.read_to_end(&mut read).unwrap();
+            assert_eq!(&read, content);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
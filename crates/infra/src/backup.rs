@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Timelike;
+use domain::{
+    backup_name, parse_backup_name, BackupInfo, BackupPolicy, BackupReason, BackupRepo, BackupVerification,
+    DomainError, DomainResult,
+};
+use sqlx::{Connection, Pool, Row, Sqlite};
+
+use crate::encryption::{open_single, sql_string, DatabaseKey};
+
+/// Sauvegardes de la base dans `backups/`, à côté du fichier `data.sqlite`
+#[derive(Clone)]
+pub struct SqliteBackupRepo {
+    pool: Pool<Sqlite>,
+    db_path: Option<PathBuf>,
+    key: Option<DatabaseKey>, // Les copies d'une base chiffrée le sont avec la même clé
+}
+
+impl SqliteBackupRepo {
+    pub(crate) fn new(pool: Pool<Sqlite>, db_path: Option<PathBuf>, key: Option<DatabaseKey>) -> Self {
+        Self { pool, db_path, key }
+    }
+
+    fn db_path(&self) -> DomainResult<&Path> {
+        self.db_path.as_deref()
+            .ok_or_else(|| DomainError::Validation("Sauvegarde impossible: base de données en mémoire".to_string()))
+    }
+
+    /// Chemin d'une sauvegarde; seuls les noms produits par `backup_name` sont acceptés
+    fn backup_path(&self, name: &str) -> DomainResult<PathBuf> {
+        if parse_backup_name(name).is_none() {
+            return Err(DomainError::Validation(format!("Nom de sauvegarde invalide: '{}'", name)));
+        }
+        let path = backup_dir(self.db_path()?).join(name);
+        if !path.is_file() {
+            return Err(DomainError::NotFound);
+        }
+        Ok(path)
+    }
+}
+
+/// Dossier des sauvegardes d'une base
+pub fn backup_dir(db_path: &Path) -> PathBuf {
+    db_path.parent().unwrap_or(Path::new(".")).join("backups")
+}
+
+/// Sauvegarde à restaurer au prochain démarrage
+fn staged_restore_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".restore");
+    db_path.with_file_name(name)
+}
+
+/// Remplace la base par la sauvegarde programmée avec `stage_restore`; à appeler avant toute connexion.
+/// La base remplacée est gardée dans `backups/` (motif `pre_restore`), avec les pages encore dans son journal WAL
+/// si elle a été passée dans ce mode (persistant dans le fichier)
+pub async fn apply_staged_restore(db_path: &Path, key: Option<&DatabaseKey>) -> anyhow::Result<bool> {
+    let staged = staged_restore_path(db_path);
+    if !staged.is_file() {
+        return Ok(false);
+    }
+    if db_path.is_file() {
+        let mut conn = open_single(db_path, key).await?;
+        let (busy, _, _): (i64, i64, i64) = sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)").fetch_one(&mut conn).await?;
+        conn.close().await?;
+        if busy != 0 {
+            anyhow::bail!("Base en cours d'utilisation, journal WAL non reporté");
+        }
+        let dir = backup_dir(db_path);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::copy(db_path, dir.join(backup_name(now(), BackupReason::PreRestore)))?;
+    }
+    // Un journal resté de l'ancienne base serait rejoué sur la sauvegarde restaurée
+    for suffix in ["-wal", "-shm"] {
+        let mut name = db_path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        name.push(suffix);
+        match std::fs::remove_file(db_path.with_file_name(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    std::fs::rename(&staged, db_path)?;
+    Ok(true)
+}
+
+fn now() -> chrono::NaiveDateTime {
+    let now = chrono::Local::now().naive_local();
+    now.with_nanosecond(0).unwrap_or(now)
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let name = path.file_name()?.to_str()?;
+    let (created_at, reason) = parse_backup_name(name)?;
+    let size_bytes = std::fs::metadata(path).ok()?.len();
+    Some(BackupInfo { name: name.to_string(), created_at, reason, size_bytes })
+}
+
+fn io_error(context: &str) -> impl Fn(std::io::Error) -> DomainError + '_ {
+    move |e| DomainError::Repo(format!("{}: {}", context, e))
+}
+
+#[async_trait::async_trait]
+impl BackupRepo for SqliteBackupRepo {
+    async fn load_backup_policy(&self) -> DomainResult<BackupPolicy> {
+        let row = sqlx::query("SELECT keep_daily, keep_weekly, keep_monthly, offsite FROM backup_policy WHERE id = 1")
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(match row {
+            Some(r) => BackupPolicy {
+                keep_daily: r.get::<i64, _>("keep_daily") as u32,
+                keep_weekly: r.get::<i64, _>("keep_weekly") as u32,
+                keep_monthly: r.get::<i64, _>("keep_monthly") as u32,
+                offsite: r.get::<i64, _>("offsite") != 0,
+            },
+            None => BackupPolicy::default(),
+        })
+    }
+
+    async fn save_backup_policy(&self, policy: BackupPolicy) -> DomainResult<()> {
+        sqlx::query(r#"INSERT INTO backup_policy (id, keep_daily, keep_weekly, keep_monthly, offsite) VALUES (1, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET keep_daily=excluded.keep_daily, keep_weekly=excluded.keep_weekly, keep_monthly=excluded.keep_monthly, offsite=excluded.offsite"#)
+            .bind(policy.keep_daily as i64)
+            .bind(policy.keep_weekly as i64)
+            .bind(policy.keep_monthly as i64)
+            .bind(policy.offsite as i64)
+            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn create_backup(&self, reason: BackupReason) -> DomainResult<BackupInfo> {
+        let dir = backup_dir(self.db_path()?);
+        tokio::fs::create_dir_all(&dir).await.map_err(io_error("Erreur création dossier de sauvegarde"))?;
+        let path = dir.join(backup_name(now(), reason));
+        if let Some(existing) = backup_info(&path) {
+            return Ok(existing);
+        }
+
+        // VACUUM INTO donne une copie cohérente sans bloquer les écritures; fichier temporaire puis renommage
+        let partial = path.with_extension("sqlite.part");
+        let _ = tokio::fs::remove_file(&partial).await;
+        let result = sqlx::query(&format!("VACUUM INTO {}", sql_string(&partial.to_string_lossy())))
+            .execute(&self.pool).await
+            .map_err(|e| DomainError::Repo(format!("Erreur sauvegarde: {}", e)));
+        let result = match result {
+            Ok(_) => tokio::fs::rename(&partial, &path).await.map_err(io_error("Erreur sauvegarde")),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+        result?;
+        backup_info(&path).ok_or_else(|| DomainError::Repo("Sauvegarde introuvable après écriture".to_string()))
+    }
+
+    async fn list_backups(&self) -> DomainResult<Vec<BackupInfo>> {
+        let dir = backup_dir(self.db_path()?);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error("Erreur lecture des sauvegardes")(e)),
+        };
+        let mut backups = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(io_error("Erreur lecture des sauvegardes"))? {
+            if let Some(info) = backup_info(&entry.path()) {
+                backups.push(info);
+            }
+        }
+        backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.name.cmp(&a.name)));
+        Ok(backups)
+    }
+
+    async fn read_backup(&self, name: &str) -> DomainResult<Vec<u8>> {
+        tokio::fs::read(self.backup_path(name)?).await.map_err(io_error("Erreur lecture de la sauvegarde"))
+    }
+
+    async fn delete_backup(&self, name: &str) -> DomainResult<()> {
+        tokio::fs::remove_file(self.backup_path(name)?).await.map_err(io_error("Erreur suppression de la sauvegarde"))
+    }
+
+    async fn verify_backup(&self, name: &str) -> DomainResult<BackupVerification> {
+        let path = self.backup_path(name)?;
+        let backup = backup_info(&path).ok_or(DomainError::NotFound)?;
+        // Une sauvegarde antérieure à un changement de clé ne s'ouvre plus avec la clé actuelle
+        let message = match open_single(&path, self.key.as_ref()).await {
+            Err(e) => e.to_string(),
+            Ok(mut conn) => {
+                let integrity = sqlx::query("PRAGMA integrity_check").fetch_all(&mut conn).await
+                    .map(|rows| rows.iter().map(|r| r.get::<String, _>(0)).collect::<Vec<_>>().join("; "))
+                    .unwrap_or_else(|e| e.to_string());
+                let migrations = sqlx::query("SELECT COUNT(*) FROM _sqlx_migrations").fetch_one(&mut conn).await;
+                conn.close().await.ok();
+                match migrations {
+                    Ok(_) => integrity,
+                    Err(_) => "Schéma absent: ce fichier n'est pas une base JLA Cash Planner".to_string(),
+                }
+            }
+        };
+        Ok(BackupVerification { backup, ok: message == "ok", message })
+    }
+
+    async fn stage_restore(&self, name: &str) -> DomainResult<()> {
+        let verification = self.verify_backup(name).await?;
+        if !verification.ok {
+            return Err(DomainError::Validation(format!("Sauvegarde {} inutilisable: {}", name, verification.message)));
+        }
+        let staged = staged_restore_path(self.db_path()?);
+        tokio::fs::copy(self.backup_path(name)?, &staged).await.map_err(io_error("Erreur préparation de la restauration"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_and_migrate;
+
+    #[tokio::test]
+    async fn test_backup_verify_and_restore() {
+        let dir = std::env::temp_dir().join(format!("cash-planner-backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("data.sqlite");
+        let repos = connect_and_migrate(&format!("sqlite:{}", db_path.display())).await.unwrap();
+        let backups = repos.backups();
+        // Mode WAL persistant, tel qu'un outil externe peut le laisser
+        sqlx::query("PRAGMA journal_mode=WAL").execute(&repos.pool).await.unwrap();
+
+        let policy = BackupPolicy { keep_daily: 3, keep_weekly: 0, keep_monthly: 0, offsite: true };
+        backups.save_backup_policy(policy).await.unwrap();
+        let backup = backups.create_backup(BackupReason::Manual).await.unwrap();
+        assert_eq!(backup.reason, BackupReason::Manual);
+        assert!(backup.size_bytes > 0);
+        assert_eq!(backups.list_backups().await.unwrap().len(), 1);
+        assert!(backups.verify_backup(&backup.name).await.unwrap().ok);
+        assert!(backups.read_backup("../data.sqlite").await.is_err());
+
+        // Changement après la sauvegarde, annulé par la restauration
+        backups.save_backup_policy(BackupPolicy::default()).await.unwrap();
+        backups.stage_restore(&backup.name).await.unwrap();
+        // Journal WAL de l'ancienne base laissé à côté, comme après un arrêt brutal
+        let wal_path = dir.join("data.sqlite-wal");
+        let stale_wal = std::fs::read(&wal_path).unwrap();
+        assert!(!stale_wal.is_empty());
+        repos.pool.close().await;
+        std::fs::write(&wal_path, &stale_wal).unwrap();
+        assert!(apply_staged_restore(&db_path, None).await.unwrap());
+        assert!(!apply_staged_restore(&db_path, None).await.unwrap());
+        assert!(!wal_path.exists());
+
+        let repos = connect_and_migrate(&format!("sqlite:{}", db_path.display())).await.unwrap();
+        let backups = repos.backups();
+        assert_eq!(backups.load_backup_policy().await.unwrap().keep_daily, 3);
+        let list = backups.list_backups().await.unwrap();
+        // La base remplacée est gardée avec sa dernière modification, reportée depuis le journal
+        let pre_restore = list.iter().find(|b| b.reason == BackupReason::PreRestore).unwrap();
+        let mut conn = open_single(&backup_dir(&db_path).join(&pre_restore.name), None).await.unwrap();
+        let keep_daily: i64 = sqlx::query_scalar("SELECT keep_daily FROM backup_policy WHERE id = 1").fetch_one(&mut conn).await.unwrap();
+        assert_eq!(keep_daily, BackupPolicy::default().keep_daily as i64);
+        conn.close().await.unwrap();
+
+        // Une copie corrompue n'est jamais restaurée
+        let corrupted = backup_dir(&db_path).join("data-20240101-120000-manual.sqlite");
+        std::fs::write(&corrupted, b"SQLite format 3\0pas une base").unwrap();
+        assert!(!backups.verify_backup("data-20240101-120000-manual.sqlite").await.unwrap().ok);
+        assert!(backups.stage_restore("data-20240101-120000-manual.sqlite").await.is_err());
+        repos.pool.close().await;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,445 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Timelike};
+use domain::{DoctorFix, DoctorIssue, DoctorIssueKind, DoctorRepo, DoctorScan, DomainError, DomainResult};
+use sqlx::{Pool, Row, Sqlite};
+
+use crate::sqlite::{parse_db_date, parse_db_datetime};
+
+/// Contrôle d'intégrité des tables, sous les dépôts typés: chaque colonne est relue comme du texte
+#[derive(Clone)]
+pub struct SqliteDoctorRepo {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteDoctorRepo {
+    pub(crate) fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+
+    async fn table_exists(&self, table: &str) -> DomainResult<bool> {
+        let found = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(found.is_some())
+    }
+
+    async fn scan_table(&self, table: &TableSpec, issues: &mut Vec<DoctorIssue>) -> DomainResult<u64> {
+        let columns = table.columns.iter()
+            .map(|c| format!("CAST({0} AS TEXT) AS {0}", c.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let rows = sqlx::query(&format!(
+            "SELECT rowid AS doctor_rowid, CAST({} AS TEXT) AS doctor_key, {} FROM {} ORDER BY rowid",
+            table.key, columns, table.name,
+        ))
+            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        for row in &rows {
+            let rowid: i64 = row.try_get("doctor_rowid").map_err(|e| DomainError::Repo(e.to_string()))?;
+            let row_id = row.try_get::<Option<String>, _>("doctor_key").ok().flatten().unwrap_or_else(|| rowid.to_string());
+            for spec in table.columns {
+                let value: Option<String> = row.try_get(spec.name).map_err(|e| DomainError::Repo(e.to_string()))?;
+                if let Some((kind, message, fix)) = check_value(spec, value.as_deref()) {
+                    issues.push(DoctorIssue {
+                        table: table.name.to_string(),
+                        rowid,
+                        row_id: row_id.clone(),
+                        column: Some(spec.name.to_string()),
+                        value,
+                        kind,
+                        message: format!("{}.{}: {}", table.name, spec.name, message),
+                        // Journal d'audit: une correction casserait le chaînage, on signale seulement
+                        fix: fix.filter(|_| !table.read_only),
+                    });
+                }
+            }
+        }
+        Ok(rows.len() as u64)
+    }
+
+    /// Opérations dont le TTC n'est pas HT + TVA: le TTC est recalculé
+    async fn scan_operation_amounts(&self, issues: &mut Vec<DoctorIssue>) -> DomainResult<()> {
+        let rows = sqlx::query(r#"
+            SELECT rowid, CAST(id AS TEXT) AS id,
+                   CAST(amount_ht_cents AS INTEGER) AS ht, CAST(vat_amount_cents AS INTEGER) AS vat,
+                   CAST(amount_ttc_cents AS TEXT) AS ttc
+            FROM operations
+            WHERE CAST(amount_ht_cents AS INTEGER) + CAST(vat_amount_cents AS INTEGER) IS NOT CAST(amount_ttc_cents AS INTEGER)
+            ORDER BY rowid
+        "#)
+            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        for row in rows {
+            let ht: Option<i64> = row.get("ht");
+            let vat: Option<i64> = row.get("vat");
+            let ttc: Option<String> = row.get("ttc");
+            let expected = ht.zip(vat).map(|(ht, vat)| ht + vat);
+            issues.push(DoctorIssue {
+                table: "operations".to_string(),
+                rowid: row.get("rowid"),
+                row_id: row.get::<Option<String>, _>("id").unwrap_or_default(),
+                column: Some(OPERATION_TTC_COLUMN.to_string()),
+                message: match expected {
+                    Some(expected) => format!(
+                        "operations.{}: TTC {} différent de HT + TVA = {}",
+                        OPERATION_TTC_COLUMN, ttc.as_deref().unwrap_or("vide"), expected,
+                    ),
+                    None => "operations: montant HT ou TVA manquant".to_string(),
+                },
+                value: ttc,
+                kind: DoctorIssueKind::AmountMismatch,
+                fix: expected.map(|value| DoctorFix::SetValue { value: value.to_string() }),
+            });
+        }
+        Ok(())
+    }
+
+    /// Mois de planning sans planning annuel: invisibles, ils bloquent la recréation de l'année
+    async fn scan_orphan_month_plannings(&self, issues: &mut Vec<DoctorIssue>) -> DomainResult<()> {
+        let rows = sqlx::query(r#"
+            SELECT rowid, CAST(id AS TEXT) AS id, CAST(year AS TEXT) AS year, CAST(month AS TEXT) AS month
+            FROM month_planning
+            WHERE year NOT IN (SELECT year FROM yearly_planning)
+            ORDER BY rowid
+        "#)
+            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        for row in rows {
+            let year: Option<String> = row.get("year");
+            let month: Option<String> = row.get("month");
+            issues.push(DoctorIssue {
+                table: "month_planning".to_string(),
+                rowid: row.get("rowid"),
+                row_id: row.get::<Option<String>, _>("id").unwrap_or_default(),
+                column: None,
+                value: None,
+                kind: DoctorIssueKind::OrphanRow,
+                message: format!(
+                    "month_planning: mois {}/{} sans planning annuel",
+                    month.as_deref().unwrap_or("?"), year.as_deref().unwrap_or("?"),
+                ),
+                fix: Some(DoctorFix::DeleteRow),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Check {
+    Uuid,
+    /// Identifiant référencé par une autre table: jamais régénéré automatiquement
+    LinkedUuid,
+    Date,
+    DateTime,
+    Enum(&'static [&'static str]),
+}
+
+struct ColumnSpec {
+    name: &'static str,
+    check: Check,
+    nullable: bool,
+}
+
+const fn col(name: &'static str, check: Check) -> ColumnSpec {
+    ColumnSpec { name, check, nullable: false }
+}
+
+const fn opt(name: &'static str, check: Check) -> ColumnSpec {
+    ColumnSpec { name, check, nullable: true }
+}
+
+struct TableSpec {
+    name: &'static str,
+    key: &'static str, // Identifiant montré à l'utilisateur
+    columns: &'static [ColumnSpec],
+    read_only: bool,
+}
+
+const fn table(name: &'static str, key: &'static str, columns: &'static [ColumnSpec]) -> TableSpec {
+    TableSpec { name, key, columns, read_only: false }
+}
+
+const OPERATION_TTC_COLUMN: &str = "amount_ttc_cents";
+
+/// Colonnes relues par `sqlite.rs` avec un format précis; les tables absentes du schéma sont ignorées
+const TABLES: &[TableSpec] = &[
+    table("invoices", "id", &[col("id", Check::Uuid), col("service_date", Check::Date), opt("paid_at", Check::Date)]),
+    table("expenses", "id", &[col("id", Check::Uuid), col("booking_date", Check::Date), opt("paid_at", Check::Date)]),
+    table("provisions", "id", &[
+        col("id", Check::Uuid),
+        col("type", Check::Enum(&["vat", "urssaf"])),
+        col("due_date", Check::Date),
+        col("status", Check::Enum(&["pending", "paid", "overdue"])),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+    ]),
+    table("months", "year", &[opt("closed_at", Check::DateTime)]),
+    table("working_days", "id", &[
+        col("id", Check::Uuid),
+        col("date", Check::Date),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+    ]),
+    table("tax_schedules", "id", &[
+        col("id", Check::LinkedUuid),
+        col("due_date", Check::Date),
+        col("period_start", Check::Date),
+        col("period_end", Check::Date),
+        col("status", Check::Enum(&["Pending", "Paid", "Overdue"])),
+        opt("paid_date", Check::Date),
+        col("created_at", Check::DateTime),
+    ]),
+    table("tax_schedule_payments", "id", &[
+        col("id", Check::Uuid),
+        col("tax_schedule_id", Check::LinkedUuid),
+        col("paid_date", Check::Date),
+        col("created_at", Check::DateTime),
+    ]),
+    table("simulations", "id", &[
+        col("id", Check::Uuid),
+        col("scenario_type", Check::Enum(&["DailyRateOptimization", "AnnualIncomeProjection", "TaxOptimization", "WorkingDaysImpact"])),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+    ]),
+    table("monthly_kpis", "id", &[col("id", Check::Uuid), col("created_at", Check::DateTime), col("updated_at", Check::DateTime)]),
+    table("operations", "id", &[
+        col("id", Check::LinkedUuid),
+        col("invoice_date", Check::Date),
+        opt("payment_date", Check::Date),
+        col("type", Check::Enum(&["sale", "purchase"])),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+        opt("deleted_at", Check::DateTime),
+    ]),
+    table("declarations", "id", &[
+        col("id", Check::Uuid),
+        col("declaration_type", Check::Enum(&["vat", "urssaf"])),
+        col("due_date", Check::Date),
+        opt("filing_date", Check::Date),
+        opt("payment_date", Check::Date),
+        col("status", Check::Enum(&["pending", "filed", "paid", "overdue"])),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+    ]),
+    table("yearly_planning", "year", &[
+        col("id", Check::Uuid),
+        col("created_at", Check::DateTime),
+        col("updated_at", Check::DateTime),
+        opt("deleted_at", Check::DateTime),
+    ]),
+    table("month_planning", "id", &[col("id", Check::Uuid), col("created_at", Check::DateTime), col("updated_at", Check::DateTime)]),
+    table("issued_invoices", "number", &[
+        col("id", Check::LinkedUuid),
+        col("issue_date", Check::Date),
+        col("due_date", Check::Date),
+        col("operation_id", Check::LinkedUuid),
+        col("created_at", Check::DateTime),
+    ]),
+    table("archived_fiscal_years", "year", &[col("archived_at", Check::DateTime), col("retain_until", Check::Date)]),
+    table("archived_documents", "key", &[col("retain_until", Check::Date)]),
+    TableSpec {
+        name: "audit_events",
+        key: "seq",
+        columns: &[
+            col("entity", Check::Enum(&["operation", "declaration", "provision", "settings", "yearly_planning", "month_planning"])),
+            col("action", Check::Enum(&["create", "update", "delete"])),
+            col("occurred_at", Check::DateTime),
+        ],
+        read_only: true,
+    },
+];
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d/%m/%Y", "%Y/%m/%d", "%d-%m-%Y", "%d.%m.%Y", "%Y%m%d"];
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M",
+    "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M",
+];
+
+fn date_only(value: &str) -> Option<NaiveDate> {
+    DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+/// Dates saisies à la main ou importées: formats français, ISO 8601 et RFC 3339 (heure locale conservée)
+fn lenient_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value).ok().map(|dt| dt.naive_local())
+        .or_else(|| DATETIME_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(value, format).ok()))
+        .or_else(|| date_only(value).and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+fn lenient_date(value: &str) -> Option<NaiveDate> {
+    date_only(value.trim()).or_else(|| lenient_datetime(value).map(|dt| dt.date()))
+}
+
+fn format_datetime(dt: NaiveDateTime) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Problème d'une valeur et correction proposée, `None` si la valeur est correcte
+fn check_value(spec: &ColumnSpec, value: Option<&str>) -> Option<(DoctorIssueKind, String, Option<DoctorFix>)> {
+    let set = |value: String| Some(DoctorFix::SetValue { value });
+    // Date de création ou de modification illisible: remplacée par l'instant présent
+    let timestamp = || {
+        let now = chrono::Local::now().naive_local();
+        matches!(spec.name, "created_at" | "updated_at").then(|| format_datetime(now.with_nanosecond(0).unwrap_or(now)))
+    };
+    let Some(value) = value else {
+        return (!spec.nullable).then(|| {
+            let fix = if matches!(spec.check, Check::DateTime) { timestamp().and_then(set) } else { None };
+            (DoctorIssueKind::MissingValue, "valeur manquante".to_string(), fix)
+        });
+    };
+
+    match spec.check {
+        Check::Uuid | Check::LinkedUuid => {
+            let canonical = value.trim().parse::<uuid::Uuid>().ok().map(|id| id.to_string());
+            if canonical.as_deref() == Some(value) {
+                return None;
+            }
+            let fix = match (canonical, spec.check) {
+                (Some(id), _) => set(id),
+                (None, Check::Uuid) => set(uuid::Uuid::new_v4().to_string()),
+                (None, _) => None,
+            };
+            Some((DoctorIssueKind::InvalidUuid, format!("UUID invalide '{}'", value), fix))
+        }
+        Check::Date => {
+            if parse_db_date(value).is_some_and(|d| d.format("%Y-%m-%d").to_string() == value) {
+                return None;
+            }
+            let fix = lenient_date(value).map(|d| d.format("%Y-%m-%d").to_string());
+            Some((DoctorIssueKind::InvalidDate, format!("date invalide '{}' (AAAA-MM-JJ attendu)", value), fix.and_then(set)))
+        }
+        Check::DateTime => {
+            // Les plannings gardent les fractions de seconde de `NaiveDateTime::to_string`
+            if parse_db_datetime(value).is_some_and(|dt| format_datetime(dt) == value || dt.to_string() == value) {
+                return None;
+            }
+            let fix = lenient_datetime(value).map(format_datetime).or_else(timestamp);
+            Some((DoctorIssueKind::InvalidDateTime, format!("date et heure invalides '{}'", value), fix.and_then(set)))
+        }
+        Check::Enum(allowed) => {
+            if allowed.contains(&value) {
+                return None;
+            }
+            let fix = allowed.iter().find(|a| a.eq_ignore_ascii_case(value.trim())).map(|a| a.to_string());
+            Some((
+                DoctorIssueKind::InvalidEnum,
+                format!("valeur '{}' inconnue ({} attendu)", value, allowed.join(", ")),
+                fix.and_then(set),
+            ))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DoctorRepo for SqliteDoctorRepo {
+    async fn scan_database(&self) -> DomainResult<DoctorScan> {
+        let mut scan = DoctorScan { rows_scanned: 0, issues: Vec::new() };
+        for table in TABLES {
+            if self.table_exists(table.name).await? {
+                scan.rows_scanned += self.scan_table(table, &mut scan.issues).await?;
+            }
+        }
+        self.scan_operation_amounts(&mut scan.issues).await?;
+        self.scan_orphan_month_plannings(&mut scan.issues).await?;
+        Ok(scan)
+    }
+
+    async fn apply_fix(&self, issue: &DoctorIssue) -> DomainResult<()> {
+        let fix = issue.fix.as_ref()
+            .ok_or_else(|| DomainError::Validation(format!("{}: pas de correction automatique", issue.message)))?;
+        // Table et colonne viennent de l'appelant: seules celles connues du diagnostic sont acceptées
+        let table = TABLES.iter().find(|t| t.name == issue.table && !t.read_only)
+            .ok_or_else(|| DomainError::Validation(format!("Table non réparable: {}", issue.table)))?;
+
+        let result = match fix {
+            DoctorFix::SetValue { value } => {
+                let column = issue.column.as_deref()
+                    .filter(|c| table.columns.iter().any(|s| s.name == *c) || (table.name == "operations" && *c == OPERATION_TTC_COLUMN))
+                    .ok_or_else(|| DomainError::Validation(format!("Colonne non réparable: {}.{}", table.name, issue.column.as_deref().unwrap_or(""))))?;
+                sqlx::query(&format!("UPDATE {0} SET {1} = ? WHERE rowid = ? AND CAST({1} AS TEXT) IS ?", table.name, column))
+                    .bind(value)
+                    .bind(issue.rowid)
+                    .bind(issue.value.as_deref())
+                    .execute(&self.pool).await
+            }
+            DoctorFix::DeleteRow if issue.kind == DoctorIssueKind::OrphanRow => {
+                sqlx::query(&format!("DELETE FROM {} WHERE rowid = ?", table.name))
+                    .bind(issue.rowid)
+                    .execute(&self.pool).await
+            }
+            DoctorFix::DeleteRow => return Err(DomainError::Validation(format!("{}: suppression refusée", issue.message))),
+        }.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(DomainError::Validation(format!(
+                "{} (ligne {}) a changé depuis le diagnostic: relancez-le", issue.table, issue.row_id,
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_and_migrate;
+    use domain::OperationRepo;
+
+    #[tokio::test]
+    async fn test_doctor_finds_and_repairs_malformed_rows() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let id = uuid::Uuid::new_v4();
+        sqlx::query(r#"
+            INSERT INTO operations (id, invoice_date, payment_date, type, amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+                                    vat_on_payments, label, created_at, updated_at)
+            VALUES (?, '05/03/2024', NULL, 'sale', 10000, 2000, 11000, 1, 'Saisie manuelle', '2024-03-05T10:00:00Z', '2024-03-05 10:00:00')
+        "#)
+            .bind(id.to_string().to_uppercase())
+            .execute(&repos.pool).await.unwrap();
+        // Mois orphelin, laissé par une suppression faite sans clés étrangères
+        let mut conn = repos.pool.acquire().await.unwrap();
+        sqlx::query("INSERT INTO yearly_planning (id, year, created_at, updated_at) VALUES (?, 2031, '2031-01-01 00:00:00', '2031-01-01 00:00:00')")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .execute(&mut *conn).await.unwrap();
+        sqlx::query("INSERT INTO month_planning (id, year, month, created_at, updated_at) VALUES (?, 2031, 1, '2031-01-01 00:00:00', '2031-01-01 00:00:00')")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .execute(&mut *conn).await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await.unwrap();
+        sqlx::query("DELETE FROM yearly_planning WHERE year = 2031").execute(&mut *conn).await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await.unwrap();
+        drop(conn);
+
+        // Une ligne malformée donne une erreur typée, plus une panique
+        let error = repos.operations().list_operations(None).await.unwrap_err();
+        match error {
+            DomainError::InvalidData(field) => {
+                assert_eq!(field.table, "operations");
+                assert_eq!(field.column, "invoice_date");
+                assert_eq!(field.value, "05/03/2024");
+            }
+            other => panic!("erreur inattendue: {other}"),
+        }
+
+        let doctor = repos.doctor();
+        let scan = doctor.scan_database().await.unwrap();
+        let kinds: Vec<_> = scan.issues.iter().map(|i| i.kind).collect();
+        assert!(kinds.contains(&DoctorIssueKind::InvalidUuid));
+        assert!(kinds.contains(&DoctorIssueKind::InvalidDate));
+        assert!(kinds.contains(&DoctorIssueKind::InvalidDateTime));
+        assert!(kinds.contains(&DoctorIssueKind::AmountMismatch));
+        assert!(kinds.contains(&DoctorIssueKind::OrphanRow));
+        assert!(scan.issues.iter().all(|i| i.fix.is_some()));
+
+        for issue in &scan.issues {
+            doctor.apply_fix(issue).await.unwrap();
+        }
+        // Une correction déjà appliquée n'est pas rejouée sur une valeur qui a changé
+        assert!(doctor.apply_fix(&scan.issues[0]).await.is_err());
+        assert!(doctor.scan_database().await.unwrap().issues.is_empty());
+
+        let operation = repos.operations().get_operation(id).await.unwrap();
+        assert_eq!(operation.invoice_date, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+        assert_eq!(operation.amount_ttc_cents, 12000);
+        assert_eq!(operation.created_at, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap().and_hms_opt(10, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,754 @@
+use chrono::NaiveDateTime;
+use domain::{DomainResult, InvoiceClient, IssuedInvoice, IssuerProfile, VatBreakdown};
+
+use crate::{embed_facturx_xml, render_invoice_pdf};
+
+pub const CII_GUIDELINE_EN16931: &str = "urn:cen.eu:en16931:2017";
+pub const FACTURX_CONFORMANCE_EN16931: &str = "EN 16931";
+const EXEMPTION_REASON: &str = "TVA non applicable, art. 293 B du CGI";
+const EXEMPTION_CODE: &str = "VATEX-FR-FRANCHISE";
+
+/// PDF Factur-X d'une facture émise: rendu visuel + XML CII attaché
+pub fn render_facturx_invoice(invoice: &IssuedInvoice, modified: NaiveDateTime) -> DomainResult<Vec<u8>> {
+    let pdf = render_invoice_pdf(invoice)?;
+    embed_facturx_xml(&pdf, invoice_to_cii_xml(invoice).as_bytes(), FACTURX_CONFORMANCE_EN16931, modified)
+}
+
+/// XML CII D16B (profil Factur-X EN 16931) d'une facture émise
+pub fn invoice_to_cii_xml(invoice: &IssuedInvoice) -> String {
+    let mut xml = XmlWriter::new();
+    xml.open("rsm:CrossIndustryInvoice", &[
+        ("xmlns:rsm", "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100"),
+        ("xmlns:qdt", "urn:un:unece:uncefact:data:standard:QualifiedDataType:100"),
+        ("xmlns:ram", "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100"),
+        ("xmlns:udt", "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100"),
+    ]);
+
+    xml.open("rsm:ExchangedDocumentContext", &[]);
+    xml.open("ram:GuidelineSpecifiedDocumentContextParameter", &[]);
+    xml.leaf("ram:ID", &[], CII_GUIDELINE_EN16931);
+    xml.close();
+    xml.close();
+
+    xml.open("rsm:ExchangedDocument", &[]);
+    xml.leaf("ram:ID", &[], &invoice.number);
+    xml.leaf("ram:TypeCode", &[], "380");
+    cii_date(&mut xml, "ram:IssueDateTime", invoice.issue_date);
+    for mention in invoice.legal_mentions() {
+        xml.open("ram:IncludedNote", &[]);
+        xml.leaf("ram:Content", &[], &mention);
+        xml.close();
+    }
+    xml.close();
+
+    xml.open("rsm:SupplyChainTradeTransaction", &[]);
+    for (index, line) in invoice.lines.iter().enumerate() {
+        let vat = line_vat(invoice, line.vat_rate_ppm);
+        xml.open("ram:IncludedSupplyChainTradeLineItem", &[]);
+        xml.open("ram:AssociatedDocumentLineDocument", &[]);
+        xml.leaf("ram:LineID", &[], &(index + 1).to_string());
+        xml.close();
+        xml.open("ram:SpecifiedTradeProduct", &[]);
+        xml.leaf("ram:Name", &[], &line.description);
+        xml.close();
+        xml.open("ram:SpecifiedLineTradeAgreement", &[]);
+        xml.open("ram:NetPriceProductTradePrice", &[]);
+        xml.leaf("ram:ChargeAmount", &[], &amount(line.unit_price_ht_cents));
+        xml.close();
+        xml.close();
+        xml.open("ram:SpecifiedLineTradeDelivery", &[]);
+        xml.leaf("ram:BilledQuantity", &[("unitCode", "C62")], &quantity(line.quantity_milli));
+        xml.close();
+        xml.open("ram:SpecifiedLineTradeSettlement", &[]);
+        xml.open("ram:ApplicableTradeTax", &[]);
+        xml.leaf("ram:TypeCode", &[], "VAT");
+        xml.leaf("ram:CategoryCode", &[], &vat.category_code);
+        xml.leaf("ram:RateApplicablePercent", &[], &percent(vat.rate_ppm));
+        xml.close();
+        xml.open("ram:SpecifiedTradeSettlementLineMonetarySummation", &[]);
+        xml.leaf("ram:LineTotalAmount", &[], &amount(line.total_ht_cents()));
+        xml.close();
+        xml.close();
+        xml.close();
+    }
+
+    xml.open("ram:ApplicableHeaderTradeAgreement", &[]);
+    cii_party(&mut xml, "ram:SellerTradeParty", &issuer_party(&invoice.issuer));
+    cii_party(&mut xml, "ram:BuyerTradeParty", &client_party(&invoice.client));
+    xml.close();
+
+    xml.open("ram:ApplicableHeaderTradeDelivery", &[]);
+    xml.close();
+
+    xml.open("ram:ApplicableHeaderTradeSettlement", &[]);
+    xml.leaf("ram:PaymentReference", &[], &invoice.number);
+    xml.leaf("ram:InvoiceCurrencyCode", &[], "EUR");
+    if let Some(iban) = &invoice.issuer.iban {
+        xml.open("ram:SpecifiedTradeSettlementPaymentMeans", &[]);
+        xml.leaf("ram:TypeCode", &[], "58");
+        xml.open("ram:PayeePartyCreditorFinancialAccount", &[]);
+        xml.leaf("ram:IBANID", &[], &iban.replace(' ', ""));
+        xml.close();
+        xml.close();
+    }
+    for vat in &invoice.vat_breakdown {
+        xml.open("ram:ApplicableTradeTax", &[]);
+        xml.leaf("ram:CalculatedAmount", &[], &amount(vat.vat_cents));
+        xml.leaf("ram:TypeCode", &[], "VAT");
+        if vat.category_code == "E" {
+            xml.leaf("ram:ExemptionReason", &[], EXEMPTION_REASON);
+        }
+        xml.leaf("ram:BasisAmount", &[], &amount(vat.basis_ht_cents));
+        xml.leaf("ram:CategoryCode", &[], &vat.category_code);
+        if vat.category_code == "E" {
+            xml.leaf("ram:ExemptionReasonCode", &[], EXEMPTION_CODE);
+        }
+        // 72: TVA exigible au paiement (prestations), 5: à la date de facture
+        xml.leaf("ram:DueDateTypeCode", &[], if vat.vat_on_payments { "72" } else { "5" });
+        xml.leaf("ram:RateApplicablePercent", &[], &percent(vat.rate_ppm));
+        xml.close();
+    }
+    xml.open("ram:SpecifiedTradePaymentTerms", &[]);
+    cii_date(&mut xml, "ram:DueDateDateTime", invoice.due_date);
+    xml.close();
+    xml.open("ram:SpecifiedTradeSettlementHeaderMonetarySummation", &[]);
+    xml.leaf("ram:LineTotalAmount", &[], &amount(invoice.total_ht_cents));
+    xml.leaf("ram:TaxBasisTotalAmount", &[], &amount(invoice.total_ht_cents));
+    xml.leaf("ram:TaxTotalAmount", &[("currencyID", "EUR")], &amount(invoice.total_vat_cents));
+    xml.leaf("ram:GrandTotalAmount", &[], &amount(invoice.total_ttc_cents));
+    xml.leaf("ram:DuePayableAmount", &[], &amount(invoice.total_ttc_cents));
+    xml.close();
+    xml.close();
+
+    xml.close();
+    xml.close();
+    xml.finish()
+}
+
+/// XML UBL 2.1 (Invoice, CustomizationID EN 16931) d'une facture émise
+pub fn invoice_to_ubl_xml(invoice: &IssuedInvoice) -> String {
+    let eur = [("currencyID", "EUR")];
+    let mut xml = XmlWriter::new();
+    xml.open("Invoice", &[
+        ("xmlns", "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"),
+        ("xmlns:cac", "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2"),
+        ("xmlns:cbc", "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2"),
+    ]);
+    xml.leaf("cbc:UBLVersionID", &[], "2.1");
+    xml.leaf("cbc:CustomizationID", &[], CII_GUIDELINE_EN16931);
+    xml.leaf("cbc:ID", &[], &invoice.number);
+    xml.leaf("cbc:IssueDate", &[], &invoice.issue_date.format("%Y-%m-%d").to_string());
+    xml.leaf("cbc:DueDate", &[], &invoice.due_date.format("%Y-%m-%d").to_string());
+    xml.leaf("cbc:InvoiceTypeCode", &[], "380");
+    for mention in invoice.legal_mentions() {
+        xml.leaf("cbc:Note", &[], &mention);
+    }
+    xml.leaf("cbc:DocumentCurrencyCode", &[], "EUR");
+
+    xml.open("cac:AccountingSupplierParty", &[]);
+    ubl_party(&mut xml, &issuer_party(&invoice.issuer));
+    xml.close();
+    xml.open("cac:AccountingCustomerParty", &[]);
+    ubl_party(&mut xml, &client_party(&invoice.client));
+    xml.close();
+
+    xml.open("cac:PaymentMeans", &[]);
+    xml.leaf("cbc:PaymentMeansCode", &[], if invoice.issuer.iban.is_some() { "58" } else { "30" });
+    xml.leaf("cbc:PaymentID", &[], &invoice.number);
+    if let Some(iban) = &invoice.issuer.iban {
+        xml.open("cac:PayeeFinancialAccount", &[]);
+        xml.leaf("cbc:ID", &[], &iban.replace(' ', ""));
+        xml.close();
+    }
+    xml.close();
+
+    xml.open("cac:TaxTotal", &[]);
+    xml.leaf("cbc:TaxAmount", &eur, &amount(invoice.total_vat_cents));
+    for vat in &invoice.vat_breakdown {
+        xml.open("cac:TaxSubtotal", &[]);
+        xml.leaf("cbc:TaxableAmount", &eur, &amount(vat.basis_ht_cents));
+        xml.leaf("cbc:TaxAmount", &eur, &amount(vat.vat_cents));
+        ubl_tax_category(&mut xml, "cac:TaxCategory", vat);
+        xml.close();
+    }
+    xml.close();
+
+    xml.open("cac:LegalMonetaryTotal", &[]);
+    xml.leaf("cbc:LineExtensionAmount", &eur, &amount(invoice.total_ht_cents));
+    xml.leaf("cbc:TaxExclusiveAmount", &eur, &amount(invoice.total_ht_cents));
+    xml.leaf("cbc:TaxInclusiveAmount", &eur, &amount(invoice.total_ttc_cents));
+    xml.leaf("cbc:PayableAmount", &eur, &amount(invoice.total_ttc_cents));
+    xml.close();
+
+    for (index, line) in invoice.lines.iter().enumerate() {
+        xml.open("cac:InvoiceLine", &[]);
+        xml.leaf("cbc:ID", &[], &(index + 1).to_string());
+        xml.leaf("cbc:InvoicedQuantity", &[("unitCode", "C62")], &quantity(line.quantity_milli));
+        xml.leaf("cbc:LineExtensionAmount", &eur, &amount(line.total_ht_cents()));
+        xml.open("cac:Item", &[]);
+        xml.leaf("cbc:Name", &[], &line.description);
+        ubl_tax_category(&mut xml, "cac:ClassifiedTaxCategory", &line_vat(invoice, line.vat_rate_ppm));
+        xml.close();
+        xml.open("cac:Price", &[]);
+        xml.leaf("cbc:PriceAmount", &eur, &amount(line.unit_price_ht_cents));
+        xml.close();
+        xml.close();
+    }
+
+    xml.close();
+    xml.finish()
+}
+
+/// Partie (vendeur ou acheteur) commune aux deux syntaxes
+struct Party {
+    name: String,
+    siren: Option<String>,
+    vat_number: Option<String>,
+    address_lines: Vec<String>,
+    postcode: Option<String>,
+    city: Option<String>,
+    email: Option<String>,
+}
+
+fn issuer_party(issuer: &IssuerProfile) -> Party {
+    let mut party = party(&issuer.name, &issuer.address, Some(&issuer.siret), issuer.vat_number.as_deref().filter(|_| !issuer.vat_exempt));
+    party.email = issuer.email.clone();
+    party
+}
+
+fn client_party(client: &InvoiceClient) -> Party {
+    party(&client.name, &client.address, client.siret.as_deref(), client.vat_number.as_deref())
+}
+
+/// Adresse libre: la dernière ligne "75002 Paris" donne le code postal et la ville, le pays est la France
+fn party(name: &str, address: &str, siret: Option<&str>, vat_number: Option<&str>) -> Party {
+    let mut address_lines: Vec<String> = address.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect();
+    let (postcode, city) = match address_lines.last().and_then(|last| last.split_once(' ')) {
+        Some((code, city)) if code.len() == 5 && code.chars().all(|c| c.is_ascii_digit()) => (Some(code.to_string()), Some(city.trim().to_string())),
+        _ => (None, None),
+    };
+    if postcode.is_some() {
+        address_lines.pop();
+    }
+    Party {
+        name: name.to_string(),
+        siren: siret.filter(|s| s.len() >= 9).map(|s| s[..9].to_string()),
+        vat_number: vat_number.map(str::to_string),
+        address_lines,
+        postcode,
+        city,
+        email: None,
+    }
+}
+
+fn cii_party(xml: &mut XmlWriter, tag: &str, party: &Party) {
+    xml.open(tag, &[]);
+    xml.leaf("ram:Name", &[], &party.name);
+    if let Some(siren) = &party.siren {
+        xml.open("ram:SpecifiedLegalOrganization", &[]);
+        xml.leaf("ram:ID", &[("schemeID", "0002")], siren);
+        xml.close();
+    }
+    xml.open("ram:PostalTradeAddress", &[]);
+    if let Some(postcode) = &party.postcode {
+        xml.leaf("ram:PostcodeCode", &[], postcode);
+    }
+    for (line, tag) in party.address_lines.iter().zip(["ram:LineOne", "ram:LineTwo", "ram:LineThree"]) {
+        xml.leaf(tag, &[], line);
+    }
+    if let Some(city) = &party.city {
+        xml.leaf("ram:CityName", &[], city);
+    }
+    xml.leaf("ram:CountryID", &[], "FR");
+    xml.close();
+    if let Some(email) = &party.email {
+        xml.open("ram:URIUniversalCommunication", &[]);
+        xml.leaf("ram:URIID", &[("schemeID", "EM")], email);
+        xml.close();
+    }
+    if let Some(vat_number) = &party.vat_number {
+        xml.open("ram:SpecifiedTaxRegistration", &[]);
+        xml.leaf("ram:ID", &[("schemeID", "VA")], vat_number);
+        xml.close();
+    }
+    xml.close();
+}
+
+fn ubl_party(xml: &mut XmlWriter, party: &Party) {
+    xml.open("cac:Party", &[]);
+    if let Some(email) = &party.email {
+        xml.leaf("cbc:EndpointID", &[("schemeID", "EM")], email);
+    }
+    xml.open("cac:PartyName", &[]);
+    xml.leaf("cbc:Name", &[], &party.name);
+    xml.close();
+    xml.open("cac:PostalAddress", &[]);
+    if let Some(line) = party.address_lines.first() {
+        xml.leaf("cbc:StreetName", &[], line);
+    }
+    if let Some(line) = party.address_lines.get(1) {
+        xml.leaf("cbc:AdditionalStreetName", &[], line);
+    }
+    if let Some(city) = &party.city {
+        xml.leaf("cbc:CityName", &[], city);
+    }
+    if let Some(postcode) = &party.postcode {
+        xml.leaf("cbc:PostalZone", &[], postcode);
+    }
+    xml.open("cac:Country", &[]);
+    xml.leaf("cbc:IdentificationCode", &[], "FR");
+    xml.close();
+    xml.close();
+    if let Some(vat_number) = &party.vat_number {
+        xml.open("cac:PartyTaxScheme", &[]);
+        xml.leaf("cbc:CompanyID", &[], vat_number);
+        xml.open("cac:TaxScheme", &[]);
+        xml.leaf("cbc:ID", &[], "VAT");
+        xml.close();
+        xml.close();
+    }
+    xml.open("cac:PartyLegalEntity", &[]);
+    xml.leaf("cbc:RegistrationName", &[], &party.name);
+    if let Some(siren) = &party.siren {
+        xml.leaf("cbc:CompanyID", &[("schemeID", "0002")], siren);
+    }
+    xml.close();
+    xml.close();
+}
+
+fn ubl_tax_category(xml: &mut XmlWriter, tag: &str, vat: &VatBreakdown) {
+    xml.open(tag, &[]);
+    xml.leaf("cbc:ID", &[], &vat.category_code);
+    xml.leaf("cbc:Percent", &[], &percent(vat.rate_ppm));
+    if vat.category_code == "E" && tag == "cac:TaxCategory" {
+        xml.leaf("cbc:TaxExemptionReasonCode", &[], EXEMPTION_CODE);
+        xml.leaf("cbc:TaxExemptionReason", &[], EXEMPTION_REASON);
+    }
+    xml.open("cac:TaxScheme", &[]);
+    xml.leaf("cbc:ID", &[], "VAT");
+    xml.close();
+    xml.close();
+}
+
+fn cii_date(xml: &mut XmlWriter, tag: &str, date: chrono::NaiveDate) {
+    xml.open(tag, &[]);
+    xml.leaf("udt:DateTimeString", &[("format", "102")], &date.format("%Y%m%d").to_string());
+    xml.close();
+}
+
+/// Catégorie de TVA d'une ligne, cohérente avec la ventilation de la facture
+fn line_vat(invoice: &IssuedInvoice, rate_ppm: i32) -> VatBreakdown {
+    let rate_ppm = if invoice.issuer.vat_exempt { 0 } else { rate_ppm };
+    invoice.vat_breakdown.iter()
+        .find(|vat| vat.rate_ppm == rate_ppm)
+        .cloned()
+        .unwrap_or(VatBreakdown { rate_ppm, basis_ht_cents: 0, vat_cents: 0, category_code: "S".to_string(), vat_on_payments: false })
+}
+
+/// 123456 -> "1234.56"
+fn amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.unsigned_abs() / 100, cents.unsigned_abs() % 100)
+}
+
+/// 2500 -> "2.500"
+fn quantity(quantity_milli: i64) -> String {
+    let sign = if quantity_milli < 0 { "-" } else { "" };
+    format!("{}{}.{:03}", sign, quantity_milli.unsigned_abs() / 1000, quantity_milli.unsigned_abs() % 1000)
+}
+
+/// 200000 ppm -> "20.00", 55000 -> "5.50"
+fn percent(rate_ppm: i32) -> String {
+    if rate_ppm % 100 == 0 {
+        format!("{}.{:02}", rate_ppm / 10_000, (rate_ppm % 10_000) / 100)
+    } else {
+        format!("{}.{:04}", rate_ppm / 10_000, rate_ppm % 10_000)
+    }
+}
+
+/// Écriture XML indentée, sans dépendance
+struct XmlWriter {
+    out: String,
+    stack: Vec<String>,
+}
+
+impl XmlWriter {
+    fn new() -> Self {
+        Self { out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"), stack: Vec::new() }
+    }
+
+    fn start_tag(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.stack.len()));
+        self.out.push('<');
+        self.out.push_str(tag);
+        for (name, value) in attributes {
+            self.out.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+        }
+        self.out.push('>');
+    }
+
+    fn open(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.start_tag(tag, attributes);
+        self.out.push('\n');
+        self.stack.push(tag.to_string());
+    }
+
+    fn leaf(&mut self, tag: &str, attributes: &[(&str, &str)], text: &str) {
+        self.start_tag(tag, attributes);
+        self.out.push_str(&escape(text));
+        self.out.push_str(&format!("</{}>\n", tag));
+    }
+
+    fn close(&mut self) {
+        if let Some(tag) = self.stack.pop() {
+            self.out.push_str(&"  ".repeat(self.stack.len()));
+            self.out.push_str(&format!("</{}>\n", tag));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        while !self.stack.is_empty() {
+            self.close();
+        }
+        self.out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use domain::{build_issued_invoice, InvoiceDraft, InvoiceLine};
+    use roxmltree::{Document, Node};
+
+    const CII_NAMESPACES: [&str; 3] = [
+        "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100",
+        "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100",
+        "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100",
+    ];
+    const UBL_NAMESPACES: [&str; 3] = [
+        "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2",
+        "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2",
+        "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2",
+    ];
+
+    /// Ordre des éléments repris à la main des XSD CII D16B, pour les seuls éléments produits, "!" = obligatoire
+    /// Ne remplace pas une validation par les XSD officiels, absents du dépôt
+    const CII_ELEMENT_ORDER: &[(&str, &[&str])] = &[
+        ("CrossIndustryInvoice", &["ExchangedDocumentContext!", "ExchangedDocument!", "SupplyChainTradeTransaction!"]),
+        ("ExchangedDocumentContext", &["TestIndicator", "BusinessProcessSpecifiedDocumentContextParameter", "GuidelineSpecifiedDocumentContextParameter!"]),
+        ("GuidelineSpecifiedDocumentContextParameter", &["ID!"]),
+        ("ExchangedDocument", &["ID!", "Name", "TypeCode!", "IssueDateTime!", "CopyIndicator", "LanguageID", "IncludedNote", "EffectiveSpecifiedPeriod"]),
+        ("IssueDateTime", &["DateTimeString!"]),
+        ("DueDateDateTime", &["DateTimeString!"]),
+        ("IncludedNote", &["ContentCode", "Content", "SubjectCode"]),
+        ("SupplyChainTradeTransaction", &["IncludedSupplyChainTradeLineItem", "ApplicableHeaderTradeAgreement!", "ApplicableHeaderTradeDelivery!", "ApplicableHeaderTradeSettlement!"]),
+        ("IncludedSupplyChainTradeLineItem", &["AssociatedDocumentLineDocument!", "SpecifiedTradeProduct", "SpecifiedLineTradeAgreement", "SpecifiedLineTradeDelivery", "SpecifiedLineTradeSettlement!"]),
+        ("AssociatedDocumentLineDocument", &["LineID!", "IncludedNote"]),
+        ("SpecifiedTradeProduct", &["ID", "GlobalID", "SellerAssignedID", "BuyerAssignedID", "Name", "Description"]),
+        ("SpecifiedLineTradeAgreement", &["BuyerOrderReferencedDocument", "GrossPriceProductTradePrice", "NetPriceProductTradePrice"]),
+        ("NetPriceProductTradePrice", &["ChargeAmount!", "BasisQuantity"]),
+        ("SpecifiedLineTradeDelivery", &["BilledQuantity"]),
+        ("SpecifiedLineTradeSettlement", &["ApplicableTradeTax", "BillingSpecifiedPeriod", "SpecifiedTradeAllowanceCharge", "SpecifiedTradeSettlementLineMonetarySummation!"]),
+        ("ApplicableTradeTax", &["CalculatedAmount", "TypeCode!", "ExemptionReason", "BasisAmount", "LineTotalBasisAmount", "AllowanceChargeBasisAmount", "CategoryCode!", "ExemptionReasonCode", "TaxPointDate", "DueDateTypeCode", "RateApplicablePercent"]),
+        ("SpecifiedTradeSettlementLineMonetarySummation", &["LineTotalAmount!", "ChargeTotalAmount", "AllowanceTotalAmount"]),
+        ("ApplicableHeaderTradeAgreement", &["BuyerReference", "SellerTradeParty!", "BuyerTradeParty!"]),
+        ("SellerTradeParty", &["ID", "GlobalID", "Name", "RoleCode", "Description", "SpecifiedLegalOrganization", "DefinedTradeContact", "PostalTradeAddress", "URIUniversalCommunication", "SpecifiedTaxRegistration"]),
+        ("BuyerTradeParty", &["ID", "GlobalID", "Name", "RoleCode", "Description", "SpecifiedLegalOrganization", "DefinedTradeContact", "PostalTradeAddress", "URIUniversalCommunication", "SpecifiedTaxRegistration"]),
+        ("SpecifiedLegalOrganization", &["ID", "TradingBusinessName", "PostalTradeAddress"]),
+        ("PostalTradeAddress", &["PostcodeCode", "LineOne", "LineTwo", "LineThree", "CityName", "CountryID!", "CountrySubDivisionName"]),
+        ("URIUniversalCommunication", &["URIID!"]),
+        ("SpecifiedTaxRegistration", &["ID!"]),
+        ("ApplicableHeaderTradeDelivery", &["ShipToTradeParty", "ActualDeliverySupplyChainEvent", "DespatchAdviceReferencedDocument", "ReceivingAdviceReferencedDocument"]),
+        ("ApplicableHeaderTradeSettlement", &["CreditorReferenceID", "PaymentReference", "TaxCurrencyCode", "InvoiceCurrencyCode!", "PayeeTradeParty", "SpecifiedTradeSettlementPaymentMeans", "ApplicableTradeTax", "BillingSpecifiedPeriod", "SpecifiedTradeAllowanceCharge", "SpecifiedTradePaymentTerms", "SpecifiedTradeSettlementHeaderMonetarySummation!", "InvoiceReferencedDocument", "ReceivableSpecifiedTradeAccountingAccount"]),
+        ("SpecifiedTradeSettlementPaymentMeans", &["TypeCode!", "Information", "ApplicableTradeSettlementFinancialCard", "PayerPartyDebtorFinancialAccount", "PayeePartyCreditorFinancialAccount", "PayeeSpecifiedCreditorFinancialInstitution"]),
+        ("PayeePartyCreditorFinancialAccount", &["IBANID", "AccountName", "ProprietaryID"]),
+        ("SpecifiedTradePaymentTerms", &["Description", "DueDateDateTime", "DirectDebitMandateID"]),
+        ("SpecifiedTradeSettlementHeaderMonetarySummation", &["LineTotalAmount", "ChargeTotalAmount", "AllowanceTotalAmount", "TaxBasisTotalAmount!", "TaxTotalAmount", "RoundingAmount", "GrandTotalAmount!", "TotalPrepaidAmount", "DuePayableAmount!"]),
+    ];
+
+    /// Ordre des éléments repris à la main des XSD UBL 2.1, pour les seuls éléments produits, "!" = obligatoire
+    const UBL_ELEMENT_ORDER: &[(&str, &[&str])] = &[
+        ("Invoice", &["UBLVersionID", "CustomizationID", "ProfileID", "ID!", "IssueDate!", "DueDate", "InvoiceTypeCode", "Note", "DocumentCurrencyCode", "BuyerReference", "AccountingSupplierParty!", "AccountingCustomerParty!", "PaymentMeans", "PaymentTerms", "TaxTotal", "LegalMonetaryTotal!", "InvoiceLine!"]),
+        ("AccountingSupplierParty", &["CustomerAssignedAccountID", "Party"]),
+        ("AccountingCustomerParty", &["CustomerAssignedAccountID", "Party"]),
+        ("Party", &["EndpointID", "PartyIdentification", "PartyName", "PostalAddress", "PartyTaxScheme", "PartyLegalEntity", "Contact"]),
+        ("PartyName", &["Name!"]),
+        ("PostalAddress", &["StreetName", "AdditionalStreetName", "CityName", "PostalZone", "CountrySubentity", "AddressLine", "Country"]),
+        ("Country", &["IdentificationCode", "Name"]),
+        ("PartyTaxScheme", &["RegistrationName", "CompanyID", "TaxScheme!"]),
+        ("TaxScheme", &["ID"]),
+        ("PartyLegalEntity", &["RegistrationName", "CompanyID"]),
+        ("PaymentMeans", &["ID", "PaymentMeansCode!", "PaymentDueDate", "PaymentChannelCode", "InstructionID", "InstructionNote", "PaymentID", "CardAccount", "PayerFinancialAccount", "PayeeFinancialAccount"]),
+        ("PayeeFinancialAccount", &["ID", "Name"]),
+        ("TaxTotal", &["TaxAmount!", "RoundingAmount", "TaxEvidenceIndicator", "TaxIncludedIndicator", "TaxSubtotal"]),
+        ("TaxSubtotal", &["TaxableAmount", "TaxAmount!", "CalculationSequenceNumeric", "TransactionCurrencyTaxAmount", "Percent", "BaseUnitMeasure", "PerUnitAmount", "TierRange", "TierRatePercent", "TaxCategory!"]),
+        ("TaxCategory", &["ID", "Name", "Percent", "BaseUnitMeasure", "PerUnitAmount", "TaxExemptionReasonCode", "TaxExemptionReason", "TierRange", "TierRatePercent", "TaxScheme!"]),
+        ("ClassifiedTaxCategory", &["ID", "Name", "Percent", "BaseUnitMeasure", "PerUnitAmount", "TaxExemptionReasonCode", "TaxExemptionReason", "TierRange", "TierRatePercent", "TaxScheme!"]),
+        ("LegalMonetaryTotal", &["LineExtensionAmount", "TaxExclusiveAmount", "TaxInclusiveAmount", "AllowanceTotalAmount", "ChargeTotalAmount", "PrepaidAmount", "PayableRoundingAmount", "PayableAmount!"]),
+        ("InvoiceLine", &["ID!", "UUID", "Note", "InvoicedQuantity", "LineExtensionAmount!", "AccountingCost", "InvoicePeriod", "OrderLineReference", "Item!", "Price"]),
+        ("Item", &["Description", "PackQuantity", "PackSizeNumeric", "CatalogueIndicator", "Name", "SellersItemIdentification", "ClassifiedTaxCategory"]),
+        ("Price", &["PriceAmount!", "BaseQuantity"]),
+    ];
+
+    /// Vérifie l'ordre, la multiplicité minimale et les espaces de noms de chaque élément
+    fn check_element_order(node: Node, schema: &[(&str, &[&str])], namespaces: &[&str]) -> Result<(), String> {
+        let name = node.tag_name().name();
+        if !namespaces.contains(&node.tag_name().namespace().unwrap_or_default()) {
+            return Err(format!("{}: espace de noms inattendu", name));
+        }
+        let children: Vec<Node> = node.children().filter(|c| c.is_element()).collect();
+        let Some((_, sequence)) = schema.iter().find(|(parent, _)| *parent == name) else {
+            return match children.first() {
+                Some(child) => Err(format!("{}: élément simple avec un enfant {}", name, child.tag_name().name())),
+                None => Ok(()),
+            };
+        };
+        let mut position = 0;
+        for child in &children {
+            let child_name = child.tag_name().name();
+            match sequence[position..].iter().position(|s| s.trim_end_matches('!') == child_name) {
+                Some(offset) => position += offset,
+                None => return Err(format!("{}: {} hors séquence", name, child_name)),
+            }
+            check_element_order(*child, schema, namespaces)?;
+        }
+        for required in sequence.iter().filter(|s| s.ends_with('!')) {
+            let required = required.trim_end_matches('!');
+            if !children.iter().any(|c| c.tag_name().name() == required) {
+                return Err(format!("{}: {} manquant", name, required));
+            }
+        }
+        Ok(())
+    }
+
+    fn cents(node: Node) -> i64 {
+        crate::facturx::parse_decimal(node.text().unwrap_or_default(), 2).unwrap()
+    }
+
+    fn descendants<'a, 'input>(document: &'a Document<'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        document.descendants().filter(move |n| n.is_element() && n.tag_name().name() == name)
+    }
+
+    fn sample_invoice(vat_exempt: bool) -> IssuedInvoice {
+        let issuer = IssuerProfile {
+            name: "Jean Dupont EI".into(),
+            address: "1 rue de la Paix\n75002 Paris".into(),
+            siret: "12345678900012".into(),
+            vat_number: Some("FR12123456789".into()),
+            vat_exempt,
+            late_penalty_rate_ppm: 100_000,
+            payment_terms_days: 30,
+            iban: Some("FR76 3000 6000 0112 3456 7890 189".into()),
+            email: Some("jean@example.fr".into()),
+        };
+        let draft = InvoiceDraft {
+            issue_date: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            is_service: true,
+            client: InvoiceClient {
+                name: "Client & Fils SA".into(),
+                address: "Bâtiment B\n10 quai Perrache\n69002 Lyon".into(),
+                siret: Some("98765432100021".into()),
+                vat_number: Some("FR45987654321".into()),
+            },
+            lines: vec![
+                InvoiceLine { description: "Développement <backend>".into(), quantity_milli: 2_500, unit_price_ht_cents: 50_000, vat_rate_ppm: 200_000 },
+                InvoiceLine { description: "Formation".into(), quantity_milli: 1_000, unit_price_ht_cents: 33_333, vat_rate_ppm: 100_000 },
+            ],
+            notes: Some("Merci".into()),
+        };
+        build_issued_invoice(issuer, draft, 7, NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(9, 0, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_cii_xml_element_order_and_totals() {
+        for vat_exempt in [false, true] {
+            let invoice = sample_invoice(vat_exempt);
+            let xml = invoice_to_cii_xml(&invoice);
+            let document = Document::parse(&xml).unwrap();
+            check_element_order(document.root_element(), CII_ELEMENT_ORDER, &CII_NAMESPACES).unwrap();
+
+            // BR-CO-10: somme des lignes, BR-CO-14: somme des TVA, BR-CO-15: TTC = HT + TVA
+            let lines: i64 = descendants(&document, "SpecifiedTradeSettlementLineMonetarySummation")
+                .map(|n| cents(n.first_element_child().unwrap()))
+                .sum();
+            let summation = descendants(&document, "SpecifiedTradeSettlementHeaderMonetarySummation").next().unwrap();
+            let total = |name: &str| cents(summation.children().find(|c| c.tag_name().name() == name).unwrap());
+            let vat: i64 = descendants(&document, "CalculatedAmount").map(cents).sum();
+            assert_eq!(lines, total("LineTotalAmount"));
+            assert_eq!(vat, total("TaxTotalAmount"));
+            assert_eq!(total("TaxBasisTotalAmount") + total("TaxTotalAmount"), total("GrandTotalAmount"));
+            assert_eq!(total("GrandTotalAmount"), invoice.total_ttc_cents);
+            assert_eq!(descendants(&document, "ExemptionReasonCode").next().is_some(), vat_exempt);
+
+            let parsed = crate::parse_cii_invoice(xml.as_bytes()).unwrap();
+            assert_eq!(parsed.invoice_number, "F2024-0007");
+            assert_eq!(parsed.total_ttc_cents, invoice.total_ttc_cents);
+            assert_eq!(parsed.vat_breakdown, invoice.vat_breakdown);
+        }
+    }
+
+    #[test]
+    fn test_ubl_xml_element_order_and_totals() {
+        for vat_exempt in [false, true] {
+            let invoice = sample_invoice(vat_exempt);
+            let xml = invoice_to_ubl_xml(&invoice);
+            let document = Document::parse(&xml).unwrap();
+            check_element_order(document.root_element(), UBL_ELEMENT_ORDER, &UBL_NAMESPACES).unwrap();
+
+            // Les montants UBL portent obligatoirement leur devise
+            for node in document.descendants().filter(|n| n.is_element() && n.tag_name().name().ends_with("Amount")) {
+                assert_eq!(node.attribute("currencyID"), Some("EUR"), "{}", node.tag_name().name());
+            }
+            let lines: i64 = descendants(&document, "InvoiceLine")
+                .map(|line| cents(line.children().find(|c| c.tag_name().name() == "LineExtensionAmount").unwrap()))
+                .sum();
+            let monetary = descendants(&document, "LegalMonetaryTotal").next().unwrap();
+            let total = |name: &str| cents(monetary.children().find(|c| c.tag_name().name() == name).unwrap());
+            let tax_total = descendants(&document, "TaxTotal").next().unwrap();
+            let vat = cents(tax_total.first_element_child().unwrap());
+            let subtotals: i64 = descendants(&document, "TaxSubtotal")
+                .map(|s| cents(s.children().find(|c| c.tag_name().name() == "TaxAmount").unwrap()))
+                .sum();
+            assert_eq!(lines, total("LineExtensionAmount"));
+            assert_eq!(subtotals, vat);
+            assert_eq!(total("TaxExclusiveAmount") + vat, total("TaxInclusiveAmount"));
+            assert_eq!(total("PayableAmount"), invoice.total_ttc_cents);
+        }
+    }
+
+    /// Valide un XML contre un schéma via xmllint, le document passant par l'entrée standard
+    fn validate_with_xmllint(schema: &std::path::Path, xml: &str) -> Result<(), String> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+        let mut child = Command::new("xmllint")
+            .args(["--noout", "--nonet", "--schema"])
+            .arg(schema)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("xmllint introuvable: {e}"))?;
+        child.stdin.take().unwrap().write_all(xml.as_bytes()).map_err(|e| e.to_string())?;
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if output.status.success() { Ok(()) } else { Err(String::from_utf8_lossy(&output.stderr).into_owned()) }
+    }
+
+    #[test]
+    #[ignore] // Requires the official XSDs under tests/fixtures/xsd (see its README) and xmllint
+    fn test_xml_validates_against_official_schemas() {
+        // cargo test -p infra test_xml_validates_against_official_schemas -- --ignored
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/xsd");
+        let cii = fixtures.join("cii-d16b/CrossIndustryInvoice_100pD16B.xsd");
+        let ubl = fixtures.join("ubl-2.1/maindoc/UBL-Invoice-2.1.xsd");
+        for schema in [&cii, &ubl] {
+            assert!(schema.is_file(), "schéma absent: {}", schema.display());
+        }
+        for vat_exempt in [false, true] {
+            let invoice = sample_invoice(vat_exempt);
+            validate_with_xmllint(&cii, &invoice_to_cii_xml(&invoice)).unwrap_or_else(|e| panic!("CII: {e}"));
+            validate_with_xmllint(&ubl, &invoice_to_ubl_xml(&invoice)).unwrap_or_else(|e| panic!("UBL: {e}"));
+        }
+    }
+
+    #[test]
+    fn test_render_facturx_invoice() {
+        let invoice = sample_invoice(false);
+        let pdf = render_facturx_invoice(&invoice, invoice.created_at).unwrap();
+        let extracted = crate::read_facturx_invoice(&pdf).unwrap().unwrap();
+        assert_eq!(extracted.profile.as_deref(), Some(CII_GUIDELINE_EN16931));
+        assert_eq!(extracted.seller_name, "Jean Dupont EI");
+        assert_eq!(extracted.total_ht_cents, invoice.total_ht_cents);
+        assert_eq!(extracted.total_vat_cents, invoice.total_vat_cents);
+    }
+
+    /// Nombre de points du contour d'un glyphe (0 sans contour)
+    fn outline_points(face: &ttf_parser::Face, glyph: ttf_parser::GlyphId) -> usize {
+        struct Count(usize);
+        impl ttf_parser::OutlineBuilder for Count {
+            fn move_to(&mut self, _: f32, _: f32) { self.0 += 1 }
+            fn line_to(&mut self, _: f32, _: f32) { self.0 += 1 }
+            fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) { self.0 += 2 }
+            fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) { self.0 += 3 }
+            fn close(&mut self) {}
+        }
+        let mut count = Count(0);
+        face.outline_glyph(glyph, &mut count);
+        count.0
+    }
+
+    /// Exigences PDF/A-3B vérifiables sans validateur: en-tête binaire, OutputIntent ICC,
+    /// polices embarquées aux chasses cohérentes et métadonnées XMP alignées sur le dictionnaire Info
+    #[test]
+    fn test_facturx_invoice_is_pdfa3b() {
+        let invoice = sample_invoice(false);
+        let pdf = render_facturx_invoice(&invoice, invoice.created_at).unwrap();
+        let second_line = pdf.split(|&b| b == b'\n').nth(1).unwrap();
+        assert!(second_line.starts_with(b"%") && second_line.iter().filter(|&&b| b > 127).count() >= 4);
+
+        let document = lopdf::Document::load_mem(&pdf).unwrap();
+        assert!(document.trailer.get(b"ID").is_ok());
+        let catalog = document.catalog().unwrap();
+        let intents = catalog.get(b"OutputIntents").and_then(lopdf::Object::as_array).unwrap();
+        let intent = intents[0].as_dict().unwrap();
+        assert_eq!(intent.get(b"S").and_then(lopdf::Object::as_name_str).unwrap(), "GTS_PDFA1");
+        let (_, profile) = document.dereference(intent.get(b"DestOutputProfile").unwrap()).unwrap();
+        let profile = profile.as_stream().unwrap();
+        assert_eq!(profile.dict.get(b"N").and_then(lopdf::Object::as_i64).unwrap(), 3);
+        let icc = profile.decompressed_content().unwrap();
+        assert_eq!((&icc[12..20], &icc[36..40]), (b"mntrRGB ".as_slice(), b"acsp".as_slice()));
+
+        // Toutes les polices embarquées, chasses de /W identiques à celles du programme de police
+        let fonts: Vec<&lopdf::Dictionary> = document.objects.values()
+            .filter_map(|o| o.as_dict().ok())
+            .filter(|d| d.get(b"Type").and_then(lopdf::Object::as_name_str).ok() == Some("Font"))
+            .collect();
+        assert!(!fonts.is_empty());
+        for font in fonts {
+            match font.get(b"Subtype").and_then(lopdf::Object::as_name_str).unwrap() {
+                "Type0" => assert_eq!(font.get(b"Encoding").and_then(lopdf::Object::as_name_str).unwrap(), "Identity-H"),
+                "CIDFontType2" => {
+                    let (_, descriptor) = document.dereference(font.get(b"FontDescriptor").unwrap()).unwrap();
+                    let (_, program) = document.dereference(descriptor.as_dict().unwrap().get(b"FontFile2").unwrap()).unwrap();
+                    let program = program.as_stream().unwrap().decompressed_content().unwrap();
+                    let face = ttf_parser::Face::parse(&program, 0).unwrap();
+                    let original = match font.get(b"BaseFont").and_then(lopdf::Object::as_name_str).unwrap().ends_with("-Bold") {
+                        true => include_bytes!("../assets/DejaVuSans-Bold.ttf").as_slice(),
+                        false => include_bytes!("../assets/DejaVuSans.ttf").as_slice(),
+                    };
+                    let original = ttf_parser::Face::parse(original, 0).unwrap();
+                    let widths = font.get(b"W").and_then(lopdf::Object::as_array).unwrap();
+                    for pair in widths.chunks(2) {
+                        let glyph = ttf_parser::GlyphId(pair[0].as_i64().unwrap() as u16);
+                        let declared = pair[1].as_array().unwrap()[0].as_float().unwrap();
+                        let actual = face.glyph_hor_advance(glyph).unwrap() as f32 * 1000.0 / face.units_per_em() as f32;
+                        assert!((declared - actual).abs() <= 1.0, "glyphe {:?}: {} déclaré, {} dans la police", glyph, declared, actual);
+                        // Les contours des glyphes utilisés sont conservés, composants des lettres accentuées compris
+                        assert_eq!(outline_points(&face, glyph), outline_points(&original, glyph), "glyphe {:?}", glyph);
+                    }
+                }
+                other => panic!("police {} non embarquée", other),
+            }
+        }
+
+        let (_, metadata) = document.dereference(catalog.get(b"Metadata").unwrap()).unwrap();
+        let metadata = metadata.as_stream().unwrap();
+        assert!(metadata.dict.get(b"Filter").is_err());
+        let xmp = std::str::from_utf8(&metadata.content).unwrap();
+        assert!(xmp.contains("<pdfaid:part>3</pdfaid:part><pdfaid:conformance>B</pdfaid:conformance>"));
+        assert!(xmp.contains("<fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>"));
+        assert!(xmp.contains("<rdf:li>Jean Dupont EI</rdf:li>"));
+        assert!(xmp.contains("<pdf:Producer>JLA Cash Planner</pdf:Producer>"));
+        let info = document.get_dictionary(document.trailer.get(b"Info").unwrap().as_reference().unwrap()).unwrap();
+        assert_eq!(info.get(b"Producer").unwrap().as_str().unwrap(), b"JLA Cash Planner");
+        assert_eq!(info.get(b"ModDate").unwrap().as_str().unwrap(), b"D:20240315090000+00'00'");
+        assert!(xmp.contains("<xmp:ModifyDate>2024-03-15T09:00:00+00:00</xmp:ModifyDate>"));
+    }
+}
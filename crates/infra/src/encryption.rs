@@ -0,0 +1,264 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{Connection, Row};
+
+use crate::sqlite::{connect_with, SqliteRepos};
+
+/// Service sous lequel les clés de base sont rangées dans le trousseau (Secret Service, Keychain…)
+pub const KEYRING_SERVICE: &str = "jla-cash-planner";
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Clé SQLCipher: phrase secrète (dérivée par SQLCipher en PBKDF2-HMAC-SHA512 avec le sel du fichier)
+/// ou clé brute de 256 bits (trousseau), utilisée sans dérivation
+#[derive(Clone, PartialEq, Eq)]
+pub enum DatabaseKey {
+    Passphrase(String),
+    Raw([u8; 32]),
+}
+
+impl std::fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseKey::Passphrase(_) => f.write_str("DatabaseKey::Passphrase(***)"),
+            DatabaseKey::Raw(_) => f.write_str("DatabaseKey::Raw(***)"),
+        }
+    }
+}
+
+impl DatabaseKey {
+    /// Nouvelle clé brute aléatoire
+    pub fn generate() -> anyhow::Result<Self> {
+        let mut key = [0u8; 32];
+        getrandom::getrandom(&mut key).map_err(|e| anyhow!("Générateur aléatoire indisponible: {}", e))?;
+        Ok(DatabaseKey::Raw(key))
+    }
+
+    /// Valeur SQL de `PRAGMA key` / `ATTACH … KEY`
+    fn sql_value(&self) -> String {
+        match self {
+            DatabaseKey::Passphrase(passphrase) => sql_string(passphrase),
+            DatabaseKey::Raw(key) => format!("\"x'{}'\"", hex::encode_upper(key)),
+        }
+    }
+}
+
+/// Ouvre (ou crée) une base chiffrée puis applique les migrations
+pub async fn connect_encrypted_and_migrate(db_path: &str, key: &DatabaseKey) -> anyhow::Result<SqliteRepos> {
+    let opts = SqliteConnectOptions::from_str(db_path)?
+        .pragma("key", key.sql_value())
+        .create_if_missing(true);
+    connect_with(opts, Some(key.clone())).await
+}
+
+/// Vrai si le fichier existe et n'a pas l'en-tête SQLite en clair
+pub fn is_database_encrypted(path: &Path) -> anyhow::Result<bool> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        // Fichier vide: SQLite le traitera comme une base neuve en clair
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+    }
+}
+
+/// Vérifie qu'une clé (ou l'absence de clé) ouvre la base
+pub async fn check_database_key(path: &Path, key: Option<&DatabaseKey>) -> bool {
+    match open_single(path, key).await {
+        Ok(conn) => {
+            conn.close().await.ok();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Chiffre sur place une base en clair
+pub async fn encrypt_database(path: &Path, key: &DatabaseKey) -> anyhow::Result<()> {
+    reencrypt(path, None, Some(key)).await
+}
+
+/// Déchiffre sur place une base chiffrée
+pub async fn decrypt_database(path: &Path, key: &DatabaseKey) -> anyhow::Result<()> {
+    reencrypt(path, Some(key), None).await
+}
+
+/// Rechiffre la base avec une nouvelle clé
+pub async fn rotate_database_key(path: &Path, old_key: &DatabaseKey, new_key: &DatabaseKey) -> anyhow::Result<()> {
+    reencrypt(path, Some(old_key), Some(new_key)).await
+}
+
+/// Copie la base vers `<fichier>.part` avec `sqlcipher_export`, vérifie la copie puis remplace l'original.
+/// Aucune connexion ne doit être ouverte sur la base pendant l'opération.
+async fn reencrypt(path: &Path, from: Option<&DatabaseKey>, to: Option<&DatabaseKey>) -> anyhow::Result<()> {
+    let partial = partial_path(path);
+    let result = export_to(path, &partial, from, to).await.and_then(|_| {
+        std::fs::rename(&partial, path).context("Remplacement de la base impossible")
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+async fn export_to(path: &Path, partial: &Path, from: Option<&DatabaseKey>, to: Option<&DatabaseKey>) -> anyhow::Result<()> {
+    let _ = std::fs::remove_file(partial);
+
+    let mut conn = open_single(path, from).await?;
+    let user_version: i64 = sqlx::query("PRAGMA user_version").fetch_one(&mut conn).await?.get(0);
+    let target_key = to.map(DatabaseKey::sql_value).unwrap_or_else(|| "''".to_string());
+    sqlx::query(&format!("ATTACH DATABASE {} AS migrated KEY {}", sql_string(&partial.to_string_lossy()), target_key))
+        .execute(&mut conn).await?;
+    sqlx::query("SELECT sqlcipher_export('migrated')").execute(&mut conn).await
+        .context("Export SQLCipher impossible")?;
+    sqlx::query(&format!("PRAGMA migrated.user_version = {}", user_version)).execute(&mut conn).await?;
+    sqlx::query("DETACH DATABASE migrated").execute(&mut conn).await?;
+    conn.close().await?;
+
+    let mut check = open_single(partial, to).await?;
+    let integrity: String = sqlx::query("PRAGMA integrity_check").fetch_one(&mut check).await?.get(0);
+    check.close().await?;
+    if integrity != "ok" {
+        return Err(anyhow!("Copie chiffrée corrompue: {}", integrity));
+    }
+    Ok(())
+}
+
+/// Connexion unique sur une base existante; échoue si la clé ne correspond pas
+pub(crate) async fn open_single(path: &Path, key: Option<&DatabaseKey>) -> anyhow::Result<SqliteConnection> {
+    if !path.exists() {
+        return Err(anyhow!("Base introuvable: {}", path.display()));
+    }
+    // Les bases attachées héritent des drapeaux d'ouverture: la copie doit pouvoir être créée
+    let mut opts = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+    if let Some(key) = key {
+        opts = opts.pragma("key", key.sql_value());
+    }
+    let mut conn = SqliteConnection::connect_with(&opts).await?;
+    // La clé n'est vérifiée qu'à la première lecture
+    if let Err(e) = sqlx::query("SELECT count(*) FROM sqlite_master").fetch_one(&mut conn).await {
+        conn.close().await.ok();
+        return Err(anyhow!("Clé de chiffrement invalide pour {}: {}", path.display(), e));
+    }
+    Ok(conn)
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+pub(crate) fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// ============ Trousseau système ============
+
+/// Lit une clé brute rangée dans le trousseau (None si absente)
+pub fn load_keyring_key(account: &str) -> anyhow::Result<Option<DatabaseKey>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, account)?;
+    let secret = match entry.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => return Err(anyhow!("Trousseau inaccessible: {}", e)),
+    };
+    let bytes = hex::decode(secret.trim()).context("Clé du trousseau illisible")?;
+    let key: [u8; 32] = bytes.try_into().map_err(|_| anyhow!("Clé du trousseau de taille invalide"))?;
+    Ok(Some(DatabaseKey::Raw(key)))
+}
+
+/// Range une clé brute dans le trousseau (les phrases secrètes n'y sont jamais stockées)
+pub fn store_keyring_key(account: &str, key: &DatabaseKey) -> anyhow::Result<()> {
+    let DatabaseKey::Raw(raw) = key else {
+        return Err(anyhow!("Seule une clé brute peut être rangée dans le trousseau"));
+    };
+    keyring::Entry::new(KEYRING_SERVICE, account)?
+        .set_password(&hex::encode(raw))
+        .map_err(|e| anyhow!("Trousseau inaccessible: {}", e))
+}
+
+/// Supprime une clé du trousseau (sans erreur si elle n'existe pas)
+pub fn delete_keyring_key(account: &str) -> anyhow::Result<()> {
+    match keyring::Entry::new(KEYRING_SERVICE, account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Trousseau inaccessible: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_plain_db(path: &Path) {
+        let opts = SqliteConnectOptions::new().filename(path).create_if_missing(true);
+        let mut conn = SqliteConnection::connect_with(&opts).await.unwrap();
+        sqlx::query("CREATE TABLE t (v TEXT)").execute(&mut conn).await.unwrap();
+        sqlx::query("INSERT INTO t (v) VALUES ('donnée')").execute(&mut conn).await.unwrap();
+        sqlx::query("PRAGMA user_version = 7").execute(&mut conn).await.unwrap();
+        conn.close().await.unwrap();
+    }
+
+    async fn read_value(path: &Path, key: Option<&DatabaseKey>) -> (String, i64) {
+        let mut conn = open_single(path, key).await.unwrap();
+        let value: String = sqlx::query("SELECT v FROM t").fetch_one(&mut conn).await.unwrap().get(0);
+        let version: i64 = sqlx::query("PRAGMA user_version").fetch_one(&mut conn).await.unwrap().get(0);
+        conn.close().await.unwrap();
+        (value, version)
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_rotate_decrypt() {
+        let dir = std::env::temp_dir().join(format!("cash-planner-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.sqlite");
+        create_plain_db(&path).await;
+        assert!(!is_database_encrypted(&path).unwrap());
+
+        let passphrase = DatabaseKey::Passphrase("l'été 2024".to_string());
+        encrypt_database(&path, &passphrase).await.unwrap();
+        assert!(is_database_encrypted(&path).unwrap());
+        assert!(!partial_path(&path).exists());
+        assert!(!check_database_key(&path, None).await);
+        assert!(!check_database_key(&path, Some(&DatabaseKey::Passphrase("autre".to_string()))).await);
+        assert_eq!(read_value(&path, Some(&passphrase)).await, ("donnée".to_string(), 7));
+
+        let raw = DatabaseKey::generate().unwrap();
+        rotate_database_key(&path, &passphrase, &raw).await.unwrap();
+        assert!(!check_database_key(&path, Some(&passphrase)).await);
+        assert_eq!(read_value(&path, Some(&raw)).await, ("donnée".to_string(), 7));
+
+        // Mauvaise clé: la base reste intacte
+        assert!(decrypt_database(&path, &passphrase).await.is_err());
+        assert!(check_database_key(&path, Some(&raw)).await);
+
+        decrypt_database(&path, &raw).await.unwrap();
+        assert!(!is_database_encrypted(&path).unwrap());
+        assert_eq!(read_value(&path, None).await, ("donnée".to_string(), 7));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_connect_encrypted_and_migrate() {
+        let dir = std::env::temp_dir().join(format!("cash-planner-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.sqlite");
+        let key = DatabaseKey::generate().unwrap();
+
+        let repos = connect_encrypted_and_migrate(&format!("sqlite:{}", path.display()), &key).await.unwrap();
+        repos.pool.close().await;
+        assert!(is_database_encrypted(&path).unwrap());
+        assert!(check_database_key(&path, Some(&key)).await);
+        assert!(format!("{:?}", key).contains("***"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
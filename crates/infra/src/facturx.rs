@@ -0,0 +1,441 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use domain::{DomainError, DomainResult, ExtractedInvoice, VatBreakdown};
+use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use roxmltree::Node;
+use sha2::{Digest, Sha256};
+
+use crate::invoice_pdf::PDF_PRODUCER;
+
+/// Noms de pièce jointe utilisés par Factur-X, ZUGFeRD et XRechnung
+const EMBEDDED_XML_NAMES: [&str; 4] = ["factur-x.xml", "zugferd-invoice.xml", "xrechnung.xml", "order-x.xml"];
+
+/// Lit la facture structurée d'un PDF Factur-X
+/// Retourne None si le fichier n'est pas un PDF ou ne contient pas de XML CII
+pub fn read_facturx_invoice(pdf: &[u8]) -> DomainResult<Option<ExtractedInvoice>> {
+    match extract_facturx_xml(pdf)? {
+        Some(xml) => parse_cii_invoice(&xml).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Cherche le XML embarqué parmi les fichiers attachés du PDF (arbre EmbeddedFiles ou AF de PDF/A-3)
+pub fn extract_facturx_xml(pdf: &[u8]) -> DomainResult<Option<Vec<u8>>> {
+    if !pdf.starts_with(b"%PDF") {
+        return Ok(None);
+    }
+    let document = Document::load_mem(pdf).map_err(|e| DomainError::Validation(format!("PDF illisible: {}", e)))?;
+
+    for object in document.objects.values() {
+        let Ok(filespec) = object.as_dict() else { continue };
+        let Ok(embedded) = filespec.get(b"EF").and_then(|ef| document.dereference(ef)).and_then(|(_, ef)| ef.as_dict()) else { continue };
+
+        let name = [b"UF".as_slice(), b"F".as_slice()].iter()
+            .find_map(|k| filespec.get(k).ok().and_then(text_string))
+            .unwrap_or_default();
+        if !EMBEDDED_XML_NAMES.iter().any(|expected| name.eq_ignore_ascii_case(expected)) {
+            continue;
+        }
+
+        let Ok((_, stream)) = embedded.get(b"F").and_then(|f| document.dereference(f)) else { continue };
+        let Ok(stream) = stream.as_stream() else { continue };
+        let content = if stream.dict.get(b"Filter").is_ok() {
+            stream.decompressed_content().map_err(|e| DomainError::Validation(format!("XML Factur-X illisible: {}", e)))?
+        } else {
+            stream.content.clone()
+        };
+        return Ok(Some(content));
+    }
+    Ok(None)
+}
+
+/// Profil de sortie sRGB déclaré par l'OutputIntent PDF/A
+const SRGB_PROFILE: &[u8] = include_bytes!("../assets/sRGB.icc");
+const SRGB_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// lopdf n'écrit que la ligne `%PDF-1.7`: PDF/A veut juste après un commentaire d'octets > 127,
+/// porté ici par la chaîne de version
+const PDF_VERSION_WITH_BINARY_COMMENT: &str = "1.7\n%âãÏÓ";
+
+/// Attache le XML CII à un PDF en tant que factur-x.xml (fichier associé /Data) et en fait un PDF/A-3B:
+/// profil sRGB en OutputIntent, métadonnées XMP pdfaid et schéma d'extension Factur-X.
+/// Le PDF reçu doit embarquer ses polices, comme ceux de `render_invoice_pdf`
+pub fn embed_facturx_xml(pdf: &[u8], xml: &[u8], conformance_level: &str, modified: NaiveDateTime) -> DomainResult<Vec<u8>> {
+    let pdf_error = |e: lopdf::Error| DomainError::Repo(format!("Erreur génération Factur-X: {}", e));
+    let mut document = Document::load_mem(pdf).map_err(|e| DomainError::Validation(format!("PDF illisible: {}", e)))?;
+    document.version = PDF_VERSION_WITH_BINARY_COMMENT.to_string();
+    let pdf_date = format!("D:{}+00'00'", modified.format("%Y%m%d%H%M%S"));
+
+    let mut file = Stream::new(dictionary! {
+        "Type" => "EmbeddedFile",
+        "Subtype" => Object::Name(b"text/xml".to_vec()),
+        "Params" => dictionary! {
+            "ModDate" => Object::string_literal(pdf_date.clone()),
+            "Size" => xml.len() as i64,
+        },
+    }, xml.to_vec());
+    file.compress().map_err(pdf_error)?;
+    let file_id = document.add_object(file);
+    let filespec_id = document.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(EMBEDDED_XML_NAMES[0]),
+        "UF" => Object::string_literal(EMBEDDED_XML_NAMES[0]),
+        "Desc" => Object::string_literal("Factur-X invoice"),
+        "AFRelationship" => "Data",
+        "EF" => dictionary! { "F" => file_id, "UF" => file_id },
+    });
+
+    // Titre et auteur repris du dictionnaire Info: ils doivent être identiques dans les métadonnées XMP
+    let info_id = document.trailer.get(b"Info").and_then(Object::as_reference).ok();
+    let info_text = |key: &[u8]| info_id
+        .and_then(|id| document.get_dictionary(id).ok())
+        .and_then(|info| info.get(key).ok().and_then(text_string));
+    let title = info_text(b"Title").unwrap_or_else(|| "Factur-X".to_string());
+    let author = info_text(b"Author");
+    let metadata_id = document.add_object(Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        facturx_xmp(&title, author.as_deref(), conformance_level, modified).into_bytes(),
+    ));
+    match info_id.and_then(|id| document.get_object_mut(id).ok()).and_then(|info| info.as_dict_mut().ok()) {
+        Some(info) => {
+            info.set("Producer", Object::string_literal(PDF_PRODUCER));
+            info.set("CreationDate", Object::string_literal(pdf_date.clone()));
+            info.set("ModDate", Object::string_literal(pdf_date));
+        }
+        None => {
+            let info_id = document.add_object(dictionary! {
+                "Title" => Object::string_literal(title),
+                "Producer" => Object::string_literal(PDF_PRODUCER),
+                "CreationDate" => Object::string_literal(pdf_date.clone()),
+                "ModDate" => Object::string_literal(pdf_date),
+            });
+            document.trailer.set("Info", info_id);
+        }
+    }
+
+    let mut profile = Stream::new(dictionary! { "N" => 3 }, SRGB_PROFILE.to_vec());
+    profile.compress().map_err(pdf_error)?;
+    let profile_id = document.add_object(profile);
+    let output_intent = dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal(SRGB_CONDITION),
+        "Info" => Object::string_literal(SRGB_CONDITION),
+        "DestOutputProfile" => profile_id,
+    };
+
+    let catalog = document.catalog_mut().map_err(pdf_error)?;
+    catalog.set("Names", dictionary! {
+        "EmbeddedFiles" => dictionary! {
+            "Names" => vec![Object::string_literal(EMBEDDED_XML_NAMES[0]), Object::Reference(filespec_id)],
+        },
+    });
+    catalog.set("AF", vec![Object::Reference(filespec_id)]);
+    catalog.set("Metadata", metadata_id);
+    catalog.set("OutputIntents", vec![Object::Dictionary(output_intent)]);
+    catalog.set("PageMode", "UseAttachments");
+
+    // Identifiant de fichier (exigé par PDF/A), dérivé du contenu pour rester reproductible
+    let file_hash = Sha256::new().chain_update(pdf).chain_update(xml).finalize();
+    let file_id = Object::String(file_hash[..16].to_vec(), StringFormat::Hexadecimal);
+    document.trailer.set("ID", vec![file_id.clone(), file_id]);
+
+    let mut buffer = Vec::new();
+    document.save_to(&mut buffer).map_err(|e| DomainError::Repo(format!("Erreur génération Factur-X: {}", e)))?;
+    Ok(buffer)
+}
+
+fn facturx_xmp(title: &str, author: Option<&str>, conformance_level: &str, modified: NaiveDateTime) -> String {
+    let date = modified.format("%Y-%m-%dT%H:%M:%S+00:00");
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let title = escape(title);
+    let creator = author
+        .map(|author| format!("<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>", escape(author)))
+        .unwrap_or_default();
+    let property = |name: &str, description: &str| format!(
+        r#"<rdf:li rdf:parseType="Resource"><pdfaProperty:name>{}</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>{}</pdfaProperty:description></rdf:li>"#,
+        name, description,
+    );
+    format!(r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"><pdfaid:part>3</pdfaid:part><pdfaid:conformance>B</pdfaid:conformance></rdf:Description>
+<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>{creator}</rdf:Description>
+<rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/"><xmp:CreateDate>{date}</xmp:CreateDate><xmp:ModifyDate>{date}</xmp:ModifyDate></rdf:Description>
+<rdf:Description rdf:about="" xmlns:pdf="http://ns.adobe.com/pdf/1.3/"><pdf:Producer>{PDF_PRODUCER}</pdf:Producer></rdf:Description>
+<rdf:Description rdf:about="" xmlns:fx="urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#"><fx:DocumentType>INVOICE</fx:DocumentType><fx:DocumentFileName>factur-x.xml</fx:DocumentFileName><fx:Version>1.0</fx:Version><fx:ConformanceLevel>{conformance_level}</fx:ConformanceLevel></rdf:Description>
+<rdf:Description rdf:about="" xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/" xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#" xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#">
+<pdfaExtension:schemas><rdf:Bag><rdf:li rdf:parseType="Resource">
+<pdfaSchema:schema>Factur-X PDFA Extension Schema</pdfaSchema:schema>
+<pdfaSchema:namespaceURI>urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#</pdfaSchema:namespaceURI>
+<pdfaSchema:prefix>fx</pdfaSchema:prefix>
+<pdfaSchema:property><rdf:Seq>
+{p1}
+{p2}
+{p3}
+{p4}
+</rdf:Seq></pdfaSchema:property>
+</rdf:li></rdf:Bag></pdfaExtension:schemas>
+</rdf:Description>
+</rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        p1 = property("DocumentFileName", "name of the embedded XML invoice file"),
+        p2 = property("DocumentType", "INVOICE"),
+        p3 = property("Version", "The actual version of the Factur-X XML schema"),
+        p4 = property("ConformanceLevel", "The conformance level of the embedded Factur-X data"),
+    )
+}
+
+/// Décode une chaîne PDF (PDFDocEncoding ou UTF-16BE avec BOM)
+fn text_string(object: &Object) -> Option<String> {
+    let Object::String(bytes, _) = object else { return None };
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+        return String::from_utf16(&units).ok();
+    }
+    Some(bytes.iter().map(|&b| b as char).collect())
+}
+
+/// Analyse un XML CII (CrossIndustryInvoice) au profil MINIMUM ou supérieur
+pub fn parse_cii_invoice(xml: &[u8]) -> DomainResult<ExtractedInvoice> {
+    let text = std::str::from_utf8(xml).map_err(|e| DomainError::Validation(format!("XML Factur-X non UTF-8: {}", e)))?;
+    let document = roxmltree::Document::parse(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| DomainError::Validation(format!("XML Factur-X invalide: {}", e)))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "CrossIndustryInvoice" {
+        return Err(DomainError::Validation(format!("Document XML non supporté: {}", root.tag_name().name())));
+    }
+
+    let header = child_path(root, &["ExchangedDocument"]).ok_or_else(|| missing("ExchangedDocument"))?;
+    let transaction = child_path(root, &["SupplyChainTradeTransaction"]).ok_or_else(|| missing("SupplyChainTradeTransaction"))?;
+    let agreement = child_path(transaction, &["ApplicableHeaderTradeAgreement"]).ok_or_else(|| missing("ApplicableHeaderTradeAgreement"))?;
+    let settlement = child_path(transaction, &["ApplicableHeaderTradeSettlement"]).ok_or_else(|| missing("ApplicableHeaderTradeSettlement"))?;
+    let summation = child_path(settlement, &["SpecifiedTradeSettlementHeaderMonetarySummation"])
+        .ok_or_else(|| missing("SpecifiedTradeSettlementHeaderMonetarySummation"))?;
+
+    let currency = child_text(settlement, &["InvoiceCurrencyCode"]).unwrap_or_else(|| "EUR".to_string());
+
+    let vat_breakdown = children(settlement, "ApplicableTradeTax")
+        .map(|tax| -> DomainResult<VatBreakdown> {
+            Ok(VatBreakdown {
+                rate_ppm: child_text(tax, &["RateApplicablePercent"]).map(|p| parse_decimal(&p, 4)).transpose()?.unwrap_or(0) as i32,
+                basis_ht_cents: amount(tax, "BasisAmount")?.unwrap_or(0),
+                vat_cents: amount(tax, "CalculatedAmount")?.unwrap_or(0),
+                category_code: child_text(tax, &["CategoryCode"]).unwrap_or_else(|| "S".to_string()),
+                vat_on_payments: child_text(tax, &["DueDateTypeCode"]).as_deref() == Some("72"),
+            })
+        })
+        .collect::<DomainResult<Vec<_>>>()?;
+
+    let total_ht_cents = amount(summation, "TaxBasisTotalAmount")?
+        .unwrap_or_else(|| vat_breakdown.iter().map(|line| line.basis_ht_cents).sum());
+    // TaxTotalAmount peut être répété dans la devise de comptabilisation: on garde celui de la facture
+    let total_vat_cents = match children(summation, "TaxTotalAmount")
+        .find(|n| n.attribute("currencyID").map(|c| c == currency).unwrap_or(true))
+        .and_then(|n| n.text())
+    {
+        Some(value) => parse_decimal(value, 2)?,
+        None => vat_breakdown.iter().map(|line| line.vat_cents).sum(),
+    };
+    let total_ttc_cents = amount(summation, "GrandTotalAmount")?.unwrap_or(total_ht_cents + total_vat_cents);
+
+    let seller_vat_id = child_path(agreement, &["SellerTradeParty"])
+        .into_iter()
+        .flat_map(|seller| children(seller, "SpecifiedTaxRegistration"))
+        .filter_map(|registration| child_path(registration, &["ID"]))
+        .find(|id| id.attribute("schemeID") == Some("VA"))
+        .and_then(|id| id.text().map(|t| t.trim().to_string()));
+
+    Ok(ExtractedInvoice {
+        profile: child_text(root, &["ExchangedDocumentContext", "GuidelineSpecifiedDocumentContextParameter", "ID"]),
+        type_code: child_text(header, &["TypeCode"]).unwrap_or_else(|| "380".to_string()),
+        invoice_number: child_text(header, &["ID"]).ok_or_else(|| missing("ExchangedDocument/ID"))?,
+        issue_date: date(header, &["IssueDateTime"])?.ok_or_else(|| missing("IssueDateTime"))?,
+        due_date: date(settlement, &["SpecifiedTradePaymentTerms", "DueDateDateTime"])?,
+        seller_name: child_text(agreement, &["SellerTradeParty", "Name"]).ok_or_else(|| missing("SellerTradeParty/Name"))?,
+        seller_vat_id,
+        buyer_name: child_text(agreement, &["BuyerTradeParty", "Name"]),
+        currency,
+        vat_breakdown,
+        total_ht_cents,
+        total_vat_cents,
+        total_ttc_cents,
+    })
+}
+
+fn missing(element: &str) -> DomainError {
+    DomainError::Validation(format!("Facture Factur-X incomplète: {} manquant", element))
+}
+
+fn children<'a, 'input: 'n, 'n>(node: Node<'a, 'input>, name: &'n str) -> impl Iterator<Item = Node<'a, 'input>> + 'n
+where
+    'a: 'n,
+{
+    node.children().filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Suit un chemin d'éléments par nom local, sans tenir compte des préfixes rsm/ram/udt
+fn child_path<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |current, name| children(current, name).next())
+}
+
+fn child_text(node: Node, path: &[&str]) -> Option<String> {
+    child_path(node, path)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+fn amount(node: Node, name: &str) -> DomainResult<Option<i64>> {
+    child_text(node, &[name]).map(|value| parse_decimal(&value, 2)).transpose()
+}
+
+/// Dates CII: <udt:DateTimeString format="102">20240315</udt:DateTimeString>
+fn date(node: Node, path: &[&str]) -> DomainResult<Option<NaiveDate>> {
+    let Some(value) = child_path(node, path).and_then(|n| child_text(n, &["DateTimeString"])) else { return Ok(None) };
+    NaiveDate::parse_from_str(&value, "%Y%m%d")
+        .map(Some)
+        .map_err(|_| DomainError::Validation(format!("Date Factur-X invalide: {}", value)))
+}
+
+/// Convertit un décimal XML en entier à `scale` chiffres après la virgule, arrondi au plus proche
+pub(crate) fn parse_decimal(value: &str, scale: u32) -> DomainResult<i64> {
+    let invalid = || DomainError::Validation(format!("Montant Factur-X invalide: {}", value));
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    if int_part.is_empty() && frac_part.is_empty()
+        || !int_part.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let mut result: i64 = if int_part.is_empty() { 0 } else { int_part.parse().map_err(|_| invalid())? };
+    let mut frac = frac_part.chars();
+    for _ in 0..scale {
+        let digit = frac.next().and_then(|c| c.to_digit(10)).unwrap_or(0);
+        result = result.checked_mul(10).and_then(|r| r.checked_add(digit as i64)).ok_or_else(invalid)?;
+    }
+    if frac.next().and_then(|c| c.to_digit(10)).unwrap_or(0) >= 5 {
+        result += 1;
+    }
+    Ok(if negative { -result } else { result })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CII: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100"
+    xmlns:ram="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100"
+    xmlns:udt="urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100">
+  <rsm:ExchangedDocumentContext>
+    <ram:GuidelineSpecifiedDocumentContextParameter><ram:ID>urn:cen.eu:en16931:2017</ram:ID></ram:GuidelineSpecifiedDocumentContextParameter>
+  </rsm:ExchangedDocumentContext>
+  <rsm:ExchangedDocument>
+    <ram:ID>FA-2024-0042</ram:ID>
+    <ram:TypeCode>380</ram:TypeCode>
+    <ram:IssueDateTime><udt:DateTimeString format="102">20240315</udt:DateTimeString></ram:IssueDateTime>
+  </rsm:ExchangedDocument>
+  <rsm:SupplyChainTradeTransaction>
+    <ram:ApplicableHeaderTradeAgreement>
+      <ram:SellerTradeParty>
+        <ram:Name>Hébergeur SAS</ram:Name>
+        <ram:SpecifiedTaxRegistration><ram:ID schemeID="VA">FR12345678901</ram:ID></ram:SpecifiedTaxRegistration>
+      </ram:SellerTradeParty>
+      <ram:BuyerTradeParty><ram:Name>Moi EI</ram:Name></ram:BuyerTradeParty>
+    </ram:ApplicableHeaderTradeAgreement>
+    <ram:ApplicableHeaderTradeSettlement>
+      <ram:InvoiceCurrencyCode>EUR</ram:InvoiceCurrencyCode>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>20.00</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>100.00</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:DueDateTypeCode>72</ram:DueDateTypeCode>
+        <ram:RateApplicablePercent>20.00</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:ApplicableTradeTax>
+        <ram:CalculatedAmount>0.55</ram:CalculatedAmount>
+        <ram:TypeCode>VAT</ram:TypeCode>
+        <ram:BasisAmount>10.00</ram:BasisAmount>
+        <ram:CategoryCode>S</ram:CategoryCode>
+        <ram:RateApplicablePercent>5.5</ram:RateApplicablePercent>
+      </ram:ApplicableTradeTax>
+      <ram:SpecifiedTradePaymentTerms>
+        <ram:DueDateDateTime><udt:DateTimeString format="102">20240414</udt:DateTimeString></ram:DueDateDateTime>
+      </ram:SpecifiedTradePaymentTerms>
+      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+        <ram:TaxBasisTotalAmount>110.00</ram:TaxBasisTotalAmount>
+        <ram:TaxTotalAmount currencyID="EUR">20.55</ram:TaxTotalAmount>
+        <ram:GrandTotalAmount>130.55</ram:GrandTotalAmount>
+      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+    </ram:ApplicableHeaderTradeSettlement>
+  </rsm:SupplyChainTradeTransaction>
+</rsm:CrossIndustryInvoice>"#;
+
+    #[test]
+    fn test_parse_cii_invoice() {
+        let invoice = parse_cii_invoice(CII.as_bytes()).unwrap();
+        assert_eq!(invoice.invoice_number, "FA-2024-0042");
+        assert_eq!(invoice.seller_name, "Hébergeur SAS");
+        assert_eq!(invoice.seller_vat_id.as_deref(), Some("FR12345678901"));
+        assert_eq!(invoice.issue_date, NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+        assert_eq!(invoice.due_date, NaiveDate::from_ymd_opt(2024, 4, 14));
+        assert_eq!((invoice.total_ht_cents, invoice.total_vat_cents, invoice.total_ttc_cents), (11_000, 2_055, 13_055));
+        assert_eq!(invoice.vat_breakdown.len(), 2);
+        assert_eq!(invoice.vat_breakdown[0].rate_ppm, 200_000);
+        assert_eq!(invoice.vat_breakdown[1].rate_ppm, 55_000);
+        assert!(!invoice.vat_on_payments());
+
+        assert!(extract_facturx_xml(b"not a pdf").unwrap().is_none());
+    }
+
+    /// PDF d'une page blanche, sans pièce jointe
+    fn blank_pdf() -> Vec<u8> {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let page_id = document.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id });
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }));
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+        let mut buffer = Vec::new();
+        document.save_to(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_embed_and_read_back() {
+        let modified = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let blank = blank_pdf();
+        assert!(extract_facturx_xml(&blank).unwrap().is_none());
+        assert!(read_facturx_invoice(&blank).unwrap().is_none());
+
+        let pdf = embed_facturx_xml(&blank, CII.as_bytes(), "EN 16931", modified).unwrap();
+        assert_eq!(extract_facturx_xml(&pdf).unwrap().as_deref(), Some(CII.as_bytes()));
+        let invoice = read_facturx_invoice(&pdf).unwrap().unwrap();
+        assert_eq!(invoice.invoice_number, "FA-2024-0042");
+        assert_eq!(invoice.total_ttc_cents, 13_055);
+        assert!(!invoice.is_credit_note());
+    }
+
+    #[test]
+    fn test_parse_credit_note() {
+        let xml = CII.replace("<ram:TypeCode>380</ram:TypeCode>", "<ram:TypeCode>381</ram:TypeCode>");
+        let credit_note = parse_cii_invoice(xml.as_bytes()).unwrap();
+        assert_eq!(credit_note.type_code, "381");
+        assert!(credit_note.is_credit_note());
+        // Les montants d'un avoir restent positifs dans le XML, le signe vient du type
+        assert_eq!(credit_note.total_ht_cents, 11_000);
+    }
+}
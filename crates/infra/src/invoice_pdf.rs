@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+
+use domain::{format_ppm_percent, DomainError, DomainResult, IssuedInvoice};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use sha2::{Digest, Sha256};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const BOTTOM: f32 = 60.0;
+
+/// Colonnes du tableau des lignes (bord droit pour les montants)
+const COL_QTY: f32 = 360.0;
+const COL_UNIT_PRICE: f32 = 440.0;
+const COL_VAT: f32 = 510.0;
+const COL_TOTAL: f32 = PAGE_WIDTH - MARGIN;
+
+/// Polices embarquées: PDF/A n'admet pas les polices standard non embarquées
+const REGULAR_FONT: (&str, &[u8]) = ("DejaVuSans", include_bytes!("../assets/DejaVuSans.ttf"));
+const BOLD_FONT: (&str, &[u8]) = ("DejaVuSans-Bold", include_bytes!("../assets/DejaVuSans-Bold.ttf"));
+
+/// Génère le PDF d'une facture émise (A4, DejaVu Sans embarquée en sous-ensemble)
+pub fn render_invoice_pdf(invoice: &IssuedInvoice) -> DomainResult<Vec<u8>> {
+    let mut page = PageWriter::new()?;
+
+    // En-tête: émetteur à gauche, références de la facture à droite
+    let mut y = PAGE_HEIGHT - MARGIN;
+    page.text(MARGIN, y, 14.0, true, &invoice.issuer.name);
+    for line in invoice.issuer.address.lines() {
+        y -= 13.0;
+        page.text(MARGIN, y, 9.0, false, line);
+    }
+    y -= 13.0;
+    page.text(MARGIN, y, 9.0, false, &format!("SIRET : {}", invoice.issuer.siret));
+    if let Some(vat_number) = invoice.issuer.vat_number.as_deref().filter(|_| !invoice.issuer.vat_exempt) {
+        y -= 13.0;
+        page.text(MARGIN, y, 9.0, false, &format!("N° TVA : {}", vat_number));
+    }
+    if let Some(email) = &invoice.issuer.email {
+        y -= 13.0;
+        page.text(MARGIN, y, 9.0, false, email);
+    }
+
+    page.text_right(COL_TOTAL, PAGE_HEIGHT - MARGIN, 20.0, true, "FACTURE");
+    page.text_right(COL_TOTAL, PAGE_HEIGHT - MARGIN - 22.0, 10.0, true, &format!("N° {}", invoice.number));
+    page.text_right(COL_TOTAL, PAGE_HEIGHT - MARGIN - 36.0, 9.0, false, &format!("Date : {}", invoice.issue_date.format("%d/%m/%Y")));
+    page.text_right(COL_TOTAL, PAGE_HEIGHT - MARGIN - 49.0, 9.0, false, &format!("Échéance : {}", invoice.due_date.format("%d/%m/%Y")));
+
+    // Client
+    let mut client_y = PAGE_HEIGHT - MARGIN - 90.0;
+    page.text(320.0, client_y, 9.0, true, "Facturé à");
+    client_y -= 14.0;
+    page.text(320.0, client_y, 11.0, true, &invoice.client.name);
+    for line in invoice.client.address.lines() {
+        client_y -= 13.0;
+        page.text(320.0, client_y, 9.0, false, line);
+    }
+    if let Some(siret) = &invoice.client.siret {
+        client_y -= 13.0;
+        page.text(320.0, client_y, 9.0, false, &format!("SIRET : {}", siret));
+    }
+    if let Some(vat_number) = &invoice.client.vat_number {
+        client_y -= 13.0;
+        page.text(320.0, client_y, 9.0, false, &format!("N° TVA : {}", vat_number));
+    }
+
+    // Lignes
+    page.y = y.min(client_y) - 35.0;
+    table_header(&mut page);
+    for line in &invoice.lines {
+        let description = wrap(&line.description, 55);
+        if page.y - 13.0 * (description.len() as f32) < BOTTOM {
+            page.new_page();
+            table_header(&mut page);
+        }
+        let row_y = page.y;
+        for (i, part) in description.iter().enumerate() {
+            page.text(MARGIN, row_y - 13.0 * i as f32, 9.0, false, part);
+        }
+        page.text_right(COL_QTY, row_y, 9.0, false, &format_quantity(line.quantity_milli));
+        page.text_right(COL_UNIT_PRICE, row_y, 9.0, false, &format_euros(line.unit_price_ht_cents));
+        let vat_rate = if invoice.issuer.vat_exempt { "-".to_string() } else { format!("{} %", format_ppm_percent(line.vat_rate_ppm)) };
+        page.text_right(COL_VAT, row_y, 9.0, false, &vat_rate);
+        page.text_right(COL_TOTAL, row_y, 9.0, false, &format_euros(line.total_ht_cents()));
+        page.y -= 13.0 * description.len() as f32 + 4.0;
+    }
+
+    // Totaux
+    page.ensure_space(30.0 + 14.0 * (invoice.vat_breakdown.len() as f32 + 2.0));
+    page.line(350.0, page.y, COL_TOTAL, page.y);
+    page.y -= 16.0;
+    page.total_row("Total HT", invoice.total_ht_cents, false);
+    if !invoice.issuer.vat_exempt {
+        for vat in &invoice.vat_breakdown {
+            page.total_row(&format!("TVA {} % sur {}", format_ppm_percent(vat.rate_ppm), format_euros(vat.basis_ht_cents)), vat.vat_cents, false);
+        }
+    }
+    page.total_row("Total TTC", invoice.total_ttc_cents, true);
+
+    // Mentions obligatoires et notes
+    page.y -= 20.0;
+    let mut mentions = invoice.legal_mentions();
+    if invoice.is_service && !invoice.issuer.vat_exempt {
+        mentions.push("TVA acquittée sur les encaissements.".to_string());
+    }
+    if let Some(iban) = &invoice.issuer.iban {
+        mentions.push(format!("Règlement par virement : IBAN {}", iban));
+    }
+    if let Some(notes) = &invoice.notes {
+        mentions.extend(notes.lines().map(str::to_string));
+    }
+    for mention in mentions {
+        for part in wrap(&mention, 110) {
+            page.ensure_space(12.0);
+            page.text(MARGIN, page.y, 8.0, false, &part);
+            page.y -= 11.0;
+        }
+    }
+
+    page.finish(&format!("Facture {}", invoice.number), &invoice.issuer.name)
+}
+
+fn table_header(page: &mut PageWriter) {
+    let y = page.y;
+    page.text(MARGIN, y, 9.0, true, "Désignation");
+    page.text_right(COL_QTY, y, 9.0, true, "Qté");
+    page.text_right(COL_UNIT_PRICE, y, 9.0, true, "PU HT");
+    page.text_right(COL_VAT, y, 9.0, true, "TVA");
+    page.text_right(COL_TOTAL, y, 9.0, true, "Total HT");
+    page.line(MARGIN, y - 5.0, COL_TOTAL, y - 5.0);
+    page.y -= 20.0;
+}
+
+/// Accumule les opérations de dessin page par page
+struct PageWriter {
+    pages: Vec<Vec<Operation>>,
+    operations: Vec<Operation>,
+    y: f32,
+    fonts: [EmbeddedFont; 2], // Normale, grasse
+}
+
+impl PageWriter {
+    fn new() -> DomainResult<Self> {
+        Ok(Self {
+            pages: Vec::new(),
+            operations: Vec::new(),
+            y: PAGE_HEIGHT - MARGIN,
+            fonts: [EmbeddedFont::load(REGULAR_FONT)?, EmbeddedFont::load(BOLD_FONT)?],
+        })
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.operations));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let glyphs = self.fonts[bold as usize].encode(text);
+        self.operations.extend([
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec![Object::Name(if bold { b"F2".to_vec() } else { b"F1".to_vec() }), size.into()]),
+            Operation::new("Td", vec![x.into(), y.into()]),
+            Operation::new("Tj", vec![Object::String(glyphs, StringFormat::Hexadecimal)]),
+            Operation::new("ET", vec![]),
+        ]);
+    }
+
+    fn text_right(&mut self, right: f32, y: f32, size: f32, bold: bool, text: &str) {
+        let width = self.fonts[bold as usize].text_width(text, size);
+        self.text(right - width, y, size, bold, text);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        self.operations.extend([
+            Operation::new("w", vec![0.5.into()]),
+            Operation::new("m", vec![x1.into(), y1.into()]),
+            Operation::new("l", vec![x2.into(), y2.into()]),
+            Operation::new("S", vec![]),
+        ]);
+    }
+
+    fn total_row(&mut self, label: &str, cents: i64, bold: bool) {
+        let y = self.y;
+        self.text_right(COL_UNIT_PRICE + 30.0, y, 9.0, bold, label);
+        self.text_right(COL_TOTAL, y, if bold { 10.0 } else { 9.0 }, bold, &format_euros(cents));
+        self.y -= 14.0;
+    }
+
+    fn finish(mut self, title: &str, author: &str) -> DomainResult<Vec<u8>> {
+        self.new_page();
+        let pdf_error = |e: &dyn std::fmt::Display| DomainError::Repo(format!("Erreur génération PDF: {}", e));
+
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let [regular, bold] = &self.fonts;
+        let regular_id = regular.add_to(&mut document)?;
+        let bold_id = bold.add_to(&mut document)?;
+        let resources_id = document.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => regular_id, "F2" => bold_id },
+        });
+
+        let mut kids = Vec::new();
+        for operations in self.pages {
+            let content = Content { operations }.encode().map_err(|e| pdf_error(&e))?;
+            let content_id = document.add_object(Stream::new(dictionary! {}, content));
+            let page_id = document.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(Object::Reference(page_id));
+        }
+        let count = kids.len() as i64;
+        document.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+        }));
+        let catalog_id = document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        let info_id = document.add_object(dictionary! {
+            "Title" => text_string(title),
+            "Author" => text_string(author),
+            "Producer" => Object::string_literal(PDF_PRODUCER),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+        document.compress();
+
+        let mut buffer = Vec::new();
+        document.save_to(&mut buffer).map_err(|e| pdf_error(&e))?;
+        Ok(buffer)
+    }
+}
+
+pub(crate) const PDF_PRODUCER: &str = "JLA Cash Planner";
+
+/// Police TrueType embarquée en CIDFontType2 (Identity-H: le texte est écrit en numéros de glyphes)
+struct EmbeddedFont {
+    name: &'static str,
+    data: &'static [u8],
+    face: ttf_parser::Face<'static>,
+    used: BTreeMap<u16, char>, // Glyphes écrits et caractère de chacun, pour le sous-ensemble et ToUnicode
+}
+
+impl EmbeddedFont {
+    fn load((name, data): (&'static str, &'static [u8])) -> DomainResult<Self> {
+        let face = ttf_parser::Face::parse(data, 0).map_err(|e| DomainError::Repo(format!("Police {} illisible: {}", name, e)))?;
+        Ok(Self { name, data, face, used: BTreeMap::new() })
+    }
+
+    /// Glyphe d'un caractère, '?' s'il manque à la police (PDF/A interdit de référencer .notdef)
+    fn glyph(&self, c: char) -> (u16, char) {
+        let c = if c == '\u{202F}' { '\u{00A0}' } else { c };
+        match self.face.glyph_index(c) {
+            Some(glyph) if glyph.0 != 0 => (glyph.0, c),
+            _ => (self.face.glyph_index('?').map(|g| g.0).unwrap_or(0), '?'),
+        }
+    }
+
+    fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut glyphs = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let (glyph, c) = self.glyph(c);
+            self.used.insert(glyph, c);
+            glyphs.extend_from_slice(&glyph.to_be_bytes());
+        }
+        glyphs
+    }
+
+    /// Chasse d'un glyphe en millièmes de corps, celle déclarée dans /W
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self.face.glyph_hor_advance(ttf_parser::GlyphId(glyph)).unwrap_or(0);
+        (advance as f32 * 1000.0 / self.face.units_per_em() as f32).round()
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.advance(self.glyph(c).0)).sum::<f32>() * size / 1000.0
+    }
+
+    fn scaled(&self, value: i16) -> i64 {
+        (value as f32 * 1000.0 / self.face.units_per_em() as f32).round() as i64
+    }
+
+    /// Ajoute la police Type0, sa police CID, le sous-ensemble de glyphes utilisés et la table ToUnicode
+    fn add_to(&self, document: &mut Document) -> DomainResult<ObjectId> {
+        let mut glyphs: Vec<u16> = self.used.keys().copied().collect();
+        glyphs.insert(0, 0);
+        let subset = subsetter::subset(self.data, 0, subsetter::Profile::pdf(&glyphs))
+            .map_err(|e| DomainError::Repo(format!("Sous-ensemble de la police {}: {}", self.name, e)))?;
+
+        // Préfixe de sous-ensemble: six majuscules dérivées des glyphes retenus
+        let digest = glyphs.iter().fold(Sha256::new(), |hash, g| hash.chain_update(g.to_be_bytes())).finalize();
+        let tag: String = digest.iter().take(6).map(|b| (b'A' + b % 26) as char).collect();
+        let base_font = Object::Name(format!("{}+{}", tag, self.name).into_bytes());
+
+        let length = subset.len() as i64;
+        let file_id = document.add_object(Stream::new(dictionary! { "Length1" => length }, subset));
+        let bbox = self.face.global_bounding_box();
+        let descriptor_id = document.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => base_font.clone(),
+            "Flags" => 32, // Non symbolique
+            "FontBBox" => vec![self.scaled(bbox.x_min).into(), self.scaled(bbox.y_min).into(), self.scaled(bbox.x_max).into(), self.scaled(bbox.y_max).into()],
+            "ItalicAngle" => 0,
+            "Ascent" => self.scaled(self.face.ascender()),
+            "Descent" => self.scaled(self.face.descender()),
+            "CapHeight" => self.scaled(self.face.capital_height().unwrap_or(self.face.ascender())),
+            "StemV" => 80,
+            "FontFile2" => file_id,
+        });
+        let widths: Vec<Object> = self.used.keys()
+            .flat_map(|&glyph| [Object::Integer(glyph as i64), Object::Array(vec![Object::Real(self.advance(glyph))])])
+            .collect();
+        let cid_font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => base_font.clone(),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "W" => widths,
+            "CIDToGIDMap" => "Identity",
+        });
+        let to_unicode_id = document.add_object(Stream::new(dictionary! {}, self.to_unicode_cmap().into_bytes()));
+        Ok(document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => base_font,
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![Object::Reference(cid_font_id)],
+            "ToUnicode" => to_unicode_id,
+        }))
+    }
+
+    /// Correspondance glyphe -> Unicode, pour copier le texte du PDF
+    fn to_unicode_cmap(&self) -> String {
+        let mut cmap = String::from("/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+            /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+            /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+            1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n");
+        let entries: Vec<(&u16, &char)> = self.used.iter().collect();
+        for chunk in entries.chunks(100) {
+            cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+            for (glyph, c) in chunk {
+                let utf16: String = c.encode_utf16(&mut [0; 2]).iter().map(|unit| format!("{:04X}", unit)).collect();
+                cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, utf16));
+            }
+            cmap.push_str("endbfchar\n");
+        }
+        cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+        cmap
+    }
+}
+
+/// Chaîne de texte PDF: PDFDocEncoding en ASCII, UTF-16BE avec BOM au-delà
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let utf16 = [0xFEFF].into_iter().chain(text.encode_utf16()).flat_map(u16::to_be_bytes).collect();
+    Object::String(utf16, StringFormat::Hexadecimal)
+}
+
+/// "1 234,56 €"
+fn format_euros(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    let euros: Vec<char> = (cents / 100).to_string().chars().collect();
+    let grouped = euros.rchunks(3).rev().map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join(" ");
+    format!("{}{},{:02} €", sign, grouped, cents % 100)
+}
+
+/// 1500 -> "1,5"
+fn format_quantity(quantity_milli: i64) -> String {
+    let decimals = format!("{:03}", (quantity_milli % 1000).abs());
+    let decimals = decimals.trim_end_matches('0');
+    if decimals.is_empty() { (quantity_milli / 1000).to_string() } else { format!("{},{}", quantity_milli / 1000, decimals) }
+}
+
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use domain::{build_issued_invoice, InvoiceClient, InvoiceDraft, InvoiceLine, IssuerProfile};
+
+    #[test]
+    fn test_render_invoice_pdf() {
+        let issuer = IssuerProfile {
+            name: "Jean Dupont EI".into(),
+            address: "1 rue de la Paix\n75002 Paris".into(),
+            siret: "12345678900012".into(),
+            vat_number: Some("FR12123456789".into()),
+            vat_exempt: false,
+            late_penalty_rate_ppm: 100_000,
+            payment_terms_days: 30,
+            iban: None,
+            email: None,
+        };
+        let draft = InvoiceDraft {
+            issue_date: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            is_service: true,
+            client: InvoiceClient { name: "Client SA".into(), address: "Lyon".into(), siret: None, vat_number: None },
+            lines: vec![InvoiceLine { description: "Développement".into(), quantity_milli: 2_500, unit_price_ht_cents: 50_000, vat_rate_ppm: 200_000 }],
+            notes: None,
+        };
+        let invoice = build_issued_invoice(issuer, draft, 1, NaiveDate::from_ymd_opt(2024, 3, 15).unwrap().and_hms_opt(9, 0, 0).unwrap()).unwrap();
+        assert_eq!(invoice.number, "F2024-0001");
+        assert_eq!((invoice.total_ht_cents, invoice.total_vat_cents), (125_000, 25_000));
+
+        let pdf = render_invoice_pdf(&invoice).unwrap();
+        let document = Document::load_mem(&pdf).unwrap();
+        assert_eq!(document.get_pages().len(), 1);
+        assert!(pdf.len() < 100_000, "polices non réduites aux glyphes utilisés: {} octets", pdf.len());
+        // Un caractère absent de la police s'affiche '?', jamais .notdef
+        let font = EmbeddedFont::load(REGULAR_FONT).unwrap();
+        assert_eq!(font.glyph('漢'), font.glyph('?'));
+        assert_eq!(format_euros(-123_456_789), "-1 234 567,89 €");
+        assert_eq!(format_quantity(2_500), "2,5");
+    }
+}
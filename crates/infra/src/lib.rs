@@ -1,5 +1,29 @@
 mod sqlite;
 mod minio;
+mod local_store;
+mod facturx;
+mod invoice_pdf;
+mod einvoice;
+mod archive;
+mod encryption;
+mod backup;
+mod doctor;
+mod unit_of_work;
+mod memory;
+mod workspace;
+mod sync;
 
 pub use sqlite::*;
 pub use minio::*;
+pub use local_store::*;
+pub use facturx::*;
+pub use invoice_pdf::*;
+pub use einvoice::*;
+pub use archive::*;
+pub use encryption::*;
+pub use backup::*;
+pub use doctor::*;
+pub use unit_of_work::*;
+pub use memory::*;
+pub use workspace::*;
+pub use sync::*;
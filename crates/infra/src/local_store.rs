@@ -0,0 +1,162 @@
+use std::path::{Component, Path, PathBuf};
+
+use domain::{DocumentStore, DomainError, DomainResult, FileInfo};
+
+use crate::minio::document_key;
+
+/// Stockage des justificatifs dans un dossier local, organisé en sous-dossiers YYYY-MM/
+#[derive(Debug, Clone)]
+pub struct LocalDocumentStore {
+    root: PathBuf,
+}
+
+impl LocalDocumentStore {
+    pub fn new(root: impl Into<PathBuf>) -> DomainResult<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .map_err(|e| DomainError::Repo(format!("Impossible de créer le dossier '{}': {}", root.display(), e)))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path_for_key(&self, key: &str) -> DomainResult<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(DomainError::Validation(format!("Clé de document invalide: {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    async fn list_folder(&self, folder: &str) -> DomainResult<Vec<FileInfo>> {
+        let dir = self.root.join(folder);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(DomainError::Repo(format!("Erreur listing fichiers: {}", e))),
+        };
+
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| DomainError::Repo(format!("Erreur listing fichiers: {}", e)))? {
+            let metadata = entry.metadata().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else { continue };
+            let last_modified = metadata
+                .modified()
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).naive_utc())
+                .map_err(|e| DomainError::Repo(e.to_string()))?;
+            files.push(FileInfo {
+                key: format!("{}/{}", folder, name),
+                size_bytes: metadata.len(),
+                last_modified,
+            });
+        }
+        files.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(files)
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for LocalDocumentStore {
+    fn backend(&self) -> &'static str { "local" }
+
+    async fn upload(&self, content: Vec<u8>, original_filename: &str, _content_type: Option<String>) -> DomainResult<String> {
+        let key = document_key(original_filename);
+        self.put(&key, content).await?;
+        Ok(key)
+    }
+
+    async fn put(&self, key: &str, content: Vec<u8>) -> DomainResult<()> {
+        let path = self.path_for_key(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| DomainError::Repo(format!("Erreur création dossier: {}", e)))?;
+        }
+        tokio::fs::write(&path, content).await.map_err(|e| DomainError::Repo(format!("Erreur écriture fichier: {}", e)))
+    }
+
+    async fn delete(&self, key: &str) -> DomainResult<()> {
+        let path = self.path_for_key(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(DomainError::NotFound),
+            Err(e) => Err(DomainError::Repo(format!("Erreur suppression fichier: {}", e))),
+        }
+    }
+
+    async fn list_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>> {
+        self.list_folder(&format!("{:04}-{:02}", year, month)).await
+    }
+
+    async fn list_all(&self) -> DomainResult<Vec<FileInfo>> {
+        let mut folders = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.root).await.map_err(|e| DomainError::Repo(format!("Erreur listing fichiers: {}", e)))?;
+        while let Some(entry) = entries.next_entry().await.map_err(|e| DomainError::Repo(format!("Erreur listing fichiers: {}", e)))? {
+            if entry.file_type().await.map(|t| t.is_dir()).unwrap_or(false) {
+                if let Some(name) = entry.file_name().to_str() {
+                    folders.push(name.to_string());
+                }
+            }
+        }
+        folders.sort();
+
+        let mut files = Vec::new();
+        for folder in folders {
+            files.extend(self.list_folder(&folder).await?);
+        }
+        Ok(files)
+    }
+
+    async fn open(&self, key: &str) -> DomainResult<Vec<u8>> {
+        let path = self.path_for_key(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(DomainError::NotFound),
+            Err(e) => Err(DomainError::Repo(format!("Erreur lecture fichier: {}", e))),
+        }
+    }
+
+    /// Les fichiers locaux ne sont lisibles que par l'utilisateur: l'URL file:// n'expire pas
+    async fn download_url(&self, key: &str, _expires_in_secs: u32) -> DomainResult<String> {
+        let path = self.path_for_key(key)?;
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Err(DomainError::NotFound);
+        }
+        Ok(path_to_url(&path))
+    }
+}
+
+fn path_to_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') { format!("file://{}", path) } else { format!("file:///{}", path) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_store_roundtrip() {
+        let root = std::env::temp_dir().join(format!("cash-planner-docs-{}", uuid::Uuid::new_v4()));
+        let store = LocalDocumentStore::new(&root).unwrap();
+
+        let key = "2024-03/facture.pdf";
+        store.put(key, b"%PDF".to_vec()).await.unwrap();
+        assert_eq!(store.open(key).await.unwrap(), b"%PDF");
+        assert!(store.download_url(key, 60).await.unwrap().starts_with("file://"));
+
+        let files = store.list_by_month(2024, 3).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].key, "2024-03/facture.pdf");
+        assert_eq!(store.stats().await.unwrap().total_size_bytes, 4);
+
+        assert!(store.put("../escape.pdf", vec![]).await.is_err());
+
+        store.delete(key).await.unwrap();
+        assert!(store.list_all().await.unwrap().is_empty());
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
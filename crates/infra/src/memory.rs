@@ -0,0 +1,912 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use domain::{
+    ArchiveRepo, ArchivedDocument, ArchivedFiscalYear, AuditChainCheck, AuditEntity, AuditEvent, AuditQuery, AuditRepo, ConfigRepo, Declaration, DeclarationRepo, DeclarationStatus,
+    DeclarationType, DomainError, DomainResult, KPIRepo, MonthId, MonthPlanning, MonthRepo, MonthStatus, MonthlyKPI,
+    NewAuditEvent, Operation, OperationPage, OperationQuery, OperationRepo, OperationType, Provision, ProvisionRepo, Settings, Simulation,
+    SimulationRepo, SyncConflict, SyncRepo, SyncSettings, SyncStamp, TrashedOperation, TrashedYearlyPlanning, UnitOfWork, UnitOfWorkFactory, WorkingDay,
+    WorkingDayRepo, WorkingDaysStats, YearlyPlanning, YearlyPlanningRepo, AUDIT_GENESIS_HASH,
+};
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::sqlite::audit_hash;
+
+/// Rows of the transactional tables, with their trash timestamps
+#[derive(Clone, Default)]
+struct MemoryState {
+    settings: Option<Settings>,
+    closed_months: HashMap<(i32, u32), NaiveDateTime>,
+    provisions: Vec<Provision>,
+    operations: Vec<(Operation, Option<NaiveDateTime>)>,
+    declarations: Vec<Declaration>,
+    plannings: Vec<(YearlyPlanning, Option<NaiveDateTime>)>,
+    audit_events: Vec<AuditEvent>,
+    working_days: Vec<WorkingDay>,
+    simulations: Vec<Simulation>,
+    kpis: Vec<MonthlyKPI>,
+    sync_settings: Option<SyncSettings>,
+    published_audit_seq: i64,
+    sync_positions: HashMap<String, i64>,
+    sync_clocks: HashMap<(&'static str, String, String), SyncStamp>, // Keyed by entity name, id and field
+    sync_conflicts: Vec<SyncConflict>,
+    archived_years: Vec<ArchivedFiscalYear>,
+    archived_documents: Vec<ArchivedDocument>,
+}
+
+/// In-memory stand-in for the repositories a unit of work covers, for tests
+/// Units of work begun on it work on a copy of the state, written back on commit
+#[derive(Clone)]
+pub struct InMemoryRepos {
+    state: Arc<Mutex<Option<MemoryState>>>, // None once the unit of work holding it has ended
+}
+
+impl Default for InMemoryRepos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryRepos {
+    pub fn new() -> Self {
+        Self { state: Arc::new(Mutex::new(Some(MemoryState::default()))) }
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut MemoryState) -> DomainResult<T>) -> DomainResult<T> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match state.as_mut() {
+            Some(state) => f(state),
+            None => Err(DomainError::Repo("Transaction déjà validée ou annulée".to_string())),
+        }
+    }
+
+    fn take(&self) -> DomainResult<MemoryState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take()
+            .ok_or_else(|| DomainError::Repo("Transaction déjà validée ou annulée".to_string()))
+    }
+}
+
+fn same_month(date: chrono::NaiveDate, month: &MonthId) -> bool {
+    date.year() == month.year && date.month() == month.month
+}
+
+#[async_trait::async_trait]
+impl ProvisionRepo for InMemoryRepos {
+    async fn upsert_provision(&self, p: Provision) -> DomainResult<()> {
+        self.with(|s| {
+            // Same conflict target as the provisions table: one provision per type and period
+            match s.provisions.iter_mut().find(|e| e.provision_type == p.provision_type && e.period_year == p.period_year && e.period_month == p.period_month) {
+                Some(existing) => *existing = Provision { created_at: existing.created_at, ..p },
+                None => s.provisions.push(p),
+            }
+            Ok(())
+        })
+    }
+
+    async fn list_provisions(&self, month: Option<MonthId>) -> DomainResult<Vec<Provision>> {
+        self.with(|s| Ok(s.provisions.iter()
+            .filter(|p| month.as_ref().is_none_or(|m| p.period_year == m.year && p.period_month == m.month))
+            .cloned()
+            .collect()))
+    }
+
+    async fn delete_provision(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            s.provisions.retain(|p| p.id != id);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ConfigRepo for InMemoryRepos {
+    async fn load_settings(&self) -> DomainResult<Settings> {
+        self.with(|s| Ok(s.settings.clone().unwrap_or_default()))
+    }
+
+    async fn save_settings(&self, settings: Settings) -> DomainResult<()> {
+        self.with(|s| {
+            s.settings = Some(settings);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl MonthRepo for InMemoryRepos {
+    async fn get_status(&self, month: &MonthId) -> DomainResult<MonthStatus> {
+        self.with(|s| Ok(MonthStatus { month: month.clone(), closed_at: s.closed_months.get(&(month.year, month.month)).copied() }))
+    }
+
+    async fn close_month(&self, month: &MonthId, closed_at: NaiveDateTime) -> DomainResult<()> {
+        self.with(|s| {
+            s.closed_months.insert((month.year, month.month), closed_at);
+            Ok(())
+        })
+    }
+
+    async fn list_closed_months(&self) -> DomainResult<Vec<MonthStatus>> {
+        self.with(|s| {
+            let mut months: Vec<MonthStatus> = s.closed_months.iter()
+                .map(|(&(year, month), &closed_at)| MonthStatus { month: MonthId::new(year, month), closed_at: Some(closed_at) })
+                .collect();
+            months.sort_by_key(|m| (m.month.year, m.month.month));
+            Ok(months)
+        })
+    }
+}
+
+impl MemoryState {
+    fn live_operations(&self) -> impl Iterator<Item = &Operation> {
+        self.operations.iter().filter(|(_, deleted_at)| deleted_at.is_none()).map(|(operation, _)| operation)
+    }
+}
+
+/// Lowercased and without diacritics, like the `unicode61 remove_diacritics 2` tokenizer of the
+/// SQLite index: letters are decomposed and their combining marks dropped. Letters that do not
+/// decompose, such as ø, ł or æ, are kept as they are
+fn folded(text: &str) -> String {
+    text.to_lowercase().nfd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+/// The filters of `OPERATION_FILTERS` in the SQLite repository
+fn matches_query(o: &Operation, q: &OperationQuery, terms: &[String]) -> bool {
+    let within = |date: Option<NaiveDate>, from: Option<NaiveDate>, to: Option<NaiveDate>| {
+        from.is_none_or(|from| date.is_some_and(|d| d >= from)) && to.is_none_or(|to| date.is_some_and(|d| d <= to))
+    };
+    let words: Vec<String> = [o.label.as_deref(), o.client.as_deref()].into_iter().flatten()
+        .flat_map(|text| text.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(folded)
+        .collect();
+    within(Some(o.invoice_date), q.invoice_from, q.invoice_to)
+        && within(o.payment_date, q.payment_from, q.payment_to)
+        && q.operation_type.as_ref().is_none_or(|t| std::mem::discriminant(t) == std::mem::discriminant(&o.operation_type))
+        && q.paid.is_none_or(|paid| paid == o.payment_date.is_some())
+        && q.min_amount_ht_cents.is_none_or(|min| o.amount_ht_cents >= min)
+        && q.max_amount_ht_cents.is_none_or(|max| o.amount_ht_cents <= max)
+        && q.client.as_deref().is_none_or(|client| o.client.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(client)))
+        && (q.categories.is_empty() || o.category.as_ref().is_some_and(|c| q.categories.contains(c)))
+        && q.has_receipt.is_none_or(|has| has == o.receipt_key.as_deref().is_some_and(|k| !k.is_empty()))
+        && terms.iter().all(|term| words.iter().any(|word| word.starts_with(term.as_str())))
+}
+
+/// Most recent invoice date first, like the operations queries
+fn sorted_by_invoice_date(mut operations: Vec<Operation>) -> Vec<Operation> {
+    operations.sort_by_key(|o| std::cmp::Reverse(o.invoice_date));
+    operations
+}
+
+#[async_trait::async_trait]
+impl OperationRepo for InMemoryRepos {
+    async fn create_operation(&self, operation: Operation) -> DomainResult<()> {
+        self.with(|s| {
+            if s.operations.iter().any(|(o, _)| o.id == operation.id) {
+                return Err(DomainError::Repo(format!("UNIQUE constraint failed: operations.id ({})", operation.id)));
+            }
+            s.operations.push((operation, None));
+            Ok(())
+        })
+    }
+
+    async fn get_operation(&self, id: uuid::Uuid) -> DomainResult<Operation> {
+        self.with(|s| s.live_operations().find(|o| o.id == id).cloned().ok_or(DomainError::NotFound))
+    }
+
+    async fn update_operation(&self, operation: Operation) -> DomainResult<()> {
+        self.with(|s| {
+            if let Some((existing, _)) = s.operations.iter_mut().find(|(o, _)| o.id == operation.id) {
+                *existing = Operation { created_at: existing.created_at, ..operation };
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            s.operations.retain(|(o, _)| o.id != id);
+            Ok(())
+        })
+    }
+
+    async fn list_operations(&self, month: Option<MonthId>) -> DomainResult<Vec<Operation>> {
+        self.with(|s| Ok(sorted_by_invoice_date(s.live_operations()
+            .filter(|o| month.as_ref().is_none_or(|m| same_month(o.invoice_date, m)))
+            .cloned()
+            .collect())))
+    }
+
+    async fn list_operations_by_type(&self, operation_type: OperationType, month: Option<MonthId>) -> DomainResult<Vec<Operation>> {
+        Ok(self.list_operations(month).await?
+            .into_iter()
+            .filter(|o| std::mem::discriminant(&o.operation_type) == std::mem::discriminant(&operation_type))
+            .collect())
+    }
+
+    async fn list_operations_by_payment_month(&self, month: MonthId) -> DomainResult<Vec<Operation>> {
+        self.with(|s| {
+            let mut operations: Vec<Operation> = s.live_operations()
+                .filter(|o| o.payment_date.is_some_and(|d| same_month(d, &month)))
+                .cloned()
+                .collect();
+            operations.sort_by_key(|o| std::cmp::Reverse(o.payment_date));
+            Ok(operations)
+        })
+    }
+
+    async fn list_operations_by_receipt_sha256(&self, sha256: &str) -> DomainResult<Vec<Operation>> {
+        self.with(|s| {
+            let mut operations: Vec<Operation> = s.live_operations()
+                .filter(|o| o.receipt_sha256.as_deref() == Some(sha256))
+                .cloned()
+                .collect();
+            operations.sort_by_key(|o| o.created_at);
+            Ok(operations)
+        })
+    }
+
+    async fn query_operations(&self, query: &OperationQuery) -> DomainResult<OperationPage> {
+        let after = query.after()?;
+        let terms: Vec<String> = query.search_terms().iter().map(|t| folded(t)).collect();
+        let position = |o: &Operation| (query.sort.value_of(o), o.id);
+        self.with(|s| {
+            let mut operations: Vec<Operation> = s.live_operations().filter(|o| matches_query(o, query, &terms)).cloned().collect();
+            operations.sort_by_key(position);
+            if query.descending {
+                operations.reverse();
+            }
+            let total = operations.len() as u64;
+            let start = after.map_or(0, |after| {
+                let last = (after.value, after.id);
+                operations.iter()
+                    .position(|o| if query.descending { position(o) < last } else { position(o) > last })
+                    .unwrap_or(operations.len())
+            });
+            let page_size = query.page_size() as usize;
+            let mut page: Vec<Operation> = operations.into_iter().skip(start).take(page_size + 1).collect();
+            let next_cursor = if page.len() > page_size {
+                page.truncate(page_size);
+                page.last().map(|o| query.cursor_of(o))
+            } else {
+                None
+            };
+            Ok(OperationPage { operations: page, total, next_cursor })
+        })
+    }
+
+    async fn trash_operation(&self, id: uuid::Uuid, deleted_at: NaiveDateTime) -> DomainResult<()> {
+        self.with(|s| match s.operations.iter_mut().find(|(o, trashed)| o.id == id && trashed.is_none()) {
+            Some((_, trashed)) => {
+                *trashed = Some(deleted_at);
+                Ok(())
+            }
+            None => Err(DomainError::NotFound),
+        })
+    }
+
+    async fn restore_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| match s.operations.iter_mut().find(|(o, trashed)| o.id == id && trashed.is_some()) {
+            Some((_, trashed)) => {
+                *trashed = None;
+                Ok(())
+            }
+            None => Err(DomainError::NotFound),
+        })
+    }
+
+    async fn list_trashed_operations(&self) -> DomainResult<Vec<TrashedOperation>> {
+        self.with(|s| {
+            let mut trashed: Vec<TrashedOperation> = s.operations.iter()
+                .filter_map(|(o, deleted_at)| deleted_at.map(|deleted_at| TrashedOperation { operation: o.clone(), deleted_at }))
+                .collect();
+            trashed.sort_by_key(|t| std::cmp::Reverse(t.deleted_at));
+            Ok(trashed)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl DeclarationRepo for InMemoryRepos {
+    async fn create_declaration(&self, declaration: Declaration) -> DomainResult<()> {
+        self.with(|s| {
+            if s.declarations.iter().any(|d| d.id == declaration.id) {
+                return Err(DomainError::Repo(format!("UNIQUE constraint failed: declarations.id ({})", declaration.id)));
+            }
+            s.declarations.push(declaration);
+            Ok(())
+        })
+    }
+
+    async fn get_declaration(&self, id: uuid::Uuid) -> DomainResult<Declaration> {
+        self.with(|s| s.declarations.iter().find(|d| d.id == id).cloned().ok_or(DomainError::NotFound))
+    }
+
+    async fn update_declaration(&self, declaration: Declaration) -> DomainResult<()> {
+        self.with(|s| {
+            if let Some(existing) = s.declarations.iter_mut().find(|d| d.id == declaration.id) {
+                *existing = Declaration { created_at: existing.created_at, ..declaration };
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_declaration(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            s.declarations.retain(|d| d.id != id);
+            Ok(())
+        })
+    }
+
+    async fn list_declarations(&self, year: Option<i32>) -> DomainResult<Vec<Declaration>> {
+        self.with(|s| {
+            let mut declarations: Vec<Declaration> = s.declarations.iter()
+                .filter(|d| year.is_none_or(|y| d.period_year == y))
+                .cloned()
+                .collect();
+            declarations.sort_by_key(|d| std::cmp::Reverse((d.period_year, d.period_month)));
+            Ok(declarations)
+        })
+    }
+
+    async fn get_declaration_by_period(&self, declaration_type: DeclarationType, year: i32, month: u32) -> DomainResult<Option<Declaration>> {
+        self.with(|s| Ok(s.declarations.iter()
+            .find(|d| std::mem::discriminant(&d.declaration_type) == std::mem::discriminant(&declaration_type)
+                && d.period_year == year
+                && d.period_month == month)
+            .cloned()))
+    }
+
+    async fn list_declarations_by_status(&self, status: DeclarationStatus) -> DomainResult<Vec<Declaration>> {
+        self.with(|s| {
+            let mut declarations: Vec<Declaration> = s.declarations.iter().filter(|d| d.status == status).cloned().collect();
+            declarations.sort_by_key(|d| d.due_date);
+            Ok(declarations)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl YearlyPlanningRepo for InMemoryRepos {
+    async fn create_yearly_planning(&self, planning: YearlyPlanning) -> DomainResult<()> {
+        self.with(|s| {
+            if s.plannings.iter().any(|(p, _)| p.year == planning.year) {
+                return Err(DomainError::Repo(format!("UNIQUE constraint failed: yearly_planning.year ({})", planning.year)));
+            }
+            s.plannings.push((planning, None));
+            Ok(())
+        })
+    }
+
+    async fn update_yearly_planning(&self, planning: YearlyPlanning) -> DomainResult<()> {
+        self.with(|s| {
+            if let Some((existing, _)) = s.plannings.iter_mut().find(|(p, _)| p.year == planning.year) {
+                *existing = YearlyPlanning { id: existing.id, created_at: existing.created_at, ..planning };
+            }
+            Ok(())
+        })
+    }
+
+    async fn get_yearly_planning(&self, year: i32) -> DomainResult<Option<YearlyPlanning>> {
+        self.with(|s| Ok(s.plannings.iter().find(|(p, trashed)| p.year == year && trashed.is_none()).map(|(p, _)| p.clone())))
+    }
+
+    async fn delete_yearly_planning(&self, year: i32) -> DomainResult<()> {
+        self.with(|s| {
+            s.plannings.retain(|(p, _)| p.year != year);
+            Ok(())
+        })
+    }
+
+    async fn list_yearly_plannings(&self) -> DomainResult<Vec<YearlyPlanning>> {
+        self.with(|s| {
+            let mut plannings: Vec<YearlyPlanning> = s.plannings.iter()
+                .filter(|(_, trashed)| trashed.is_none())
+                .map(|(p, _)| p.clone())
+                .collect();
+            plannings.sort_by_key(|p| std::cmp::Reverse(p.year));
+            Ok(plannings)
+        })
+    }
+
+    async fn update_month_planning(&self, month_planning: MonthPlanning) -> DomainResult<()> {
+        self.with(|s| {
+            let month = s.plannings.iter_mut()
+                .filter(|(p, _)| p.year == month_planning.year)
+                .flat_map(|(p, _)| p.months.iter_mut())
+                .find(|m| m.month == month_planning.month);
+            if let Some(month) = month {
+                *month = MonthPlanning { id: month.id, created_at: month.created_at, ..month_planning };
+            }
+            Ok(())
+        })
+    }
+
+    async fn get_month_planning(&self, year: i32, month: u32) -> DomainResult<Option<MonthPlanning>> {
+        Ok(self.get_yearly_planning(year).await?.and_then(|p| p.months.into_iter().find(|m| m.month == month)))
+    }
+
+    async fn trash_yearly_planning(&self, year: i32, deleted_at: NaiveDateTime) -> DomainResult<()> {
+        self.with(|s| match s.plannings.iter_mut().find(|(p, trashed)| p.year == year && trashed.is_none()) {
+            Some((_, trashed)) => {
+                *trashed = Some(deleted_at);
+                Ok(())
+            }
+            None => Err(DomainError::NotFound),
+        })
+    }
+
+    async fn restore_yearly_planning(&self, year: i32) -> DomainResult<()> {
+        self.with(|s| match s.plannings.iter_mut().find(|(p, trashed)| p.year == year && trashed.is_some()) {
+            Some((_, trashed)) => {
+                *trashed = None;
+                Ok(())
+            }
+            None => Err(DomainError::NotFound),
+        })
+    }
+
+    async fn list_trashed_yearly_plannings(&self) -> DomainResult<Vec<TrashedYearlyPlanning>> {
+        self.with(|s| {
+            let mut trashed: Vec<TrashedYearlyPlanning> = s.plannings.iter()
+                .filter_map(|(p, deleted_at)| deleted_at.map(|deleted_at| TrashedYearlyPlanning { planning: p.clone(), deleted_at }))
+                .collect();
+            trashed.sort_by_key(|t| std::cmp::Reverse(t.deleted_at));
+            Ok(trashed)
+        })
+    }
+}
+
+fn to_json(value: &Option<serde_json::Value>) -> DomainResult<Option<String>> {
+    value.as_ref().map(serde_json::to_string).transpose().map_err(|e| DomainError::Validation(e.to_string()))
+}
+
+/// Same chaining as the audit_events table, hashing the JSON states as serialized
+fn event_hash(event: &AuditEvent) -> DomainResult<String> {
+    let new_event = NewAuditEvent {
+        entity: event.entity,
+        entity_id: event.entity_id.clone(),
+        action: event.action,
+        before: event.before.clone(),
+        after: event.after.clone(),
+        origin: event.origin.clone(),
+    };
+    let occurred_at = event.occurred_at.format("%Y-%m-%d %H:%M:%S").to_string();
+    Ok(audit_hash(&event.prev_hash, event.seq, &new_event, to_json(&event.before)?.as_deref(), to_json(&event.after)?.as_deref(), &occurred_at))
+}
+
+#[async_trait::async_trait]
+impl AuditRepo for InMemoryRepos {
+    async fn append_audit_event(&self, event: NewAuditEvent) -> DomainResult<AuditEvent> {
+        let now = chrono::Utc::now().naive_utc();
+        let now = now.with_nanosecond(0).unwrap_or(now);
+        self.with(|s| {
+            let (seq, prev_hash) = match s.audit_events.last() {
+                Some(head) => (head.seq + 1, head.hash.clone()),
+                None => (1, AUDIT_GENESIS_HASH.to_string()),
+            };
+            let mut appended = AuditEvent {
+                seq,
+                entity: event.entity,
+                entity_id: event.entity_id,
+                action: event.action,
+                before: event.before,
+                after: event.after,
+                occurred_at: now,
+                origin: event.origin,
+                prev_hash,
+                hash: String::new(),
+            };
+            appended.hash = event_hash(&appended)?;
+            s.audit_events.push(appended.clone());
+            Ok(appended)
+        })
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> DomainResult<Vec<AuditEvent>> {
+        self.with(|s| Ok(s.audit_events.iter().rev()
+            .filter(|e| query.entity.is_none_or(|entity| e.entity == entity))
+            .filter(|e| query.entity_id.as_ref().is_none_or(|id| &e.entity_id == id))
+            .filter(|e| query.before_seq.is_none_or(|seq| e.seq < seq))
+            .filter(|e| query.after_seq.is_none_or(|seq| e.seq > seq))
+            .take(query.limit.map_or(usize::MAX, |l| l as usize))
+            .cloned()
+            .collect()))
+    }
+
+    async fn verify_audit_chain(&self) -> DomainResult<AuditChainCheck> {
+        self.with(|s| {
+            let mut check = AuditChainCheck { events_count: s.audit_events.len() as u64, last_hash: None, broken_at_seq: None, message: None };
+            let mut prev_hash = AUDIT_GENESIS_HASH.to_string();
+            for (index, event) in s.audit_events.iter().enumerate() {
+                let problem = if event.seq != index as i64 + 1 {
+                    Some(format!("Événement manquant avant le n°{}", event.seq))
+                } else if event.prev_hash != prev_hash {
+                    Some(format!("Chaînage rompu au n°{}", event.seq))
+                } else if event_hash(event)? != event.hash {
+                    Some(format!("Contenu modifié au n°{}", event.seq))
+                } else {
+                    None
+                };
+                if let Some(message) = problem {
+                    check.broken_at_seq = Some(event.seq);
+                    check.message = Some(message);
+                    break;
+                }
+                prev_hash = event.hash.clone();
+            }
+            check.last_hash = s.audit_events.last().map(|e| e.hash.clone());
+            Ok(check)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkingDayRepo for InMemoryRepos {
+    async fn create_working_day(&self, working_day: WorkingDay) -> DomainResult<()> {
+        self.with(|s| {
+            if s.working_days.iter().any(|w| w.id == working_day.id) {
+                return Err(DomainError::Repo(format!("Journée {} déjà enregistrée", working_day.id)));
+            }
+            s.working_days.push(working_day);
+            Ok(())
+        })
+    }
+
+    async fn update_working_day(&self, working_day: WorkingDay) -> DomainResult<()> {
+        self.with(|s| {
+            if let Some(existing) = s.working_days.iter_mut().find(|w| w.id == working_day.id) {
+                *existing = WorkingDay { created_at: existing.created_at, ..working_day };
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_working_day(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            s.working_days.retain(|w| w.id != id);
+            Ok(())
+        })
+    }
+
+    async fn get_working_day(&self, id: uuid::Uuid) -> DomainResult<WorkingDay> {
+        self.with(|s| s.working_days.iter().find(|w| w.id == id).cloned().ok_or(DomainError::NotFound))
+    }
+
+    async fn list_working_days(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<WorkingDay>> {
+        self.with(|s| {
+            let mut days: Vec<WorkingDay> = s.working_days.iter()
+                .filter(|w| start_date.is_none_or(|start| w.date >= start) && end_date.is_none_or(|end| w.date <= end))
+                .cloned()
+                .collect();
+            days.sort_by_key(|w| std::cmp::Reverse(w.date));
+            Ok(days)
+        })
+    }
+
+    async fn get_working_days_for_month(&self, month: &MonthId) -> DomainResult<Vec<WorkingDay>> {
+        self.with(|s| {
+            let mut days: Vec<WorkingDay> = s.working_days.iter().filter(|w| same_month(w.date, month)).cloned().collect();
+            days.sort_by_key(|w| w.date);
+            Ok(days)
+        })
+    }
+
+    async fn get_working_days_stats(&self, start_date: NaiveDate, end_date: NaiveDate) -> DomainResult<WorkingDaysStats> {
+        let days = self.list_working_days(Some(start_date), Some(end_date)).await?;
+        let count = days.len() as f64;
+        let average = |total: f64| if days.is_empty() { 0.0 } else { total / count };
+        let total_billable_hours: f64 = days.iter().map(|w| w.billable_hours).sum();
+        let total_worked_hours: f64 = days.iter().map(|w| w.hours_worked).sum();
+        Ok(WorkingDaysStats {
+            total_working_days: count,
+            total_billable_hours,
+            total_worked_hours,
+            average_daily_rate_cents: average(days.iter().map(|w| w.hourly_rate_cents as f64 * w.billable_hours).sum()) as i64,
+            average_hourly_rate_cents: average(days.iter().map(|w| w.hourly_rate_cents as f64).sum()) as i64,
+            utilization_rate: if total_worked_hours > 0.0 { total_billable_hours / total_worked_hours } else { 0.0 },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SimulationRepo for InMemoryRepos {
+    async fn create_simulation(&self, simulation: Simulation) -> DomainResult<()> {
+        self.with(|s| {
+            if s.simulations.iter().any(|e| e.id == simulation.id) {
+                return Err(DomainError::Repo(format!("Simulation {} déjà enregistrée", simulation.id)));
+            }
+            s.simulations.push(simulation);
+            Ok(())
+        })
+    }
+
+    async fn update_simulation(&self, simulation: Simulation) -> DomainResult<()> {
+        self.with(|s| {
+            if let Some(existing) = s.simulations.iter_mut().find(|e| e.id == simulation.id) {
+                *existing = Simulation { created_at: existing.created_at, ..simulation };
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_simulation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            s.simulations.retain(|e| e.id != id);
+            Ok(())
+        })
+    }
+
+    async fn get_simulation(&self, id: uuid::Uuid) -> DomainResult<Simulation> {
+        self.with(|s| s.simulations.iter().find(|e| e.id == id).cloned().ok_or(DomainError::NotFound))
+    }
+
+    async fn list_simulations(&self) -> DomainResult<Vec<Simulation>> {
+        self.with(|s| {
+            let mut simulations = s.simulations.clone();
+            simulations.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+            Ok(simulations)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl KPIRepo for InMemoryRepos {
+    async fn save_monthly_kpi(&self, kpi: MonthlyKPI) -> DomainResult<()> {
+        self.with(|s| {
+            // Same conflict target as the monthly_kpis table: one row per month
+            match s.kpis.iter_mut().find(|k| k.month.year == kpi.month.year && k.month.month == kpi.month.month) {
+                Some(existing) => *existing = MonthlyKPI { created_at: existing.created_at, ..kpi },
+                None => s.kpis.push(kpi),
+            }
+            Ok(())
+        })
+    }
+
+    async fn get_monthly_kpi(&self, month: &MonthId) -> DomainResult<Option<MonthlyKPI>> {
+        self.with(|s| Ok(s.kpis.iter().find(|k| k.month.year == month.year && k.month.month == month.month).cloned()))
+    }
+
+    async fn list_monthly_kpis(&self, start_month: &MonthId, end_month: &MonthId) -> DomainResult<Vec<MonthlyKPI>> {
+        self.with(|s| {
+            let key = |m: &MonthId| (m.year, m.month);
+            let mut kpis: Vec<MonthlyKPI> = s.kpis.iter()
+                .filter(|k| key(&k.month) >= key(start_month) && key(&k.month) <= key(end_month))
+                .cloned()
+                .collect();
+            kpis.sort_by_key(|k| key(&k.month));
+            Ok(kpis)
+        })
+    }
+
+    async fn delete_monthly_kpi(&self, month: &MonthId) -> DomainResult<()> {
+        self.with(|s| {
+            s.kpis.retain(|k| k.month.year != month.year || k.month.month != month.month);
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncRepo for InMemoryRepos {
+    async fn load_sync_settings(&self) -> DomainResult<Option<SyncSettings>> {
+        self.with(|s| Ok(s.sync_settings.clone()))
+    }
+
+    async fn save_sync_settings(&self, settings: SyncSettings) -> DomainResult<()> {
+        self.with(|s| {
+            s.sync_settings = Some(settings);
+            Ok(())
+        })
+    }
+
+    async fn published_audit_seq(&self) -> DomainResult<i64> {
+        self.with(|s| Ok(s.published_audit_seq))
+    }
+
+    async fn set_published_audit_seq(&self, seq: i64) -> DomainResult<()> {
+        self.with(|s| {
+            if s.sync_settings.is_none() {
+                return Err(DomainError::Validation("Synchronisation non configurée".into()));
+            }
+            s.published_audit_seq = seq;
+            Ok(())
+        })
+    }
+
+    async fn applied_positions(&self) -> DomainResult<HashMap<String, i64>> {
+        self.with(|s| Ok(s.sync_positions.clone()))
+    }
+
+    async fn set_applied_position(&self, peer: &str, seq: i64) -> DomainResult<()> {
+        self.with(|s| {
+            s.sync_positions.insert(peer.to_string(), seq);
+            Ok(())
+        })
+    }
+
+    async fn field_clocks(&self, entity: AuditEntity, entity_id: &str) -> DomainResult<HashMap<String, SyncStamp>> {
+        self.with(|s| Ok(s.sync_clocks.iter()
+            .filter(|((e, id, _), _)| *e == entity.as_str() && id == entity_id)
+            .map(|((_, _, field), stamp)| (field.clone(), stamp.clone()))
+            .collect()))
+    }
+
+    async fn advance_field_clock(&self, entity: AuditEntity, entity_id: &str, field: &str, stamp: &SyncStamp) -> DomainResult<()> {
+        self.with(|s| {
+            let clock = s.sync_clocks.entry((entity.as_str(), entity_id.to_string(), field.to_string())).or_insert_with(|| stamp.clone());
+            if *clock < *stamp {
+                *clock = stamp.clone();
+            }
+            Ok(())
+        })
+    }
+
+    async fn add_sync_conflict(&self, conflict: SyncConflict) -> DomainResult<()> {
+        self.with(|s| {
+            s.sync_conflicts.push(conflict);
+            Ok(())
+        })
+    }
+
+    async fn list_sync_conflicts(&self) -> DomainResult<Vec<SyncConflict>> {
+        self.with(|s| {
+            let mut conflicts = s.sync_conflicts.clone();
+            conflicts.sort_by_key(|c| std::cmp::Reverse(c.detected_at));
+            Ok(conflicts)
+        })
+    }
+
+    async fn dismiss_sync_conflict(&self, id: uuid::Uuid) -> DomainResult<()> {
+        self.with(|s| {
+            let count = s.sync_conflicts.len();
+            s.sync_conflicts.retain(|c| c.id != id);
+            if s.sync_conflicts.len() == count {
+                return Err(DomainError::NotFound);
+            }
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ArchiveRepo for InMemoryRepos {
+    async fn archive_fiscal_year(&self, archive: ArchivedFiscalYear, documents: Vec<ArchivedDocument>) -> DomainResult<()> {
+        self.with(|s| {
+            if s.archived_years.iter().any(|a| a.year == archive.year) {
+                return Err(DomainError::Validation(format!("L'exercice {} est déjà archivé", archive.year)));
+            }
+            s.archived_years.push(archive);
+            s.archived_years.sort_by_key(|a| a.year);
+            for document in documents {
+                match s.archived_documents.iter_mut().find(|d| d.key == document.key) {
+                    // A document shared by several years keeps the latest retention date
+                    Some(existing) => {
+                        if document.retain_until > existing.retain_until {
+                            existing.fiscal_year = document.fiscal_year;
+                            existing.retain_until = document.retain_until;
+                        }
+                        existing.object_locked |= document.object_locked;
+                    }
+                    None => s.archived_documents.push(document),
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn list_archived_years(&self) -> DomainResult<Vec<ArchivedFiscalYear>> {
+        self.with(|s| Ok(s.archived_years.clone()))
+    }
+
+    async fn list_archived_documents(&self) -> DomainResult<Vec<ArchivedDocument>> {
+        self.with(|s| {
+            let mut documents = s.archived_documents.clone();
+            documents.sort_by(|a, b| (a.fiscal_year, &a.key).cmp(&(b.fiscal_year, &b.key)));
+            Ok(documents)
+        })
+    }
+
+    async fn get_archived_document(&self, key: &str) -> DomainResult<Option<ArchivedDocument>> {
+        self.with(|s| Ok(s.archived_documents.iter().find(|d| d.key == key).cloned()))
+    }
+
+    async fn mark_object_locked(&self, key: &str) -> DomainResult<()> {
+        self.with(|s| {
+            let document = s.archived_documents.iter_mut().find(|d| d.key == key).ok_or(DomainError::NotFound)?;
+            document.object_locked = true;
+            Ok(())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWorkFactory for InMemoryRepos {
+    async fn begin(&self) -> DomainResult<Box<dyn UnitOfWork>> {
+        let snapshot = self.with(|s| Ok(s.clone()))?;
+        Ok(Box::new(InMemoryUnitOfWork {
+            target: self.clone(),
+            working: InMemoryRepos { state: Arc::new(Mutex::new(Some(snapshot))) },
+        }))
+    }
+}
+
+/// Works on a snapshot of the store; committing replaces the store with it, so the last commit wins
+pub struct InMemoryUnitOfWork {
+    target: InMemoryRepos,
+    working: InMemoryRepos,
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for InMemoryUnitOfWork {
+    fn provisions(&self) -> Arc<dyn ProvisionRepo> { Arc::new(self.working.clone()) }
+    fn config(&self) -> Arc<dyn ConfigRepo> { Arc::new(self.working.clone()) }
+    fn months(&self) -> Arc<dyn MonthRepo> { Arc::new(self.working.clone()) }
+    fn operations(&self) -> Arc<dyn OperationRepo> { Arc::new(self.working.clone()) }
+    fn declarations(&self) -> Arc<dyn DeclarationRepo> { Arc::new(self.working.clone()) }
+    fn yearly_planning(&self) -> Arc<dyn YearlyPlanningRepo> { Arc::new(self.working.clone()) }
+    fn audit(&self) -> Arc<dyn AuditRepo> { Arc::new(self.working.clone()) }
+    fn working_days(&self) -> Arc<dyn WorkingDayRepo> { Arc::new(self.working.clone()) }
+    fn simulations(&self) -> Arc<dyn SimulationRepo> { Arc::new(self.working.clone()) }
+    fn kpis(&self) -> Arc<dyn KPIRepo> { Arc::new(self.working.clone()) }
+    fn sync(&self) -> Arc<dyn SyncRepo> { Arc::new(self.working.clone()) }
+    fn archives(&self) -> Arc<dyn ArchiveRepo> { Arc::new(self.working.clone()) }
+
+    async fn commit(&self) -> DomainResult<()> {
+        let state = self.working.take()?;
+        self.target.with(|s| {
+            *s = state;
+            Ok(())
+        })
+    }
+
+    async fn rollback(&self) -> DomainResult<()> {
+        self.working.take().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::ProvisionType;
+
+    fn provision(amount_cents: i64) -> Provision {
+        let now = chrono::Utc::now().naive_utc();
+        Provision {
+            id: uuid::Uuid::new_v4(),
+            period_year: 2025,
+            period_month: 3,
+            provision_type: ProvisionType::Vat,
+            amount_cents,
+            due_date: chrono::NaiveDate::from_ymd_opt(2025, 4, 24).unwrap(),
+            status: domain::ProvisionStatus::Pending,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_unit_of_work_isolates_until_commit() {
+        let repos = InMemoryRepos::new();
+        let march = MonthId::new(2025, 3);
+
+        let unit = repos.begin().await.unwrap();
+        unit.provisions().upsert_provision(provision(12000)).await.unwrap();
+        unit.months().close_month(&march, chrono::Utc::now().naive_utc()).await.unwrap();
+        assert!(repos.list_provisions(None).await.unwrap().is_empty());
+        unit.rollback().await.unwrap();
+        assert!(repos.get_status(&march).await.unwrap().closed_at.is_none());
+        assert!(unit.provisions().list_provisions(None).await.is_err());
+
+        let unit = repos.begin().await.unwrap();
+        unit.provisions().upsert_provision(provision(12000)).await.unwrap();
+        unit.provisions().upsert_provision(provision(15000)).await.unwrap();
+        unit.months().close_month(&march, chrono::Utc::now().naive_utc()).await.unwrap();
+        unit.commit().await.unwrap();
+        let provisions = repos.list_provisions(Some(march.clone())).await.unwrap();
+        assert_eq!(provisions.iter().map(|p| p.amount_cents).collect::<Vec<_>>(), vec![15000]);
+        assert!(repos.get_status(&march).await.unwrap().closed_at.is_some());
+    }
+}
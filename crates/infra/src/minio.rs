@@ -1,8 +1,6 @@
-use std::collections::HashMap;
-
 use bytes::Bytes;
-use chrono::{Utc, Datelike};
-use domain::{DomainError, DomainResult};
+use chrono::{NaiveDate, Utc, Datelike};
+use domain::{DocumentStore, DomainError, DomainResult, FileInfo, StorageStats};
 use mime_guess::MimeGuess;
 use s3::{Bucket, Region, creds::Credentials};
 use s3::bucket_ops::BucketConfiguration;
@@ -16,8 +14,8 @@ pub struct MinioConfig {
     pub access_key: String,
     pub secret_key: String,
     pub bucket_name: String,
-    pub public_url: String,
     pub use_ssl: bool,
+    pub object_lock: bool, // Bucket créé avec Object Lock: les documents archivés y sont verrouillés
 }
 
 impl Default for MinioConfig {
@@ -28,8 +26,8 @@ impl Default for MinioConfig {
             access_key: std::env::var("MINIO_ACCESS_KEY").unwrap_or_else(|_| "yOV6ceBtGNt99h1yqSvW".to_string()),
             secret_key: std::env::var("MINIO_SECRET_KEY").unwrap_or_else(|_| "dxn0LFYgzJaSmoEUXkCpUBc3f6FSWpzFjiQG4QG2".to_string()),
             bucket_name: std::env::var("MINIO_BUCKET_NAME").unwrap_or_else(|_| "cash-planner".to_string()),
-            public_url: std::env::var("MINIO_PUBLIC_URL").unwrap_or_else(|_| "https://minio.jla-dev.com".to_string()),
             use_ssl: std::env::var("MINIO_PORT").unwrap_or_else(|_| "443".to_string()) == "443",
+            object_lock: std::env::var("MINIO_OBJECT_LOCK").map(|v| v == "true").unwrap_or(false),
         }
     }
 }
@@ -77,6 +75,29 @@ impl MinioService {
         Ok(service)
     }
 
+    /// Construit le service sans contacter le serveur: les erreurs de connexion
+    /// remontent à la première opération au lieu de bloquer le démarrage
+    pub fn new_lazy(config: MinioConfig) -> DomainResult<Self> {
+        let credentials = Credentials::new(
+            Some(&config.access_key),
+            Some(&config.secret_key),
+            None,
+            None,
+            None,
+        ).map_err(|e| DomainError::Repo(format!("Erreur credentials MinIO: {}", e)))?;
+
+        let scheme = if config.use_ssl { "https" } else { "http" };
+        let region = Region::Custom {
+            region: "us-east-1".to_string(),
+            endpoint: format!("{}://{}:{}", scheme, config.endpoint, config.port),
+        };
+
+        let bucket = Bucket::new(&config.bucket_name, region, credentials)
+            .map_err(|e| DomainError::Repo(format!("Erreur création bucket: {}", e)))?
+            .with_path_style();
+        Ok(Self { bucket, config })
+    }
+
     /// Vérifie que le bucket existe, le crée si nécessaire
     async fn ensure_bucket_exists(&self) -> DomainResult<()> {
         println!("🔍 Vérification existence du bucket '{}'...", self.config.bucket_name);
@@ -198,24 +219,8 @@ impl MinioService {
         original_filename: &str,
         content_type: Option<String>
     ) -> DomainResult<String> {
-        // Génération du nom de fichier avec organisation par dossier YYYY-MM/
-        let now = Utc::now();
-        let folder = format!("{:04}-{:02}", now.year(), now.month());
-        
-        // Générer un nom unique pour éviter les collisions
-        let file_extension = std::path::Path::new(original_filename)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("bin");
-        
-        let unique_filename = format!("{}_{}_{}.{}",
-            now.format("%d_%H%M%S"),
-            Uuid::new_v4().to_string()[..8].to_string(),
-            sanitize_filename(original_filename),
-            file_extension
-        );
-        
-        let s3_key = format!("{}/{}", folder, unique_filename);
+        let s3_key = document_key(original_filename);
+        let unique_filename = s3_key.rsplit('/').next().unwrap_or(&s3_key).to_string();
         
         // Détection automatique du type MIME si non fourni
         let _content_type = content_type.unwrap_or_else(|| {
@@ -263,30 +268,69 @@ impl MinioService {
             }
         };
 
-        // Construire l'URL publique
-        let public_url = format!("{}/{}/{}", 
-            self.config.public_url.trim_end_matches('/'), 
-            self.config.bucket_name, 
-            s3_key
-        );
-        
-        println!("✓ Fichier uploadé avec succès. URL: {}", public_url);
-        Ok(public_url)
+        println!("✓ Fichier uploadé avec succès. Clé: {}", s3_key);
+        Ok(s3_key)
+    }
+
+    /// Upload un fichier sous une clé donnée
+    pub async fn put_file(&self, s3_key: &str, file_content: &[u8]) -> DomainResult<()> {
+        self.ensure_bucket_exists().await?;
+        self.bucket
+            .put_object(s3_key, file_content)
+            .await
+            .map_err(|e| DomainError::Repo(format!("Erreur upload fichier: {}", e)))?;
+        Ok(())
+    }
+
+    /// Télécharger le contenu d'un fichier
+    pub async fn read_file(&self, s3_key: &str) -> DomainResult<Vec<u8>> {
+        let response = self.bucket
+            .get_object(s3_key)
+            .await
+            .map_err(|e| DomainError::Repo(format!("Erreur lecture fichier: {}", e)))?;
+        if response.status_code() != 200 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(response.bytes().to_vec())
+    }
+
+    /// URL GET signée, valable `expires_in_secs` secondes (le bucket reste privé)
+    pub fn presigned_get_url(&self, s3_key: &str, expires_in_secs: u32) -> DomainResult<String> {
+        self.bucket
+            .presign_get(s3_key, expires_in_secs, None)
+            .map_err(|e| DomainError::Repo(format!("Erreur génération URL signée: {}", e)))
     }
 
     /// Supprimer un fichier
-    pub async fn delete_file(&self, file_url: &str) -> DomainResult<()> {
-        // Extraire la clé S3 depuis l'URL publique
-        let s3_key = self.extract_s3_key_from_url(file_url)?;
-        
+    pub async fn delete_file(&self, s3_key: &str) -> DomainResult<()> {
         let _response = self.bucket
-            .delete_object(&s3_key)
+            .delete_object(s3_key)
             .await
             .map_err(|e| DomainError::Repo(format!("Erreur suppression fichier: {}", e)))?;
 
         Ok(())
     }
 
+    /// Verrouille un fichier jusqu'à `retain_until` (Object Lock en mode COMPLIANCE)
+    /// L'objet est réécrit avec les en-têtes de rétention: la version verrouillée ne peut plus être supprimée
+    pub async fn lock_file(&self, s3_key: &str, retain_until: NaiveDate) -> DomainResult<bool> {
+        if !self.config.object_lock {
+            return Ok(false);
+        }
+        let content = self.read_file(s3_key).await?;
+        let mut bucket = self.bucket.clone();
+        bucket.add_header("x-amz-object-lock-mode", "COMPLIANCE");
+        bucket.add_header("x-amz-object-lock-retain-until-date", &format!("{}T23:59:59Z", retain_until.format("%Y-%m-%d")));
+        let response = bucket
+            .put_object(s3_key, &content)
+            .await
+            .map_err(|e| DomainError::Repo(format!("Erreur verrouillage fichier: {}", e)))?;
+        if response.status_code() != 200 {
+            return Err(DomainError::Repo(format!("Verrouillage refusé pour {} (HTTP {})", s3_key, response.status_code())));
+        }
+        Ok(true)
+    }
+
     /// Lister les fichiers d'un mois donné
     pub async fn list_files_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>> {
         let folder_prefix = format!("{:04}-{:02}/", year, month);
@@ -302,15 +346,8 @@ impl MinioService {
                 // rust-s3 0.32 returns Vec<ListBucketResult>, each with contents field
                 for list_result in list_results {
                     for object in list_result.contents {
-                        let public_url = format!("{}/{}/{}", 
-                            self.config.public_url.trim_end_matches('/'), 
-                            self.config.bucket_name, 
-                            object.key
-                        );
-                        
                         files.push(FileInfo {
                             key: object.key,
-                            public_url,
                             size_bytes: object.size as u64,
                             last_modified: chrono::DateTime::parse_from_rfc3339(&object.last_modified)
                                 .map_err(|e| DomainError::Repo(format!("Erreur parsing date: {}", e)))?
@@ -330,17 +367,6 @@ impl MinioService {
         }
     }
 
-    /// Extraire la clé S3 depuis une URL publique
-    fn extract_s3_key_from_url(&self, file_url: &str) -> DomainResult<String> {
-        let base_url = format!("{}/{}/", self.config.public_url, self.config.bucket_name);
-        
-        if let Some(s3_key) = file_url.strip_prefix(&base_url) {
-            Ok(s3_key.to_string())
-        } else {
-            Err(DomainError::Validation(format!("URL invalide: {}", file_url)))
-        }
-    }
-
     /// Obtenir les statistiques de stockage
     pub async fn get_storage_stats(&self) -> DomainResult<StorageStats> {
         println!("📊 Calcul des statistiques de stockage...");
@@ -383,21 +409,82 @@ impl MinioService {
     }
 }
 
-/// Information sur un fichier stocké
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct FileInfo {
-    pub key: String,
-    pub public_url: String,
-    pub size_bytes: u64,
-    pub last_modified: chrono::NaiveDateTime,
+#[async_trait::async_trait]
+impl DocumentStore for MinioService {
+    fn backend(&self) -> &'static str { "minio" }
+
+    async fn upload(&self, content: Vec<u8>, original_filename: &str, content_type: Option<String>) -> DomainResult<String> {
+        self.upload_file(Bytes::from(content), original_filename, content_type).await
+    }
+
+    async fn put(&self, key: &str, content: Vec<u8>) -> DomainResult<()> {
+        self.put_file(key, &content).await
+    }
+
+    async fn delete(&self, key: &str) -> DomainResult<()> {
+        self.delete_file(key).await
+    }
+
+    async fn list_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>> {
+        self.list_files_by_month(year, month).await
+    }
+
+    async fn list_all(&self) -> DomainResult<Vec<FileInfo>> {
+        self.ensure_bucket_exists().await?;
+        let list_results = self.bucket
+            .list("".to_string(), None)
+            .await
+            .map_err(|e| DomainError::Repo(format!("Erreur listing fichiers: {}", e)))?;
+        let mut files = Vec::new();
+        for list_result in list_results {
+            for object in list_result.contents {
+                files.push(FileInfo {
+                    size_bytes: object.size,
+                    last_modified: chrono::DateTime::parse_from_rfc3339(&object.last_modified)
+                        .map_err(|e| DomainError::Repo(format!("Erreur parsing date: {}", e)))?
+                        .naive_utc(),
+                    key: object.key,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    async fn open(&self, key: &str) -> DomainResult<Vec<u8>> {
+        self.read_file(key).await
+    }
+
+    async fn download_url(&self, key: &str, expires_in_secs: u32) -> DomainResult<String> {
+        self.presigned_get_url(key, expires_in_secs)
+    }
+
+    async fn set_retention(&self, key: &str, retain_until: NaiveDate) -> DomainResult<bool> {
+        self.lock_file(key, retain_until).await
+    }
+
+    async fn stats(&self) -> DomainResult<StorageStats> {
+        self.get_storage_stats().await
+    }
 }
 
-/// Statistiques de stockage
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub struct StorageStats {
-    pub total_files: u64,
-    pub total_size_bytes: u64,
-    pub files_by_type: HashMap<String, u64>,
+/// Clé unique d'un nouveau document: dossier YYYY-MM/ du mois courant + nom horodaté
+pub(crate) fn document_key(original_filename: &str) -> String {
+    let now = Utc::now();
+    let folder = format!("{:04}-{:02}", now.year(), now.month());
+
+    // Générer un nom unique pour éviter les collisions
+    let file_extension = std::path::Path::new(original_filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bin");
+
+    format!("{}/{}_{}_{}.{}",
+        folder,
+        now.format("%d_%H%M%S"),
+        &Uuid::new_v4().to_string()[..8],
+        sanitize_filename(original_filename),
+        file_extension
+    )
 }
 
 /// Utilitaire pour nettoyer les noms de fichiers
@@ -433,26 +520,32 @@ mod tests {
     
     #[test]
     fn test_extract_s3_key() {
-        // Create a mock MinIO service configuration
-        let config = MinioConfig {
-            public_url: "https://minio.example.com".to_string(),
-            bucket_name: "test_bucket".to_string(),
-            ..Default::default()
-        };
-        
-        // We can't create a real MinioService in unit tests without credentials,
-        // so let's test the utility function logic directly
+        // Public URLs stored before keys were used are migrated to their key
         let url = "https://minio.example.com/test_bucket/2024-01/file.pdf";
-        let expected_key = "2024-01/file.pdf";
-        
-        // Test URL format
-        assert!(url.contains(expected_key));
-        
-        // Test the URL construction logic
-        let base_url = format!("{}/{}/", config.public_url, config.bucket_name);
-        assert_eq!(base_url, "https://minio.example.com/test_bucket/");
+        assert_eq!(domain::document_key_from_url(url).as_deref(), Some("2024-01/file.pdf"));
+        assert_eq!(domain::document_key_from_url("https://minio.example.com/test_bucket/file.pdf"), None);
     }
-    
+
+    #[tokio::test]
+    #[ignore] // Requires a local S3-compatible server
+    async fn test_presigned_url_roundtrip() {
+        // docker run -p 9000:9000 -e MINIO_ROOT_USER=minioadmin -e MINIO_ROOT_PASSWORD=minioadmin minio/minio server /data
+        // MINIO_ENDPOINT=localhost MINIO_PORT=9000 MINIO_ACCESS_KEY=minioadmin MINIO_SECRET_KEY=minioadmin \
+        //   cargo test -p infra test_presigned_url_roundtrip -- --ignored
+        let service = MinioService::new(MinioConfig::default()).await.expect("MinIO local");
+
+        let key = "2024-01/presign-test.pdf";
+        service.put_file(key, b"%PDF-1.4").await.unwrap();
+        assert_eq!(service.read_file(key).await.unwrap(), b"%PDF-1.4");
+
+        let url = service.presigned_get_url(key, 60).unwrap();
+        assert!(url.contains(key));
+        assert!(url.contains("X-Amz-Signature="));
+        assert!(url.contains("X-Amz-Expires=60"));
+
+        service.delete_file(key).await.unwrap();
+    }
+
     #[tokio::test]
     #[ignore] // Ignored because it requires real MinIO credentials
     async fn test_minio_connection() {
@@ -1,29 +1,85 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
 use domain::{
     ConfigRepo, DomainError, DomainResult, Expense, ExpenseRepo, Invoice, InvoiceRepo, 
     MonthId, MonthRepo, MonthStatus, Provision, ProvisionType, ProvisionStatus, ProvisionRepo, Settings,
     // New domain imports
-    WorkingDay, WorkingDayRepo, WorkingDaysStats, TaxSchedule, TaxScheduleRepo, TaxType, TaxScheduleStatus,
+    WorkingDay, WorkingDayRepo, WorkingDaysStats, TaxSchedule, TaxScheduleRepo, TaxType, TaxScheduleStatus, TaxPayment,
     Simulation, SimulationRepo, SimulationParameters, SimulationScenario,
     MonthlyKPI, KPIRepo, Operation, OperationRepo, OperationType,
+    OperationQuery, OperationPage, OperationSortKey, OperationSortValue,
     Declaration, DeclarationRepo, DeclarationType, DeclarationStatus,
     // Yearly Planning imports
-    YearlyPlanning, MonthPlanning, YearlyPlanningRepo
+    YearlyPlanning, MonthPlanning, YearlyPlanningRepo,
+    // Invoice issuance
+    IssuedInvoice, IssuedInvoiceRepo, IssuerProfile, InvoiceClient, InvoiceLine, compute_invoice_vat,
+    // Legal archive
+    ArchiveRepo, ArchivedFiscalYear, ArchivedDocument,
+    // Backups
+    BackupReason, BackupRepo,
+    // Audit trail
+    AuditRepo, AuditEvent, NewAuditEvent, AuditQuery, AuditChainCheck, AuditEntity, AuditAction,
+    audit_hash_input, AUDIT_GENESIS_HASH,
+    // Synchronisation
+    SyncRepo, SyncSettings, SyncStamp, SyncConflict, SyncSide,
+    // Trash
+    TrashedOperation, TrashedYearlyPlanning,
+    // Database doctor
+    InvalidField
 };
-use sqlx::{sqlite::{SqliteConnectOptions}, Pool, Row, Sqlite};
+use sha2::{Digest, Sha256};
+use sqlx::{sqlite::{SqliteArguments, SqliteConnectOptions}, Connection, Decode, Pool, Row, Sqlite, Type, TypeInfo, ValueRef};
+
+use crate::backup::SqliteBackupRepo;
+use crate::doctor::SqliteDoctorRepo;
+use crate::encryption::DatabaseKey;
+use crate::unit_of_work::{Db, SqliteUnitOfWorkFactory};
 
 #[derive(Clone)]
 pub struct SqliteRepos {
     pub pool: Pool<Sqlite>,
+    db_path: Option<PathBuf>, // None for in-memory databases
+    key: Option<DatabaseKey>,
 }
 
 pub async fn connect_and_migrate(db_path: &str) -> anyhow::Result<SqliteRepos> {
     let opts = SqliteConnectOptions::from_str(db_path)?.create_if_missing(true);
+    connect_with(opts, None).await
+}
+
+/// 0004 deduplicates declarations before indexing their period, which its first version did not
+const REVISED_MIGRATIONS: [i64; 1] = [4];
+
+/// Snapshot the database before applying pending migrations to existing data
+pub(crate) async fn connect_with(opts: SqliteConnectOptions, key: Option<DatabaseKey>) -> anyhow::Result<SqliteRepos> {
+    let filename = opts.clone().get_filename().to_path_buf();
     let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(5).connect_with(opts).await?;
-    sqlx::migrate!("./migrations").run(&pool).await?;
-    Ok(SqliteRepos { pool })
+    let db_path = Some(filename).filter(|path| path.is_file());
+    let repos = SqliteRepos { pool, db_path, key };
+
+    let migrator = sqlx::migrate!("./migrations");
+    let applied: Vec<i64> = match sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='_sqlx_migrations'")
+        .fetch_optional(&repos.pool).await?
+    {
+        Some(_) => sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1").fetch_all(&repos.pool).await?,
+        None => Vec::new(),
+    };
+    let pending = migrator.iter().any(|m| !applied.contains(&m.version));
+    if pending && !applied.is_empty() && repos.db_path.is_some() {
+        repos.backups().create_backup(BackupReason::PreMigration).await?;
+    }
+    // Edited after release with steps that change nothing once applied: the checksum recorded
+    // by databases that ran the former version is brought up to date instead of refusing them
+    for migration in migrator.iter().filter(|m| REVISED_MIGRATIONS.contains(&m.version) && applied.contains(&m.version)) {
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = ? WHERE version = ?")
+            .bind(migration.checksum.to_vec())
+            .bind(migration.version)
+            .execute(&repos.pool).await?;
+    }
+    migrator.run(&repos.pool).await?;
+    Ok(repos)
 }
 
 #[derive(Clone)]
@@ -31,43 +87,178 @@ pub struct SqliteInvoiceRepo { pool: Pool<Sqlite> }
 #[derive(Clone)]
 pub struct SqliteExpenseRepo { pool: Pool<Sqlite> }
 #[derive(Clone)]
-pub struct SqliteProvisionRepo { pool: Pool<Sqlite> }
+pub struct SqliteProvisionRepo { pub(crate) db: Db }
 #[derive(Clone)]
-pub struct SqliteConfigRepo { pool: Pool<Sqlite> }
+pub struct SqliteConfigRepo { pub(crate) db: Db }
 #[derive(Clone)]
-pub struct SqliteMonthRepo { pool: Pool<Sqlite> }
+pub struct SqliteMonthRepo { pub(crate) db: Db }
 
 // New repository structs
 #[derive(Clone)]
-pub struct SqliteOperationRepo { pool: Pool<Sqlite> }
+pub struct SqliteOperationRepo { pub(crate) db: Db }
 #[derive(Clone)]
-pub struct SqliteDeclarationRepo { pool: Pool<Sqlite> }
+pub struct SqliteDeclarationRepo { pub(crate) db: Db }
 #[derive(Clone)]
-pub struct SqliteWorkingDayRepo { pool: Pool<Sqlite> }
+pub struct SqliteWorkingDayRepo { pub(crate) db: Db }
 #[derive(Clone)]
 pub struct SqliteTaxScheduleRepo { pool: Pool<Sqlite> }
 #[derive(Clone)]
-pub struct SqliteSimulationRepo { pool: Pool<Sqlite> }
+pub struct SqliteSimulationRepo { pub(crate) db: Db }
+#[derive(Clone)]
+pub struct SqliteKPIRepo { pub(crate) db: Db }
+#[derive(Clone)]
+pub struct SqliteYearlyPlanningRepo { pub(crate) db: Db }
 #[derive(Clone)]
-pub struct SqliteKPIRepo { pool: Pool<Sqlite> }
+pub struct SqliteIssuedInvoiceRepo { pool: Pool<Sqlite> }
 #[derive(Clone)]
-pub struct SqliteYearlyPlanningRepo { pool: Pool<Sqlite> }
+pub struct SqliteArchiveRepo { pub(crate) db: Db }
+#[derive(Clone)]
+pub struct SqliteAuditRepo { pub(crate) db: Db }
+#[derive(Clone)]
+pub struct SqliteSyncRepo { pub(crate) db: Db }
 
 impl SqliteRepos {
     pub fn invoices(&self) -> SqliteInvoiceRepo { SqliteInvoiceRepo { pool: self.pool.clone() } }
     pub fn expenses(&self) -> SqliteExpenseRepo { SqliteExpenseRepo { pool: self.pool.clone() } }
-    pub fn provisions(&self) -> SqliteProvisionRepo { SqliteProvisionRepo { pool: self.pool.clone() } }
-    pub fn config(&self) -> SqliteConfigRepo { SqliteConfigRepo { pool: self.pool.clone() } }
-    pub fn months(&self) -> SqliteMonthRepo { SqliteMonthRepo { pool: self.pool.clone() } }
+    pub fn provisions(&self) -> SqliteProvisionRepo { SqliteProvisionRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn config(&self) -> SqliteConfigRepo { SqliteConfigRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn months(&self) -> SqliteMonthRepo { SqliteMonthRepo { db: Db::Pool(self.pool.clone()) } }
     
     // New repository accessors
-    pub fn operations(&self) -> SqliteOperationRepo { SqliteOperationRepo { pool: self.pool.clone() } }
-    pub fn declarations(&self) -> SqliteDeclarationRepo { SqliteDeclarationRepo { pool: self.pool.clone() } }
-    pub fn working_days(&self) -> SqliteWorkingDayRepo { SqliteWorkingDayRepo { pool: self.pool.clone() } }
+    pub fn operations(&self) -> SqliteOperationRepo { SqliteOperationRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn declarations(&self) -> SqliteDeclarationRepo { SqliteDeclarationRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn working_days(&self) -> SqliteWorkingDayRepo { SqliteWorkingDayRepo { db: Db::Pool(self.pool.clone()) } }
     pub fn tax_schedules(&self) -> SqliteTaxScheduleRepo { SqliteTaxScheduleRepo { pool: self.pool.clone() } }
-    pub fn simulations(&self) -> SqliteSimulationRepo { SqliteSimulationRepo { pool: self.pool.clone() } }
-    pub fn kpis(&self) -> SqliteKPIRepo { SqliteKPIRepo { pool: self.pool.clone() } }
-    pub fn yearly_planning(&self) -> SqliteYearlyPlanningRepo { SqliteYearlyPlanningRepo { pool: self.pool.clone() } }
+    pub fn simulations(&self) -> SqliteSimulationRepo { SqliteSimulationRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn kpis(&self) -> SqliteKPIRepo { SqliteKPIRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn yearly_planning(&self) -> SqliteYearlyPlanningRepo { SqliteYearlyPlanningRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn issued_invoices(&self) -> SqliteIssuedInvoiceRepo { SqliteIssuedInvoiceRepo { pool: self.pool.clone() } }
+    pub fn archives(&self) -> SqliteArchiveRepo { SqliteArchiveRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn audit(&self) -> SqliteAuditRepo { SqliteAuditRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn sync(&self) -> SqliteSyncRepo { SqliteSyncRepo { db: Db::Pool(self.pool.clone()) } }
+    pub fn backups(&self) -> SqliteBackupRepo {
+        SqliteBackupRepo::new(self.pool.clone(), self.db_path.clone(), self.key.clone())
+    }
+    pub fn doctor(&self) -> SqliteDoctorRepo { SqliteDoctorRepo::new(self.pool.clone()) }
+    pub fn units_of_work(&self) -> SqliteUnitOfWorkFactory { SqliteUnitOfWorkFactory::new(self.pool.clone()) }
+}
+
+// ============ Row Decoding ============
+
+pub(crate) fn parse_db_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+/// Planning tables store `NaiveDateTime::to_string()`, with fractional seconds
+pub(crate) fn parse_db_datetime(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+/// Fallible access to the columns of a row: a malformed value becomes `DomainError::InvalidData`
+/// instead of a panic, so the database doctor can point at it
+struct Columns<'r> {
+    row: &'r sqlx::sqlite::SqliteRow,
+    table: &'static str,
+}
+
+impl<'r> Columns<'r> {
+    fn of(table: &'static str, row: &'r sqlx::sqlite::SqliteRow) -> Self {
+        Self { row, table }
+    }
+
+    fn get<T: Decode<'r, Sqlite> + Type<Sqlite>>(&self, column: &str) -> DomainResult<T> {
+        self.row.try_get(column).map_err(|e| match e {
+            sqlx::Error::ColumnDecode { .. } => {
+                let found = self.row.try_get_raw(column).map(|v| v.type_info().name().to_string()).unwrap_or_default();
+                let value = self.raw_text(column).unwrap_or(found);
+                self.invalid(column, &value, &format!("valeur {}", T::type_info().name()))
+            }
+            e => DomainError::Repo(e.to_string()),
+        })
+    }
+
+    fn uuid(&self, column: &str) -> DomainResult<uuid::Uuid> {
+        self.parse(column, |s| s.parse().ok(), "UUID")
+    }
+
+    fn date(&self, column: &str) -> DomainResult<NaiveDate> {
+        self.parse(column, parse_db_date, "date AAAA-MM-JJ")
+    }
+
+    fn opt_date(&self, column: &str) -> DomainResult<Option<NaiveDate>> {
+        self.opt_parse(column, parse_db_date, "date AAAA-MM-JJ")
+    }
+
+    fn datetime(&self, column: &str) -> DomainResult<NaiveDateTime> {
+        self.parse(column, parse_db_datetime, "date et heure AAAA-MM-JJ HH:MM:SS")
+    }
+
+    fn opt_datetime(&self, column: &str) -> DomainResult<Option<NaiveDateTime>> {
+        self.opt_parse(column, parse_db_datetime, "date et heure AAAA-MM-JJ HH:MM:SS")
+    }
+
+    fn parse<T>(&self, column: &str, parse: impl Fn(&str) -> Option<T>, expected: &str) -> DomainResult<T> {
+        let value: String = self.get(column)?;
+        parse(&value).ok_or_else(|| self.invalid(column, &value, expected))
+    }
+
+    fn opt_parse<T>(&self, column: &str, parse: impl Fn(&str) -> Option<T>, expected: &str) -> DomainResult<Option<T>> {
+        let value: Option<String> = self.get(column)?;
+        value.map(|v| parse(&v).ok_or_else(|| self.invalid(column, &v, expected))).transpose()
+    }
+
+    fn raw_text(&self, column: &str) -> Option<String> {
+        self.row.try_get_unchecked::<Option<String>, _>(column).ok().flatten()
+    }
+
+    /// Key of the row for the error message: first of the usual identifying columns that was selected
+    fn row_id(&self) -> String {
+        ["id", "key", "seq", "year"].iter()
+            .find_map(|column| self.raw_text(column))
+            .unwrap_or_else(|| "?".to_string())
+    }
+
+    fn invalid(&self, column: &str, value: &str, expected: &str) -> DomainError {
+        DomainError::InvalidData(InvalidField {
+            table: self.table.to_string(),
+            row_id: self.row_id(),
+            column: column.to_string(),
+            value: value.to_string(),
+            expected: expected.to_string(),
+        })
+    }
+}
+
+fn row_to_invoice(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Invoice> {
+    let c = Columns::of("invoices", row);
+    Ok(Invoice {
+        id: c.uuid("id")?,
+        number: c.get("number")?,
+        client: c.get("client")?,
+        service_date: c.date("service_date")?,
+        amount_ht: c.get("amount_ht")?,
+        vat_rate_ppm: c.get("vat_rate_ppm")?,
+        amount_tva: c.get("amount_tva")?,
+        amount_ttc: c.get("amount_ttc")?,
+        paid_at: c.opt_date("paid_at")?,
+        source: c.get("source")?,
+    })
+}
+
+fn row_to_expense(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Expense> {
+    let c = Columns::of("expenses", row);
+    Ok(Expense {
+        id: c.uuid("id")?,
+        label: c.get("label")?,
+        category: c.get("category")?,
+        booking_date: c.date("booking_date")?,
+        amount_ht: c.get("amount_ht")?,
+        vat_rate_ppm: c.get("vat_rate_ppm")?,
+        amount_tva: c.get("amount_tva")?,
+        amount_ttc: c.get("amount_ttc")?,
+        paid_at: c.opt_date("paid_at")?,
+        receipt_path: c.get("receipt_path")?,
+    })
 }
 
 #[async_trait::async_trait]
@@ -78,33 +269,11 @@ impl InvoiceRepo for SqliteInvoiceRepo {
             let rows = sqlx::query(r#"SELECT id, number, client, service_date, amount_ht, vat_rate_ppm, amount_tva, amount_ttc, paid_at, source FROM invoices WHERE paid_at IS NOT NULL AND substr(paid_at,1,7)=?"#)
                 .bind(ym)
                 .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-            Ok(rows.into_iter().map(|r| Invoice{
-                id: r.get::<String,_>("id").parse().unwrap(),
-                number: r.get("number"),
-                client: r.get("client"),
-                service_date: NaiveDate::parse_from_str(&r.get::<String,_>("service_date"), "%Y-%m-%d").unwrap(),
-                amount_ht: r.get("amount_ht"),
-                vat_rate_ppm: r.get("vat_rate_ppm"),
-                amount_tva: r.get("amount_tva"),
-                amount_ttc: r.get("amount_ttc"),
-                paid_at: r.get::<Option<String>,_>("paid_at").map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-                source: r.get("source"),
-            }).collect())
+            rows.iter().map(row_to_invoice).collect()
         } else {
             let rows = sqlx::query(r#"SELECT id, number, client, service_date, amount_ht, vat_rate_ppm, amount_tva, amount_ttc, paid_at, source FROM invoices"#)
                 .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-            Ok(rows.into_iter().map(|r| Invoice{
-                id: r.get::<String,_>("id").parse().unwrap(),
-                number: r.get("number"),
-                client: r.get("client"),
-                service_date: NaiveDate::parse_from_str(&r.get::<String,_>("service_date"), "%Y-%m-%d").unwrap(),
-                amount_ht: r.get("amount_ht"),
-                vat_rate_ppm: r.get("vat_rate_ppm"),
-                amount_tva: r.get("amount_tva"),
-                amount_ttc: r.get("amount_ttc"),
-                paid_at: r.get::<Option<String>,_>("paid_at").map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-                source: r.get("source"),
-            }).collect())
+            rows.iter().map(row_to_invoice).collect()
         }
     }
 
@@ -134,33 +303,11 @@ impl ExpenseRepo for SqliteExpenseRepo {
             let rows = sqlx::query(r#"SELECT id, label, category, booking_date, amount_ht, vat_rate_ppm, amount_tva, amount_ttc, paid_at, receipt_path FROM expenses WHERE paid_at IS NOT NULL AND substr(paid_at,1,7)=?"#)
                 .bind(ym)
                 .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-            Ok(rows.into_iter().map(|r| Expense{
-                id: r.get::<String,_>("id").parse().unwrap(),
-                label: r.get("label"),
-                category: r.get("category"),
-                booking_date: NaiveDate::parse_from_str(&r.get::<String,_>("booking_date"), "%Y-%m-%d").unwrap(),
-                amount_ht: r.get("amount_ht"),
-                vat_rate_ppm: r.get("vat_rate_ppm"),
-                amount_tva: r.get("amount_tva"),
-                amount_ttc: r.get("amount_ttc"),
-                paid_at: r.get::<Option<String>,_>("paid_at").map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-                receipt_path: r.get("receipt_path"),
-            }).collect())
+            rows.iter().map(row_to_expense).collect()
         } else {
             let rows = sqlx::query(r#"SELECT id, label, category, booking_date, amount_ht, vat_rate_ppm, amount_tva, amount_ttc, paid_at, receipt_path FROM expenses"#)
                 .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-            Ok(rows.into_iter().map(|r| Expense{
-                id: r.get::<String,_>("id").parse().unwrap(),
-                label: r.get("label"),
-                category: r.get("category"),
-                booking_date: NaiveDate::parse_from_str(&r.get::<String,_>("booking_date"), "%Y-%m-%d").unwrap(),
-                amount_ht: r.get("amount_ht"),
-                vat_rate_ppm: r.get("vat_rate_ppm"),
-                amount_tva: r.get("amount_tva"),
-                amount_ttc: r.get("amount_ttc"),
-                paid_at: r.get::<Option<String>,_>("paid_at").map(|s| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-                receipt_path: r.get("receipt_path"),
-            }).collect())
+            rows.iter().map(row_to_expense).collect()
         }
     }
 
@@ -204,7 +351,14 @@ impl ProvisionRepo for SqliteProvisionRepo {
             .bind(status_str)
             .bind(p.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(p.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn delete_provision(&self, id: uuid::Uuid) -> DomainResult<()> {
+        sqlx::query("DELETE FROM provisions WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -213,54 +367,64 @@ impl ProvisionRepo for SqliteProvisionRepo {
             sqlx::query(r#"SELECT id, period_year, period_month, type, amount_cents, due_date, status, created_at, updated_at FROM provisions WHERE period_year = ? AND period_month = ?"#)
                 .bind(m.year)
                 .bind(m.month as i64)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         } else {
             sqlx::query(r#"SELECT id, period_year, period_month, type, amount_cents, due_date, status, created_at, updated_at FROM provisions"#)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         };
-        Ok(rows.into_iter().map(|r| {
-            let provision_type = match r.get::<String,_>("type").as_str() {
-                "vat" => ProvisionType::Vat,
-                "urssaf" => ProvisionType::Urssaf,
-                _ => ProvisionType::Vat,
-            };
-            let status = match r.get::<String,_>("status").as_str() {
-                "pending" => ProvisionStatus::Pending,
-                "paid" => ProvisionStatus::Paid,
-                "overdue" => ProvisionStatus::Overdue,
-                _ => ProvisionStatus::Pending,
-            };
-            Provision{
-                id: r.get::<String,_>("id").parse().unwrap(),
-                period_year: r.get("period_year"),
-                period_month: r.get::<i64,_>("period_month") as u32,
-                provision_type,
-                amount_cents: r.get("amount_cents"),
-                due_date: NaiveDate::parse_from_str(&r.get::<String,_>("due_date"), "%Y-%m-%d").unwrap(),
-                status,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            }
-        }).collect())
+        rows.iter().map(row_to_provision).collect()
+    }
+}
+
+fn string_to_provision_type(s: &str) -> Option<ProvisionType> {
+    match s {
+        "vat" => Some(ProvisionType::Vat),
+        "urssaf" => Some(ProvisionType::Urssaf),
+        _ => None,
     }
 }
 
+fn string_to_provision_status(s: &str) -> Option<ProvisionStatus> {
+    match s {
+        "pending" => Some(ProvisionStatus::Pending),
+        "paid" => Some(ProvisionStatus::Paid),
+        "overdue" => Some(ProvisionStatus::Overdue),
+        _ => None,
+    }
+}
+
+fn row_to_provision(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Provision> {
+    let c = Columns::of("provisions", row);
+    Ok(Provision {
+        id: c.uuid("id")?,
+        period_year: c.get("period_year")?,
+        period_month: c.get::<i64>("period_month")? as u32,
+        provision_type: c.parse("type", string_to_provision_type, "vat ou urssaf")?,
+        amount_cents: c.get("amount_cents")?,
+        due_date: c.date("due_date")?,
+        status: c.parse("status", string_to_provision_status, "pending, paid ou overdue")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
 #[async_trait::async_trait]
 impl ConfigRepo for SqliteConfigRepo {
     async fn load_settings(&self) -> DomainResult<Settings> {
         let row = sqlx::query(r#"SELECT default_vat_rate_ppm, urssaf_rate_ppm, vat_declare_day, vat_pay_day, urssaf_pay_day, buffer_cents, forecast_ht_cents, forecast_expenses_ttc_cents, forecast_expense_vat_rate_ppm FROM settings WHERE id=1"#)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         if let Some(r) = row {
+            let c = Columns::of("settings", &r);
             Ok(Settings{
-                default_vat_rate_ppm: r.get("default_vat_rate_ppm"),
-                urssaf_rate_ppm: r.get("urssaf_rate_ppm"),
-                vat_declare_day: r.get::<i64,_>("vat_declare_day") as u8,
-                vat_pay_day: r.get::<i64,_>("vat_pay_day") as u8,
-                urssaf_pay_day: r.get::<i64,_>("urssaf_pay_day") as u8,
-                buffer_cents: r.get("buffer_cents"),
-                forecast_ht_cents: r.get("forecast_ht_cents"),
-                forecast_expenses_ttc_cents: r.get("forecast_expenses_ttc_cents"),
-                forecast_expense_vat_rate_ppm: r.get("forecast_expense_vat_rate_ppm"),
+                default_vat_rate_ppm: c.get("default_vat_rate_ppm")?,
+                urssaf_rate_ppm: c.get("urssaf_rate_ppm")?,
+                vat_declare_day: c.get::<i64>("vat_declare_day")? as u8,
+                vat_pay_day: c.get::<i64>("vat_pay_day")? as u8,
+                urssaf_pay_day: c.get::<i64>("urssaf_pay_day")? as u8,
+                buffer_cents: c.get("buffer_cents")?,
+                forecast_ht_cents: c.get("forecast_ht_cents")?,
+                forecast_expenses_ttc_cents: c.get("forecast_expenses_ttc_cents")?,
+                forecast_expense_vat_rate_ppm: c.get("forecast_expense_vat_rate_ppm")?,
             })
         } else {
             Ok(Settings::default())
@@ -268,7 +432,8 @@ impl ConfigRepo for SqliteConfigRepo {
     }
 
     async fn save_settings(&self, s: Settings) -> DomainResult<()> {
-        sqlx::query(r#"INSERT INTO settings (id, default_vat_rate_ppm, urssaf_rate_ppm, vat_declare_day, vat_pay_day, urssaf_pay_day, buffer_cents, forecast_ht_cents, forecast_expenses_ttc_cents, forecast_expense_vat_rate_ppm) VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT(id) DO UPDATE SET default_vat_rate_ppm=excluded.default_vat_rate_ppm, urssaf_rate_ppm=excluded.urssaf_rate_ppm, vat_declare_day=excluded.vat_declare_day, vat_pay_day=excluded.vat_pay_day, urssaf_pay_day=excluded.urssaf_pay_day, buffer_cents=excluded.buffer_cents, forecast_ht_cents=excluded.forecast_ht_cents, forecast_expenses_ttc_cents=excluded.forecast_expenses_ttc_cents, forecast_expense_vat_rate_ppm=excluded.forecast_expense_vat_rate_ppm"#)
+        let now = chrono::Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+        sqlx::query(r#"INSERT INTO settings (id, default_vat_rate_ppm, urssaf_rate_ppm, vat_declare_day, vat_pay_day, urssaf_pay_day, buffer_cents, forecast_ht_cents, forecast_expenses_ttc_cents, forecast_expense_vat_rate_ppm, created_at, updated_at) VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT(id) DO UPDATE SET default_vat_rate_ppm=excluded.default_vat_rate_ppm, urssaf_rate_ppm=excluded.urssaf_rate_ppm, vat_declare_day=excluded.vat_declare_day, vat_pay_day=excluded.vat_pay_day, urssaf_pay_day=excluded.urssaf_pay_day, buffer_cents=excluded.buffer_cents, forecast_ht_cents=excluded.forecast_ht_cents, forecast_expenses_ttc_cents=excluded.forecast_expenses_ttc_cents, forecast_expense_vat_rate_ppm=excluded.forecast_expense_vat_rate_ppm, updated_at=excluded.updated_at"#)
             .bind(s.default_vat_rate_ppm)
             .bind(s.urssaf_rate_ppm)
             .bind(s.vat_declare_day as i64)
@@ -278,7 +443,9 @@ impl ConfigRepo for SqliteConfigRepo {
             .bind(s.forecast_ht_cents)
             .bind(s.forecast_expenses_ttc_cents)
             .bind(s.forecast_expense_vat_rate_ppm)
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .bind(&now)
+            .bind(&now)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 }
@@ -286,19 +453,34 @@ impl ConfigRepo for SqliteConfigRepo {
 #[async_trait::async_trait]
 impl MonthRepo for SqliteMonthRepo {
     async fn get_status(&self, month: &MonthId) -> DomainResult<MonthStatus> {
-        let row = sqlx::query(r#"SELECT closed_at FROM months WHERE year=? AND month=?"#)
+        let row = sqlx::query(r#"SELECT year, closed_at FROM months WHERE year=? AND month=?"#)
             .bind(month.year).bind(month.month as i64)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-        let closed_at = row.and_then(|r| r.get::<Option<String>,_>("closed_at").map(|s| chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").unwrap()));
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        let closed_at = match row {
+            Some(r) => Columns::of("months", &r).opt_datetime("closed_at")?,
+            None => None,
+        };
         Ok(MonthStatus{ month: month.clone(), closed_at })
     }
 
     async fn close_month(&self, month: &MonthId, closed_at: chrono::NaiveDateTime) -> DomainResult<()> {
         sqlx::query(r#"INSERT INTO months (year, month, closed_at) VALUES (?, ?, ?) ON CONFLICT(year, month) DO UPDATE SET closed_at=excluded.closed_at"#)
             .bind(month.year).bind(month.month as i64).bind(closed_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
+
+    async fn list_closed_months(&self) -> DomainResult<Vec<MonthStatus>> {
+        let rows = sqlx::query(r#"SELECT year, month, closed_at FROM months WHERE closed_at IS NOT NULL ORDER BY year, month"#)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(|r| {
+            let c = Columns::of("months", r);
+            Ok(MonthStatus {
+                month: MonthId::new(c.get::<i64>("year")? as i32, c.get::<i64>("month")? as u32),
+                closed_at: c.opt_datetime("closed_at")?,
+            })
+        }).collect()
+    }
 }
 
 // ============ New Repository Implementations ============
@@ -318,7 +500,7 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
             .bind(working_day.description)
             .bind(working_day.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(working_day.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -335,14 +517,14 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
             .bind(working_day.description)
             .bind(working_day.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(working_day.id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn delete_working_day(&self, id: uuid::Uuid) -> DomainResult<()> {
         sqlx::query(r#"DELETE FROM working_days WHERE id = ?"#)
             .bind(id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -352,22 +534,9 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
             FROM working_days WHERE id = ?
         "#)
             .bind(id.to_string())
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        if let Some(r) = row {
-            Ok(WorkingDay {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                date: NaiveDate::parse_from_str(&r.get::<String,_>("date"), "%Y-%m-%d").unwrap(),
-                hours_worked: r.get("hours_worked"),
-                billable_hours: r.get("billable_hours"),
-                hourly_rate_cents: r.get("hourly_rate_cents"),
-                description: r.get("description"),
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            })
-        } else {
-            Err(DomainError::NotFound)
-        }
+        row.as_ref().map(row_to_working_day).unwrap_or(Err(DomainError::NotFound))
     }
 
     async fn list_working_days(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<WorkingDay>> {
@@ -396,18 +565,9 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
             sql_query = sql_query.bind(end.format("%Y-%m-%d").to_string());
         }
         
-        let rows = sql_query.fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        let rows = sql_query.fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| WorkingDay {
-            id: r.get::<String,_>("id").parse().unwrap(),
-            date: NaiveDate::parse_from_str(&r.get::<String,_>("date"), "%Y-%m-%d").unwrap(),
-            hours_worked: r.get("hours_worked"),
-            billable_hours: r.get("billable_hours"),
-            hourly_rate_cents: r.get("hourly_rate_cents"),
-            description: r.get("description"),
-            created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-        }).collect())
+        rows.iter().map(row_to_working_day).collect()
     }
 
     async fn get_working_days_for_month(&self, month: &MonthId) -> DomainResult<Vec<WorkingDay>> {
@@ -419,18 +579,9 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
             ORDER BY date
         "#)
             .bind(ym)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| WorkingDay {
-            id: r.get::<String,_>("id").parse().unwrap(),
-            date: NaiveDate::parse_from_str(&r.get::<String,_>("date"), "%Y-%m-%d").unwrap(),
-            hours_worked: r.get("hours_worked"),
-            billable_hours: r.get("billable_hours"),
-            hourly_rate_cents: r.get("hourly_rate_cents"),
-            description: r.get("description"),
-            created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-        }).collect())
+        rows.iter().map(row_to_working_day).collect()
     }
 
     async fn get_working_days_stats(&self, start_date: NaiveDate, end_date: NaiveDate) -> DomainResult<WorkingDaysStats> {
@@ -446,13 +597,15 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
         "#)
             .bind(start_date.format("%Y-%m-%d").to_string())
             .bind(end_date.format("%Y-%m-%d").to_string())
-            .fetch_one(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_one(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        let total_working_days: f64 = row.get::<i64,_>("total_working_days") as f64;
-        let total_billable_hours: f64 = row.get::<f64,_>("total_billable_hours");
-        let total_worked_hours: f64 = row.get::<f64,_>("total_worked_hours");
-        let avg_daily_rate_cents: i64 = row.get::<f64,_>("avg_daily_rate_cents") as i64;
-        let avg_hourly_rate_cents: i64 = row.get::<f64,_>("avg_hourly_rate_cents") as i64;
+        // Aggregates are NULL over an empty range
+        let c = Columns::of("working_days", &row);
+        let total_working_days: f64 = c.get::<i64>("total_working_days")? as f64;
+        let total_billable_hours: f64 = c.get::<Option<f64>>("total_billable_hours")?.unwrap_or(0.0);
+        let total_worked_hours: f64 = c.get::<Option<f64>>("total_worked_hours")?.unwrap_or(0.0);
+        let avg_daily_rate_cents: i64 = c.get::<Option<f64>>("avg_daily_rate_cents")?.unwrap_or(0.0) as i64;
+        let avg_hourly_rate_cents: i64 = c.get::<Option<f64>>("avg_hourly_rate_cents")?.unwrap_or(0.0) as i64;
         let utilization_rate = if total_worked_hours > 0.0 { total_billable_hours / total_worked_hours } else { 0.0 };
         
         Ok(WorkingDaysStats {
@@ -466,63 +619,129 @@ impl WorkingDayRepo for SqliteWorkingDayRepo {
     }
 }
 
+fn row_to_working_day(row: &sqlx::sqlite::SqliteRow) -> DomainResult<WorkingDay> {
+    let c = Columns::of("working_days", row);
+    Ok(WorkingDay {
+        id: c.uuid("id")?,
+        date: c.date("date")?,
+        hours_worked: c.get("hours_worked")?,
+        billable_hours: c.get("billable_hours")?,
+        hourly_rate_cents: c.get("hourly_rate_cents")?,
+        description: c.get("description")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
+// Helper functions for TaxSchedule serialization/deserialization
+fn tax_type_to_string(tax_type: &TaxType) -> &str {
+    match tax_type {
+        TaxType::Vat => "Vat",
+        TaxType::Urssaf => "Urssaf",
+        TaxType::IncomeTax => "IncomeTax",
+        TaxType::Other(ref s) => s,
+    }
+}
+
+fn string_to_tax_type(s: &str) -> TaxType {
+    match s {
+        "Vat" => TaxType::Vat,
+        "Urssaf" => TaxType::Urssaf,
+        "IncomeTax" => TaxType::IncomeTax,
+        s => TaxType::Other(s.to_string()),
+    }
+}
+
+fn tax_schedule_status_to_string(status: &TaxScheduleStatus) -> &'static str {
+    match status {
+        TaxScheduleStatus::Pending => "Pending",
+        TaxScheduleStatus::Paid => "Paid",
+        TaxScheduleStatus::Overdue => "Overdue",
+    }
+}
+
+fn string_to_tax_schedule_status(s: &str) -> Option<TaxScheduleStatus> {
+    match s {
+        "Pending" => Some(TaxScheduleStatus::Pending),
+        "Paid" => Some(TaxScheduleStatus::Paid),
+        "Overdue" => Some(TaxScheduleStatus::Overdue),
+        _ => None,
+    }
+}
+
+fn row_to_tax_schedule(row: &sqlx::sqlite::SqliteRow) -> DomainResult<TaxSchedule> {
+    let c = Columns::of("tax_schedules", row);
+    Ok(TaxSchedule {
+        id: c.uuid("id")?,
+        tax_type: string_to_tax_type(&c.get::<String>("tax_type")?),
+        due_date: c.date("due_date")?,
+        amount_cents: c.get("amount_cents")?,
+        period_start: c.date("period_start")?,
+        period_end: c.date("period_end")?,
+        status: c.parse("status", string_to_tax_schedule_status, "Pending, Paid ou Overdue")?,
+        paid_date: c.opt_date("paid_date")?,
+        paid_amount_cents: c.get("paid_amount_cents")?,
+        payment_reference: c.get("payment_reference")?,
+        created_at: c.datetime("created_at")?,
+    })
+}
+
+fn row_to_tax_payment(row: &sqlx::sqlite::SqliteRow) -> DomainResult<TaxPayment> {
+    let c = Columns::of("tax_schedule_payments", row);
+    Ok(TaxPayment {
+        id: c.uuid("id")?,
+        tax_schedule_id: c.uuid("tax_schedule_id")?,
+        paid_date: c.date("paid_date")?,
+        amount_cents: c.get("amount_cents")?,
+        reference: c.get("reference")?,
+        created_at: c.datetime("created_at")?,
+    })
+}
+
 #[async_trait::async_trait]
 impl TaxScheduleRepo for SqliteTaxScheduleRepo {
     async fn create_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()> {
-        let tax_type_str = match tax_schedule.tax_type {
-            TaxType::Vat => "Vat",
-            TaxType::Urssaf => "Urssaf", 
-            TaxType::IncomeTax => "IncomeTax",
-            TaxType::Other(ref s) => s,
-        };
-        
-        let status_str = match tax_schedule.status {
-            TaxScheduleStatus::Pending => "Pending",
-            TaxScheduleStatus::Paid => "Paid",
-            TaxScheduleStatus::Overdue => "Overdue",
-        };
-        
+        let paid_date = tax_schedule.paid_date.map(|d| d.format("%Y-%m-%d").to_string());
+
         sqlx::query(r#"
-            INSERT INTO tax_schedules (id, tax_type, due_date, amount_cents, period_start, period_end, status, created_at) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO tax_schedules (
+                id, tax_type, due_date, amount_cents, period_start, period_end, status,
+                paid_date, paid_amount_cents, payment_reference, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
             .bind(tax_schedule.id.to_string())
-            .bind(tax_type_str)
+            .bind(tax_type_to_string(&tax_schedule.tax_type))
             .bind(tax_schedule.due_date.format("%Y-%m-%d").to_string())
             .bind(tax_schedule.amount_cents)
             .bind(tax_schedule.period_start.format("%Y-%m-%d").to_string())
             .bind(tax_schedule.period_end.format("%Y-%m-%d").to_string())
-            .bind(status_str)
+            .bind(tax_schedule_status_to_string(&tax_schedule.status))
+            .bind(paid_date)
+            .bind(tax_schedule.paid_amount_cents)
+            .bind(tax_schedule.payment_reference)
             .bind(tax_schedule.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn update_tax_schedule(&self, tax_schedule: TaxSchedule) -> DomainResult<()> {
-        let tax_type_str = match tax_schedule.tax_type {
-            TaxType::Vat => "Vat",
-            TaxType::Urssaf => "Urssaf",
-            TaxType::IncomeTax => "IncomeTax", 
-            TaxType::Other(ref s) => s,
-        };
-        
-        let status_str = match tax_schedule.status {
-            TaxScheduleStatus::Pending => "Pending",
-            TaxScheduleStatus::Paid => "Paid",
-            TaxScheduleStatus::Overdue => "Overdue",
-        };
-        
+        let paid_date = tax_schedule.paid_date.map(|d| d.format("%Y-%m-%d").to_string());
+
         sqlx::query(r#"
             UPDATE tax_schedules 
-            SET tax_type = ?, due_date = ?, amount_cents = ?, period_start = ?, period_end = ?, status = ?
+            SET tax_type = ?, due_date = ?, amount_cents = ?, period_start = ?, period_end = ?, status = ?,
+                paid_date = ?, paid_amount_cents = ?, payment_reference = ?
             WHERE id = ?
         "#)
-            .bind(tax_type_str)
+            .bind(tax_type_to_string(&tax_schedule.tax_type))
             .bind(tax_schedule.due_date.format("%Y-%m-%d").to_string())
             .bind(tax_schedule.amount_cents)
             .bind(tax_schedule.period_start.format("%Y-%m-%d").to_string())
             .bind(tax_schedule.period_end.format("%Y-%m-%d").to_string())
-            .bind(status_str)
+            .bind(tax_schedule_status_to_string(&tax_schedule.status))
+            .bind(paid_date)
+            .bind(tax_schedule.paid_amount_cents)
+            .bind(tax_schedule.payment_reference)
             .bind(tax_schedule.id.to_string())
             .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
@@ -537,46 +756,18 @@ impl TaxScheduleRepo for SqliteTaxScheduleRepo {
 
     async fn get_tax_schedule(&self, id: uuid::Uuid) -> DomainResult<TaxSchedule> {
         let row = sqlx::query(r#"
-            SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status, created_at 
+            SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status,
+                   paid_date, paid_amount_cents, payment_reference, created_at
             FROM tax_schedules WHERE id = ?
         "#)
             .bind(id.to_string())
             .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        if let Some(r) = row {
-            let tax_type_str: String = r.get("tax_type");
-            let tax_type = match tax_type_str.as_str() {
-                "Vat" => TaxType::Vat,
-                "Urssaf" => TaxType::Urssaf,
-                "IncomeTax" => TaxType::IncomeTax,
-                s => TaxType::Other(s.to_string()),
-            };
-            
-            let status_str: String = r.get("status");
-            let status = match status_str.as_str() {
-                "Pending" => TaxScheduleStatus::Pending,
-                "Paid" => TaxScheduleStatus::Paid,
-                "Overdue" => TaxScheduleStatus::Overdue,
-                _ => TaxScheduleStatus::Pending,
-            };
-            
-            Ok(TaxSchedule {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                tax_type,
-                due_date: NaiveDate::parse_from_str(&r.get::<String,_>("due_date"), "%Y-%m-%d").unwrap(),
-                amount_cents: r.get("amount_cents"),
-                period_start: NaiveDate::parse_from_str(&r.get::<String,_>("period_start"), "%Y-%m-%d").unwrap(),
-                period_end: NaiveDate::parse_from_str(&r.get::<String,_>("period_end"), "%Y-%m-%d").unwrap(),
-                status,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            })
-        } else {
-            Err(DomainError::NotFound)
-        }
+        row.as_ref().map(row_to_tax_schedule).unwrap_or(Err(DomainError::NotFound))
     }
 
     async fn list_tax_schedules(&self, start_date: Option<NaiveDate>, end_date: Option<NaiveDate>) -> DomainResult<Vec<TaxSchedule>> {
-        let mut query = String::from("SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status, created_at FROM tax_schedules");
+        let mut query = String::from("SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status, paid_date, paid_amount_cents, payment_reference, created_at FROM tax_schedules");
         let mut conditions = Vec::new();
         
         if start_date.is_some() {
@@ -603,74 +794,89 @@ impl TaxScheduleRepo for SqliteTaxScheduleRepo {
         
         let rows = sql_query.fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| {
-            let tax_type_str: String = r.get("tax_type");
-            let tax_type = match tax_type_str.as_str() {
-                "Vat" => TaxType::Vat,
-                "Urssaf" => TaxType::Urssaf,
-                "IncomeTax" => TaxType::IncomeTax,
-                s => TaxType::Other(s.to_string()),
-            };
-            
-            let status_str: String = r.get("status");
-            let status = match status_str.as_str() {
-                "Pending" => TaxScheduleStatus::Pending,
-                "Paid" => TaxScheduleStatus::Paid,
-                "Overdue" => TaxScheduleStatus::Overdue,
-                _ => TaxScheduleStatus::Pending,
-            };
-            
-            TaxSchedule {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                tax_type,
-                due_date: NaiveDate::parse_from_str(&r.get::<String,_>("due_date"), "%Y-%m-%d").unwrap(),
-                amount_cents: r.get("amount_cents"),
-                period_start: NaiveDate::parse_from_str(&r.get::<String,_>("period_start"), "%Y-%m-%d").unwrap(),
-                period_end: NaiveDate::parse_from_str(&r.get::<String,_>("period_end"), "%Y-%m-%d").unwrap(),
-                status,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            }
-        }).collect())
+        rows.iter().map(row_to_tax_schedule).collect()
     }
 
     async fn get_overdue_schedules(&self, as_of_date: NaiveDate) -> DomainResult<Vec<TaxSchedule>> {
+        // Overdue means past due with an amount still outstanding, whatever the stored status says
         let rows = sqlx::query(r#"
-            SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status, created_at 
+            SELECT id, tax_type, due_date, amount_cents, period_start, period_end, status,
+                   paid_date, paid_amount_cents, payment_reference, created_at
             FROM tax_schedules 
-            WHERE due_date < ? AND status = 'Pending'
+            WHERE due_date < ? AND paid_amount_cents < amount_cents
             ORDER BY due_date
         "#)
             .bind(as_of_date.format("%Y-%m-%d").to_string())
             .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| {
-            let tax_type_str: String = r.get("tax_type");
-            let tax_type = match tax_type_str.as_str() {
-                "Vat" => TaxType::Vat,
-                "Urssaf" => TaxType::Urssaf,
-                "IncomeTax" => TaxType::IncomeTax,
-                s => TaxType::Other(s.to_string()),
-            };
-            
-            TaxSchedule {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                tax_type,
-                due_date: NaiveDate::parse_from_str(&r.get::<String,_>("due_date"), "%Y-%m-%d").unwrap(),
-                amount_cents: r.get("amount_cents"),
-                period_start: NaiveDate::parse_from_str(&r.get::<String,_>("period_start"), "%Y-%m-%d").unwrap(),
-                period_end: NaiveDate::parse_from_str(&r.get::<String,_>("period_end"), "%Y-%m-%d").unwrap(),
-                status: TaxScheduleStatus::Overdue,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            }
-        }).collect())
+        rows.iter().map(|r| Ok(TaxSchedule { status: TaxScheduleStatus::Overdue, ..row_to_tax_schedule(r)? })).collect()
     }
 
-    async fn mark_as_paid(&self, id: uuid::Uuid, _paid_date: NaiveDate) -> DomainResult<()> {
-        sqlx::query(r#"UPDATE tax_schedules SET status = 'Paid' WHERE id = ?"#)
-            .bind(id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+    async fn mark_as_paid(&self, id: uuid::Uuid, paid_date: NaiveDate) -> DomainResult<()> {
+        let schedule = self.get_tax_schedule(id).await?;
+        let outstanding = schedule.outstanding_cents();
+        if outstanding == 0 {
+            return Ok(());
+        }
+        self.record_payment(TaxPayment {
+            id: uuid::Uuid::new_v4(),
+            tax_schedule_id: id,
+            paid_date,
+            amount_cents: outstanding,
+            reference: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }).await
+    }
+
+    async fn record_payment(&self, payment: TaxPayment) -> DomainResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        sqlx::query(r#"
+            INSERT INTO tax_schedule_payments (id, tax_schedule_id, paid_date, amount_cents, reference, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+        "#)
+            .bind(payment.id.to_string())
+            .bind(payment.tax_schedule_id.to_string())
+            .bind(payment.paid_date.format("%Y-%m-%d").to_string())
+            .bind(payment.amount_cents)
+            .bind(payment.reference.clone())
+            .bind(payment.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let result = sqlx::query(r#"
+            UPDATE tax_schedules
+            SET paid_amount_cents = paid_amount_cents + ?,
+                paid_date = ?,
+                payment_reference = COALESCE(?, payment_reference),
+                status = CASE WHEN paid_amount_cents + ? >= amount_cents THEN 'Paid' ELSE status END
+            WHERE id = ?
+        "#)
+            .bind(payment.amount_cents)
+            .bind(payment.paid_date.format("%Y-%m-%d").to_string())
+            .bind(payment.reference)
+            .bind(payment.amount_cents)
+            .bind(payment.tax_schedule_id.to_string())
+            .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+
+        tx.commit().await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
+
+    async fn list_payments(&self, tax_schedule_id: uuid::Uuid) -> DomainResult<Vec<TaxPayment>> {
+        let rows = sqlx::query(r#"
+            SELECT id, tax_schedule_id, paid_date, amount_cents, reference, created_at
+            FROM tax_schedule_payments
+            WHERE tax_schedule_id = ?
+            ORDER BY paid_date, created_at
+        "#)
+            .bind(tax_schedule_id.to_string())
+            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        rows.iter().map(row_to_tax_payment).collect()
+    }
 }
 
 #[async_trait::async_trait]
@@ -697,7 +903,7 @@ impl SimulationRepo for SqliteSimulationRepo {
             .bind(results_json)
             .bind(simulation.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(simulation.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -723,14 +929,14 @@ impl SimulationRepo for SqliteSimulationRepo {
             .bind(results_json)
             .bind(simulation.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(simulation.id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn delete_simulation(&self, id: uuid::Uuid) -> DomainResult<()> {
         sqlx::query(r#"DELETE FROM simulations WHERE id = ?"#)
             .bind(id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -740,36 +946,9 @@ impl SimulationRepo for SqliteSimulationRepo {
             FROM simulations WHERE id = ?
         "#)
             .bind(id.to_string())
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        if let Some(r) = row {
-            let scenario_type_str: String = r.get("scenario_type");
-            let scenario_type = match scenario_type_str.as_str() {
-                "DailyRateOptimization" => SimulationScenario::DailyRateOptimization,
-                "AnnualIncomeProjection" => SimulationScenario::AnnualIncomeProjection,
-                "TaxOptimization" => SimulationScenario::TaxOptimization,
-                "WorkingDaysImpact" => SimulationScenario::WorkingDaysImpact,
-                _ => SimulationScenario::DailyRateOptimization,
-            };
-            
-            let parameters_json: String = r.get("parameters");
-            let parameters: SimulationParameters = serde_json::from_str(&parameters_json).map_err(|e| DomainError::Validation(e.to_string()))?;
-            
-            let results_json: Option<String> = r.get("results");
-            let results = results_json.map(|json| serde_json::from_str(&json)).transpose().map_err(|e| DomainError::Validation(e.to_string()))?;
-            
-            Ok(Simulation {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                name: r.get("name"),
-                scenario_type,
-                parameters,
-                results,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            })
-        } else {
-            Err(DomainError::NotFound)
-        }
+        row.as_ref().map(row_to_simulation).unwrap_or(Err(DomainError::NotFound))
     }
 
     async fn list_simulations(&self) -> DomainResult<Vec<Simulation>> {
@@ -778,40 +957,35 @@ impl SimulationRepo for SqliteSimulationRepo {
             FROM simulations 
             ORDER BY updated_at DESC
         "#)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        let mut simulations = Vec::new();
-        for r in rows {
-            let scenario_type_str: String = r.get("scenario_type");
-            let scenario_type = match scenario_type_str.as_str() {
-                "DailyRateOptimization" => SimulationScenario::DailyRateOptimization,
-                "AnnualIncomeProjection" => SimulationScenario::AnnualIncomeProjection,
-                "TaxOptimization" => SimulationScenario::TaxOptimization,
-                "WorkingDaysImpact" => SimulationScenario::WorkingDaysImpact,
-                _ => SimulationScenario::DailyRateOptimization,
-            };
-            
-            let parameters_json: String = r.get("parameters");
-            let parameters: SimulationParameters = serde_json::from_str(&parameters_json).map_err(|e| DomainError::Validation(e.to_string()))?;
-            
-            let results_json: Option<String> = r.get("results");
-            let results = results_json.map(|json| serde_json::from_str(&json)).transpose().map_err(|e| DomainError::Validation(e.to_string()))?;
-            
-            simulations.push(Simulation {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                name: r.get("name"),
-                scenario_type,
-                parameters,
-                results,
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            });
-        }
-        
-        Ok(simulations)
+        rows.iter().map(row_to_simulation).collect()
     }
 }
 
+fn string_to_simulation_scenario(s: &str) -> Option<SimulationScenario> {
+    match s {
+        "DailyRateOptimization" => Some(SimulationScenario::DailyRateOptimization),
+        "AnnualIncomeProjection" => Some(SimulationScenario::AnnualIncomeProjection),
+        "TaxOptimization" => Some(SimulationScenario::TaxOptimization),
+        "WorkingDaysImpact" => Some(SimulationScenario::WorkingDaysImpact),
+        _ => None,
+    }
+}
+
+fn row_to_simulation(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Simulation> {
+    let c = Columns::of("simulations", row);
+    Ok(Simulation {
+        id: c.uuid("id")?,
+        name: c.get("name")?,
+        scenario_type: c.parse("scenario_type", string_to_simulation_scenario, "scénario de simulation")?,
+        parameters: c.parse("parameters", |s| serde_json::from_str::<SimulationParameters>(s).ok(), "paramètres JSON")?,
+        results: c.opt_parse("results", |s| serde_json::from_str(s).ok(), "résultats JSON")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
 #[async_trait::async_trait]
 impl KPIRepo for SqliteKPIRepo {
     async fn save_monthly_kpi(&self, kpi: MonthlyKPI) -> DomainResult<()> {
@@ -858,7 +1032,7 @@ impl KPIRepo for SqliteKPIRepo {
             .bind(kpi.utilization_rate)
             .bind(kpi.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(kpi.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -873,31 +1047,9 @@ impl KPIRepo for SqliteKPIRepo {
         "#)
             .bind(month.year)
             .bind(month.month as i64)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        if let Some(r) = row {
-            Ok(Some(MonthlyKPI {
-                id: r.get::<String,_>("id").parse().unwrap(),
-                month: MonthId { year: r.get("year"), month: r.get::<i64,_>("month") as u32 },
-                revenue_ht_cents: r.get("revenue_ht_cents"),
-                revenue_ttc_cents: r.get("revenue_ttc_cents"),
-                expenses_ttc_cents: r.get("expenses_ttc_cents"),
-                working_days: r.get("working_days"),
-                billable_hours: r.get("billable_hours"),
-                average_daily_rate_cents: r.get("average_daily_rate_cents"),
-                average_hourly_rate_cents: r.get("average_hourly_rate_cents"),
-                vat_collected_cents: r.get("vat_collected_cents"),
-                vat_due_cents: r.get("vat_due_cents"),
-                urssaf_due_cents: r.get("urssaf_due_cents"),
-                net_margin_cents: r.get("net_margin_cents"),
-                profitability_ratio: r.get("profitability_ratio"),
-                utilization_rate: r.get("utilization_rate"),
-                created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            }))
-        } else {
-            Ok(None)
-        }
+        row.as_ref().map(row_to_monthly_kpi).transpose()
     }
 
     async fn list_monthly_kpis(&self, start_month: &MonthId, end_month: &MonthId) -> DomainResult<Vec<MonthlyKPI>> {
@@ -917,38 +1069,43 @@ impl KPIRepo for SqliteKPIRepo {
             .bind(end_month.year)
             .bind(end_month.year)
             .bind(end_month.month as i64)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| MonthlyKPI {
-            id: r.get::<String,_>("id").parse().unwrap(),
-            month: MonthId { year: r.get("year"), month: r.get::<i64,_>("month") as u32 },
-            revenue_ht_cents: r.get("revenue_ht_cents"),
-            revenue_ttc_cents: r.get("revenue_ttc_cents"),
-            expenses_ttc_cents: r.get("expenses_ttc_cents"),
-            working_days: r.get("working_days"),
-            billable_hours: r.get("billable_hours"),
-            average_daily_rate_cents: r.get("average_daily_rate_cents"),
-            average_hourly_rate_cents: r.get("average_hourly_rate_cents"),
-            vat_collected_cents: r.get("vat_collected_cents"),
-            vat_due_cents: r.get("vat_due_cents"),
-            urssaf_due_cents: r.get("urssaf_due_cents"),
-            net_margin_cents: r.get("net_margin_cents"),
-            profitability_ratio: r.get("profitability_ratio"),
-            utilization_rate: r.get("utilization_rate"),
-            created_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&r.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-        }).collect())
+        rows.iter().map(row_to_monthly_kpi).collect()
     }
 
     async fn delete_monthly_kpi(&self, month: &MonthId) -> DomainResult<()> {
         sqlx::query(r#"DELETE FROM monthly_kpis WHERE year = ? AND month = ?"#)
             .bind(month.year)
             .bind(month.month as i64)
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 }
 
+fn row_to_monthly_kpi(row: &sqlx::sqlite::SqliteRow) -> DomainResult<MonthlyKPI> {
+    let c = Columns::of("monthly_kpis", row);
+    Ok(MonthlyKPI {
+        id: c.uuid("id")?,
+        month: MonthId { year: c.get("year")?, month: c.get::<i64>("month")? as u32 },
+        revenue_ht_cents: c.get("revenue_ht_cents")?,
+        revenue_ttc_cents: c.get("revenue_ttc_cents")?,
+        expenses_ttc_cents: c.get("expenses_ttc_cents")?,
+        working_days: c.get("working_days")?,
+        billable_hours: c.get("billable_hours")?,
+        average_daily_rate_cents: c.get("average_daily_rate_cents")?,
+        average_hourly_rate_cents: c.get("average_hourly_rate_cents")?,
+        vat_collected_cents: c.get("vat_collected_cents")?,
+        vat_due_cents: c.get("vat_due_cents")?,
+        urssaf_due_cents: c.get("urssaf_due_cents")?,
+        net_margin_cents: c.get("net_margin_cents")?,
+        profitability_ratio: c.get("profitability_ratio")?,
+        utilization_rate: c.get("utilization_rate")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
 // Helper functions for Operation serialization/deserialization
 fn operation_type_to_string(operation_type: &OperationType) -> &'static str {
     match operation_type {
@@ -957,73 +1114,171 @@ fn operation_type_to_string(operation_type: &OperationType) -> &'static str {
     }
 }
 
-fn string_to_operation_type(s: &str) -> OperationType {
+fn string_to_operation_type(s: &str) -> Option<OperationType> {
     match s {
-        "purchase" => OperationType::Purchase,
-        "sale" => OperationType::Sale,
-        _ => OperationType::Sale, // default fallback
+        "purchase" => Some(OperationType::Purchase),
+        "sale" => Some(OperationType::Sale),
+        _ => None,
     }
 }
 
-fn row_to_operation(row: &sqlx::sqlite::SqliteRow) -> Operation {
-    Operation {
-        id: row.get::<String,_>("id").parse().unwrap(),
-        invoice_date: NaiveDate::parse_from_str(&row.get::<String,_>("invoice_date"), "%Y-%m-%d").unwrap(),
-        payment_date: row.get::<Option<String>,_>("payment_date")
-            .map(|s: String| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-        operation_type: string_to_operation_type(&row.get::<String,_>("type")),
-        amount_ht_cents: row.get("amount_ht_cents"),
-        vat_amount_cents: row.get("vat_amount_cents"),
-        amount_ttc_cents: row.get("amount_ttc_cents"),
-        vat_on_payments: row.get::<i64,_>("vat_on_payments") != 0,
-        label: row.get("label"),
-        receipt_url: row.get("receipt_url"),
-        created_at: NaiveDateTime::parse_from_str(&row.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-        updated_at: NaiveDateTime::parse_from_str(&row.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
+fn row_to_operation(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Operation> {
+    let c = Columns::of("operations", row);
+    Ok(Operation {
+        id: c.uuid("id")?,
+        invoice_date: c.date("invoice_date")?,
+        payment_date: c.opt_date("payment_date")?,
+        operation_type: c.parse("type", string_to_operation_type, "purchase ou sale")?,
+        amount_ht_cents: c.get("amount_ht_cents")?,
+        vat_amount_cents: c.get("vat_amount_cents")?,
+        amount_ttc_cents: c.get("amount_ttc_cents")?,
+        vat_on_payments: c.get::<i64>("vat_on_payments")? != 0,
+        label: c.get("label")?,
+        client: c.get("client")?,
+        category: c.get("category")?,
+        receipt_key: c.get("receipt_key")?,
+        receipt_sha256: c.get("receipt_sha256")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
+/// Filters of an operation query, each one off when its parameter is NULL
+const OPERATION_FILTERS: &str = r#"deleted_at IS NULL
+    AND (? IS NULL OR invoice_date >= ?) AND (? IS NULL OR invoice_date <= ?)
+    AND (? IS NULL OR payment_date >= ?) AND (? IS NULL OR payment_date <= ?)
+    AND (? IS NULL OR type = ?)
+    AND (? IS NULL OR (payment_date IS NOT NULL) = ?)
+    AND (? IS NULL OR amount_ht_cents >= ?) AND (? IS NULL OR amount_ht_cents <= ?)
+    AND (? IS NULL OR client = ? COLLATE NOCASE)
+    AND (? IS NULL OR category IN (SELECT value FROM json_each(?)))
+    AND (? IS NULL OR (COALESCE(receipt_key, '') <> '') = ?)
+    AND (? IS NULL OR id IN (SELECT k.id FROM operations_fts f JOIN operations_fts_keys k ON k.docid = f.rowid WHERE operations_fts MATCH ?))"#;
+
+/// Parameters of `OPERATION_FILTERS`, in order
+struct OperationFilters {
+    invoice_from: Option<String>,
+    invoice_to: Option<String>,
+    payment_from: Option<String>,
+    payment_to: Option<String>,
+    operation_type: Option<&'static str>,
+    paid: Option<bool>,
+    min_amount_ht_cents: Option<i64>,
+    max_amount_ht_cents: Option<i64>,
+    client: Option<String>,
+    categories: Option<String>, // JSON array
+    has_receipt: Option<bool>,
+    text: Option<String>, // FTS5 query: every word, as a prefix
+}
+
+impl OperationFilters {
+    fn of(query: &OperationQuery) -> DomainResult<Self> {
+        let date = |d: Option<NaiveDate>| d.map(|d| d.format("%Y-%m-%d").to_string());
+        let terms = query.search_terms();
+        Ok(Self {
+            invoice_from: date(query.invoice_from),
+            invoice_to: date(query.invoice_to),
+            payment_from: date(query.payment_from),
+            payment_to: date(query.payment_to),
+            operation_type: query.operation_type.as_ref().map(operation_type_to_string),
+            paid: query.paid,
+            min_amount_ht_cents: query.min_amount_ht_cents,
+            max_amount_ht_cents: query.max_amount_ht_cents,
+            client: query.client.clone(),
+            categories: if query.categories.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&query.categories).map_err(|e| DomainError::Repo(e.to_string()))?)
+            },
+            has_receipt: query.has_receipt,
+            text: (!terms.is_empty()).then(|| terms.iter().map(|t| format!("\"{}\"*", t)).collect::<Vec<_>>().join(" ")),
+        })
+    }
+
+    fn bind<'q>(&self, query: SqliteQuery<'q>) -> SqliteQuery<'q> {
+        query
+            .bind(self.invoice_from.clone()).bind(self.invoice_from.clone())
+            .bind(self.invoice_to.clone()).bind(self.invoice_to.clone())
+            .bind(self.payment_from.clone()).bind(self.payment_from.clone())
+            .bind(self.payment_to.clone()).bind(self.payment_to.clone())
+            .bind(self.operation_type).bind(self.operation_type)
+            .bind(self.paid).bind(self.paid)
+            .bind(self.min_amount_ht_cents).bind(self.min_amount_ht_cents)
+            .bind(self.max_amount_ht_cents).bind(self.max_amount_ht_cents)
+            .bind(self.client.clone()).bind(self.client.clone())
+            .bind(self.categories.clone()).bind(self.categories.clone())
+            .bind(self.has_receipt).bind(self.has_receipt)
+            .bind(self.text.clone()).bind(self.text.clone())
     }
 }
 
+type SqliteQuery<'q> = sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>;
+
+/// Expression an operation query sorts on, matching `OperationSortKey::value_of`
+fn operation_sort_column(sort: OperationSortKey) -> &'static str {
+    match sort {
+        OperationSortKey::InvoiceDate => "invoice_date",
+        OperationSortKey::PaymentDate => "COALESCE(payment_date, '')",
+        OperationSortKey::AmountHt => "amount_ht_cents",
+        OperationSortKey::Label => "lower(COALESCE(label, ''))",
+        OperationSortKey::CreatedAt => "created_at",
+    }
+}
+
+fn bind_sort_value<'q>(query: SqliteQuery<'q>, value: &OperationSortValue) -> SqliteQuery<'q> {
+    match value {
+        OperationSortValue::Cents(cents) => query.bind(*cents),
+        OperationSortValue::Text(text) => query.bind(text.clone()),
+    }
+}
+
+/// Shared by OperationRepo and invoice issuance, which inserts the sale inside its own transaction
+async fn insert_operation<'e, E: sqlx::Executor<'e, Database = Sqlite>>(executor: E, operation: &Operation) -> DomainResult<()> {
+    sqlx::query(r#"
+        INSERT INTO operations (
+            id, invoice_date, payment_date, type,
+            amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+            vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    "#)
+        .bind(operation.id.to_string())
+        .bind(operation.invoice_date.format("%Y-%m-%d").to_string())
+        .bind(operation.payment_date.map(|d| d.format("%Y-%m-%d").to_string()))
+        .bind(operation_type_to_string(&operation.operation_type))
+        .bind(operation.amount_ht_cents)
+        .bind(operation.vat_amount_cents)
+        .bind(operation.amount_ttc_cents)
+        .bind(if operation.vat_on_payments { 1 } else { 0 })
+        .bind(operation.label.clone())
+        .bind(operation.client.clone())
+        .bind(operation.category.clone())
+        .bind(operation.receipt_key.clone())
+        .bind(operation.receipt_sha256.clone())
+        .bind(operation.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(operation.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(executor).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl OperationRepo for SqliteOperationRepo {
     async fn create_operation(&self, operation: Operation) -> DomainResult<()> {
-        let payment_date = operation.payment_date.map(|d| d.format("%Y-%m-%d").to_string());
-
-        sqlx::query(r#"
-            INSERT INTO operations (
-                id, invoice_date, payment_date, type,
-                amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                vat_on_payments, label, receipt_url, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#)
-            .bind(operation.id.to_string())
-            .bind(operation.invoice_date.format("%Y-%m-%d").to_string())
-            .bind(payment_date)
-            .bind(operation_type_to_string(&operation.operation_type))
-            .bind(operation.amount_ht_cents)
-            .bind(operation.vat_amount_cents)
-            .bind(operation.amount_ttc_cents)
-            .bind(if operation.vat_on_payments { 1 } else { 0 })
-            .bind(operation.label)
-            .bind(operation.receipt_url)
-            .bind(operation.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .bind(operation.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-        Ok(())
+        insert_operation(&mut *self.db.acquire().await?, &operation).await
     }
 
     async fn get_operation(&self, id: uuid::Uuid) -> DomainResult<Operation> {
         let row = sqlx::query(r#"
             SELECT id, invoice_date, payment_date, type,
                    amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                   vat_on_payments, label, receipt_url, created_at, updated_at
-            FROM operations WHERE id = ?
+                   vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
+            FROM operations WHERE id = ? AND deleted_at IS NULL
         "#)
             .bind(id.to_string())
-            .fetch_one(&self.pool).await.map_err(|e| match e {
+            .fetch_one(&mut *self.db.acquire().await?).await.map_err(|e| match e {
                 sqlx::Error::RowNotFound => DomainError::NotFound,
                 _ => DomainError::Repo(e.to_string())
             })?;
-        Ok(row_to_operation(&row))
+        row_to_operation(&row)
     }
 
     async fn update_operation(&self, operation: Operation) -> DomainResult<()> {
@@ -1033,7 +1288,7 @@ impl OperationRepo for SqliteOperationRepo {
             UPDATE operations SET 
                 invoice_date = ?, payment_date = ?, type = ?,
                 amount_ht_cents = ?, vat_amount_cents = ?, amount_ttc_cents = ?,
-                vat_on_payments = ?, label = ?, receipt_url = ?, updated_at = ?
+                vat_on_payments = ?, label = ?, client = ?, category = ?, receipt_key = ?, receipt_sha256 = ?, updated_at = ?
             WHERE id = ?
         "#)
             .bind(operation.invoice_date.format("%Y-%m-%d").to_string())
@@ -1044,17 +1299,20 @@ impl OperationRepo for SqliteOperationRepo {
             .bind(operation.amount_ttc_cents)
             .bind(if operation.vat_on_payments { 1 } else { 0 })
             .bind(operation.label)
-            .bind(operation.receipt_url)
+            .bind(operation.client)
+            .bind(operation.category)
+            .bind(operation.receipt_key)
+            .bind(operation.receipt_sha256)
             .bind(operation.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(operation.id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn delete_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
         sqlx::query(r#"DELETE FROM operations WHERE id = ?"#)
             .bind(id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -1064,25 +1322,26 @@ impl OperationRepo for SqliteOperationRepo {
             sqlx::query(r#"
                 SELECT id, invoice_date, payment_date, type,
                        amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                       vat_on_payments, label, receipt_url, created_at, updated_at
+                       vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
                 FROM operations 
-                WHERE substr(invoice_date, 1, 7) = ? 
+                WHERE substr(invoice_date, 1, 7) = ? AND deleted_at IS NULL
                 ORDER BY invoice_date DESC
             "#)
                 .bind(ym)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         } else {
             sqlx::query(r#"
                 SELECT id, invoice_date, payment_date, type,
                        amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                       vat_on_payments, label, receipt_url, created_at, updated_at
+                       vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
                 FROM operations 
+                WHERE deleted_at IS NULL
                 ORDER BY invoice_date DESC
             "#)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         };
         
-        Ok(rows.into_iter().map(|r| row_to_operation(&r)).collect())
+        rows.iter().map(row_to_operation).collect()
     }
 
     async fn list_operations_by_type(&self, operation_type: OperationType, month: Option<MonthId>) -> DomainResult<Vec<Operation>> {
@@ -1091,28 +1350,28 @@ impl OperationRepo for SqliteOperationRepo {
             sqlx::query(r#"
                 SELECT id, invoice_date, payment_date, type,
                        amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                       vat_on_payments, label, receipt_url, created_at, updated_at
+                       vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
                 FROM operations 
-                WHERE type = ? AND substr(invoice_date, 1, 7) = ?
+                WHERE type = ? AND substr(invoice_date, 1, 7) = ? AND deleted_at IS NULL
                 ORDER BY invoice_date DESC
             "#)
                 .bind(operation_type_to_string(&operation_type))
                 .bind(ym)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         } else {
             sqlx::query(r#"
                 SELECT id, invoice_date, payment_date, type,
                        amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                       vat_on_payments, label, receipt_url, created_at, updated_at
+                       vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
                 FROM operations 
-                WHERE type = ?
+                WHERE type = ? AND deleted_at IS NULL
                 ORDER BY invoice_date DESC
             "#)
                 .bind(operation_type_to_string(&operation_type))
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         };
         
-        Ok(rows.into_iter().map(|r| row_to_operation(&r)).collect())
+        rows.iter().map(row_to_operation).collect()
     }
 
     async fn list_operations_by_payment_month(&self, month: MonthId) -> DomainResult<Vec<Operation>> {
@@ -1120,15 +1379,109 @@ impl OperationRepo for SqliteOperationRepo {
         let rows = sqlx::query(r#"
             SELECT id, invoice_date, payment_date, type,
                    amount_ht_cents, vat_amount_cents, amount_ttc_cents,
-                   vat_on_payments, label, receipt_url, created_at, updated_at
+                   vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
             FROM operations 
-            WHERE payment_date IS NOT NULL AND substr(payment_date, 1, 7) = ?
+            WHERE payment_date IS NOT NULL AND substr(payment_date, 1, 7) = ? AND deleted_at IS NULL
             ORDER BY payment_date DESC
         "#)
             .bind(ym)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| row_to_operation(&r)).collect())
+        rows.iter().map(row_to_operation).collect()
+    }
+
+    async fn list_operations_by_receipt_sha256(&self, sha256: &str) -> DomainResult<Vec<Operation>> {
+        let rows = sqlx::query(r#"
+            SELECT id, invoice_date, payment_date, type,
+                   amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+                   vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
+            FROM operations
+            WHERE receipt_sha256 = ? AND deleted_at IS NULL
+            ORDER BY created_at
+        "#)
+            .bind(sha256)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        rows.iter().map(row_to_operation).collect()
+    }
+
+    async fn query_operations(&self, query: &OperationQuery) -> DomainResult<OperationPage> {
+        let after = query.after()?;
+        let page_size = query.page_size();
+        let column = operation_sort_column(query.sort);
+        let (direction, past) = if query.descending { ("DESC", "<") } else { ("ASC", ">") };
+        let filters = OperationFilters::of(query)?;
+
+        let count = format!("SELECT COUNT(*) AS total FROM operations WHERE {}", OPERATION_FILTERS);
+        let total: i64 = filters.bind(sqlx::query(&count))
+            .fetch_one(&mut *self.db.acquire().await?).await
+            .and_then(|row| row.try_get("total"))
+            .map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let start = if after.is_some() { format!("AND ({column} {past} ? OR ({column} = ? AND id {past} ?))") } else { String::new() };
+        let sql = format!(r#"
+            SELECT id, invoice_date, payment_date, type,
+                   amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+                   vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at
+            FROM operations
+            WHERE {OPERATION_FILTERS} {start}
+            ORDER BY {column} {direction}, id {direction}
+            LIMIT ?
+        "#);
+        let mut select = filters.bind(sqlx::query(&sql));
+        if let Some(after) = &after {
+            select = bind_sort_value(bind_sort_value(select, &after.value), &after.value).bind(after.id.to_string());
+        }
+        let rows = select
+            .bind(page_size as i64 + 1)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let mut operations = rows.iter().map(row_to_operation).collect::<DomainResult<Vec<_>>>()?;
+        let next_cursor = if operations.len() > page_size as usize {
+            operations.truncate(page_size as usize);
+            operations.last().map(|o| query.cursor_of(o))
+        } else {
+            None
+        };
+        Ok(OperationPage { operations, total: total as u64, next_cursor })
+    }
+
+    async fn trash_operation(&self, id: uuid::Uuid, deleted_at: NaiveDateTime) -> DomainResult<()> {
+        let result = sqlx::query("UPDATE operations SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(deleted_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .bind(id.to_string())
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn restore_operation(&self, id: uuid::Uuid) -> DomainResult<()> {
+        let result = sqlx::query("UPDATE operations SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL")
+            .bind(id.to_string())
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn list_trashed_operations(&self) -> DomainResult<Vec<TrashedOperation>> {
+        let rows = sqlx::query(r#"
+            SELECT id, invoice_date, payment_date, type,
+                   amount_ht_cents, vat_amount_cents, amount_ttc_cents,
+                   vat_on_payments, label, client, category, receipt_key, receipt_sha256, created_at, updated_at, deleted_at
+            FROM operations
+            WHERE deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC
+        "#)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        rows.iter().map(|r| Ok(TrashedOperation {
+            operation: row_to_operation(r)?,
+            deleted_at: Columns::of("operations", r).datetime("deleted_at")?,
+        })).collect()
     }
 }
 
@@ -1140,57 +1493,62 @@ fn declaration_type_to_string(declaration_type: &DeclarationType) -> &'static st
     }
 }
 
-fn string_to_declaration_type(s: &str) -> DeclarationType {
+fn string_to_declaration_type(s: &str) -> Option<DeclarationType> {
     match s {
-        "vat" => DeclarationType::Vat,
-        "urssaf" => DeclarationType::Urssaf,
-        _ => DeclarationType::Vat, // default fallback
+        "vat" => Some(DeclarationType::Vat),
+        "urssaf" => Some(DeclarationType::Urssaf),
+        _ => None,
     }
 }
 
 fn declaration_status_to_string(status: &DeclarationStatus) -> &'static str {
     match status {
         DeclarationStatus::Pending => "pending",
+        DeclarationStatus::Filed => "filed",
         DeclarationStatus::Paid => "paid",
         DeclarationStatus::Overdue => "overdue",
     }
 }
 
-fn string_to_declaration_status(s: &str) -> DeclarationStatus {
+fn string_to_declaration_status(s: &str) -> Option<DeclarationStatus> {
     match s {
-        "pending" => DeclarationStatus::Pending,
-        "paid" => DeclarationStatus::Paid,
-        "overdue" => DeclarationStatus::Overdue,
-        _ => DeclarationStatus::Pending, // default fallback
+        "pending" => Some(DeclarationStatus::Pending),
+        "filed" => Some(DeclarationStatus::Filed),
+        "paid" => Some(DeclarationStatus::Paid),
+        "overdue" => Some(DeclarationStatus::Overdue),
+        _ => None,
     }
 }
 
-fn row_to_declaration(row: &sqlx::sqlite::SqliteRow) -> Declaration {
-    Declaration {
-        id: row.get::<String,_>("id").parse().unwrap(),
-        declaration_type: string_to_declaration_type(&row.get::<String,_>("declaration_type")),
-        period_year: row.get("period_year"),
-        period_month: row.get::<i64,_>("period_month") as u32,
-        amount_due_cents: row.get("amount_due_cents"),
-        due_date: NaiveDate::parse_from_str(&row.get::<String,_>("due_date"), "%Y-%m-%d").unwrap(),
-        payment_date: row.get::<Option<String>,_>("payment_date")
-            .map(|s: String| NaiveDate::parse_from_str(&s, "%Y-%m-%d").unwrap()),
-        status: string_to_declaration_status(&row.get::<String,_>("status")),
-        created_at: NaiveDateTime::parse_from_str(&row.get::<String,_>("created_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-        updated_at: NaiveDateTime::parse_from_str(&row.get::<String,_>("updated_at"), "%Y-%m-%d %H:%M:%S").unwrap(),
-    }
+fn row_to_declaration(row: &sqlx::sqlite::SqliteRow) -> DomainResult<Declaration> {
+    let c = Columns::of("declarations", row);
+    Ok(Declaration {
+        id: c.uuid("id")?,
+        declaration_type: c.parse("declaration_type", string_to_declaration_type, "vat ou urssaf")?,
+        period_year: c.get("period_year")?,
+        period_month: c.get::<i64>("period_month")? as u32,
+        amount_due_cents: c.get("amount_due_cents")?,
+        due_date: c.date("due_date")?,
+        filing_date: c.opt_date("filing_date")?,
+        payment_date: c.opt_date("payment_date")?,
+        status: c.parse("status", string_to_declaration_status, "pending, filed, paid ou overdue")?,
+        recomputed_amount_cents: c.get("recomputed_amount_cents")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
 }
 
 #[async_trait::async_trait]
 impl DeclarationRepo for SqliteDeclarationRepo {
     async fn create_declaration(&self, declaration: Declaration) -> DomainResult<()> {
+        let filing_date = declaration.filing_date.map(|d| d.format("%Y-%m-%d").to_string());
         let payment_date = declaration.payment_date.map(|d| d.format("%Y-%m-%d").to_string());
 
         sqlx::query(r#"
             INSERT INTO declarations (
                 id, declaration_type, period_year, period_month, amount_due_cents,
-                due_date, payment_date, status, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#)
             .bind(declaration.id.to_string())
             .bind(declaration_type_to_string(&declaration.declaration_type))
@@ -1198,35 +1556,38 @@ impl DeclarationRepo for SqliteDeclarationRepo {
             .bind(declaration.period_month as i64)
             .bind(declaration.amount_due_cents)
             .bind(declaration.due_date.format("%Y-%m-%d").to_string())
+            .bind(filing_date)
             .bind(payment_date)
             .bind(declaration_status_to_string(&declaration.status))
+            .bind(declaration.recomputed_amount_cents)
             .bind(declaration.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(declaration.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn get_declaration(&self, id: uuid::Uuid) -> DomainResult<Declaration> {
         let row = sqlx::query(r#"
             SELECT id, declaration_type, period_year, period_month, amount_due_cents,
-                   due_date, payment_date, status, created_at, updated_at
+                   due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
             FROM declarations WHERE id = ?
         "#)
             .bind(id.to_string())
-            .fetch_one(&self.pool).await.map_err(|e| match e {
+            .fetch_one(&mut *self.db.acquire().await?).await.map_err(|e| match e {
                 sqlx::Error::RowNotFound => DomainError::NotFound,
                 _ => DomainError::Repo(e.to_string())
             })?;
-        Ok(row_to_declaration(&row))
+        row_to_declaration(&row)
     }
 
     async fn update_declaration(&self, declaration: Declaration) -> DomainResult<()> {
+        let filing_date = declaration.filing_date.map(|d| d.format("%Y-%m-%d").to_string());
         let payment_date = declaration.payment_date.map(|d| d.format("%Y-%m-%d").to_string());
 
         sqlx::query(r#"
             UPDATE declarations SET 
                 declaration_type = ?, period_year = ?, period_month = ?, amount_due_cents = ?,
-                due_date = ?, payment_date = ?, status = ?, updated_at = ?
+                due_date = ?, filing_date = ?, payment_date = ?, status = ?, recomputed_amount_cents = ?, updated_at = ?
             WHERE id = ?
         "#)
             .bind(declaration_type_to_string(&declaration.declaration_type))
@@ -1234,18 +1595,20 @@ impl DeclarationRepo for SqliteDeclarationRepo {
             .bind(declaration.period_month as i64)
             .bind(declaration.amount_due_cents)
             .bind(declaration.due_date.format("%Y-%m-%d").to_string())
+            .bind(filing_date)
             .bind(payment_date)
             .bind(declaration_status_to_string(&declaration.status))
+            .bind(declaration.recomputed_amount_cents)
             .bind(declaration.updated_at.format("%Y-%m-%d %H:%M:%S").to_string())
             .bind(declaration.id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
     async fn delete_declaration(&self, id: uuid::Uuid) -> DomainResult<()> {
         sqlx::query(r#"DELETE FROM declarations WHERE id = ?"#)
             .bind(id.to_string())
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -1253,62 +1616,92 @@ impl DeclarationRepo for SqliteDeclarationRepo {
         let rows = if let Some(y) = year {
             sqlx::query(r#"
                 SELECT id, declaration_type, period_year, period_month, amount_due_cents,
-                       due_date, payment_date, status, created_at, updated_at
+                       due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
                 FROM declarations 
                 WHERE period_year = ? 
                 ORDER BY period_year DESC, period_month DESC
             "#)
                 .bind(y)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         } else {
             sqlx::query(r#"
                 SELECT id, declaration_type, period_year, period_month, amount_due_cents,
-                       due_date, payment_date, status, created_at, updated_at
+                       due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
                 FROM declarations 
                 ORDER BY period_year DESC, period_month DESC
             "#)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?
         };
         
-        Ok(rows.into_iter().map(|r| row_to_declaration(&r)).collect())
+        rows.iter().map(row_to_declaration).collect()
     }
 
     async fn get_declaration_by_period(&self, declaration_type: DeclarationType, year: i32, month: u32) -> DomainResult<Option<Declaration>> {
         let row = sqlx::query(r#"
             SELECT id, declaration_type, period_year, period_month, amount_due_cents,
-                   due_date, payment_date, status, created_at, updated_at
+                   due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
             FROM declarations 
             WHERE declaration_type = ? AND period_year = ? AND period_month = ?
         "#)
             .bind(declaration_type_to_string(&declaration_type))
             .bind(year)
             .bind(month as i64)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(row.map(|r| row_to_declaration(&r)))
+        row.as_ref().map(row_to_declaration).transpose()
     }
 
     async fn list_declarations_by_status(&self, status: DeclarationStatus) -> DomainResult<Vec<Declaration>> {
         let rows = sqlx::query(r#"
             SELECT id, declaration_type, period_year, period_month, amount_due_cents,
-                   due_date, payment_date, status, created_at, updated_at
+                   due_date, filing_date, payment_date, status, recomputed_amount_cents, created_at, updated_at
             FROM declarations 
             WHERE status = ?
             ORDER BY due_date ASC
         "#)
             .bind(declaration_status_to_string(&status))
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(rows.into_iter().map(|r| row_to_declaration(&r)).collect())
+        rows.iter().map(row_to_declaration).collect()
     }
 }
 
 // ============ Yearly Planning Repository Implementation ============
 
+fn row_to_month_planning(row: &sqlx::sqlite::SqliteRow) -> DomainResult<MonthPlanning> {
+    let c = Columns::of("month_planning", row);
+    Ok(MonthPlanning {
+        id: c.uuid("id")?,
+        year: c.get::<i64>("year")? as i32,
+        month: c.get::<i64>("month")? as u32,
+        max_working_days: c.get::<i64>("max_working_days")? as i32,
+        holidays_taken: c.get::<i64>("holidays_taken")? as i32,
+        public_holidays: c.get::<i64>("public_holidays")? as i32,
+        working_days: c.get::<i64>("working_days")? as i32,
+        estimated_revenue_cents: c.get("estimated_revenue_cents")?,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
+fn row_to_yearly_planning(row: &sqlx::sqlite::SqliteRow, months: Vec<MonthPlanning>) -> DomainResult<YearlyPlanning> {
+    let c = Columns::of("yearly_planning", row);
+    Ok(YearlyPlanning {
+        id: c.uuid("id")?,
+        year: c.get::<i64>("year")? as i32,
+        tjm_cents: c.get("tjm_cents")?,
+        max_working_days_limit: c.get::<i64>("max_working_days_limit")? as i32,
+        months,
+        created_at: c.datetime("created_at")?,
+        updated_at: c.datetime("updated_at")?,
+    })
+}
+
 #[async_trait::async_trait]
 impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
     async fn create_yearly_planning(&self, planning: YearlyPlanning) -> DomainResult<()> {
-        let mut tx = self.pool.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
         // Insert yearly planning
         sqlx::query(r#"
@@ -1349,7 +1742,8 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
     }
 
     async fn update_yearly_planning(&self, planning: YearlyPlanning) -> DomainResult<()> {
-        let mut tx = self.pool.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
         // Update yearly planning
         sqlx::query(r#"
@@ -1398,10 +1792,10 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
         let yearly_row = sqlx::query(r#"
             SELECT id, year, tjm_cents, max_working_days_limit, created_at, updated_at
             FROM yearly_planning 
-            WHERE year = ?
+            WHERE year = ? AND deleted_at IS NULL
         "#)
             .bind(year)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
         let Some(yearly_row) = yearly_row else { return Ok(None); };
         
@@ -1414,39 +1808,18 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
             ORDER BY month ASC
         "#)
             .bind(year)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        let months: Vec<MonthPlanning> = month_rows.iter().map(|row| MonthPlanning {
-            id: uuid::Uuid::parse_str(&row.get::<String, _>("id")).unwrap(),
-            year: row.get::<i64, _>("year") as i32,
-            month: row.get::<i64, _>("month") as u32,
-            max_working_days: row.get::<i64, _>("max_working_days") as i32,
-            holidays_taken: row.get::<i64, _>("holidays_taken") as i32,
-            public_holidays: row.get::<i64, _>("public_holidays") as i32,
-            working_days: row.get::<i64, _>("working_days") as i32,
-            estimated_revenue_cents: row.get::<i64, _>("estimated_revenue_cents"),
-            created_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("created_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("updated_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-        }).collect();
+        let months = month_rows.iter().map(row_to_month_planning).collect::<DomainResult<Vec<_>>>()?;
         
-        let yearly_planning = YearlyPlanning {
-            id: uuid::Uuid::parse_str(&yearly_row.get::<String, _>("id")).unwrap(),
-            year: yearly_row.get::<i64, _>("year") as i32,
-            tjm_cents: yearly_row.get::<i64, _>("tjm_cents"),
-            max_working_days_limit: yearly_row.get::<i64, _>("max_working_days_limit") as i32,
-            months,
-            created_at: NaiveDateTime::parse_from_str(&yearly_row.get::<String, _>("created_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&yearly_row.get::<String, _>("updated_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-        };
-        
-        Ok(Some(yearly_planning))
+        Ok(Some(row_to_yearly_planning(&yearly_row, months)?))
     }
 
     async fn delete_yearly_planning(&self, year: i32) -> DomainResult<()> {
         // Delete will cascade to month_planning thanks to foreign key
         sqlx::query("DELETE FROM yearly_planning WHERE year = ?")
             .bind(year)
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -1454,14 +1827,15 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
         let yearly_rows = sqlx::query(r#"
             SELECT id, year, tjm_cents, max_working_days_limit, created_at, updated_at
             FROM yearly_planning 
+            WHERE deleted_at IS NULL
             ORDER BY year DESC
         "#)
-            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
         let mut plannings = Vec::new();
         
         for yearly_row in yearly_rows {
-            let year = yearly_row.get::<i64, _>("year") as i32;
+            let year = Columns::of("yearly_planning", &yearly_row).get::<i64>("year")? as i32;
             
             // Get month plannings for this year
             let month_rows = sqlx::query(r#"
@@ -1472,32 +1846,11 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
                 ORDER BY month ASC
             "#)
                 .bind(year)
-                .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
-            
-            let months: Vec<MonthPlanning> = month_rows.iter().map(|row| MonthPlanning {
-                id: uuid::Uuid::parse_str(&row.get::<String, _>("id")).unwrap(),
-                year: row.get::<i64, _>("year") as i32,
-                month: row.get::<i64, _>("month") as u32,
-                max_working_days: row.get::<i64, _>("max_working_days") as i32,
-                holidays_taken: row.get::<i64, _>("holidays_taken") as i32,
-                public_holidays: row.get::<i64, _>("public_holidays") as i32,
-                working_days: row.get::<i64, _>("working_days") as i32,
-                estimated_revenue_cents: row.get::<i64, _>("estimated_revenue_cents"),
-                created_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("created_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("updated_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-            }).collect();
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
             
-            let yearly_planning = YearlyPlanning {
-                id: uuid::Uuid::parse_str(&yearly_row.get::<String, _>("id")).unwrap(),
-                year,
-                tjm_cents: yearly_row.get::<i64, _>("tjm_cents"),
-                max_working_days_limit: yearly_row.get::<i64, _>("max_working_days_limit") as i32,
-                months,
-                created_at: NaiveDateTime::parse_from_str(&yearly_row.get::<String, _>("created_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-                updated_at: NaiveDateTime::parse_from_str(&yearly_row.get::<String, _>("updated_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-            };
+            let months = month_rows.iter().map(row_to_month_planning).collect::<DomainResult<Vec<_>>>()?;
             
-            plannings.push(yearly_planning);
+            plannings.push(row_to_yearly_planning(&yearly_row, months)?);
         }
         
         Ok(plannings)
@@ -1518,7 +1871,7 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
             .bind(month_planning.updated_at.to_string())
             .bind(month_planning.year)
             .bind(month_planning.month as i64)
-            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         Ok(())
     }
 
@@ -1528,22 +1881,985 @@ impl YearlyPlanningRepo for SqliteYearlyPlanningRepo {
                    working_days, estimated_revenue_cents, created_at, updated_at
             FROM month_planning 
             WHERE year = ? AND month = ?
+              AND year IN (SELECT year FROM yearly_planning WHERE deleted_at IS NULL)
         "#)
             .bind(year)
             .bind(month as i64)
-            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
         
-        Ok(row.map(|row| MonthPlanning {
-            id: uuid::Uuid::parse_str(&row.get::<String, _>("id")).unwrap(),
-            year: row.get::<i64, _>("year") as i32,
-            month: row.get::<i64, _>("month") as u32,
-            max_working_days: row.get::<i64, _>("max_working_days") as i32,
-            holidays_taken: row.get::<i64, _>("holidays_taken") as i32,
-            public_holidays: row.get::<i64, _>("public_holidays") as i32,
-            working_days: row.get::<i64, _>("working_days") as i32,
-            estimated_revenue_cents: row.get::<i64, _>("estimated_revenue_cents"),
-            created_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("created_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-            updated_at: NaiveDateTime::parse_from_str(&row.get::<String, _>("updated_at"), "%Y-%m-%d %H:%M:%S%.f").unwrap(),
-        }))
+        row.as_ref().map(row_to_month_planning).transpose()
+    }
+
+    async fn trash_yearly_planning(&self, year: i32, deleted_at: NaiveDateTime) -> DomainResult<()> {
+        let result = sqlx::query("UPDATE yearly_planning SET deleted_at = ? WHERE year = ? AND deleted_at IS NULL")
+            .bind(deleted_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .bind(year)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn restore_yearly_planning(&self, year: i32) -> DomainResult<()> {
+        let result = sqlx::query("UPDATE yearly_planning SET deleted_at = NULL WHERE year = ? AND deleted_at IS NOT NULL")
+            .bind(year)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn list_trashed_yearly_plannings(&self) -> DomainResult<Vec<TrashedYearlyPlanning>> {
+        let yearly_rows = sqlx::query(r#"
+            SELECT id, year, tjm_cents, max_working_days_limit, created_at, updated_at, deleted_at
+            FROM yearly_planning
+            WHERE deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC
+        "#)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let mut trashed = Vec::new();
+        for yearly_row in yearly_rows {
+            let year = Columns::of("yearly_planning", &yearly_row).get::<i64>("year")? as i32;
+            let month_rows = sqlx::query(r#"
+                SELECT id, year, month, max_working_days, holidays_taken, public_holidays,
+                       working_days, estimated_revenue_cents, created_at, updated_at
+                FROM month_planning
+                WHERE year = ?
+                ORDER BY month ASC
+            "#)
+                .bind(year)
+                .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+            let months = month_rows.iter().map(row_to_month_planning).collect::<DomainResult<Vec<_>>>()?;
+
+            trashed.push(TrashedYearlyPlanning {
+                planning: row_to_yearly_planning(&yearly_row, months)?,
+                deleted_at: Columns::of("yearly_planning", &yearly_row).datetime("deleted_at")?,
+            });
+        }
+        Ok(trashed)
+    }
+}
+
+// ============ Invoice Issuance Repository Implementation ============
+
+const ISSUED_INVOICE_COLUMNS: &str = "id, number, year, sequence, issue_date, due_date, is_service, issuer_json, \
+    client_name, client_address, client_siret, client_vat_number, total_ht_cents, total_vat_cents, total_ttc_cents, \
+    notes, operation_id, pdf_key, created_at";
+
+impl SqliteIssuedInvoiceRepo {
+    async fn row_to_issued_invoice(&self, row: &sqlx::sqlite::SqliteRow) -> DomainResult<IssuedInvoice> {
+        let c = Columns::of("issued_invoices", row);
+        let id: String = c.get("id")?;
+        let lines = sqlx::query(r#"
+            SELECT description, quantity_milli, unit_price_ht_cents, vat_rate_ppm
+            FROM issued_invoice_lines WHERE invoice_id = ? ORDER BY position
+        "#)
+            .bind(&id)
+            .fetch_all(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+            .iter()
+            .map(|line| {
+                let l = Columns::of("issued_invoice_lines", line);
+                Ok(InvoiceLine {
+                    description: l.get("description")?,
+                    quantity_milli: l.get("quantity_milli")?,
+                    unit_price_ht_cents: l.get("unit_price_ht_cents")?,
+                    vat_rate_ppm: l.get::<i64>("vat_rate_ppm")? as i32,
+                })
+            })
+            .collect::<DomainResult<Vec<_>>>()?;
+
+        let issuer: IssuerProfile = c.parse("issuer_json", |s| serde_json::from_str(s).ok(), "émetteur JSON")?;
+        let is_service = c.get::<i64>("is_service")? != 0;
+        Ok(IssuedInvoice {
+            id: c.uuid("id")?,
+            number: c.get("number")?,
+            year: c.get::<i64>("year")? as i32,
+            sequence: c.get::<i64>("sequence")? as u32,
+            issue_date: c.date("issue_date")?,
+            due_date: c.date("due_date")?,
+            is_service,
+            vat_breakdown: compute_invoice_vat(&lines, issuer.vat_exempt, is_service),
+            issuer,
+            client: InvoiceClient {
+                name: c.get("client_name")?,
+                address: c.get("client_address")?,
+                siret: c.get("client_siret")?,
+                vat_number: c.get("client_vat_number")?,
+            },
+            lines,
+            total_ht_cents: c.get("total_ht_cents")?,
+            total_vat_cents: c.get("total_vat_cents")?,
+            total_ttc_cents: c.get("total_ttc_cents")?,
+            notes: c.get("notes")?,
+            operation_id: c.uuid("operation_id")?,
+            pdf_key: c.get("pdf_key")?,
+            created_at: c.datetime("created_at")?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IssuedInvoiceRepo for SqliteIssuedInvoiceRepo {
+    async fn load_issuer_profile(&self) -> DomainResult<Option<IssuerProfile>> {
+        let row = sqlx::query(r#"
+            SELECT name, address, siret, vat_number, vat_exempt, late_penalty_rate_ppm, payment_terms_days, iban, email
+            FROM issuer_profile WHERE id = 1
+        "#)
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        row.map(|r| {
+            let c = Columns::of("issuer_profile", &r);
+            Ok(IssuerProfile {
+                name: c.get("name")?,
+                address: c.get("address")?,
+                siret: c.get("siret")?,
+                vat_number: c.get("vat_number")?,
+                vat_exempt: c.get::<i64>("vat_exempt")? != 0,
+                late_penalty_rate_ppm: c.get::<i64>("late_penalty_rate_ppm")? as i32,
+                payment_terms_days: c.get::<i64>("payment_terms_days")? as u32,
+                iban: c.get("iban")?,
+                email: c.get("email")?,
+            })
+        }).transpose()
+    }
+
+    async fn save_issuer_profile(&self, profile: IssuerProfile) -> DomainResult<()> {
+        sqlx::query(r#"
+            INSERT INTO issuer_profile (id, name, address, siret, vat_number, vat_exempt, late_penalty_rate_ppm, payment_terms_days, iban, email, updated_at)
+            VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            ON CONFLICT(id) DO UPDATE SET name=excluded.name, address=excluded.address, siret=excluded.siret, vat_number=excluded.vat_number,
+                vat_exempt=excluded.vat_exempt, late_penalty_rate_ppm=excluded.late_penalty_rate_ppm, payment_terms_days=excluded.payment_terms_days,
+                iban=excluded.iban, email=excluded.email, updated_at=excluded.updated_at
+        "#)
+            .bind(profile.name)
+            .bind(profile.address)
+            .bind(profile.siret)
+            .bind(profile.vat_number)
+            .bind(if profile.vat_exempt { 1 } else { 0 })
+            .bind(profile.late_penalty_rate_ppm)
+            .bind(profile.payment_terms_days as i64)
+            .bind(profile.iban)
+            .bind(profile.email)
+            .execute(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn next_invoice_sequence(&self, year: i32) -> DomainResult<u32> {
+        let last: Option<i64> = sqlx::query_scalar("SELECT last_number FROM invoice_sequences WHERE year = ?")
+            .bind(year)
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(last.unwrap_or(0) as u32 + 1)
+    }
+
+    async fn issue_invoice(&self, invoice: IssuedInvoice, operation: Operation) -> DomainResult<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let last: Option<i64> = sqlx::query_scalar("SELECT last_number FROM invoice_sequences WHERE year = ?")
+            .bind(invoice.year)
+            .fetch_optional(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if last.unwrap_or(0) + 1 != invoice.sequence as i64 {
+            return Err(DomainError::Validation(format!("Le numéro {} n'est plus le prochain numéro de facture", invoice.number)));
+        }
+        sqlx::query(r#"
+            INSERT INTO invoice_sequences (year, last_number) VALUES (?, ?)
+            ON CONFLICT(year) DO UPDATE SET last_number = excluded.last_number
+        "#)
+            .bind(invoice.year)
+            .bind(invoice.sequence as i64)
+            .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        insert_operation(&mut *tx, &operation).await?;
+
+        let issuer_json = serde_json::to_string(&invoice.issuer).map_err(|e| DomainError::Validation(e.to_string()))?;
+        sqlx::query(r#"
+            INSERT INTO issued_invoices (
+                id, number, year, sequence, issue_date, due_date, is_service, issuer_json,
+                client_name, client_address, client_siret, client_vat_number,
+                total_ht_cents, total_vat_cents, total_ttc_cents, notes, operation_id, pdf_key, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+            .bind(invoice.id.to_string())
+            .bind(&invoice.number)
+            .bind(invoice.year)
+            .bind(invoice.sequence as i64)
+            .bind(invoice.issue_date.format("%Y-%m-%d").to_string())
+            .bind(invoice.due_date.format("%Y-%m-%d").to_string())
+            .bind(if invoice.is_service { 1 } else { 0 })
+            .bind(issuer_json)
+            .bind(&invoice.client.name)
+            .bind(&invoice.client.address)
+            .bind(&invoice.client.siret)
+            .bind(&invoice.client.vat_number)
+            .bind(invoice.total_ht_cents)
+            .bind(invoice.total_vat_cents)
+            .bind(invoice.total_ttc_cents)
+            .bind(&invoice.notes)
+            .bind(invoice.operation_id.to_string())
+            .bind(&invoice.pdf_key)
+            .bind(invoice.created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        for (position, line) in invoice.lines.iter().enumerate() {
+            sqlx::query(r#"
+                INSERT INTO issued_invoice_lines (invoice_id, position, description, quantity_milli, unit_price_ht_cents, vat_rate_ppm)
+                VALUES (?, ?, ?, ?, ?, ?)
+            "#)
+                .bind(invoice.id.to_string())
+                .bind(position as i64)
+                .bind(&line.description)
+                .bind(line.quantity_milli)
+                .bind(line.unit_price_ht_cents)
+                .bind(line.vat_rate_ppm)
+                .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        }
+
+        tx.commit().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_issued_invoice(&self, id: uuid::Uuid) -> DomainResult<IssuedInvoice> {
+        let row = sqlx::query(&format!("SELECT {} FROM issued_invoices WHERE id = ?", ISSUED_INVOICE_COLUMNS))
+            .bind(id.to_string())
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?
+            .ok_or(DomainError::NotFound)?;
+        self.row_to_issued_invoice(&row).await
+    }
+
+    async fn find_issued_invoice_by_operation(&self, operation_id: uuid::Uuid) -> DomainResult<Option<IssuedInvoice>> {
+        let row = sqlx::query(&format!("SELECT {} FROM issued_invoices WHERE operation_id = ?", ISSUED_INVOICE_COLUMNS))
+            .bind(operation_id.to_string())
+            .fetch_optional(&self.pool).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        match row {
+            Some(row) => Ok(Some(self.row_to_issued_invoice(&row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_issued_invoices(&self, year: Option<i32>) -> DomainResult<Vec<IssuedInvoice>> {
+        let rows = match year {
+            Some(year) => sqlx::query(&format!("SELECT {} FROM issued_invoices WHERE year = ? ORDER BY sequence", ISSUED_INVOICE_COLUMNS))
+                .bind(year)
+                .fetch_all(&self.pool).await,
+            None => sqlx::query(&format!("SELECT {} FROM issued_invoices ORDER BY year, sequence", ISSUED_INVOICE_COLUMNS))
+                .fetch_all(&self.pool).await,
+        }.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let mut invoices = Vec::with_capacity(rows.len());
+        for row in &rows {
+            invoices.push(self.row_to_issued_invoice(row).await?);
+        }
+        Ok(invoices)
+    }
+}
+
+// ============ Legal Archive Repository Implementation ============
+
+fn row_to_archived_document(row: &sqlx::sqlite::SqliteRow) -> DomainResult<ArchivedDocument> {
+    let c = Columns::of("archived_documents", row);
+    Ok(ArchivedDocument {
+        key: c.get("key")?,
+        fiscal_year: c.get::<i64>("fiscal_year")? as i32,
+        sha256: c.get("sha256")?,
+        retain_until: c.date("retain_until")?,
+        object_locked: c.get::<i64>("object_locked")? != 0,
+    })
+}
+
+#[async_trait::async_trait]
+impl ArchiveRepo for SqliteArchiveRepo {
+    async fn archive_fiscal_year(&self, archive: ArchivedFiscalYear, documents: Vec<ArchivedDocument>) -> DomainResult<()> {
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let inserted = sqlx::query(r#"
+            INSERT INTO archived_fiscal_years (year, archived_at, retain_until, operations_count)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(year) DO NOTHING
+        "#)
+            .bind(archive.year)
+            .bind(archive.archived_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .bind(archive.retain_until.format("%Y-%m-%d").to_string())
+            .bind(archive.operations_count as i64)
+            .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if inserted.rows_affected() == 0 {
+            return Err(DomainError::Validation(format!("L'exercice {} est déjà archivé", archive.year)));
+        }
+
+        for document in documents {
+            sqlx::query(r#"
+                INSERT INTO archived_documents (key, fiscal_year, sha256, retain_until, object_locked)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(key) DO UPDATE SET
+                    fiscal_year = CASE WHEN excluded.retain_until > retain_until THEN excluded.fiscal_year ELSE fiscal_year END,
+                    retain_until = MAX(retain_until, excluded.retain_until),
+                    object_locked = MAX(object_locked, excluded.object_locked)
+            "#)
+                .bind(&document.key)
+                .bind(document.fiscal_year)
+                .bind(&document.sha256)
+                .bind(document.retain_until.format("%Y-%m-%d").to_string())
+                .bind(if document.object_locked { 1 } else { 0 })
+                .execute(&mut *tx).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        }
+
+        tx.commit().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_archived_years(&self) -> DomainResult<Vec<ArchivedFiscalYear>> {
+        let rows = sqlx::query("SELECT year, archived_at, retain_until, operations_count FROM archived_fiscal_years ORDER BY year")
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(|row| {
+            let c = Columns::of("archived_fiscal_years", row);
+            Ok(ArchivedFiscalYear {
+                year: c.get::<i64>("year")? as i32,
+                archived_at: c.datetime("archived_at")?,
+                retain_until: c.date("retain_until")?,
+                operations_count: c.get::<i64>("operations_count")? as u32,
+            })
+        }).collect()
+    }
+
+    async fn list_archived_documents(&self) -> DomainResult<Vec<ArchivedDocument>> {
+        let rows = sqlx::query("SELECT key, fiscal_year, sha256, retain_until, object_locked FROM archived_documents ORDER BY fiscal_year, key")
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(row_to_archived_document).collect()
+    }
+
+    async fn get_archived_document(&self, key: &str) -> DomainResult<Option<ArchivedDocument>> {
+        let row = sqlx::query("SELECT key, fiscal_year, sha256, retain_until, object_locked FROM archived_documents WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        row.as_ref().map(row_to_archived_document).transpose()
+    }
+
+    async fn mark_object_locked(&self, key: &str) -> DomainResult<()> {
+        let updated = sqlx::query("UPDATE archived_documents SET object_locked = 1 WHERE key = ?")
+            .bind(key)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if updated.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+// ============ Audit Trail ============
+
+/// Concurrent appends race for the next sequence number; the loser retries on the new head
+const AUDIT_APPEND_ATTEMPTS: usize = 5;
+
+pub(crate) fn audit_hash(prev_hash: &str, seq: i64, event: &NewAuditEvent, before: Option<&str>, after: Option<&str>, occurred_at: &str) -> String {
+    let input = audit_hash_input(
+        prev_hash, seq, event.entity.as_str(), &event.entity_id, event.action.as_str(), before, after, occurred_at, &event.origin,
+    );
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+fn row_to_audit_event(row: &sqlx::sqlite::SqliteRow) -> DomainResult<AuditEvent> {
+    let c = Columns::of("audit_events", row);
+    Ok(AuditEvent {
+        seq: c.get("seq")?,
+        entity: c.parse("entity", AuditEntity::parse, "entité du journal")?,
+        entity_id: c.get("entity_id")?,
+        action: c.parse("action", AuditAction::parse, "create, update ou delete")?,
+        before: c.opt_parse("before_json", |s| serde_json::from_str(s).ok(), "état JSON")?,
+        after: c.opt_parse("after_json", |s| serde_json::from_str(s).ok(), "état JSON")?,
+        occurred_at: c.datetime("occurred_at")?,
+        origin: c.get("origin")?,
+        prev_hash: c.get("prev_hash")?,
+        hash: c.get("hash")?,
+    })
+}
+
+#[async_trait::async_trait]
+impl AuditRepo for SqliteAuditRepo {
+    async fn append_audit_event(&self, event: NewAuditEvent) -> DomainResult<AuditEvent> {
+        let before = event.before.as_ref().map(serde_json::to_string).transpose().map_err(|e| DomainError::Validation(e.to_string()))?;
+        let after = event.after.as_ref().map(serde_json::to_string).transpose().map_err(|e| DomainError::Validation(e.to_string()))?;
+        let now = chrono::Utc::now().naive_utc();
+        let now = now.with_nanosecond(0).unwrap_or(now);
+        let occurred_at = now.format("%Y-%m-%d %H:%M:%S").to_string();
+
+        for _ in 0..AUDIT_APPEND_ATTEMPTS {
+            let head = sqlx::query("SELECT seq, hash FROM audit_events ORDER BY seq DESC LIMIT 1")
+                .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+            let (seq, prev_hash) = match head {
+                Some(row) => (row.get::<i64, _>("seq") + 1, row.get::<String, _>("hash")),
+                None => (1, AUDIT_GENESIS_HASH.to_string()),
+            };
+            let hash = audit_hash(&prev_hash, seq, &event, before.as_deref(), after.as_deref(), &occurred_at);
+
+            let inserted = sqlx::query(r#"
+                INSERT INTO audit_events (seq, entity, entity_id, action, before_json, after_json, occurred_at, origin, prev_hash, hash)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#)
+                .bind(seq)
+                .bind(event.entity.as_str())
+                .bind(&event.entity_id)
+                .bind(event.action.as_str())
+                .bind(before.as_deref())
+                .bind(after.as_deref())
+                .bind(&occurred_at)
+                .bind(&event.origin)
+                .bind(&prev_hash)
+                .bind(&hash)
+                .execute(&mut *self.db.acquire().await?).await;
+            match inserted {
+                Ok(_) => {
+                    return Ok(AuditEvent {
+                        seq,
+                        entity: event.entity,
+                        entity_id: event.entity_id,
+                        action: event.action,
+                        before: event.before,
+                        after: event.after,
+                        occurred_at: now,
+                        origin: event.origin,
+                        prev_hash,
+                        hash,
+                    });
+                }
+                Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+                Err(e) => return Err(DomainError::Repo(e.to_string())),
+            }
+        }
+        Err(DomainError::Repo("Journal d'audit: trop d'écritures simultanées".to_string()))
+    }
+
+    async fn list_audit_events(&self, query: AuditQuery) -> DomainResult<Vec<AuditEvent>> {
+        let entity = query.entity.map(|e| e.as_str());
+        let rows = sqlx::query(r#"
+            SELECT seq, entity, entity_id, action, before_json, after_json, occurred_at, origin, prev_hash, hash
+            FROM audit_events
+            WHERE (? IS NULL OR entity = ?)
+              AND (? IS NULL OR entity_id = ?)
+              AND (? IS NULL OR seq < ?)
+              AND (? IS NULL OR seq > ?)
+            ORDER BY seq DESC
+            LIMIT ?
+        "#)
+            .bind(entity).bind(entity)
+            .bind(query.entity_id.as_deref()).bind(query.entity_id.as_deref())
+            .bind(query.before_seq).bind(query.before_seq)
+            .bind(query.after_seq).bind(query.after_seq)
+            .bind(query.limit.map(|l| l as i64).unwrap_or(-1))
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(row_to_audit_event).collect()
+    }
+
+    async fn verify_audit_chain(&self) -> DomainResult<AuditChainCheck> {
+        let rows = sqlx::query(r#"
+            SELECT seq, entity, entity_id, action, before_json, after_json, occurred_at, origin, prev_hash, hash
+            FROM audit_events ORDER BY seq
+        "#)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+
+        let mut check = AuditChainCheck { events_count: rows.len() as u64, last_hash: None, broken_at_seq: None, message: None };
+        let mut prev_hash = AUDIT_GENESIS_HASH.to_string();
+        for (index, row) in rows.iter().enumerate() {
+            let c = Columns::of("audit_events", row);
+            let seq: i64 = c.get("seq")?;
+            let stored_prev: String = c.get("prev_hash")?;
+            let stored_hash: String = c.get("hash")?;
+            let before: Option<String> = c.get("before_json")?;
+            let after: Option<String> = c.get("after_json")?;
+            let input = audit_hash_input(
+                &stored_prev, seq, &c.get::<String>("entity")?, &c.get::<String>("entity_id")?, &c.get::<String>("action")?,
+                before.as_deref(), after.as_deref(), &c.get::<String>("occurred_at")?, &c.get::<String>("origin")?,
+            );
+            let problem = if seq != index as i64 + 1 {
+                Some(format!("Événement manquant avant le n°{}", seq))
+            } else if stored_prev != prev_hash {
+                Some(format!("Chaînage rompu au n°{}", seq))
+            } else if hex::encode(Sha256::digest(input.as_bytes())) != stored_hash {
+                Some(format!("Contenu modifié au n°{}", seq))
+            } else {
+                None
+            };
+            if let Some(message) = problem {
+                check.broken_at_seq = Some(seq);
+                check.message = Some(message);
+                break;
+            }
+            prev_hash = stored_hash;
+        }
+        check.last_hash = rows.last().map(|row| Columns::of("audit_events", row).get("hash")).transpose()?;
+        Ok(check)
+    }
+}
+
+// ============ Synchronisation ============
+
+fn row_to_sync_conflict(row: &sqlx::sqlite::SqliteRow) -> DomainResult<SyncConflict> {
+    let c = Columns::of("sync_conflicts", row);
+    Ok(SyncConflict {
+        id: c.uuid("id")?,
+        peer: c.get("peer")?,
+        entity: c.parse("entity", AuditEntity::parse, "entité du journal")?,
+        entity_id: c.get("entity_id")?,
+        field: c.get("field")?,
+        local_value: c.opt_parse("local_json", |s| serde_json::from_str(s).ok(), "valeur JSON")?,
+        remote_value: c.opt_parse("remote_json", |s| serde_json::from_str(s).ok(), "valeur JSON")?,
+        kept: c.parse("kept", SyncSide::parse, "local ou remote")?,
+        message: c.get("message")?,
+        detected_at: c.datetime("detected_at")?,
+    })
+}
+
+#[async_trait::async_trait]
+impl SyncRepo for SqliteSyncRepo {
+    async fn load_sync_settings(&self) -> DomainResult<Option<SyncSettings>> {
+        let row = sqlx::query("SELECT peer_id, shared_dir FROM sync_settings WHERE id = 1")
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        row.map(|row| {
+            let c = Columns::of("sync_settings", &row);
+            Ok(SyncSettings { peer_id: c.get("peer_id")?, shared_dir: c.get("shared_dir")? })
+        }).transpose()
+    }
+
+    async fn save_sync_settings(&self, settings: SyncSettings) -> DomainResult<()> {
+        sqlx::query(r#"INSERT INTO sync_settings (id, peer_id, shared_dir) VALUES (1, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET peer_id = excluded.peer_id, shared_dir = excluded.shared_dir"#)
+            .bind(&settings.peer_id)
+            .bind(&settings.shared_dir)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn published_audit_seq(&self) -> DomainResult<i64> {
+        let seq: Option<i64> = sqlx::query_scalar("SELECT published_audit_seq FROM sync_settings WHERE id = 1")
+            .fetch_optional(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    async fn set_published_audit_seq(&self, seq: i64) -> DomainResult<()> {
+        let updated = sqlx::query("UPDATE sync_settings SET published_audit_seq = ? WHERE id = 1")
+            .bind(seq)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if updated.rows_affected() == 0 {
+            return Err(DomainError::Validation("Synchronisation non configurée".into()));
+        }
+        Ok(())
+    }
+
+    async fn applied_positions(&self) -> DomainResult<std::collections::HashMap<String, i64>> {
+        let rows = sqlx::query("SELECT peer, seq FROM sync_positions")
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(|row| {
+            let c = Columns::of("sync_positions", row);
+            Ok((c.get("peer")?, c.get("seq")?))
+        }).collect()
+    }
+
+    async fn set_applied_position(&self, peer: &str, seq: i64) -> DomainResult<()> {
+        sqlx::query("INSERT INTO sync_positions (peer, seq) VALUES (?, ?) ON CONFLICT(peer) DO UPDATE SET seq = excluded.seq")
+            .bind(peer)
+            .bind(seq)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn field_clocks(&self, entity: AuditEntity, entity_id: &str) -> DomainResult<std::collections::HashMap<String, SyncStamp>> {
+        let rows = sqlx::query("SELECT field, changed_at, peer, seq FROM sync_field_clocks WHERE entity = ? AND entity_id = ?")
+            .bind(entity.as_str())
+            .bind(entity_id)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(|row| {
+            let c = Columns::of("sync_field_clocks", row);
+            Ok((c.get("field")?, SyncStamp { changed_at: c.datetime("changed_at")?, peer: c.get("peer")?, seq: c.get("seq")? }))
+        }).collect()
+    }
+
+    async fn advance_field_clock(&self, entity: AuditEntity, entity_id: &str, field: &str, stamp: &SyncStamp) -> DomainResult<()> {
+        let current = self.field_clocks(entity, entity_id).await?.remove(field);
+        if current.is_some_and(|current| current >= *stamp) {
+            return Ok(());
+        }
+        sqlx::query(r#"INSERT INTO sync_field_clocks (entity, entity_id, field, changed_at, peer, seq) VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(entity, entity_id, field) DO UPDATE SET changed_at = excluded.changed_at, peer = excluded.peer, seq = excluded.seq"#)
+            .bind(entity.as_str())
+            .bind(entity_id)
+            .bind(field)
+            .bind(stamp.changed_at.to_string())
+            .bind(&stamp.peer)
+            .bind(stamp.seq)
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn add_sync_conflict(&self, conflict: SyncConflict) -> DomainResult<()> {
+        let to_json = |value: &Option<serde_json::Value>| value.as_ref().map(serde_json::to_string).transpose()
+            .map_err(|e| DomainError::Validation(e.to_string()));
+        sqlx::query(r#"
+            INSERT INTO sync_conflicts (id, peer, entity, entity_id, field, local_json, remote_json, kept, message, detected_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+            .bind(conflict.id.to_string())
+            .bind(&conflict.peer)
+            .bind(conflict.entity.as_str())
+            .bind(&conflict.entity_id)
+            .bind(&conflict.field)
+            .bind(to_json(&conflict.local_value)?)
+            .bind(to_json(&conflict.remote_value)?)
+            .bind(conflict.kept.as_str())
+            .bind(&conflict.message)
+            .bind(conflict.detected_at.to_string())
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_sync_conflicts(&self) -> DomainResult<Vec<SyncConflict>> {
+        let rows = sqlx::query(r#"
+            SELECT id, peer, entity, entity_id, field, local_json, remote_json, kept, message, detected_at
+            FROM sync_conflicts ORDER BY detected_at DESC
+        "#)
+            .fetch_all(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        rows.iter().map(row_to_sync_conflict).collect()
+    }
+
+    async fn dismiss_sync_conflict(&self, id: uuid::Uuid) -> DomainResult<()> {
+        let deleted = sqlx::query("DELETE FROM sync_conflicts WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&mut *self.db.acquire().await?).await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        if deleted.rows_affected() == 0 {
+            return Err(DomainError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(entity_id: &str, action: AuditAction, amount: Option<i64>) -> NewAuditEvent {
+        NewAuditEvent {
+            entity: AuditEntity::Declaration,
+            entity_id: entity_id.to_string(),
+            action,
+            before: None,
+            after: amount.map(|a| serde_json::json!({ "amount_due_cents": a })),
+            origin: "generate_declaration".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_trail_chain() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let audit = repos.audit();
+
+        let first = audit.append_audit_event(event("d1", AuditAction::Create, Some(1200))).await.unwrap();
+        assert_eq!(first.seq, 1);
+        assert_eq!(first.prev_hash, AUDIT_GENESIS_HASH);
+        let second = audit.append_audit_event(event("d1", AuditAction::Update, Some(1500))).await.unwrap();
+        assert_eq!(second.prev_hash, first.hash);
+        audit.append_audit_event(event("d2", AuditAction::Create, Some(90))).await.unwrap();
+
+        let history = audit.list_audit_events(AuditQuery {
+            entity: Some(AuditEntity::Declaration),
+            entity_id: Some("d1".to_string()),
+            ..AuditQuery::default()
+        }).await.unwrap();
+        assert_eq!(history.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(history[0].after, Some(serde_json::json!({ "amount_due_cents": 1500 })));
+        let check = audit.verify_audit_chain().await.unwrap();
+        assert!(check.is_valid());
+        assert_eq!(check.events_count, 3);
+
+        // Append-only: the triggers refuse any change
+        assert!(sqlx::query("UPDATE audit_events SET after_json = NULL WHERE seq = 2").execute(&repos.pool).await.is_err());
+        assert!(sqlx::query("DELETE FROM audit_events WHERE seq = 3").execute(&repos.pool).await.is_err());
+
+        // Tampering behind the triggers' back is detected
+        sqlx::query("DROP TRIGGER audit_events_no_update").execute(&repos.pool).await.unwrap();
+        sqlx::query(r#"UPDATE audit_events SET after_json = '{"amount_due_cents":1}' WHERE seq = 2"#).execute(&repos.pool).await.unwrap();
+        let check = audit.verify_audit_chain().await.unwrap();
+        assert_eq!(check.broken_at_seq, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_revised_migration_keeps_opening() {
+        let dir = std::env::temp_dir().join(format!("cash-planner-migrations-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = format!("sqlite://{}", dir.join("cash.db").display());
+        let repos = connect_and_migrate(&url).await.unwrap();
+        // As recorded by the first version of 0004
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = 4").execute(&repos.pool).await.unwrap();
+        repos.pool.close().await;
+
+        let reopened = connect_and_migrate(&url).await.unwrap();
+        let checksum: Vec<u8> = sqlx::query_scalar("SELECT checksum FROM _sqlx_migrations WHERE version = 4")
+            .fetch_one(&reopened.pool).await.unwrap();
+        assert_ne!(checksum, vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_trash_hides_and_restores() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let operations = repos.operations();
+        let plannings = repos.yearly_planning();
+        let now = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        let operation = Operation {
+            id: uuid::Uuid::new_v4(),
+            invoice_date: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+            payment_date: Some(NaiveDate::from_ymd_opt(2024, 3, 8).unwrap()),
+            operation_type: OperationType::Sale,
+            amount_ht_cents: 100000,
+            vat_amount_cents: 20000,
+            amount_ttc_cents: 120000,
+            vat_on_payments: true,
+            label: Some("Mission".to_string()),
+            client: None,
+            category: None,
+            receipt_key: None,
+            receipt_sha256: None,
+            created_at: now,
+            updated_at: now,
+        };
+        operations.create_operation(operation.clone()).await.unwrap();
+        operations.trash_operation(operation.id, now).await.unwrap();
+        assert!(matches!(operations.get_operation(operation.id).await, Err(DomainError::NotFound)));
+        assert!(operations.list_operations(None).await.unwrap().is_empty());
+        assert!(operations.list_operations_by_payment_month(MonthId::new(2024, 3)).await.unwrap().is_empty());
+        let trashed = operations.list_trashed_operations().await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].deleted_at, now);
+        assert!(matches!(operations.trash_operation(operation.id, now).await, Err(DomainError::NotFound)));
+
+        operations.restore_operation(operation.id).await.unwrap();
+        assert_eq!(operations.list_operations(None).await.unwrap().len(), 1);
+        assert!(operations.list_trashed_operations().await.unwrap().is_empty());
+
+        // A trashed planning hides its months too
+        let month = MonthPlanning {
+            id: uuid::Uuid::new_v4(),
+            year: 2024,
+            month: 3,
+            max_working_days: 21,
+            holidays_taken: 0,
+            public_holidays: 0,
+            working_days: 21,
+            estimated_revenue_cents: 1000000,
+            created_at: now,
+            updated_at: now,
+        };
+        let planning = YearlyPlanning {
+            id: uuid::Uuid::new_v4(),
+            year: 2024,
+            tjm_cents: 50000,
+            max_working_days_limit: 214,
+            months: vec![month],
+            created_at: now,
+            updated_at: now,
+        };
+        plannings.create_yearly_planning(planning).await.unwrap();
+        plannings.trash_yearly_planning(2024, now).await.unwrap();
+        assert!(plannings.get_yearly_planning(2024).await.unwrap().is_none());
+        assert!(plannings.get_month_planning(2024, 3).await.unwrap().is_none());
+        assert!(plannings.list_yearly_plannings().await.unwrap().is_empty());
+        let trashed = plannings.list_trashed_yearly_plannings().await.unwrap();
+        assert_eq!(trashed[0].planning.months.len(), 1);
+
+        plannings.restore_yearly_planning(2024).await.unwrap();
+        assert_eq!(plannings.get_yearly_planning(2024).await.unwrap().unwrap().months.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_bookkeeping() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let sync = repos.sync();
+        assert!(sync.load_sync_settings().await.unwrap().is_none());
+        assert!(sync.set_published_audit_seq(3).await.is_err());
+
+        sync.save_sync_settings(SyncSettings { peer_id: "bureau".to_string(), shared_dir: "/partage".to_string() }).await.unwrap();
+        sync.set_published_audit_seq(3).await.unwrap();
+        sync.save_sync_settings(SyncSettings { peer_id: "bureau".to_string(), shared_dir: "/nas/partage".to_string() }).await.unwrap();
+        assert_eq!(sync.load_sync_settings().await.unwrap().unwrap().shared_dir, "/nas/partage");
+        assert_eq!(sync.published_audit_seq().await.unwrap(), 3);
+
+        sync.set_applied_position("portable", 4).await.unwrap();
+        sync.set_applied_position("portable", 7).await.unwrap();
+        assert_eq!(sync.applied_positions().await.unwrap().get("portable"), Some(&7));
+
+        // A clock only moves forward; the installation name, then the log position, break ties
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap();
+        let later = SyncStamp { changed_at: at("2025-03-01 10:00:00.5"), peer: "bureau".to_string(), seq: 4 };
+        let earlier = SyncStamp { changed_at: at("2025-03-01 10:00:00"), peer: "portable".to_string(), seq: 9 };
+        let tie = SyncStamp { changed_at: at("2025-03-01 10:00:00.5"), peer: "portable".to_string(), seq: 1 };
+        sync.advance_field_clock(AuditEntity::Operation, "op-1", "label", &later).await.unwrap();
+        sync.advance_field_clock(AuditEntity::Operation, "op-1", "label", &earlier).await.unwrap();
+        sync.advance_field_clock(AuditEntity::Operation, "op-2", "label", &earlier).await.unwrap();
+        assert_eq!(sync.field_clocks(AuditEntity::Operation, "op-1").await.unwrap()["label"], later);
+        sync.advance_field_clock(AuditEntity::Operation, "op-1", "label", &tie).await.unwrap();
+        assert_eq!(sync.field_clocks(AuditEntity::Operation, "op-1").await.unwrap()["label"], tie);
+        let next = SyncStamp { seq: 2, ..tie };
+        sync.advance_field_clock(AuditEntity::Operation, "op-1", "label", &next).await.unwrap();
+        assert_eq!(sync.field_clocks(AuditEntity::Operation, "op-1").await.unwrap()["label"], next);
+
+        let conflict = SyncConflict {
+            id: uuid::Uuid::new_v4(),
+            peer: "portable".to_string(),
+            entity: AuditEntity::Operation,
+            entity_id: "op-1".to_string(),
+            field: Some("label".to_string()),
+            local_value: Some(serde_json::json!("Mission A")),
+            remote_value: Some(serde_json::json!("Mission B")),
+            kept: SyncSide::Remote,
+            message: "Modifié ici et sur portable".to_string(),
+            detected_at: chrono::Utc::now().naive_utc(),
+        };
+        sync.add_sync_conflict(conflict.clone()).await.unwrap();
+        let listed = sync.list_sync_conflicts().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].kept, listed[0].remote_value.clone()), (SyncSide::Remote, conflict.remote_value));
+        sync.dismiss_sync_conflict(conflict.id).await.unwrap();
+        assert!(sync.list_sync_conflicts().await.unwrap().is_empty());
+        assert!(sync.dismiss_sync_conflict(conflict.id).await.is_err());
+    }
+
+    /// Described by its label, client and category; those with a category have a receipt
+    fn queried(n: u128, operation_type: OperationType, invoice: &str, payment: Option<&str>, amount_ht_cents: i64, described: (&str, Option<&str>, Option<&str>)) -> Operation {
+        let (label, client, category) = described;
+        let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let now = date(invoice).and_hms_opt(9, 0, 0).unwrap();
+        Operation {
+            id: uuid::Uuid::from_u128(n),
+            invoice_date: date(invoice),
+            payment_date: payment.map(date),
+            operation_type,
+            amount_ht_cents,
+            vat_amount_cents: amount_ht_cents / 5,
+            amount_ttc_cents: amount_ht_cents + amount_ht_cents / 5,
+            vat_on_payments: true,
+            label: Some(label.to_string()),
+            client: client.map(str::to_string),
+            category: category.map(str::to_string),
+            receipt_key: category.map(|c| format!("2024-03/{}.pdf", c)),
+            receipt_sha256: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    async fn check_operation_query(operations: &dyn OperationRepo) {
+        let ids = |page: &OperationPage| page.operations.iter().map(|o| o.id.as_u128()).collect::<Vec<_>>();
+        for operation in [
+            queried(1, OperationType::Sale, "2024-03-05", Some("2024-03-20"), 100000, ("Mission Développement", Some("ACME"), Some("prestation"))),
+            queried(2, OperationType::Sale, "2024-04-02", None, 50000, ("Formation", Some("Bêta Conseil"), Some("formation"))),
+            queried(3, OperationType::Purchase, "2024-03-15", Some("2024-03-15"), 12000, ("Logiciel comptable", Some("Éditeur"), Some("logiciel"))),
+            queried(4, OperationType::Purchase, "2024-05-01", None, 3000, ("Café", None, None)),
+            queried(5, OperationType::Sale, "2024-03-01", None, 100000, ("Mission annulée", Some("ACME"), None)),
+        ] {
+            operations.create_operation(operation).await.unwrap();
+        }
+        operations.trash_operation(uuid::Uuid::from_u128(5), NaiveDateTime::default()).await.unwrap();
+        let query = |q: OperationQuery| async move { operations.query_operations(&q).await.unwrap() };
+
+        let all = query(OperationQuery::default()).await;
+        assert_eq!((ids(&all), all.total, all.next_cursor.clone()), (vec![1, 3, 2, 4], 4, None));
+        // Words of the label or the client, by prefix, accents ignored
+        assert_eq!(ids(&query(OperationQuery { text: Some("dev".into()), ..Default::default() }).await), vec![1]);
+        assert_eq!(ids(&query(OperationQuery { text: Some("beta".into()), ..Default::default() }).await), vec![2]);
+        assert_eq!(ids(&query(OperationQuery { text: Some("mission, dév".into()), ..Default::default() }).await), vec![1]);
+        assert!(query(OperationQuery { text: Some("mission zzz".into()), ..Default::default() }).await.operations.is_empty());
+
+        assert_eq!(ids(&query(OperationQuery { paid: Some(false), ..Default::default() }).await), vec![2, 4]);
+        assert_eq!(ids(&query(OperationQuery { has_receipt: Some(true), ..Default::default() }).await), vec![1, 3, 2]);
+        assert_eq!(ids(&query(OperationQuery { operation_type: Some(OperationType::Purchase), ..Default::default() }).await), vec![3, 4]);
+        assert_eq!(ids(&query(OperationQuery { client: Some("acme".into()), ..Default::default() }).await), vec![1]);
+        let categories = vec!["formation".to_string(), "logiciel".to_string()];
+        assert_eq!(ids(&query(OperationQuery { categories, ..Default::default() }).await), vec![3, 2]);
+        let amounts = OperationQuery { min_amount_ht_cents: Some(10000), max_amount_ht_cents: Some(60000), ..Default::default() };
+        assert_eq!(ids(&query(amounts).await), vec![3, 2]);
+        let paid_late = OperationQuery {
+            payment_from: NaiveDate::from_ymd_opt(2024, 3, 16),
+            payment_to: NaiveDate::from_ymd_opt(2024, 3, 31),
+            ..Default::default()
+        };
+        assert_eq!(ids(&query(paid_late).await), vec![1]);
+        let by_payment = OperationQuery { sort: OperationSortKey::PaymentDate, ..Default::default() };
+        assert_eq!(ids(&query(by_payment).await), vec![2, 4, 3, 1]);
+
+        // Pages follow each other from the cursor; the total covers every page
+        let by_amount = OperationQuery { sort: OperationSortKey::AmountHt, descending: true, limit: Some(3), ..Default::default() };
+        let first = query(by_amount.clone()).await;
+        assert_eq!((ids(&first), first.total), (vec![1, 2, 3], 4));
+        let second = query(OperationQuery { cursor: first.next_cursor.clone(), ..by_amount.clone() }).await;
+        assert_eq!((ids(&second), second.total, second.next_cursor), (vec![4], 4, None));
+        let by_label = OperationQuery { sort: OperationSortKey::Label, cursor: first.next_cursor, ..Default::default() };
+        assert!(matches!(operations.query_operations(&by_label).await, Err(DomainError::Validation(_))));
+
+        // The search follows edits and deletions
+        let mut lunch = operations.get_operation(uuid::Uuid::from_u128(4)).await.unwrap();
+        lunch.label = Some("Déjeuner client Dvořák".to_string());
+        operations.update_operation(lunch).await.unwrap();
+        assert_eq!(ids(&query(OperationQuery { text: Some("dejeuner".into()), ..Default::default() }).await), vec![4]);
+        // Any diacritic, not only those of French
+        assert_eq!(ids(&query(OperationQuery { text: Some("dvorak".into()), ..Default::default() }).await), vec![4]);
+        assert!(query(OperationQuery { text: Some("cafe".into()), ..Default::default() }).await.operations.is_empty());
+        operations.delete_operation(uuid::Uuid::from_u128(3)).await.unwrap();
+        assert!(query(OperationQuery { text: Some("logiciel".into()), ..Default::default() }).await.operations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_operations() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        check_operation_query(&repos.operations()).await;
+        // The in-memory repository answers the same
+        check_operation_query(&crate::memory::InMemoryRepos::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_archived_year_triggers() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let operations = repos.operations();
+        let now = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let operation = |n: u128, invoice_date: NaiveDate| Operation {
+            id: uuid::Uuid::from_u128(n),
+            invoice_date,
+            payment_date: None,
+            operation_type: OperationType::Sale,
+            amount_ht_cents: 100000,
+            vat_amount_cents: 20000,
+            amount_ttc_cents: 120000,
+            vat_on_payments: true,
+            label: Some("Mission".to_string()),
+            client: None,
+            category: None,
+            receipt_key: None,
+            receipt_sha256: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let archived = operation(1, NaiveDate::from_ymd_opt(2023, 6, 15).unwrap());
+        let open = operation(2, NaiveDate::from_ymd_opt(2024, 1, 10).unwrap());
+        operations.create_operation(archived.clone()).await.unwrap();
+        operations.create_operation(open.clone()).await.unwrap();
+
+        let archive = ArchivedFiscalYear { year: 2023, archived_at: now, retain_until: domain::retention_until(2023), operations_count: 1 };
+        repos.archives().archive_fiscal_year(archive.clone(), Vec::new()).await.unwrap();
+        assert!(matches!(repos.archives().archive_fiscal_year(archive, Vec::new()).await, Err(DomainError::Validation(_))));
+
+        // Neither edited, moved out, moved in, added nor deleted
+        let edited = Operation { amount_ht_cents: 1, ..archived.clone() };
+        assert!(operations.update_operation(edited).await.is_err());
+        let moved_out = Operation { invoice_date: open.invoice_date, ..archived.clone() };
+        assert!(operations.update_operation(moved_out).await.is_err());
+        let moved_in = Operation { invoice_date: archived.invoice_date, ..open.clone() };
+        assert!(operations.update_operation(moved_in).await.is_err());
+        assert!(operations.create_operation(operation(3, archived.invoice_date)).await.is_err());
+        assert!(operations.delete_operation(archived.id).await.is_err());
+
+        // The payment of an unpaid operation can still be recorded, once
+        let paid = Operation { payment_date: NaiveDate::from_ymd_opt(2024, 2, 1), ..archived.clone() };
+        operations.update_operation(paid.clone()).await.unwrap();
+        let repaid = Operation { payment_date: NaiveDate::from_ymd_opt(2024, 2, 2), ..paid };
+        assert!(operations.update_operation(repaid).await.is_err());
+        assert_eq!(operations.get_operation(archived.id).await.unwrap().payment_date, NaiveDate::from_ymd_opt(2024, 2, 1));
     }
 }
@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+use domain::{ChangeLog, DomainError, DomainResult, SyncChange, SyncLogs, UnreadableSyncLog};
+use tokio::io::AsyncWriteExt;
+
+/// Extension of the change logs in the shared folder
+pub const CHANGE_LOG_EXTENSION: &str = "jsonl";
+
+/// Change logs in a folder shared by the installations (Syncthing, NAS): `<peer_id>.jsonl`, one
+/// JSON change per line. Each installation only ever writes its own file, so the folder tool never
+/// has to merge a file; a line is only read once its newline has arrived
+#[derive(Debug, Clone)]
+pub struct FolderChangeLog {
+    dir: PathBuf,
+}
+
+impl FolderChangeLog {
+    pub fn new(dir: impl Into<PathBuf>) -> DomainResult<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| DomainError::Repo(format!("Impossible de créer le dossier partagé '{}': {}", dir.display(), e)))?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, peer: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", peer, CHANGE_LOG_EXTENSION))
+    }
+
+    async fn read_log(&self, path: &Path) -> DomainResult<String> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(DomainError::Repo(format!("Journal de synchronisation illisible '{}': {}", path.display(), e))),
+        }
+    }
+}
+
+/// Lines ended by a newline; a trailing fragment is a write still in progress or in transfer
+fn complete_lines(content: &str) -> impl Iterator<Item = &str> {
+    let complete = content.rfind('\n').map_or("", |end| &content[..end]);
+    complete.split('\n').filter(|line| !line.trim().is_empty())
+}
+
+/// Log file names are installation names; conflict copies made by the folder tool are skipped
+fn log_peer(path: &Path) -> Option<String> {
+    if path.extension()? != CHANGE_LOG_EXTENSION {
+        return None;
+    }
+    let peer = path.file_stem()?.to_str()?;
+    let valid = !peer.is_empty() && peer.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    valid.then(|| peer.to_string())
+}
+
+#[async_trait::async_trait]
+impl ChangeLog for FolderChangeLog {
+    async fn log_length(&self, peer: &str) -> DomainResult<i64> {
+        Ok(complete_lines(&self.read_log(&self.path(peer)).await?).count() as i64)
+    }
+
+    async fn append_changes(&self, peer: &str, changes: &[SyncChange]) -> DomainResult<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let path = self.path(peer);
+        let write_error = |e: std::io::Error| DomainError::Repo(format!("Erreur écriture du journal de synchronisation: {}", e));
+        // A line cut by an interrupted append would glue onto the next one: drop it first
+        let content = self.read_log(&path).await?;
+        let complete = content.rfind('\n').map_or(0, |end| end + 1);
+        if complete < content.len() {
+            let file = tokio::fs::OpenOptions::new().write(true).open(&path).await.map_err(write_error)?;
+            file.set_len(complete as u64).await.map_err(write_error)?;
+        }
+
+        let mut lines = String::new();
+        for change in changes {
+            lines.push_str(&serde_json::to_string(change).map_err(|e| DomainError::Repo(e.to_string()))?);
+            lines.push('\n');
+        }
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await.map_err(write_error)?;
+        file.write_all(lines.as_bytes()).await.map_err(write_error)?;
+        file.sync_all().await.map_err(write_error)
+    }
+
+    async fn read_changes(&self, own_peer: &str) -> DomainResult<SyncLogs> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await
+            .map_err(|e| DomainError::Repo(format!("Dossier partagé illisible '{}': {}", self.dir.display(), e)))?;
+        let mut logs = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| DomainError::Repo(e.to_string()))? {
+            match log_peer(&entry.path()) {
+                Some(peer) if peer != own_peer => logs.push((peer, entry.path())),
+                _ => {}
+            }
+        }
+        logs.sort();
+
+        let mut read = SyncLogs::default();
+        for (peer, path) in logs {
+            match self.read_peer_log(&peer, &path).await {
+                Ok(changes) => read.changes.extend(changes),
+                Err(e) => read.unreadable.push(UnreadableSyncLog { peer, error: e.to_string() }),
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl FolderChangeLog {
+    async fn read_peer_log(&self, peer: &str, path: &Path) -> DomainResult<Vec<SyncChange>> {
+        let content = self.read_log(path).await?;
+        let mut changes = Vec::new();
+        for (index, line) in complete_lines(&content).enumerate() {
+            let change: SyncChange = serde_json::from_str(line).map_err(|e| DomainError::Repo(format!(
+                "Journal de synchronisation '{}' illisible ligne {}: {}", path.display(), index + 1, e
+            )))?;
+            if change.peer != peer || change.seq != index as i64 + 1 {
+                return Err(DomainError::Repo(format!(
+                    "Journal de synchronisation '{}' incohérent ligne {}", path.display(), index + 1
+                )));
+            }
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use domain::{interleave_sync_changes, merge_sync_change, AuditAction, AuditEntity, SyncSide, SyncStamp};
+
+    fn change(peer: &str, seq: i64, at: &str, before: serde_json::Value, after: serde_json::Value) -> SyncChange {
+        SyncChange {
+            peer: peer.to_string(),
+            seq,
+            entity: AuditEntity::Operation,
+            entity_id: "op-1".to_string(),
+            action: AuditAction::Update,
+            before: Some(before),
+            after: Some(after),
+            changed_at: chrono::NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M:%S").unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_folder_change_log() {
+        let dir = std::env::temp_dir().join(format!("cash-planner-sync-{}", uuid::Uuid::new_v4()));
+        let desktop = FolderChangeLog::new(&dir).unwrap();
+        let laptop = FolderChangeLog::new(&dir).unwrap();
+        let state = serde_json::json!({ "label": "Mission" });
+        let first = change("bureau", 1, "2025-03-01 10:00:00", state.clone(), state.clone());
+        let second = change("bureau", 2, "2025-03-01 11:00:00", state.clone(), state.clone());
+
+        desktop.append_changes("bureau", std::slice::from_ref(&first)).await.unwrap();
+        // A line still in transfer is not read, and is dropped before the next append
+        let mut partial = serde_json::to_string(&second).unwrap();
+        partial.truncate(20);
+        std::fs::write(dir.join("bureau.jsonl"), format!("{}\n{}", serde_json::to_string(&first).unwrap(), partial)).unwrap();
+        assert_eq!(laptop.log_length("bureau").await.unwrap(), 1);
+        assert_eq!(laptop.read_changes("portable").await.unwrap().changes.len(), 1);
+        desktop.append_changes("bureau", &[second]).await.unwrap();
+
+        // Conflict copies of the folder tool and other files are ignored, and so is the own log
+        std::fs::write(dir.join("bureau.sync-conflict-20250301-120000.jsonl"), "garbage\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "garbage\n").unwrap();
+        laptop.append_changes("portable", &[change("portable", 1, "2025-03-01 12:00:00", state.clone(), state)]).await.unwrap();
+        let read = laptop.read_changes("portable").await.unwrap().changes;
+        assert_eq!(read.iter().map(|c| (c.peer.as_str(), c.seq)).collect::<Vec<_>>(), vec![("bureau", 1), ("bureau", 2)]);
+        assert_eq!(desktop.read_changes("bureau").await.unwrap().changes.len(), 1);
+
+        // A log whose positions do not follow each other, or with a broken line, is left out alone
+        std::fs::write(dir.join("tiers.jsonl"), format!("{}\n", serde_json::to_string(&read[1]).unwrap())).unwrap();
+        std::fs::write(dir.join("autre.jsonl"), "{pas du json\n").unwrap();
+        let logs = laptop.read_changes("portable").await.unwrap();
+        assert_eq!(logs.changes.len(), 2);
+        let unreadable: Vec<&str> = logs.unreadable.iter().map(|u| u.peer.as_str()).collect();
+        assert_eq!(unreadable, ["autre", "tiers"]);
+        assert!(logs.unreadable[1].error.contains("incohérent"));
+    }
+
+    #[test]
+    fn test_merge_converges_whatever_the_order() {
+        let base = serde_json::json!({ "id": "op-1", "label": "Mission", "amount_cents": 1000, "paid": false, "updated_at": "2025-03-01T09:00:00" });
+        // Both installations edit the label; only the desktop edits the amount, only the laptop the payment
+        let desktop = change("bureau", 1, "2025-03-01 10:00:00", base.clone(),
+            serde_json::json!({ "id": "op-1", "label": "Mission A", "amount_cents": 2000, "paid": false, "updated_at": "2025-03-01T10:00:00" }));
+        let laptop = change("portable", 1, "2025-03-01 11:00:00", base.clone(),
+            serde_json::json!({ "id": "op-1", "label": "Mission B", "amount_cents": 1000, "paid": true, "updated_at": "2025-03-01T11:00:00" }));
+        let clocks = |c: &SyncChange| c.changed_fields().into_iter().map(|f| (f, c.stamp())).collect::<HashMap<String, SyncStamp>>();
+
+        let on_desktop = merge_sync_change(desktop.after.as_ref().unwrap(), &clocks(&desktop), &laptop);
+        let on_laptop = merge_sync_change(laptop.after.as_ref().unwrap(), &clocks(&laptop), &desktop);
+        assert_eq!(on_desktop.state, on_laptop.state);
+        assert_eq!(on_desktop.state["label"], "Mission B");
+        assert_eq!(on_desktop.state["amount_cents"], 2000);
+        assert_eq!(on_desktop.state["paid"], true);
+
+        // The label was written on both sides: a conflict on each, resolved the same way
+        assert_eq!(on_desktop.conflicts.len(), 1);
+        assert_eq!((on_desktop.conflicts[0].field.as_str(), on_desktop.conflicts[0].kept), ("label", SyncSide::Remote));
+        assert_eq!((on_laptop.conflicts[0].field.as_str(), on_laptop.conflicts[0].kept), ("label", SyncSide::Local));
+        assert!(on_laptop.taken.contains(&"amount_cents".to_string()) && !on_laptop.taken.contains(&"label".to_string()));
+
+        // Replaying a change already merged writes nothing
+        let replayed = merge_sync_change(&on_desktop.state, &clocks(&laptop), &laptop);
+        assert!(replayed.taken.is_empty() && replayed.conflicts.is_empty());
+
+        // Logs are interleaved by stamp, but a log never overtakes itself, even with a clock set back
+        let set_back = change("bureau", 2, "2025-03-01 09:30:00", base.clone(), base);
+        let ordered = interleave_sync_changes(vec![laptop, desktop, set_back]);
+        assert_eq!(ordered.iter().map(|c| (c.peer.as_str(), c.seq)).collect::<Vec<_>>(), vec![("bureau", 1), ("bureau", 2), ("portable", 1)]);
+    }
+}
@@ -0,0 +1,250 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use domain::{
+    ArchiveRepo, AuditRepo, ConfigRepo, DeclarationRepo, DomainError, DomainResult, KPIRepo, MonthRepo, OperationRepo,
+    ProvisionRepo, SimulationRepo, SyncRepo, UnitOfWork, UnitOfWorkFactory, WorkingDayRepo, YearlyPlanningRepo,
+};
+use sqlx::pool::PoolConnection;
+use sqlx::{Pool, Sqlite, SqliteConnection, Transaction};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::sqlite::{
+    SqliteArchiveRepo, SqliteAuditRepo, SqliteConfigRepo, SqliteDeclarationRepo, SqliteKPIRepo, SqliteMonthRepo, SqliteOperationRepo,
+    SqliteProvisionRepo, SqliteSimulationRepo, SqliteSyncRepo, SqliteWorkingDayRepo, SqliteYearlyPlanningRepo,
+};
+
+type SharedTransaction = Arc<Mutex<Option<Transaction<'static, Sqlite>>>>;
+
+/// Where a repository runs its statements: straight on the pool, or inside a unit of work
+#[derive(Clone)]
+pub(crate) enum Db {
+    Pool(Pool<Sqlite>),
+    Tx(SharedTransaction),
+}
+
+impl Db {
+    /// A connection for one statement; inside a unit of work, every repository waits for the same one
+    pub(crate) async fn acquire(&self) -> DomainResult<DbConnection> {
+        match self {
+            Db::Pool(pool) => pool.acquire().await
+                .map(DbConnection::Pooled)
+                .map_err(|e| DomainError::Repo(e.to_string())),
+            Db::Tx(tx) => {
+                let guard = tx.clone().lock_owned().await;
+                if guard.is_none() {
+                    return Err(DomainError::Repo("Transaction déjà validée ou annulée".to_string()));
+                }
+                Ok(DbConnection::Tx(guard))
+            }
+        }
+    }
+}
+
+pub(crate) enum DbConnection {
+    Pooled(PoolConnection<Sqlite>),
+    Tx(OwnedMutexGuard<Option<Transaction<'static, Sqlite>>>), // Always `Some`, checked by `Db::acquire`
+}
+
+impl Deref for DbConnection {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        match self {
+            DbConnection::Pooled(conn) => conn,
+            DbConnection::Tx(tx) => tx.as_deref().expect("transaction checked by Db::acquire"),
+        }
+    }
+}
+
+impl DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        match self {
+            DbConnection::Pooled(conn) => conn,
+            DbConnection::Tx(tx) => tx.as_deref_mut().expect("transaction checked by Db::acquire"),
+        }
+    }
+}
+
+/// Opens units of work on the application database
+#[derive(Clone)]
+pub struct SqliteUnitOfWorkFactory {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteUnitOfWorkFactory {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWorkFactory for SqliteUnitOfWorkFactory {
+    async fn begin(&self) -> DomainResult<Box<dyn UnitOfWork>> {
+        let tx = self.pool.begin().await.map_err(|e| DomainError::Repo(e.to_string()))?;
+        Ok(Box::new(SqliteUnitOfWork { tx: Arc::new(Mutex::new(Some(tx))) }))
+    }
+}
+
+/// One SQLite transaction shared by the repositories it hands out
+/// Statements issued through them are serialized on the transaction's connection
+pub struct SqliteUnitOfWork {
+    tx: SharedTransaction,
+}
+
+impl SqliteUnitOfWork {
+    fn db(&self) -> Db {
+        Db::Tx(self.tx.clone())
+    }
+
+    async fn take(&self) -> DomainResult<Transaction<'static, Sqlite>> {
+        self.tx.lock().await.take()
+            .ok_or_else(|| DomainError::Repo("Transaction déjà validée ou annulée".to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for SqliteUnitOfWork {
+    fn provisions(&self) -> Arc<dyn ProvisionRepo> { Arc::new(SqliteProvisionRepo { db: self.db() }) }
+    fn config(&self) -> Arc<dyn ConfigRepo> { Arc::new(SqliteConfigRepo { db: self.db() }) }
+    fn months(&self) -> Arc<dyn MonthRepo> { Arc::new(SqliteMonthRepo { db: self.db() }) }
+    fn operations(&self) -> Arc<dyn OperationRepo> { Arc::new(SqliteOperationRepo { db: self.db() }) }
+    fn declarations(&self) -> Arc<dyn DeclarationRepo> { Arc::new(SqliteDeclarationRepo { db: self.db() }) }
+    fn yearly_planning(&self) -> Arc<dyn YearlyPlanningRepo> { Arc::new(SqliteYearlyPlanningRepo { db: self.db() }) }
+    fn audit(&self) -> Arc<dyn AuditRepo> { Arc::new(SqliteAuditRepo { db: self.db() }) }
+    fn working_days(&self) -> Arc<dyn WorkingDayRepo> { Arc::new(SqliteWorkingDayRepo { db: self.db() }) }
+    fn simulations(&self) -> Arc<dyn SimulationRepo> { Arc::new(SqliteSimulationRepo { db: self.db() }) }
+    fn kpis(&self) -> Arc<dyn KPIRepo> { Arc::new(SqliteKPIRepo { db: self.db() }) }
+    fn sync(&self) -> Arc<dyn SyncRepo> { Arc::new(SqliteSyncRepo { db: self.db() }) }
+    fn archives(&self) -> Arc<dyn ArchiveRepo> { Arc::new(SqliteArchiveRepo { db: self.db() }) }
+
+    async fn commit(&self) -> DomainResult<()> {
+        self.take().await?.commit().await.map_err(|e| DomainError::Repo(e.to_string()))
+    }
+
+    async fn rollback(&self) -> DomainResult<()> {
+        self.take().await?.rollback().await.map_err(|e| DomainError::Repo(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::connect_and_migrate;
+    use domain::{
+        AuditAction, AuditEntity, AuditQuery, MonthId, MonthPlanning, MonthlyKPI, NewAuditEvent, WorkingDay, YearlyPlanning,
+    };
+
+    fn planning(year: i32) -> YearlyPlanning {
+        let now = chrono::Utc::now().naive_utc();
+        YearlyPlanning {
+            id: uuid::Uuid::new_v4(),
+            year,
+            tjm_cents: 50000,
+            max_working_days_limit: 214,
+            months: (1..=12).map(|month| MonthPlanning {
+                id: uuid::Uuid::new_v4(),
+                year,
+                month,
+                max_working_days: 21,
+                holidays_taken: 0,
+                public_holidays: 1,
+                working_days: 20,
+                estimated_revenue_cents: 1000000,
+                created_at: now,
+                updated_at: now,
+            }).collect(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    async fn create_audited(unit: &dyn UnitOfWork, year: i32) {
+        unit.yearly_planning().create_yearly_planning(planning(year)).await.unwrap();
+        unit.audit().append_audit_event(NewAuditEvent {
+            entity: AuditEntity::YearlyPlanning,
+            entity_id: year.to_string(),
+            action: AuditAction::Create,
+            before: None,
+            after: Some(serde_json::json!({ "year": year })),
+            origin: "create_yearly_planning".to_string(),
+        }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unit_of_work_commits_or_rolls_back_across_repositories() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let factory = repos.units_of_work();
+
+        let unit = factory.begin().await.unwrap();
+        create_audited(unit.as_ref(), 2025).await;
+        assert!(unit.yearly_planning().get_yearly_planning(2025).await.unwrap().is_some());
+        unit.rollback().await.unwrap();
+        assert!(repos.yearly_planning().get_yearly_planning(2025).await.unwrap().is_none());
+        assert!(repos.audit().list_audit_events(AuditQuery::default()).await.unwrap().is_empty());
+        // An ended unit refuses any further call
+        assert!(unit.yearly_planning().list_yearly_plannings().await.is_err());
+        assert!(unit.commit().await.is_err());
+
+        let unit = factory.begin().await.unwrap();
+        create_audited(unit.as_ref(), 2026).await;
+        unit.commit().await.unwrap();
+        let stored = repos.yearly_planning().get_yearly_planning(2026).await.unwrap().unwrap();
+        assert_eq!(stored.months.len(), 12);
+        assert_eq!(repos.audit().list_audit_events(AuditQuery::default()).await.unwrap().len(), 1);
+
+        // Dropped without committing: rolled back
+        let unit = factory.begin().await.unwrap();
+        create_audited(unit.as_ref(), 2027).await;
+        drop(unit);
+        assert!(repos.yearly_planning().get_yearly_planning(2027).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unit_of_work_covers_working_days_and_kpis() {
+        let repos = connect_and_migrate("sqlite::memory:").await.unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        let day = WorkingDay {
+            id: uuid::Uuid::new_v4(),
+            date: chrono::NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(),
+            hours_worked: 8.0,
+            billable_hours: 7.0,
+            hourly_rate_cents: 7500,
+            description: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let kpi = MonthlyKPI {
+            id: uuid::Uuid::new_v4(),
+            month: MonthId::new(2025, 3),
+            revenue_ht_cents: 1000000,
+            revenue_ttc_cents: 1200000,
+            expenses_ttc_cents: 120000,
+            working_days: 20.0,
+            billable_hours: 140.0,
+            average_daily_rate_cents: 50000,
+            average_hourly_rate_cents: 7142,
+            vat_collected_cents: 200000,
+            vat_due_cents: 180000,
+            urssaf_due_cents: 212000,
+            net_margin_cents: 688000,
+            profitability_ratio: 0.57,
+            utilization_rate: 0.875,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let unit = repos.units_of_work().begin().await.unwrap();
+        unit.working_days().create_working_day(day.clone()).await.unwrap();
+        unit.kpis().save_monthly_kpi(kpi.clone()).await.unwrap();
+        unit.rollback().await.unwrap();
+        assert!(repos.working_days().list_working_days(None, None).await.unwrap().is_empty());
+
+        let unit = repos.units_of_work().begin().await.unwrap();
+        unit.working_days().create_working_day(day).await.unwrap();
+        unit.kpis().save_monthly_kpi(kpi).await.unwrap();
+        unit.commit().await.unwrap();
+        assert_eq!(repos.working_days().list_working_days(None, None).await.unwrap().len(), 1);
+        assert!(repos.kpis().get_monthly_kpi(&MonthId::new(2025, 3)).await.unwrap().is_some());
+    }
+}
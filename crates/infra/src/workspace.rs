@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use domain::{
+    BusinessRegime, DocumentStore, DomainError, DomainResult, FileInfo, Workspace, WorkspaceList, DEFAULT_WORKSPACE_ID,
+};
+
+use crate::minio::document_key;
+
+/// Registry of the workspaces, next to the default workspace's database
+pub const WORKSPACES_FILE: &str = "workspaces.json";
+
+/// Workspaces of an installation: `workspaces.json` in the data folder, one folder per workspace
+/// The default workspace keeps the data folder itself, so existing installations need no move
+#[derive(Debug, Clone)]
+pub struct WorkspaceRegistry {
+    base: PathBuf,
+}
+
+impl WorkspaceRegistry {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self { base: base.into() }
+    }
+
+    /// Without a registry, the installation only has the default workspace
+    pub fn load(&self) -> DomainResult<WorkspaceList> {
+        match std::fs::read_to_string(self.base.join(WORKSPACES_FILE)) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| DomainError::Repo(format!("Registre des espaces illisible: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let default = Workspace::new(DEFAULT_WORKSPACE_ID, "Principal", None, BusinessRegime::MicroEntreprise, chrono::Utc::now().naive_utc())?;
+                Ok(WorkspaceList { active: default.id.clone(), workspaces: vec![default] })
+            }
+            Err(e) => Err(DomainError::Repo(format!("Registre des espaces illisible: {}", e))),
+        }
+    }
+
+    /// Written to a temporary file first: an interrupted write never loses the registry
+    fn save(&self, list: &WorkspaceList) -> DomainResult<()> {
+        let content = serde_json::to_string_pretty(list).map_err(|e| DomainError::Repo(e.to_string()))?;
+        let path = self.base.join(WORKSPACES_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| DomainError::Repo(format!("Erreur enregistrement du registre des espaces: {}", e)))
+    }
+
+    pub fn get(&self, id: &str) -> DomainResult<Workspace> {
+        self.load()?.workspaces.into_iter().find(|w| w.id == id).ok_or(DomainError::NotFound)
+    }
+
+    pub fn active(&self) -> DomainResult<Workspace> {
+        let list = self.load()?;
+        self.get(&list.active)
+    }
+
+    pub fn create(&self, workspace: Workspace) -> DomainResult<()> {
+        workspace.validate()?;
+        let mut list = self.load()?;
+        if list.workspaces.iter().any(|w| w.id == workspace.id) {
+            return Err(DomainError::Validation(format!("L'espace '{}' existe déjà", workspace.id)));
+        }
+        std::fs::create_dir_all(self.dir(&workspace))
+            .map_err(|e| DomainError::Repo(format!("Impossible de créer le dossier de l'espace: {}", e)))?;
+        list.workspaces.push(workspace);
+        self.save(&list)
+    }
+
+    /// Name, SIREN and regime; the folder and document prefix of a workspace never change
+    pub fn update_identity(&self, workspace: Workspace) -> DomainResult<Workspace> {
+        workspace.validate()?;
+        let mut list = self.load()?;
+        let existing = list.workspaces.iter_mut().find(|w| w.id == workspace.id).ok_or(DomainError::NotFound)?;
+        existing.name = workspace.name;
+        existing.siren = workspace.siren;
+        existing.regime = workspace.regime;
+        let updated = existing.clone();
+        self.save(&list)?;
+        Ok(updated)
+    }
+
+    pub fn set_active(&self, id: &str) -> DomainResult<Workspace> {
+        let mut list = self.load()?;
+        let workspace = list.workspaces.iter().find(|w| w.id == id).cloned().ok_or(DomainError::NotFound)?;
+        list.active = workspace.id.clone();
+        self.save(&list)?;
+        Ok(workspace)
+    }
+
+    /// Folder of the workspace's database, backups and encryption settings
+    pub fn dir(&self, workspace: &Workspace) -> PathBuf {
+        if workspace.id == DEFAULT_WORKSPACE_ID {
+            self.base.clone()
+        } else {
+            self.base.join("workspaces").join(&workspace.id)
+        }
+    }
+
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+}
+
+/// Documents of a workspace kept under its prefix in a bucket shared with the other workspaces
+/// Keys stay "YYYY-MM/<fichier>" for the application; only the inner store, which must list
+/// its keys recursively (MinIO), sees the prefix. Local stores get a folder per workspace instead
+pub struct PrefixedDocumentStore {
+    inner: Arc<dyn DocumentStore>,
+    prefix: String,
+}
+
+impl PrefixedDocumentStore {
+    pub fn new(inner: Arc<dyn DocumentStore>, prefix: &str) -> Self {
+        Self { inner, prefix: prefix.trim_end_matches('/').to_string() }
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}/{}", self.prefix, key)
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for PrefixedDocumentStore {
+    fn backend(&self) -> &'static str { self.inner.backend() }
+
+    async fn upload(&self, content: Vec<u8>, original_filename: &str, _content_type: Option<String>) -> DomainResult<String> {
+        let key = document_key(original_filename);
+        self.put(&key, content).await?;
+        Ok(key)
+    }
+
+    async fn put(&self, key: &str, content: Vec<u8>) -> DomainResult<()> {
+        self.inner.put(&self.full_key(key), content).await
+    }
+
+    async fn delete(&self, key: &str) -> DomainResult<()> {
+        self.inner.delete(&self.full_key(key)).await
+    }
+
+    async fn list_by_month(&self, year: i32, month: u32) -> DomainResult<Vec<FileInfo>> {
+        let folder = format!("{:04}-{:02}/", year, month);
+        Ok(self.list_all().await?.into_iter().filter(|f| f.key.starts_with(&folder)).collect())
+    }
+
+    async fn list_all(&self) -> DomainResult<Vec<FileInfo>> {
+        let prefix = format!("{}/", self.prefix);
+        Ok(self.inner.list_all().await?
+            .into_iter()
+            .filter_map(|f| Some(FileInfo { key: f.key.strip_prefix(&prefix)?.to_string(), ..f }))
+            .collect())
+    }
+
+    async fn open(&self, key: &str) -> DomainResult<Vec<u8>> {
+        self.inner.open(&self.full_key(key)).await
+    }
+
+    async fn download_url(&self, key: &str, expires_in_secs: u32) -> DomainResult<String> {
+        self.inner.download_url(&self.full_key(key), expires_in_secs).await
+    }
+
+    async fn set_retention(&self, key: &str, retain_until: NaiveDate) -> DomainResult<bool> {
+        self.inner.set_retention(&self.full_key(key), retain_until).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_registry() {
+        let base = std::env::temp_dir().join(format!("cash-planner-workspaces-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&base).unwrap();
+        let registry = WorkspaceRegistry::new(&base);
+
+        // An installation without registry opens its existing database as the default workspace
+        let default = registry.active().unwrap();
+        assert_eq!(default.id, DEFAULT_WORKSPACE_ID);
+        assert_eq!(registry.dir(&default), base);
+        assert_eq!(default.document_prefix, "");
+
+        let now = chrono::Utc::now().naive_utc();
+        let sasu = Workspace::new("sasu-conseil", "SASU Conseil", Some("123 456 789".to_string()), BusinessRegime::Sasu, now).unwrap();
+        assert_eq!(sasu.siren.as_deref(), Some("123456789"));
+        assert_eq!(sasu.document_prefix, "workspaces/sasu-conseil");
+        registry.create(sasu.clone()).unwrap();
+        assert!(registry.dir(&sasu).is_dir());
+        assert!(registry.create(sasu.clone()).is_err());
+        assert!(Workspace::new("Mon Espace", "x", None, BusinessRegime::Other, now).is_err());
+        assert!(Workspace::new("ei", "EI", Some("12345".to_string()), BusinessRegime::EntrepriseIndividuelle, now).is_err());
+
+        registry.set_active("sasu-conseil").unwrap();
+        assert_eq!(registry.active().unwrap().id, "sasu-conseil");
+        assert!(registry.set_active("unknown").is_err());
+
+        let renamed = Workspace { name: "Conseil SASU".to_string(), document_prefix: "elsewhere".to_string(), ..sasu };
+        let updated = registry.update_identity(renamed).unwrap();
+        assert_eq!(updated.name, "Conseil SASU");
+        assert_eq!(updated.document_prefix, "workspaces/sasu-conseil");
+        let list = registry.load().unwrap();
+        assert_eq!(list.workspaces.len(), 2);
+        assert_eq!(list.active, "sasu-conseil");
+    }
+}
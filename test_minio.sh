@@ -2,6 +2,29 @@
 
 # Test script for MinIO connection
 # This script sets the environment variables and runs the MinIO connection test
+# Usage: ./test_minio.sh          -> remote MinIO
+#        ./test_minio.sh local    -> local S3-compatible server (docker, port 9000)
+
+if [ "$1" = "local" ]; then
+    echo "🐳 Starting local MinIO stand-in..."
+    docker run -d --rm --name cash-planner-minio-test -p 9000:9000 \
+        -e MINIO_ROOT_USER=minioadmin -e MINIO_ROOT_PASSWORD=minioadmin \
+        minio/minio server /data > /dev/null
+    sleep 3
+
+    export MINIO_ENDPOINT="localhost"
+    export MINIO_PORT="9000"
+    export MINIO_ACCESS_KEY="minioadmin"
+    export MINIO_SECRET_KEY="minioadmin"
+    export MINIO_BUCKET_NAME="cash-planner-test"
+
+    echo "🧪 Running presigned URL test against local MinIO..."
+    cargo test -p infra test_presigned_url_roundtrip -- --ignored --nocapture
+    status=$?
+
+    docker stop cash-planner-minio-test > /dev/null
+    exit $status
+fi
 
 echo "🔧 Setting up MinIO environment variables..."
 
@@ -10,17 +33,15 @@ export MINIO_PORT="443"
 export MINIO_ACCESS_KEY="yOV6ceBtGNt99h1yqSvW"
 export MINIO_SECRET_KEY="dxn0LFYgzJaSmoEUXkCpUBc3f6FSWpzFjiQG4QG2"
 export MINIO_BUCKET_NAME="cash-planner"
-export MINIO_PUBLIC_URL="https://minio.jla-dev.com"
 
 echo "📊 MinIO Configuration:"
 echo "  Endpoint: $MINIO_ENDPOINT:$MINIO_PORT"
 echo "  Access Key: ${MINIO_ACCESS_KEY:0:8}***"
 echo "  Bucket: $MINIO_BUCKET_NAME"
-echo "  Public URL: $MINIO_PUBLIC_URL"
 echo ""
 
 echo "🧪 Running MinIO connection test..."
 cargo test test_minio_connection -- --ignored --nocapture
 
 echo ""
-echo "✅ Test completed!"
\ No newline at end of file
+echo "✅ Test completed!"